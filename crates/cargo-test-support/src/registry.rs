@@ -7,8 +7,10 @@ use std::collections::BTreeMap;
 use std::fmt::Write as _;
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Write};
-use std::net::TcpListener;
+use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use tar::{Builder, Header};
 use url::Url;
@@ -244,6 +246,125 @@ impl RegistryBuilder {
     }
 }
 
+/// A minimal HTTP server serving the files in a directory, used to test
+/// registries whose index is fetched over HTTP (`sparse+` URLs).
+///
+/// Every file is given an `ETag` based on its contents, and requests with a
+/// matching `If-None-Match` header get a `304 Not Modified` response. Each
+/// request is recorded as `"<status> <path>"` and can be inspected with
+/// [`HttpServer::requests`].
+///
+/// The server is shut down when this is dropped.
+pub struct HttpServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+    done: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl HttpServer {
+    /// Starts serving the files under `root`.
+    pub fn new(root: PathBuf) -> HttpServer {
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let addr = t!(listener.local_addr());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let done = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let requests = Arc::clone(&requests);
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                for conn in listener.incoming() {
+                    if done.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(conn) = conn {
+                        HttpServer::respond(&root, conn, &requests);
+                    }
+                }
+            })
+        };
+        HttpServer {
+            addr,
+            requests,
+            done,
+            handle: Some(handle),
+        }
+    }
+
+    /// The `http://` URL of the root of the server.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The requests served so far, in order, as `"<status> <path>"`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn respond(root: &Path, conn: TcpStream, requests: &Mutex<Vec<String>>) {
+        let mut conn = BufReader::new(conn);
+        let mut lines = (&mut conn)
+            .lines()
+            .map(|s| s.unwrap_or_default())
+            .take_while(|s| !s.trim().is_empty());
+        let request = match lines.next() {
+            Some(line) => line,
+            None => return,
+        };
+        let mut if_none_match = None;
+        for line in lines {
+            if let Some((key, value)) = line.split_once(':') {
+                if key.trim().eq_ignore_ascii_case("if-none-match") {
+                    if_none_match = Some(value.trim().to_string());
+                }
+            }
+        }
+
+        let path = request.split_whitespace().nth(1).unwrap_or("/");
+        let file = root.join(path.trim_start_matches('/'));
+        let (status, headers, body) = match fs::read(&file) {
+            Ok(contents) if file.is_file() => {
+                let etag = format!("\"{}\"", cksum(&contents));
+                if if_none_match.as_ref() == Some(&etag) {
+                    ("304 Not Modified", String::new(), Vec::new())
+                } else {
+                    ("200 OK", format!("ETag: {}\r\n", etag), contents)
+                }
+            }
+            _ => ("404 Not Found", String::new(), Vec::new()),
+        };
+        requests
+            .lock()
+            .unwrap()
+            .push(format!("{} {}", &status[..3], path));
+
+        let stream = conn.get_mut();
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {}\r\n\
+             {}\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\
+             \r\n",
+            status,
+            headers,
+            body.len()
+        );
+        let _ = stream.write_all(&body);
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        // Wake up the accept loop so it notices that it's done.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
 /// A builder for creating a new package in a registry.
 ///
 /// This uses "source replacement" using an automatically generated
@@ -618,6 +739,9 @@ impl Package {
             "yanked": self.yanked,
             "links": self.links,
         });
+        if let Some(rust_version) = &self.rust_version {
+            json["rust_version"] = serde_json::json!(rust_version);
+        }
         if let Some(f2) = &features2 {
             json["features2"] = serde_json::json!(f2);
             json["v"] = serde_json::json!(2);
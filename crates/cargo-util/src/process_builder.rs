@@ -9,7 +9,9 @@ use std::ffi::{OsStr, OsString};
 use std::fmt;
 use std::iter::once;
 use std::path::Path;
-use std::process::{Command, Output, Stdio};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
 
 /// A builder object for an external process, similar to [`std::process::Command`].
 #[derive(Clone, Debug)]
@@ -29,6 +31,9 @@ pub struct ProcessBuilder {
     jobserver: Option<Client>,
     /// `true` to include environment variable in display.
     display_env_vars: bool,
+    /// Where to record the peak memory usage of the process, see
+    /// [`ProcessBuilder::record_peak_memory`].
+    peak_memory: Option<Arc<AtomicU64>>,
 }
 
 impl fmt::Display for ProcessBuilder {
@@ -68,6 +73,7 @@ impl ProcessBuilder {
             env: BTreeMap::new(),
             jobserver: None,
             display_env_vars: false,
+            peak_memory: None,
         }
     }
 
@@ -161,6 +167,17 @@ impl ProcessBuilder {
         self
     }
 
+    /// Records the peak resident set size of the process, in bytes, in
+    /// `peak` once it exits, if it is larger than the value already there.
+    ///
+    /// This includes the largest of the process's own children, such as a
+    /// linker started by rustc. It is only measured by
+    /// [`ProcessBuilder::exec_with_streaming`], and only on Unix.
+    pub fn record_peak_memory(&mut self, peak: Arc<AtomicU64>) -> &mut Self {
+        self.peak_memory = Some(peak);
+        self
+    }
+
     /// Runs the process, waiting for completion, and mapping non-success exit codes to an error.
     pub fn exec(&self) -> Result<()> {
         let mut command = self.build_command();
@@ -292,7 +309,10 @@ impl ProcessBuilder {
                 data.drain(..idx);
                 *pos = 0;
             })?;
-            child.wait()
+            match &self.peak_memory {
+                Some(peak) => imp::wait_with_peak_memory(&mut child, peak),
+                None => child.wait(),
+            }
         })()
         .with_context(|| {
             ProcessError::new(&format!("could not execute process {}", self), None, None)
@@ -384,9 +404,11 @@ impl ProcessBuilder {
 
 #[cfg(unix)]
 mod imp {
-    use super::{ProcessBuilder, ProcessError};
+    use super::{Child, ExitStatus, ProcessBuilder, ProcessError};
     use anyhow::Result;
-    use std::os::unix::process::CommandExt;
+    use std::io;
+    use std::os::unix::process::{CommandExt, ExitStatusExt};
+    use std::sync::atomic::{AtomicU64, Ordering};
 
     pub fn exec_replace(process_builder: &ProcessBuilder) -> Result<()> {
         let mut command = process_builder.build_command();
@@ -397,12 +419,40 @@ mod imp {
             None,
         )))
     }
+
+    /// Waits for `child` like `Child::wait`, but using `wait4` to also learn
+    /// about its peak memory usage.
+    pub fn wait_with_peak_memory(child: &mut Child, peak: &AtomicU64) -> io::Result<ExitStatus> {
+        let mut status = 0;
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            let pid = child.id() as libc::pid_t;
+            if unsafe { libc::wait4(pid, &mut status, 0, &mut usage) } != -1 {
+                break;
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+        // `ru_maxrss` is in kilobytes, except on macOS where it is in bytes.
+        let maxrss = usage.ru_maxrss.max(0) as u64;
+        let bytes = if cfg!(target_os = "macos") {
+            maxrss
+        } else {
+            maxrss * 1024
+        };
+        peak.fetch_max(bytes, Ordering::SeqCst);
+        Ok(ExitStatus::from_raw(status))
+    }
 }
 
 #[cfg(windows)]
 mod imp {
-    use super::{ProcessBuilder, ProcessError};
+    use super::{Child, ExitStatus, ProcessBuilder, ProcessError};
     use anyhow::Result;
+    use std::io;
+    use std::sync::atomic::AtomicU64;
     use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE};
     use winapi::um::consoleapi::SetConsoleCtrlHandler;
 
@@ -421,4 +471,9 @@ mod imp {
         // Just execute the process as normal.
         process_builder.exec()
     }
+
+    /// Peak memory usage isn't measured on Windows.
+    pub fn wait_with_peak_memory(child: &mut Child, _peak: &AtomicU64) -> io::Result<ExitStatus> {
+        child.wait()
+    }
 }
@@ -0,0 +1,169 @@
+use crate::command_prelude::*;
+
+use cargo::core::dependency::DepKind;
+use cargo::ops::{self, AddOptions, DepOp};
+use cargo::util::print_available_packages;
+use cargo::util::toml_mut::DepTable;
+
+pub fn cli() -> App {
+    subcommand("add")
+        .about("Add dependencies to a Cargo.toml manifest file")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(
+            Arg::with_name("crate")
+                .value_name("DEP_ID")
+                .multiple(true)
+                .help("Crates to add, optionally with a version requirement (`name@req`)"),
+        )
+        .arg(opt("dev", "Add as a development dependency").conflicts_with("build"))
+        .arg(opt("build", "Add as a build dependency"))
+        .arg(opt("target", "Add as a dependency of the given target platform").value_name("TARGET"))
+        .arg(
+            multi_opt(
+                "features",
+                "FEATURES",
+                "Space or comma separated list of features to activate",
+            )
+            .short("F"),
+        )
+        .arg(opt("no-default-features", "Disable the default features"))
+        .arg(
+            opt("default-features", "Re-enable the default features")
+                .conflicts_with("no-default-features"),
+        )
+        .arg(opt("optional", "Mark the dependency as optional"))
+        .arg(opt("no-optional", "Mark the dependency as required").conflicts_with("optional"))
+        .arg(opt("rename", "Rename the dependency").value_name("NAME"))
+        .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .arg(
+            opt("path", "Filesystem path to a local package to add")
+                .value_name("PATH")
+                .conflicts_with_all(&["git", "registry"]),
+        )
+        .arg(
+            opt("git", "Git repository location")
+                .value_name("URI")
+                .conflicts_with("registry"),
+        )
+        .arg(
+            opt("branch", "Git branch to use")
+                .value_name("BRANCH")
+                .requires("git")
+                .conflicts_with_all(&["tag", "rev"]),
+        )
+        .arg(
+            opt("tag", "Git tag to use")
+                .value_name("TAG")
+                .requires("git")
+                .conflicts_with("rev"),
+        )
+        .arg(
+            opt("rev", "Git revision to use")
+                .value_name("REV")
+                .requires("git"),
+        )
+        .arg_package("Package to add the dependencies to")
+        .arg_manifest_path()
+        .arg_dry_run("Don't actually write the manifest")
+        .after_help("Run `cargo help add` for more detailed information.\n")
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    if args.is_present_with_zero_values("package") {
+        print_available_packages(&ws)?;
+    }
+
+    let crates = values(args, "crate");
+    let path = args.value_of_path("path", config);
+    let git = args.value_of("git").map(|s| s.to_string());
+    if crates.is_empty() && path.is_none() && git.is_none() {
+        return Err(anyhow::format_err!(
+            "at least one crate to add must be given, or `--path` or `--git`"
+        )
+        .into());
+    }
+    if crates.len() > 1 {
+        for flag in ["rename", "path", "git", "features"] {
+            if args.is_present(flag) {
+                return Err(anyhow::format_err!(
+                    "`--{}` can only be used when adding a single crate",
+                    flag
+                )
+                .into());
+            }
+        }
+    }
+    let registry = if args.is_present("registry") {
+        args.registry(config)?
+    } else {
+        None
+    };
+
+    let op = DepOp {
+        crate_spec: None,
+        rename: args.value_of("rename").map(|s| s.to_string()),
+        features: values(args, "features")
+            .iter()
+            .flat_map(|s| s.split_whitespace())
+            .flat_map(|s| s.split(','))
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string())
+            .collect(),
+        default_features: flag_pair(args, "default-features", "no-default-features"),
+        optional: flag_pair(args, "optional", "no-optional"),
+        registry,
+        path,
+        git,
+        branch: args.value_of("branch").map(|s| s.to_string()),
+        tag: args.value_of("tag").map(|s| s.to_string()),
+        rev: args.value_of("rev").map(|s| s.to_string()),
+    };
+    let dependencies = if crates.is_empty() {
+        vec![op]
+    } else {
+        crates
+            .into_iter()
+            .map(|spec| DepOp {
+                crate_spec: Some(spec),
+                ..op.clone()
+            })
+            .collect()
+    };
+
+    let kind = if args.is_present("dev") {
+        DepKind::Development
+    } else if args.is_present("build") {
+        DepKind::Build
+    } else {
+        DepKind::Normal
+    };
+    let dry_run = args.is_present("dry-run");
+    let opts = AddOptions {
+        config,
+        spec: ops::manifest_package(&ws, args.value_of("package"), "add")?,
+        dependencies,
+        section: DepTable::new(kind, args.value_of("target").map(|s| s.to_string())),
+        dry_run,
+    };
+    ops::add(&opts)?;
+
+    if !dry_run {
+        // Reload the workspace with the new manifest to update the lock file.
+        let ws = args.workspace(config)?;
+        ops::resolve_ws(&ws)?;
+    }
+    Ok(())
+}
+
+/// Returns `Some(true)` for `--yes`, `Some(false)` for `--no`, and `None` if
+/// neither is given.
+fn flag_pair(args: &ArgMatches<'_>, yes: &str, no: &str) -> Option<bool> {
+    if args.is_present(yes) {
+        Some(true)
+    } else if args.is_present(no) {
+        Some(false)
+    } else {
+        None
+    }
+}
@@ -65,6 +65,8 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         no_run: args.is_present("no-run"),
         no_fail_fast: args.is_present("no-fail-fast"),
         compile_opts,
+        test_jobs: 1,
+        reports: Vec::new(),
     };
 
     let bench_args = args.value_of("BENCHNAME").into_iter();
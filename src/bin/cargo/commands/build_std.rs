@@ -0,0 +1,34 @@
+use crate::command_prelude::*;
+use anyhow::anyhow;
+use cargo::drop_println;
+use cargo::ops;
+
+pub fn cli() -> App {
+    subcommand("build-std")
+        .about("Build the standard library into a sysroot that later builds reuse")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg_jobs()
+        .arg_release("Build the standard library in release mode, with optimizations")
+        .arg_profile("Build the standard library with the specified profile")
+        .arg_target_triple("Build for the target triple")
+        .arg_target_dir()
+        .arg_manifest_path()
+        .arg_message_format()
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.nightly_features_allowed {
+        return Err(anyhow!("`cargo build-std` can only be used on the nightly channel").into());
+    }
+    let ws = args.workspace(config)?;
+    let compile_opts = args.compile_options(
+        config,
+        CompileMode::Build,
+        Some(&ws),
+        ProfileChecking::Checked,
+    )?;
+    for sysroot in ops::build_std(&ws, &compile_opts)? {
+        drop_println!(config, "{}", sysroot.display());
+    }
+    Ok(())
+}
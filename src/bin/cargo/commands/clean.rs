@@ -1,5 +1,6 @@
 use crate::command_prelude::*;
 
+use super::gc;
 use cargo::ops::{self, CleanOptions};
 use cargo::util::print_available_packages;
 
@@ -15,9 +16,14 @@ pub fn cli() -> App {
         .arg_profile("Clean artifacts of the specified profile")
         .arg_doc("Whether or not to clean just the documentation directory")
         .after_help("Run `cargo help clean` for more detailed information.\n")
+        .subcommand(gc::cli())
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if let ("gc", Some(args)) = args.subcommand() {
+        return gc::exec(config, args);
+    }
+
     let ws = args.workspace(config)?;
 
     if args.is_present_with_zero_values("package") {
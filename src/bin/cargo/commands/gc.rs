@@ -0,0 +1,47 @@
+use crate::command_prelude::*;
+
+use anyhow::anyhow;
+use cargo::ops::{self, GcOptions};
+
+pub fn cli() -> App {
+    subcommand("gc")
+        .about("Remove unused entries from the global package cache (unstable)")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(
+            opt(
+                "max-age",
+                "Remove entries which haven't been used for longer than DURATION",
+            )
+            .value_name("DURATION"),
+        )
+        .arg(
+            opt(
+                "max-size",
+                "Remove least recently used entries until the cache is at most SIZE",
+            )
+            .value_name("SIZE"),
+        )
+        .arg(opt(
+            "dry-run",
+            "Display what would be removed without removing it",
+        ))
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().gc {
+        return Err(anyhow!("the `gc` command is unstable, pass `-Z gc` to enable it").into());
+    }
+    let opts = GcOptions {
+        max_age: args
+            .value_of("max-age")
+            .map(ops::parse_max_age)
+            .transpose()?,
+        max_size: args
+            .value_of("max-size")
+            .map(ops::parse_max_size)
+            .transpose()?,
+        dry_run: args.is_present("dry-run"),
+    };
+    ops::gc(config, &opts)?;
+    Ok(())
+}
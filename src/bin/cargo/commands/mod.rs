@@ -2,14 +2,17 @@ use crate::command_prelude::*;
 
 pub fn builtin() -> Vec<App> {
     vec![
+        add::cli(),
         bench::cli(),
         build::cli(),
+        build_std::cli(),
         check::cli(),
         clean::cli(),
         config::cli(),
         doc::cli(),
         fetch::cli(),
         fix::cli(),
+        gc::cli(),
         generate_lockfile::cli(),
         git_checkout::cli(),
         init::cli(),
@@ -24,6 +27,7 @@ pub fn builtin() -> Vec<App> {
         pkgid::cli(),
         publish::cli(),
         read_manifest::cli(),
+        remove::cli(),
         report::cli(),
         run::cli(),
         rustc::cli(),
@@ -42,14 +46,17 @@ pub fn builtin() -> Vec<App> {
 
 pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> CliResult> {
     let f = match cmd {
+        "add" => add::exec,
         "bench" => bench::exec,
         "build" => build::exec,
+        "build-std" => build_std::exec,
         "check" => check::exec,
         "clean" => clean::exec,
         "config" => config::exec,
         "doc" => doc::exec,
         "fetch" => fetch::exec,
         "fix" => fix::exec,
+        "gc" => gc::exec,
         "generate-lockfile" => generate_lockfile::exec,
         "git-checkout" => git_checkout::exec,
         "init" => init::exec,
@@ -64,6 +71,7 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
         "pkgid" => pkgid::exec,
         "publish" => publish::exec,
         "read-manifest" => read_manifest::exec,
+        "remove" => remove::exec,
         "report" => report::exec,
         "run" => run::exec,
         "rustc" => rustc::exec,
@@ -82,14 +90,17 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
     Some(f)
 }
 
+pub mod add;
 pub mod bench;
 pub mod build;
+pub mod build_std;
 pub mod check;
 pub mod clean;
 pub mod config;
 pub mod doc;
 pub mod fetch;
 pub mod fix;
+pub mod gc;
 pub mod generate_lockfile;
 pub mod git_checkout;
 pub mod help;
@@ -105,6 +116,7 @@ pub mod package;
 pub mod pkgid;
 pub mod publish;
 pub mod read_manifest;
+pub mod remove;
 pub mod report;
 pub mod run;
 pub mod rustc;
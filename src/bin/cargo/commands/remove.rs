@@ -0,0 +1,63 @@
+use crate::command_prelude::*;
+
+use cargo::core::dependency::DepKind;
+use cargo::ops::{self, RemoveOptions};
+use cargo::util::print_available_packages;
+use cargo::util::toml_mut::DepTable;
+
+pub fn cli() -> App {
+    subcommand("remove")
+        .about("Remove dependencies from a Cargo.toml manifest file")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(
+            Arg::with_name("dependencies")
+                .value_name("DEP_ID")
+                .multiple(true)
+                .required(true)
+                .help("Dependencies to remove"),
+        )
+        .arg(opt("dev", "Remove as a development dependency").conflicts_with("build"))
+        .arg(opt("build", "Remove as a build dependency"))
+        .arg(
+            opt(
+                "target",
+                "Remove as a dependency of the given target platform",
+            )
+            .value_name("TARGET"),
+        )
+        .arg_package("Package to remove the dependencies from")
+        .arg_manifest_path()
+        .arg_dry_run("Don't actually write the manifest")
+        .after_help("Run `cargo help remove` for more detailed information.\n")
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    if args.is_present_with_zero_values("package") {
+        print_available_packages(&ws)?;
+    }
+
+    let kind = if args.is_present("dev") {
+        DepKind::Development
+    } else if args.is_present("build") {
+        DepKind::Build
+    } else {
+        DepKind::Normal
+    };
+    let dry_run = args.is_present("dry-run");
+    let opts = RemoveOptions {
+        config,
+        spec: ops::manifest_package(&ws, args.value_of("package"), "remove")?,
+        dependencies: values(args, "dependencies"),
+        section: DepTable::new(kind, args.value_of("target").map(|s| s.to_string())),
+        dry_run,
+    };
+    ops::remove(&opts)?;
+
+    if !dry_run {
+        // Reload the workspace with the new manifest to update the lock file.
+        let ws = args.workspace(config)?;
+        ops::resolve_ws(&ws)?;
+    }
+    Ok(())
+}
@@ -2,6 +2,7 @@ use crate::command_prelude::*;
 use anyhow::anyhow;
 use cargo::core::compiler::future_incompat::{OnDiskReports, REPORT_PREAMBLE};
 use cargo::drop_println;
+use cargo::ops;
 
 pub fn cli() -> App {
     subcommand("report")
@@ -20,6 +21,19 @@ pub fn cli() -> App {
                     .value_name("id"),
                 ),
         )
+        .subcommand(
+            subcommand("timings")
+                .about("Compares the timings of two builds saved with `-Ztimings=file`")
+                .arg(
+                    opt("compare", "The timing reports of the old and the new build")
+                        .value_names(&["OLD", "NEW"])
+                        .required(true),
+                )
+                .arg(opt(
+                    "html",
+                    "Save the comparison to `cargo-timing-compare.html` instead of printing it",
+                )),
+        )
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
@@ -28,6 +42,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
     }
     match args.subcommand() {
         ("future-incompatibilities", Some(args)) => report_future_incompatibilies(config, args),
+        ("timings", Some(args)) => report_timings(config, args),
         (cmd, _) => panic!("unexpected command `{}`", cmd),
     }
 }
@@ -43,3 +58,15 @@ fn report_future_incompatibilies(config: &Config, args: &ArgMatches<'_>) -> CliR
     drop(config.shell().print_ansi_stdout(report.as_bytes()));
     Ok(())
 }
+
+fn report_timings(config: &Config, args: &ArgMatches<'_>) -> CliResult {
+    let mut paths = args.values_of_os("compare").unwrap_or_default();
+    let (old, new) = (paths.next().unwrap(), paths.next().unwrap());
+    let opts = ops::CompareTimingsOptions {
+        old: config.cwd().join(old),
+        new: config.cwd().join(new),
+        html: args.is_present("html"),
+    };
+    ops::compare_timings(config, &opts)?;
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use crate::command_prelude::*;
 use anyhow::Error;
 use cargo::ops::{self, CompileFilter, FilterRule, LibRule};
+use cargo::CargoResult;
 
 pub fn cli() -> App {
     subcommand("test")
@@ -40,6 +41,25 @@ pub fn cli() -> App {
         .arg(opt("doc", "Test only this library's documentation"))
         .arg(opt("no-run", "Compile, but don't run tests"))
         .arg(opt("no-fail-fast", "Run all tests regardless of failure"))
+        .arg(
+            opt(
+                "test-jobs",
+                "Number of test binaries to run in parallel, defaults to 1 (unstable)",
+            )
+            .value_name("N"),
+        )
+        .arg(
+            opt(
+                "partition",
+                "Only build and run a share of the test targets, for example `hash:1/3` (unstable)",
+            )
+            .value_name("KIND:M/N"),
+        )
+        .arg(multi_opt(
+            "report",
+            "FORMAT=PATH",
+            "Write a report of the test results, for example `junit=report.xml` (unstable)",
+        ))
         .arg_package_spec(
             "Package to run tests for",
             "Test all packages in the workspace",
@@ -114,10 +134,41 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         }
     }
 
+    let reports = args
+        .values_of("report")
+        .unwrap_or_default()
+        .map(|report| ops::TestReportOutput::parse(report, config.cwd()))
+        .collect::<CargoResult<Vec<_>>>()?;
+    if !reports.is_empty() {
+        config.cli_unstable().fail_if_stable_opt("--report", 2832)?;
+        if no_run {
+            return Err(anyhow::format_err!("Can't write a test report with --no-run").into());
+        }
+    }
+
+    let test_jobs = args.value_of_u32("test-jobs")?;
+    if test_jobs.is_some() {
+        config
+            .cli_unstable()
+            .fail_if_stable_opt("--test-jobs", 5609)?;
+    }
+    if test_jobs == Some(0) {
+        return Err(anyhow::format_err!("--test-jobs must be at least 1").into());
+    }
+
+    if let Some(partition) = args.value_of("partition") {
+        config
+            .cli_unstable()
+            .fail_if_stable_opt("--partition", 10293)?;
+        compile_opts.partition = Some(ops::Partition::parse(partition)?);
+    }
+
     let ops = ops::TestOptions {
         no_run,
         no_fail_fast: args.is_present("no-fail-fast"),
         compile_opts,
+        test_jobs: test_jobs.unwrap_or(1) as usize,
+        reports,
     };
 
     let err = ops::run_tests(&ws, &ops, &test_args)?;
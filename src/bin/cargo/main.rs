@@ -31,7 +31,10 @@ fn main() {
         }
     };
 
-    let result = match cargo::ops::fix_maybe_exec_rustc(&config) {
+    let helper_or_fix = cargo::ops::build_sandbox_maybe_exec(&config)
+        .and_then(|sandbox| Ok(sandbox || cargo::ops::remote_cache_fs_maybe_exec()?))
+        .and_then(|helper| Ok(helper || cargo::ops::fix_maybe_exec_rustc(&config)?));
+    let result = match helper_or_fix {
         Ok(true) => Ok(()),
         Ok(false) => {
             let _token = cargo::util::job::setup();
@@ -1,7 +1,8 @@
 use crate::core::compiler::CompileKind;
+use crate::util::config::PathAndArgs;
 use crate::util::interning::InternedString;
 use crate::util::{CargoResult, Config, RustfixDiagnosticServer};
-use anyhow::bail;
+use anyhow::{bail, Context as _};
 use cargo_util::ProcessBuilder;
 use serde::ser;
 use std::cell::RefCell;
@@ -39,6 +40,15 @@ pub struct BuildConfig {
     pub export_dir: Option<PathBuf>,
     /// `true` to output a future incompatibility report at the end of the build
     pub future_incompat_report: bool,
+    /// How much memory, in bytes, the units being built at the same time
+    /// are expected to need at most.
+    pub max_memory: Option<u64>,
+    /// A directory to share the artifacts of non-path dependencies in, with
+    /// other workspaces.
+    pub shared_cache: Option<PathBuf>,
+    /// A helper process to share the artifacts of non-path dependencies
+    /// through, with other machines.
+    pub remote_cache: Option<PathAndArgs>,
 }
 
 impl BuildConfig {
@@ -46,6 +56,9 @@ impl BuildConfig {
     /// configured options are:
     ///
     /// * `build.jobs`
+    /// * `build.max-memory`
+    /// * `build.remote-cache`
+    /// * `build.shared-cache`
     /// * `build.target`
     /// * `target.$target.ar`
     /// * `target.$target.linker`
@@ -72,6 +85,47 @@ impl BuildConfig {
         if jobs == 0 {
             anyhow::bail!("jobs may not be 0");
         }
+        let max_memory = match &cfg.max_memory {
+            Some(_) if !config.cli_unstable().max_memory => {
+                config.shell().warn(
+                    "`build.max-memory` in cargo config was ignored, \
+                     the -Zmax-memory command-line flag is required",
+                )?;
+                None
+            }
+            Some(size) => Some(
+                crate::ops::parse_max_size(size)
+                    .with_context(|| "invalid `build.max-memory` config value")?,
+            ),
+            None => None,
+        };
+        let shared_cache = match &cfg.shared_cache {
+            Some(_) if !config.cli_unstable().shared_cache => {
+                config.shell().warn(
+                    "`build.shared-cache` in cargo config was ignored, \
+                     the -Zshared-cache command-line flag is required",
+                )?;
+                None
+            }
+            Some(path) => match path.raw_value().strip_prefix("~/") {
+                Some(rest) => match home::home_dir() {
+                    Some(home) => Some(home.join(rest)),
+                    None => bail!("could not find the home directory for `build.shared-cache`"),
+                },
+                None => Some(path.resolve_path(config)),
+            },
+            None => None,
+        };
+        let remote_cache = match &cfg.remote_cache {
+            Some(_) if !config.cli_unstable().remote_cache => {
+                config.shell().warn(
+                    "`build.remote-cache` in cargo config was ignored, \
+                     the -Zremote-cache command-line flag is required",
+                )?;
+                None
+            }
+            remote_cache => remote_cache.clone(),
+        };
 
         Ok(BuildConfig {
             requested_kinds,
@@ -86,6 +140,9 @@ impl BuildConfig {
             rustfix_diagnostic_server: RefCell::new(None),
             export_dir: None,
             future_incompat_report: false,
+            max_memory,
+            shared_cache,
+            remote_cache,
         })
     }
 
@@ -158,20 +215,25 @@ impl ser::Serialize for CompileMode {
     where
         S: ser::Serializer,
     {
-        use self::CompileMode::*;
-        match *self {
-            Test => "test".serialize(s),
-            Build => "build".serialize(s),
-            Check { .. } => "check".serialize(s),
-            Bench => "bench".serialize(s),
-            Doc { .. } => "doc".serialize(s),
-            Doctest => "doctest".serialize(s),
-            RunCustomBuild => "run-custom-build".serialize(s),
-        }
+        self.as_str().serialize(s)
     }
 }
 
 impl CompileMode {
+    /// Returns the name of the mode, as used in JSON messages.
+    pub fn as_str(self) -> &'static str {
+        use self::CompileMode::*;
+        match self {
+            Test => "test",
+            Build => "build",
+            Check { .. } => "check",
+            Bench => "bench",
+            Doc { .. } => "doc",
+            Doctest => "doctest",
+            RunCustomBuild => "run-custom-build",
+        }
+    }
+
     /// Returns `true` if the unit is being checked.
     pub fn is_check(self) -> bool {
         matches!(self, CompileMode::Check { .. })
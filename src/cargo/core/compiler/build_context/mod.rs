@@ -1,3 +1,4 @@
+use crate::core::compiler::standard_lib::Sysroot;
 use crate::core::compiler::unit_graph::UnitGraph;
 use crate::core::compiler::{BuildConfig, CompileKind, Unit};
 use crate::core::profiles::Profiles;
@@ -49,6 +50,10 @@ pub struct BuildContext<'a, 'cfg> {
 
     /// The list of all kinds that are involved in this build
     pub all_kinds: HashSet<CompileKind>,
+
+    /// The sysroots with the standard library that was built beforehand,
+    /// with `build-std.sysroot`, for the kinds they are used for.
+    pub std_sysroots: HashMap<CompileKind, Sysroot>,
 }
 
 impl<'a, 'cfg> BuildContext<'a, 'cfg> {
@@ -80,6 +85,7 @@ impl<'a, 'cfg> BuildContext<'a, 'cfg> {
             roots,
             unit_graph,
             all_kinds,
+            std_sysroots: HashMap::new(),
         })
     }
 
@@ -0,0 +1,171 @@
+//! Sandboxing of build scripts, `-Zbuild-sandbox`.
+//!
+//! A sandboxed build script is not run directly. Cargo runs itself instead,
+//! with the [`SandboxPolicy`] of the build script as JSON in the
+//! `__CARGO_BUILD_SANDBOX` environment variable, the same way it runs itself
+//! as rustc for `cargo fix`. That process restricts itself, and then executes
+//! the build script, which inherits the restrictions. See
+//! `ops::build_sandbox_maybe_exec`.
+//!
+//! A build script may read its package, its own executable, the toolchain
+//! and the system directories, and may only write to `OUT_DIR` and a
+//! temporary directory. It has no network access. The package can grant it
+//! more with `[package.build-capabilities]`, and the user can grant all build
+//! scripts access to more paths with `build.sandbox-read` and
+//! `build.sandbox-write`.
+//!
+//! Before executing the build script, the sandbox writes a [`SandboxReport`]
+//! of the restrictions it couldn't apply. If there are any, the build script
+//! isn't run, unless `build.allow-incomplete-sandbox` is set.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use cargo_util::ProcessBuilder;
+use serde::{Deserialize, Serialize};
+
+use super::{CompileKind, Context, Unit};
+use crate::util::CargoResult;
+
+/// The environment variable that makes Cargo act as the sandbox of a build
+/// script, set to its [`SandboxPolicy`].
+pub const SANDBOX_ENV: &str = "__CARGO_BUILD_SANDBOX";
+
+/// System directories that build scripts may always read, if they exist.
+///
+/// Only the build script's own entry of `/proc` is readable, as the others
+/// show the environment of every process of the same user.
+const SYSTEM_DIRS: &[&str] = &[
+    "/bin",
+    "/dev",
+    "/etc",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/nix",
+    "/opt",
+    "/proc/self",
+    "/proc/cpuinfo",
+    "/sbin",
+    "/sys",
+    "/usr",
+];
+
+/// Files that build scripts may always write.
+const SYSTEM_FILES: &[&str] = &["/dev/full", "/dev/null", "/dev/zero"];
+
+/// What a sandboxed build script may access.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SandboxPolicy {
+    /// The package of the build script, for error messages.
+    pub package: String,
+    /// Paths that may be read and executed.
+    pub read: Vec<PathBuf>,
+    /// Paths that may be read, executed and written.
+    pub write: Vec<PathBuf>,
+    /// Whether the network may be accessed.
+    pub network: bool,
+    /// Whether the build script is run even if the sandbox couldn't apply
+    /// all restrictions.
+    pub allow_incomplete: bool,
+    /// Where the sandbox writes its [`SandboxReport`].
+    pub report: PathBuf,
+}
+
+/// What the sandbox reports back to Cargo before it runs the build script.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SandboxReport {
+    /// The restrictions that couldn't be applied, and why.
+    pub incomplete: Vec<String>,
+}
+
+impl SandboxReport {
+    /// Reads the report at `path`, or returns `None` if the sandbox failed
+    /// before writing it.
+    pub fn read(path: &Path) -> Option<SandboxReport> {
+        let report = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&report).ok()
+    }
+}
+
+impl SandboxPolicy {
+    /// Returns the policy for the build script in `script_dir` run by
+    /// `unit`, which writes to `out_dir` and `tmp_dir`. The sandbox reports
+    /// to `report`.
+    pub fn new(
+        cx: &Context<'_, '_>,
+        unit: &Unit,
+        script_dir: &Path,
+        out_dir: &Path,
+        tmp_dir: &Path,
+        report: &Path,
+    ) -> CargoResult<SandboxPolicy> {
+        if !cfg!(target_os = "linux") {
+            bail!("sandboxing build scripts with -Zbuild-sandbox is only supported on Linux");
+        }
+        let bcx = cx.bcx;
+        let capabilities = unit.pkg.manifest().build_capabilities();
+        let mut read: Vec<PathBuf> = SYSTEM_DIRS.iter().map(PathBuf::from).collect();
+        read.push(unit.pkg.root().to_path_buf());
+        read.push(script_dir.to_path_buf());
+        // The build scripts of dependencies may point to their sources or
+        // outputs with `cargo:KEY=VALUE` metadata, like the headers of a C
+        // library.
+        for dep in cx.unit_deps(unit) {
+            if dep.unit.mode.is_run_custom_build() {
+                read.push(dep.unit.pkg.root().to_path_buf());
+                read.push(cx.files().build_script_out_dir(&dep.unit));
+            }
+        }
+        // The toolchain, which may live in the home directory.
+        read.push(bcx.target_data.info(CompileKind::Host).sysroot.clone());
+        for tool in [bcx.rustc().path.as_path(), bcx.config.cargo_exe()?] {
+            if let Some(dir) = tool.parent().filter(|dir| dir.is_absolute()) {
+                read.push(dir.to_path_buf());
+            }
+        }
+        read.push(bcx.config.home().join("bin").into_path_unlocked());
+        if let Ok(rustup_home) = home::rustup_home() {
+            read.push(rustup_home);
+        }
+        read.extend(capabilities.read.iter().cloned());
+        read.extend(config_paths(cx, "build.sandbox-read")?);
+
+        let mut write: Vec<PathBuf> = SYSTEM_FILES.iter().map(PathBuf::from).collect();
+        write.push(out_dir.to_path_buf());
+        write.push(tmp_dir.to_path_buf());
+        write.extend(capabilities.write.iter().cloned());
+        write.extend(config_paths(cx, "build.sandbox-write")?);
+
+        Ok(SandboxPolicy {
+            package: unit.pkg.to_string(),
+            read,
+            write,
+            network: capabilities.network,
+            allow_incomplete: bcx.config.build_config()?.allow_incomplete_sandbox == Some(true),
+            report: report.to_path_buf(),
+        })
+    }
+
+    /// Wraps the command running the build script so that it's run in the
+    /// sandbox by `cargo`.
+    pub fn wrap(&self, cmd: ProcessBuilder, cargo: &Path) -> CargoResult<ProcessBuilder> {
+        let mut cmd = cmd.wrapped(Some(cargo));
+        cmd.env(SANDBOX_ENV, serde_json::to_string(self)?);
+        Ok(cmd)
+    }
+}
+
+/// Returns the paths of the list `key` in cargo config, which are relative to
+/// the directory containing the `.cargo` directory they're defined in.
+fn config_paths(cx: &Context<'_, '_>, key: &str) -> CargoResult<Vec<PathBuf>> {
+    let config = cx.bcx.config;
+    Ok(match config.get_list(key)? {
+        Some(list) => list
+            .val
+            .iter()
+            .map(|(path, def)| def.root(config).join(path))
+            .collect(),
+        None => Vec::new(),
+    })
+}
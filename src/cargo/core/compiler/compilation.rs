@@ -85,6 +85,9 @@ pub struct Compilation<'cfg> {
     /// Libraries to test with rustdoc.
     pub to_doc_test: Vec<Doctest>,
 
+    /// The libraries of the standard library built with `-Zbuild-std`.
+    pub std_libs: Vec<UnitOutput>,
+
     /// The target host triple.
     pub host: String,
 
@@ -142,6 +145,7 @@ impl<'cfg> Compilation<'cfg> {
             root_crate_names: Vec::new(),
             extra_env: HashMap::new(),
             to_doc_test: Vec::new(),
+            std_libs: Vec::new(),
             config: bcx.config,
             host: bcx.host_triple().to_string(),
             rustc_process: rustc,
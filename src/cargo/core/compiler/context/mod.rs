@@ -18,6 +18,8 @@ use super::fingerprint::Fingerprint;
 use super::job_queue::JobQueue;
 use super::layout::Layout;
 use super::lto::Lto;
+use super::remote_cache::RemoteCache;
+use super::shared_cache::SharedCache;
 use super::unit_graph::UnitDep;
 use super::{
     BuildContext, Compilation, CompileKind, CompileMode, Executor, FileFlavor, RustDocFingerprint,
@@ -81,6 +83,10 @@ pub struct Context<'a, 'cfg> {
     /// compilation is happening (only object, only bitcode, both, etc), and is
     /// precalculated early on.
     pub lto: HashMap<Unit, Lto>,
+
+    /// The cache of build artifacts shared with other workspaces, if
+    /// `build.shared-cache` or `build.remote-cache` is set.
+    pub shared_cache: Option<SharedCache>,
 }
 
 impl<'a, 'cfg> Context<'a, 'cfg> {
@@ -104,6 +110,10 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         };
 
         let pipelining = bcx.config.build_config()?.pipelining.unwrap_or(true);
+        let remote_cache = match &bcx.build_config.remote_cache {
+            Some(helper) => Some(RemoteCache::new(bcx.config, helper)?),
+            None => None,
+        };
 
         Ok(Self {
             bcx,
@@ -121,6 +131,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             rustc_clients: HashMap::new(),
             pipelining,
             lto: HashMap::new(),
+            shared_cache: SharedCache::new(bcx.build_config.shared_cache.clone(), remote_cache),
         })
     }
 
@@ -177,6 +188,16 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
         }
 
         // Collect the result of the build into `self.compilation`.
+        for unit in self.bcx.unit_graph.keys() {
+            if unit.is_std && unit.mode == CompileMode::Build && unit.target.is_lib() {
+                for output in self.outputs(unit)?.iter() {
+                    if output.flavor == FileFlavor::Linkable {
+                        let std_lib = self.unit_output(unit, &output.path);
+                        self.compilation.std_libs.push(std_lib);
+                    }
+                }
+            }
+        }
         for unit in &self.bcx.roots {
             // Collect tests and executables.
             for output in self.outputs(unit)?.iter() {
@@ -1,3 +1,4 @@
+use super::build_sandbox::{SandboxPolicy, SandboxReport};
 use super::job::{Freshness, Job, Work};
 use super::{fingerprint, Context, LinkType, Unit};
 use crate::core::compiler::context::Metadata;
@@ -5,6 +6,7 @@ use crate::core::compiler::job_queue::JobState;
 use crate::core::{profiles::ProfileRoot, PackageId, Target};
 use crate::util::errors::CargoResult;
 use crate::util::machine_message::{self, Message};
+use crate::util::to_semver::parse_rust_version;
 use crate::util::{internal, profile};
 use anyhow::{bail, Context as _};
 use cargo_platform::Cfg;
@@ -16,6 +18,13 @@ use std::str;
 use std::sync::{Arc, Mutex};
 
 const CARGO_WARNING: &str = "cargo:warning=";
+const CARGO_NAMESPACED_WARNING: &str = "cargo::warning=";
+const CARGO_NAMESPACED_ERROR: &str = "cargo::error=";
+
+/// The first Rust version whose Cargo parses the `cargo::` prefix of build
+/// script directives. Older versions take `cargo::KEY=VALUE` as metadata with
+/// the key `:KEY`.
+const NAMESPACED_DIRECTIVES_VERSION: semver::Version = semver::Version::new(1, 57, 0);
 
 /// Contains the parsed output of a custom build script.
 #[derive(Clone, Debug, Hash, Default)]
@@ -42,6 +51,8 @@ pub struct BuildOutput {
     /// These are only displayed if this is a "local" package, `-vv` is used,
     /// or there is a build error for any target in this package.
     pub warnings: Vec<String>,
+    /// Errors reported with `cargo::error`, which fail the build.
+    pub errors: Vec<String>,
 }
 
 /// Map of packages to build script output.
@@ -289,6 +300,24 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
         script_out_dir.clone(),
     );
     let build_scripts = cx.build_scripts.get(unit).cloned();
+    // Sandboxed build scripts can't write to the system's temporary
+    // directory, so they get their own.
+    let tmp_dir = script_run_dir.join("tmp");
+    let sandbox = if bcx.config.cli_unstable().build_sandbox {
+        cmd.env("TMPDIR", &tmp_dir);
+        let report = script_run_dir.join("sandbox-report.json");
+        Some(SandboxPolicy::new(
+            cx,
+            unit,
+            &script_dir,
+            &script_out_dir,
+            &tmp_dir,
+            &report,
+        )?)
+    } else {
+        None
+    };
+    let cargo_exe = bcx.config.cargo_exe()?.to_path_buf();
     let json_messages = bcx.build_config.emit_json();
     let extra_verbose = bcx.config.extra_verbose();
     let (prev_output, prev_script_out_dir) = prev_build_output(cx, unit);
@@ -298,10 +327,13 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     paths::create_dir_all(&script_out_dir)?;
 
     let extra_link_arg = cx.bcx.config.cli_unstable().extra_link_arg;
+    let namespaced_directives = cx.bcx.config.cli_unstable().namespaced_directives;
     let nightly_features_allowed = cx.bcx.config.nightly_features_allowed;
     let targets: Vec<Target> = unit.pkg.targets().to_vec();
+    let rust_version = unit.pkg.rust_version().map(|v| v.to_string());
     // Need a separate copy for the fresh closure.
     let targets_fresh = targets.clone();
+    let rust_version_fresh = rust_version.clone();
 
     // Prepare the unit of "dirty work" which will actually run the custom build
     // command.
@@ -354,31 +386,92 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
 
         // And now finally, run the build command itself!
         state.running(&cmd);
+        state.measure_memory(&mut cmd);
         let timestamp = paths::set_invocation_time(&script_run_dir)?;
         let prefix = format!("[{} {}] ", id.name(), id.version());
         let mut warnings_in_case_of_panic = Vec::new();
-        let output = cmd
-            .exec_with_streaming(
-                &mut |stdout| {
-                    if let Some(warning) = stdout.strip_prefix(CARGO_WARNING) {
-                        warnings_in_case_of_panic.push(warning.to_owned());
-                    }
-                    if extra_verbose {
-                        state.stdout(format!("{}{}", prefix, stdout))?;
-                    }
-                    Ok(())
-                },
-                &mut |stderr| {
-                    if extra_verbose {
-                        state.stderr(format!("{}{}", prefix, stderr))?;
-                    }
-                    Ok(())
-                },
-                true,
-            )
-            .with_context(|| format!("failed to run custom build command for `{}`", pkg_descr));
+        let mut errors_in_case_of_panic = Vec::new();
+        let cmd = match &sandbox {
+            Some(sandbox) => {
+                paths::create_dir_all(&tmp_dir)?;
+                let _ = paths::remove_file(&sandbox.report);
+                sandbox.wrap(cmd, &cargo_exe)?
+            }
+            None => cmd,
+        };
+        let output = cmd.exec_with_streaming(
+            &mut |stdout| {
+                let namespaced_warning = stdout
+                    .strip_prefix(CARGO_NAMESPACED_WARNING)
+                    .filter(|_| namespaced_directives);
+                if let Some(warning) =
+                    namespaced_warning.or_else(|| stdout.strip_prefix(CARGO_WARNING))
+                {
+                    warnings_in_case_of_panic.push(warning.to_owned());
+                }
+                if let Some(error) = stdout
+                    .strip_prefix(CARGO_NAMESPACED_ERROR)
+                    .filter(|_| namespaced_directives)
+                {
+                    errors_in_case_of_panic.push(error.to_owned());
+                }
+                if extra_verbose {
+                    state.stdout(format!("{}{}", prefix, stdout))?;
+                }
+                Ok(())
+            },
+            &mut |stderr| {
+                if extra_verbose {
+                    state.stderr(format!("{}{}", prefix, stderr))?;
+                }
+                Ok(())
+            },
+            true,
+        );
+
+        // The report is only there if the sandbox got to run the build
+        // script, or refused to.
+        let report = sandbox
+            .as_ref()
+            .and_then(|sandbox| SandboxReport::read(&sandbox.report));
+        if let (Some(sandbox), Some(report)) = (&sandbox, &report) {
+            if !report.incomplete.is_empty() {
+                let incomplete = report.incomplete.join("\n  ");
+                if !sandbox.allow_incomplete {
+                    anyhow::bail!(
+                        "the build script of `{}` was not run, because the build script \
+                         sandbox is incomplete:\n  {}\n\n\
+                         Set `build.allow-incomplete-sandbox = true` in cargo config to run \
+                         build scripts without these restrictions.",
+                        pkg_descr,
+                        incomplete
+                    );
+                }
+                state.warning(format!(
+                    "the build script sandbox of `{}` is incomplete:\n  {}",
+                    pkg_descr, incomplete
+                ))?;
+            }
+        }
 
-        if let Err(error) = output {
+        if let Err(mut error) = output {
+            // The errors the script reported explain its failure best.
+            if !errors_in_case_of_panic.is_empty() {
+                error = error.context(errors_in_case_of_panic.join("\n"));
+            }
+            error = error.context(format!(
+                "failed to run custom build command for `{}`",
+                pkg_descr
+            ));
+            if report.is_some() {
+                error = error.context(format!(
+                    "the build script of `{}` failed in the build script sandbox\n\n\
+                     Build scripts may only read their package and write to OUT_DIR, and may \
+                     not access the network, unless their package grants it in \
+                     `[package.build-capabilities]`.",
+                    pkg_descr
+                ));
+            }
             insert_warnings_in_build_outputs(
                 build_script_outputs,
                 id,
@@ -410,10 +503,25 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
             &script_out_dir,
             &script_out_dir,
             extra_link_arg,
+            namespaced_directives,
             nightly_features_allowed,
             &targets,
+            rust_version.as_deref(),
         )?;
 
+        if !parsed_output.errors.is_empty() {
+            let error = anyhow::format_err!("{}", parsed_output.errors.join("\n")).context(
+                format!("failed to run custom build command for `{}`", pkg_descr),
+            );
+            insert_warnings_in_build_outputs(
+                build_script_outputs,
+                id,
+                metadata_hash,
+                parsed_output.warnings,
+            );
+            return Err(error);
+        }
+
         if json_messages {
             emit_build_output(state, &parsed_output, script_out_dir.as_path(), id)?;
         }
@@ -438,8 +546,10 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
                 &prev_script_out_dir,
                 &script_out_dir,
                 extra_link_arg,
+                namespaced_directives,
                 nightly_features_allowed,
                 &targets_fresh,
+                rust_version_fresh.as_deref(),
             )?,
         };
 
@@ -455,7 +565,7 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     });
 
     let mut job = if cx.bcx.build_config.build_plan {
-        Job::new_dirty(Work::noop())
+        Job::new_dirty(Work::noop(), None)
     } else {
         fingerprint::prepare_target(cx, unit, false)?
     };
@@ -491,8 +601,10 @@ impl BuildOutput {
         script_out_dir_when_generated: &Path,
         script_out_dir: &Path,
         extra_link_arg: bool,
+        namespaced_directives: bool,
         nightly_features_allowed: bool,
         targets: &[Target],
+        rust_version: Option<&str>,
     ) -> CargoResult<BuildOutput> {
         let contents = paths::read_bytes(path)?;
         BuildOutput::parse(
@@ -502,14 +614,18 @@ impl BuildOutput {
             script_out_dir_when_generated,
             script_out_dir,
             extra_link_arg,
+            namespaced_directives,
             nightly_features_allowed,
             targets,
+            rust_version,
         )
     }
 
     // Parses the output of a script.
     // The `pkg_descr` is used for error messages.
     // The `library_name` is used for determining if RUSTC_BOOTSTRAP should be allowed.
+    // The `cargo::` prefix is only allowed with `namespaced_directives`, and
+    // the `rust_version` of the package is used for determining if it is supported.
     pub fn parse(
         input: &[u8],
         // Takes String instead of InternedString so passing `unit.pkg.name()` will give a compile error.
@@ -518,8 +634,10 @@ impl BuildOutput {
         script_out_dir_when_generated: &Path,
         script_out_dir: &Path,
         extra_link_arg: bool,
+        namespaced_directives: bool,
         nightly_features_allowed: bool,
         targets: &[Target],
+        rust_version: Option<&str>,
     ) -> CargoResult<BuildOutput> {
         let mut library_paths = Vec::new();
         let mut library_links = Vec::new();
@@ -530,22 +648,51 @@ impl BuildOutput {
         let mut rerun_if_changed = Vec::new();
         let mut rerun_if_env_changed = Vec::new();
         let mut warnings = Vec::new();
+        let mut errors = Vec::new();
         let whence = format!("build script of `{}`", pkg_descr);
+        // Packages which support older versions than the one that introduced
+        // the `cargo::` prefix can't use it.
+        let namespaced_supported = !namespaced_directives
+            || match rust_version {
+                Some(rust_version) => {
+                    parse_rust_version(rust_version)? >= NAMESPACED_DIRECTIVES_VERSION
+                }
+                None => true,
+            };
 
         for line in input.split(|b| *b == b'\n') {
             let line = match str::from_utf8(line) {
                 Ok(line) => line.trim(),
                 Err(..) => continue,
             };
-            let mut iter = line.splitn(2, ':');
-            if iter.next() != Some("cargo") {
+            // Directives with the `cargo::` prefix are checked, while unknown
+            // ones with the `cargo:` prefix are metadata.
+            let namespaced_data = line
+                .strip_prefix("cargo::")
+                .filter(|_| namespaced_directives);
+            let (namespaced, data) = if let Some(data) = namespaced_data {
+                (true, data)
+            } else if let Some(data) = line.strip_prefix("cargo:") {
+                (false, data)
+            } else {
                 // skip this line since it doesn't start with "cargo:"
                 continue;
-            }
-            let data = match iter.next() {
-                Some(val) => val,
-                None => continue,
             };
+            let prefix = if namespaced { "cargo::" } else { "cargo:" };
+            if namespaced && !namespaced_supported {
+                let first = &NAMESPACED_DIRECTIVES_VERSION;
+                bail!(
+                    "the {} uses the `cargo::` prefix, which is only supported as of Rust {}.{}, \
+                     but the package's `rust-version` is {}\n\
+                     Use the `cargo:` prefix instead, or raise `rust-version` to {}.{}.",
+                    whence,
+                    first.major,
+                    first.minor,
+                    rust_version.unwrap_or_default(),
+                    first.major,
+                    first.minor
+                );
+            }
 
             // getting the `key=value` part of the line
             let mut iter = data.splitn(2, '=');
@@ -565,6 +712,18 @@ impl BuildOutput {
 
             // Keep in sync with TargetConfig::parse_links_overrides.
             match key {
+                "metadata" if namespaced => {
+                    let (key, value) = value.split_once('=').ok_or_else(|| {
+                        anyhow::format_err!(
+                            "invalid output in {}: `{}`\n\
+                             Expected a line with `cargo::metadata=KEY=VALUE`.",
+                            whence,
+                            line
+                        )
+                    })?;
+                    metadata.push((key.to_string(), value.to_string()));
+                }
+                "error" if namespaced => errors.push(value.to_string()),
                 "rustc-flags" => {
                     let (paths, links) = BuildOutput::parse_rustc_flags(&value, &whence)?;
                     library_links.extend(links.into_iter());
@@ -575,14 +734,14 @@ impl BuildOutput {
                 "rustc-link-arg-cdylib" | "rustc-cdylib-link-arg" => {
                     if !targets.iter().any(|target| target.is_cdylib()) {
                         warnings.push(format!(
-                            "cargo:{} was specified in the build script of {}, \
+                            "{}{} was specified in the build script of {}, \
                              but that package does not contain a cdylib target\n\
                              \n\
                              Allowing this was an unintended change in the 1.50 \
                              release, and may become an error in the future. \
                              For more information, see \
                              <https://github.com/rust-lang/cargo/issues/9562>.",
-                            key, pkg_descr
+                            prefix, key, pkg_descr
                         ));
                     }
                     linker_args.push((LinkType::Cdylib, value))
@@ -591,8 +750,9 @@ impl BuildOutput {
                     if extra_link_arg {
                         if !targets.iter().any(|target| target.is_bin()) {
                             bail!(
-                                "invalid instruction `cargo:{}` from {}\n\
+                                "invalid instruction `{}{}` from {}\n\
                                  The package {} does not have a bin target.",
+                                prefix,
                                 key,
                                 whence,
                                 pkg_descr
@@ -600,7 +760,7 @@ impl BuildOutput {
                         }
                         linker_args.push((LinkType::Bin, value));
                     } else {
-                        warnings.push(format!("cargo:{} requires -Zextra-link-arg flag", key));
+                        warnings.push(format!("{}{} requires -Zextra-link-arg flag", prefix, key));
                     }
                 }
                 "rustc-link-arg-bin" => {
@@ -609,11 +769,13 @@ impl BuildOutput {
                         let bin_name = parts.next().unwrap().to_string();
                         let arg = parts.next().ok_or_else(|| {
                             anyhow::format_err!(
-                                "invalid instruction `cargo:{}={}` from {}\n\
-                                 The instruction should have the form cargo:{}=BIN=ARG",
+                                "invalid instruction `{}{}={}` from {}\n\
+                                 The instruction should have the form {}{}=BIN=ARG",
+                                prefix,
                                 key,
                                 value,
                                 whence,
+                                prefix,
                                 key
                             )
                         })?;
@@ -622,8 +784,9 @@ impl BuildOutput {
                             .any(|target| target.is_bin() && target.name() == bin_name)
                         {
                             bail!(
-                                "invalid instruction `cargo:{}` from {}\n\
+                                "invalid instruction `{}{}` from {}\n\
                                  The package {} does not have a bin target with the name `{}`.",
+                                prefix,
                                 key,
                                 whence,
                                 pkg_descr,
@@ -632,14 +795,14 @@ impl BuildOutput {
                         }
                         linker_args.push((LinkType::SingleBin(bin_name), arg.to_string()));
                     } else {
-                        warnings.push(format!("cargo:{} requires -Zextra-link-arg flag", key));
+                        warnings.push(format!("{}{} requires -Zextra-link-arg flag", prefix, key));
                     }
                 }
                 "rustc-link-arg" => {
                     if extra_link_arg {
                         linker_args.push((LinkType::All, value));
                     } else {
-                        warnings.push(format!("cargo:{} requires -Zextra-link-arg flag", key));
+                        warnings.push(format!("{}{} requires -Zextra-link-arg flag", prefix, key));
                     }
                 }
                 "rustc-cfg" => cfgs.push(value.to_string()),
@@ -693,6 +856,15 @@ impl BuildOutput {
                 "warning" => warnings.push(value.to_string()),
                 "rerun-if-changed" => rerun_if_changed.push(PathBuf::from(value)),
                 "rerun-if-env-changed" => rerun_if_env_changed.push(value.to_string()),
+                _ if namespaced => bail!(
+                    "invalid output in {}: `{}`\n\
+                     Unknown directive `cargo::{}`. Metadata for dependents is passed \
+                     with `cargo::metadata={}=VALUE`.",
+                    whence,
+                    line,
+                    key,
+                    key
+                ),
                 _ => metadata.push((key.to_string(), value.to_string())),
             }
         }
@@ -707,6 +879,7 @@ impl BuildOutput {
             rerun_if_changed,
             rerun_if_env_changed,
             warnings,
+            errors,
         })
     }
 
@@ -936,6 +1109,7 @@ fn prev_build_output(cx: &mut Context<'_, '_>, unit: &Unit) -> (Option<BuildOutp
         .unwrap_or_else(|_| script_out_dir.clone());
 
     let extra_link_arg = cx.bcx.config.cli_unstable().extra_link_arg;
+    let namespaced_directives = cx.bcx.config.cli_unstable().namespaced_directives;
 
     (
         BuildOutput::parse_file(
@@ -945,8 +1119,10 @@ fn prev_build_output(cx: &mut Context<'_, '_>, unit: &Unit) -> (Option<BuildOutp
             &prev_script_out_dir,
             &script_out_dir,
             extra_link_arg,
+            namespaced_directives,
             cx.bcx.config.nightly_features_allowed,
             unit.pkg.targets(),
+            unit.pkg.rust_version(),
         )
         .ok(),
         prev_script_out_dir,
@@ -103,9 +103,10 @@
 //! - A file with a 16 hex-digit hash. This is the Fingerprint hash, used for
 //!   quick loading and comparison.
 //! - A `.json` file that contains details about the Fingerprint. This is only
-//!   used to log details about *why* a fingerprint is considered dirty.
-//!   `CARGO_LOG=cargo::core::compiler::fingerprint=trace cargo build` can be
-//!   used to display this log information.
+//!   used to find out *why* a fingerprint is considered dirty, see
+//!   [`DirtyReason`]. `cargo build -v` shows the reason for each rebuilt
+//!   unit, and `CARGO_LOG=cargo::core::compiler::fingerprint=trace cargo build`
+//!   can be used to display more detailed log information.
 //! - A "dep-info" file which is a translation of rustc's `*.d` dep-info files
 //!   to a Cargo-specific format that tweaks file names and is optimized for
 //!   reading quickly.
@@ -207,6 +208,21 @@
 //! build, so it takes a conservative approach of assuming the file was *not*
 //! included, and it should be rebuilt during the next build.
 //!
+//! #### Checksum freshness
+//!
+//! With `-Zchecksum-freshness`, `translate_dep_info` additionally records the
+//! size and SHA-256 hash of each source file listed in the dep-info. When the
+//! mtime of such a file says that the unit is stale, `find_stale_file` hashes
+//! the file and only considers it changed if the contents differ. This allows
+//! reusing a target directory restored from a cache after a fresh checkout,
+//! where every source file looks newer than the build.
+//!
+//! Files modified after the build started are not given a checksum, since the
+//! contents hashed after the build may not be what the compiler saw. Those
+//! are always checked by mtime alone. Files in the target directory (such as
+//! generated sources or, with `-Zbinary-dep-depinfo`, dependency artifacts)
+//! are also left out, as they are expected to be restored with their mtimes.
+//!
 //! #### Rustdoc mtime handling
 //!
 //! Rustdoc does not emit a dep-info file, so Cargo currently has a relatively
@@ -228,7 +244,14 @@
 //! In the new-style, each `rerun-if` directive is translated to the
 //! corresponding `LocalFingerprint` variant. The `RerunIfChanged` variant
 //! compares the mtime of the given filenames against the mtime of the
-//! "output" file.
+//! "output" file. Directories are scanned recursively, and glob patterns are
+//! expanded, with the matching files recorded in the fingerprint so that
+//! adding or removing one reruns the build script.
+//!
+//! With `-Zchecksum-freshness`, the `RerunIfChanged` variant instead records
+//! the checksum of every file, which is part of the fingerprint hash, and
+//! mtimes aren't used. Files modified while the build script runs are
+//! recorded without a checksum, so it runs again next time.
 //!
 //! Similar to normal units, the build script "output" file mtime is rewound
 //! to the time just before the build script is executed to handle mid-build
@@ -313,6 +336,7 @@
 //! <https://github.com/rust-lang/cargo/issues?q=is%3Aissue+is%3Aopen+label%3AA-rebuild-detection>
 
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryInto;
 use std::env;
 use std::hash::{self, Hash, Hasher};
@@ -322,7 +346,7 @@ use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
 use anyhow::{bail, format_err, Context as _};
-use cargo_util::{paths, ProcessBuilder};
+use cargo_util::{paths, ProcessBuilder, Sha256};
 use filetime::FileTime;
 use log::{debug, info};
 use serde::de;
@@ -334,6 +358,7 @@ use crate::core::Package;
 use crate::util;
 use crate::util::errors::CargoResult;
 use crate::util::interning::InternedString;
+use crate::util::restricted_names::is_glob_pattern;
 use crate::util::{internal, path_args, profile, StableHasher};
 use crate::CARGO_ENV;
 
@@ -371,8 +396,7 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
     // information about failed comparisons to aid in debugging.
     let fingerprint = calculate(cx, unit)?;
     let mtime_on_use = cx.bcx.config.cli_unstable().mtime_on_use;
-    let compare = compare_old_fingerprint(&loc, &*fingerprint, mtime_on_use);
-    log_compare(unit, &compare);
+    let dirty_reason = compare_old_fingerprint(unit, &loc, &*fingerprint, mtime_on_use);
 
     // If our comparison failed (e.g., we're going to trigger a rebuild of this
     // crate), then we also ensure the source of the crate passes all
@@ -384,7 +408,7 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
     // directory sources which will use this hook to perform an integrity check
     // on all files in the source to ensure they haven't changed. If they have
     // changed then an error is issued.
-    if compare.is_err() {
+    if dirty_reason.is_some() {
         let source_id = unit.pkg.package_id().source_id();
         let sources = bcx.packages.sources();
         let source = sources
@@ -393,8 +417,26 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
         source.verify(unit.pkg.package_id())?;
     }
 
-    if compare.is_ok() && !force {
-        return Ok(Job::new_fresh());
+    let dirty_reason = match dirty_reason {
+        Some(dirty_reason) => dirty_reason,
+        None if force => DirtyReason::Forced,
+        None => return Ok(Job::new_fresh()),
+    };
+
+    // A unit that isn't up to date here may have been built by another
+    // workspace using the same shared build cache, in which case it's copied
+    // from there and is then fresh.
+    let shared_cache = match cx.shared_cache.clone() {
+        Some(cache) if !force => cache
+            .entry(cx, unit, fingerprint.hash_u64())?
+            .map(|entry| (cache, entry)),
+        _ => None,
+    };
+    if let Some((cache, entry)) = &shared_cache {
+        if cache.restore(bcx.config, entry)? {
+            write_fingerprint(&loc, &fingerprint)?;
+            return Ok(Job::new_fresh());
+        }
     }
 
     // Clear out the old fingerprint file if it exists. This protects when
@@ -456,7 +498,7 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
             // here. See documentation on `build_script_local_fingerprints`
             // below for more information. Despite this just try to proceed and
             // hobble along if it happens to return `Some`.
-            if let Some(new_local) = (gen_local)(&deps, None)? {
+            if let Some(new_local) = (gen_local)(&deps, None, true)? {
                 *fingerprint.local.lock().unwrap() = new_local;
             }
 
@@ -465,8 +507,12 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
     } else {
         Work::new(move |_| write_fingerprint(&loc, &fingerprint))
     };
+    let write_fingerprint = match shared_cache {
+        Some((cache, entry)) => write_fingerprint.then(cache.store(entry)),
+        None => write_fingerprint,
+    };
 
-    Ok(Job::new_dirty(write_fingerprint))
+    Ok(Job::new_dirty(write_fingerprint, Some(dirty_reason)))
 }
 
 /// Dependency edge information for fingerprints. This is generated for each
@@ -559,12 +605,23 @@ pub struct Fingerprint {
 }
 
 /// Indication of the status on the filesystem for a particular unit.
-enum FsStatus {
+#[derive(Clone, Debug)]
+pub enum FsStatus {
     /// This unit is to be considered stale, even if hash information all
     /// matches. The filesystem inputs have changed (or are missing) and the
     /// unit needs to subsequently be recompiled.
     Stale,
 
+    /// This unit is stale because one of its own inputs or outputs changed
+    /// or is missing.
+    StaleItem(StaleItem),
+
+    /// This unit is stale because a dependency was rebuilt after it.
+    StaleDependency { name: InternedString },
+
+    /// This unit is stale because a dependency is stale itself.
+    StaleDepFingerprint { name: InternedString },
+
     /// This unit is up-to-date. All outputs and their corresponding mtime are
     /// listed in the payload here for other dependencies to compare against.
     UpToDate { mtimes: HashMap<PathBuf, FileTime> },
@@ -574,7 +631,10 @@ impl FsStatus {
     fn up_to_date(&self) -> bool {
         match self {
             FsStatus::UpToDate { .. } => true,
-            FsStatus::Stale => false,
+            FsStatus::Stale
+            | FsStatus::StaleItem(_)
+            | FsStatus::StaleDependency { .. }
+            | FsStatus::StaleDepFingerprint { .. } => false,
         }
     }
 }
@@ -669,9 +729,21 @@ enum LocalFingerprint {
     ///
     /// This is considered up-to-date if all of the `paths` are older than
     /// `output`, otherwise we need to recompile.
+    ///
+    /// With `-Zrerun-if-changed-glob`, the paths which are glob patterns are
+    /// listed in `patterns` instead, and expanded to the files in `matches`,
+    /// relative to `pkg.root()` as well. With `-Zchecksum-freshness`,
+    /// `checksums` lists every file in `paths` and `matches`, including those
+    /// in directories, and is used instead of their mtimes.
     RerunIfChanged {
         output: PathBuf,
         paths: Vec<PathBuf>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        patterns: Vec<PathBuf>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        matches: Vec<PathBuf>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        checksums: Option<BTreeMap<PathBuf, Option<FileChecksum>>>,
     },
 
     /// This represents a single `rerun-if-env-changed` annotation printed by a
@@ -681,7 +753,8 @@ enum LocalFingerprint {
     RerunIfEnvChanged { var: String, val: Option<String> },
 }
 
-enum StaleItem {
+#[derive(Clone, Debug)]
+pub enum StaleItem {
     MissingFile(PathBuf),
     ChangedFile {
         reference: PathBuf,
@@ -753,16 +826,43 @@ impl LocalFingerprint {
                         current,
                     }));
                 }
-                Ok(find_stale_file(mtime_cache, &dep_info, info.files.iter()))
+                Ok(find_stale_file(
+                    mtime_cache,
+                    &dep_info,
+                    info.files.iter(),
+                    &info.checksums,
+                ))
             }
 
             // We need to verify that no paths listed in `paths` are newer than
             // the `output` path itself, or the last time the build script ran.
-            LocalFingerprint::RerunIfChanged { output, paths } => Ok(find_stale_file(
-                mtime_cache,
-                &target_root.join(output),
-                paths.iter().map(|p| pkg_root.join(p)),
-            )),
+            LocalFingerprint::RerunIfChanged {
+                output,
+                paths,
+                matches,
+                checksums,
+                ..
+            } => {
+                let output = target_root.join(output);
+                let literal_paths = paths.iter().map(|p| pkg_root.join(p));
+                if checksums.is_none() {
+                    let paths = literal_paths.chain(matches.iter().map(|p| pkg_root.join(p)));
+                    return Ok(find_stale_file(
+                        mtime_cache,
+                        &output,
+                        paths,
+                        &HashMap::new(),
+                    ));
+                }
+                // The checksums are part of the hash, so only files which
+                // don't exist are left, which always rerun the build script.
+                for path in std::iter::once(output).chain(literal_paths) {
+                    if !path.exists() {
+                        return Ok(Some(StaleItem::MissingFile(path)));
+                    }
+                }
+                Ok(None)
+            }
 
             // These have no dependencies on the filesystem, and their values
             // are included natively in the `Fingerprint` hash so nothing
@@ -825,58 +925,55 @@ impl Fingerprint {
     /// serialized to filesystem.
     ///
     /// The purpose of this is exclusively to produce a diagnostic message
-    /// indicating why we're recompiling something. This function always returns
-    /// an error, it will never return success.
-    fn compare(&self, old: &Fingerprint) -> CargoResult<()> {
+    /// indicating why we're recompiling something. It is only called once
+    /// the two fingerprints are known to differ.
+    fn compare(&self, old: &Fingerprint) -> DirtyReason {
         if self.rustc != old.rustc {
-            bail!("rust compiler has changed")
+            return DirtyReason::RustcChanged;
         }
         if self.features != old.features {
-            bail!(
-                "features have changed: previously {}, now {}",
-                old.features,
-                self.features
-            )
+            return DirtyReason::FeaturesChanged {
+                old: old.features.clone(),
+                new: self.features.clone(),
+            };
         }
         if self.target != old.target {
-            bail!("target configuration has changed")
+            return DirtyReason::TargetConfigurationChanged;
         }
         if self.path != old.path {
-            bail!("path to the source has changed")
+            return DirtyReason::PathToSourceChanged;
         }
         if self.profile != old.profile {
-            bail!("profile configuration has changed")
+            return DirtyReason::ProfileConfigurationChanged;
         }
         if self.rustflags != old.rustflags {
-            bail!(
-                "RUSTFLAGS has changed: previously {:?}, now {:?}",
-                old.rustflags,
-                self.rustflags
-            )
+            return DirtyReason::RustflagsChanged {
+                old: old.rustflags.clone(),
+                new: self.rustflags.clone(),
+            };
         }
         if self.metadata != old.metadata {
-            bail!("metadata changed")
+            return DirtyReason::MetadataChanged;
         }
         if self.config != old.config {
-            bail!("configuration settings have changed")
+            return DirtyReason::ConfigSettingsChanged;
         }
         if self.compile_kind != old.compile_kind {
-            bail!("compile kind (rustc target) changed")
+            return DirtyReason::CompileKindChanged;
         }
         let my_local = self.local.lock().unwrap();
         let old_local = old.local.lock().unwrap();
         if my_local.len() != old_local.len() {
-            bail!("local lens changed");
+            return DirtyReason::LocalLengthsChanged;
         }
         for (new, old) in my_local.iter().zip(old_local.iter()) {
             match (new, old) {
                 (LocalFingerprint::Precalculated(a), LocalFingerprint::Precalculated(b)) => {
                     if a != b {
-                        bail!(
-                            "precalculated components have changed: previously {}, now {}",
-                            b,
-                            a
-                        )
+                        return DirtyReason::PrecalculatedComponentsChanged {
+                            old: b.to_string(),
+                            new: a.to_string(),
+                        };
                     }
                 }
                 (
@@ -884,36 +981,66 @@ impl Fingerprint {
                     LocalFingerprint::CheckDepInfo { dep_info: bdep },
                 ) => {
                     if adep != bdep {
-                        bail!(
-                            "dep info output changed: previously {:?}, now {:?}",
-                            bdep,
-                            adep
-                        )
+                        return DirtyReason::DepInfoOutputChanged {
+                            old: bdep.clone(),
+                            new: adep.clone(),
+                        };
                     }
                 }
                 (
                     LocalFingerprint::RerunIfChanged {
                         output: aout,
                         paths: apaths,
+                        patterns: apatterns,
+                        matches: amatches,
+                        checksums: achecksums,
                     },
                     LocalFingerprint::RerunIfChanged {
                         output: bout,
                         paths: bpaths,
+                        patterns: bpatterns,
+                        matches: bmatches,
+                        checksums: bchecksums,
                     },
                 ) => {
                     if aout != bout {
-                        bail!(
-                            "rerun-if-changed output changed: previously {:?}, now {:?}",
-                            bout,
-                            aout
-                        )
+                        return DirtyReason::RerunIfChangedOutputFileChanged {
+                            old: bout.clone(),
+                            new: aout.clone(),
+                        };
                     }
                     if apaths != bpaths {
-                        bail!(
-                            "rerun-if-changed output changed: previously {:?}, now {:?}",
-                            bpaths,
-                            apaths,
-                        )
+                        return DirtyReason::RerunIfChangedOutputPathsChanged {
+                            old: bpaths.clone(),
+                            new: apaths.clone(),
+                        };
+                    }
+                    if apatterns != bpatterns {
+                        return DirtyReason::RerunIfChangedOutputPathsChanged {
+                            old: bpatterns.clone(),
+                            new: apatterns.clone(),
+                        };
+                    }
+                    if amatches != bmatches {
+                        return DirtyReason::RerunIfChangedMatchesChanged {
+                            old: bmatches.clone(),
+                            new: amatches.clone(),
+                        };
+                    }
+                    match (achecksums, bchecksums) {
+                        (Some(achecksums), Some(bchecksums)) => {
+                            let changed = achecksums
+                                .keys()
+                                .chain(bchecksums.keys())
+                                .find(|p| achecksums.get(*p) != bchecksums.get(*p));
+                            if let Some(path) = changed {
+                                return DirtyReason::RerunIfChangedFileChanged {
+                                    path: path.clone(),
+                                };
+                            }
+                        }
+                        (None, None) => {}
+                        _ => return DirtyReason::RerunIfChangedChecksumsChanged,
                     }
                 }
                 (
@@ -927,57 +1054,56 @@ impl Fingerprint {
                     },
                 ) => {
                     if *akey != *bkey {
-                        bail!("env vars changed: previously {}, now {}", bkey, akey);
+                        return DirtyReason::EnvVarsChanged {
+                            old: bkey.clone(),
+                            new: akey.clone(),
+                        };
                     }
                     if *avalue != *bvalue {
-                        bail!(
-                            "env var `{}` changed: previously {:?}, now {:?}",
-                            akey,
-                            bvalue,
-                            avalue
-                        )
+                        return DirtyReason::EnvVarChanged {
+                            name: akey.clone(),
+                            old_value: bvalue.clone(),
+                            new_value: avalue.clone(),
+                        };
+                    }
+                }
+                (a, b) => {
+                    return DirtyReason::LocalFingerprintTypeChanged {
+                        old: b.kind(),
+                        new: a.kind(),
                     }
                 }
-                (a, b) => bail!(
-                    "local fingerprint type has changed ({} => {})",
-                    b.kind(),
-                    a.kind()
-                ),
             }
         }
 
         if self.deps.len() != old.deps.len() {
-            bail!("number of dependencies has changed")
+            return DirtyReason::NumberOfDependenciesChanged {
+                old: old.deps.len(),
+                new: self.deps.len(),
+            };
         }
         for (a, b) in self.deps.iter().zip(old.deps.iter()) {
             if a.name != b.name {
-                let e = format_err!("`{}` != `{}`", a.name, b.name)
-                    .context("unit dependency name changed");
-                return Err(e);
+                return DirtyReason::UnitDependencyNameChanged {
+                    old: b.name,
+                    new: a.name,
+                };
             }
 
             if a.fingerprint.hash_u64() != b.fingerprint.hash_u64() {
-                let e = format_err!(
-                    "new ({}/{:x}) != old ({}/{:x})",
-                    a.name,
-                    a.fingerprint.hash_u64(),
-                    b.name,
-                    b.fingerprint.hash_u64()
-                )
-                .context("unit dependency information changed");
-                return Err(e);
+                return DirtyReason::UnitDependencyInfoChanged { name: a.name };
             }
         }
 
         if !self.fs_status.up_to_date() {
-            bail!("current filesystem status shows we're outdated");
+            return DirtyReason::FsStatusOutdated(self.fs_status.clone());
         }
 
         // This typically means some filesystem modifications happened or
         // something transitive was odd. In general we should strive to provide
         // a better error message than this, so if you see this message a lot it
         // likely means this method needs to be updated!
-        bail!("two fingerprint comparison turned up nothing obvious");
+        DirtyReason::NothingObvious
     }
 
     /// Dynamically inspect the local filesystem to update the `fs_status` field
@@ -1011,6 +1137,7 @@ impl Fingerprint {
                 // exists, so leave ourselves as stale and bail out.
                 Err(e) => {
                     debug!("failed to get mtime of {:?}: {}", output, e);
+                    self.fs_status = FsStatus::StaleItem(StaleItem::MissingFile(output.clone()));
                     return Ok(());
                 }
             };
@@ -1038,7 +1165,13 @@ impl Fingerprint {
             let dep_mtimes = match &dep.fingerprint.fs_status {
                 FsStatus::UpToDate { mtimes } => mtimes,
                 // If our dependency is stale, so are we, so bail out.
-                FsStatus::Stale => return Ok(()),
+                FsStatus::Stale
+                | FsStatus::StaleItem(_)
+                | FsStatus::StaleDependency { .. }
+                | FsStatus::StaleDepFingerprint { .. } => {
+                    self.fs_status = FsStatus::StaleDepFingerprint { name: dep.name };
+                    return Ok(());
+                }
             };
 
             // If our dependency edge only requires the rmeta file to be present
@@ -1076,6 +1209,7 @@ impl Fingerprint {
                     "dependency on `{}` is newer than we are {} > {} {:?}",
                     dep.name, dep_mtime, max_mtime, pkg_root
                 );
+                self.fs_status = FsStatus::StaleDependency { name: dep.name };
                 return Ok(());
             }
         }
@@ -1089,6 +1223,7 @@ impl Fingerprint {
                 local.find_stale_item(mtime_cache, pkg_root, target_root, cargo_exe)?
             {
                 item.log();
+                self.fs_status = FsStatus::StaleItem(item);
                 return Ok(());
             }
         }
@@ -1212,6 +1347,272 @@ impl StaleItem {
     }
 }
 
+/// The reason a unit needs to be rebuilt, as found by comparing its
+/// fingerprint with the one saved by the previous build.
+///
+/// This is shown in verbose output, and in `dirty-unit` JSON messages.
+#[derive(Clone, Debug)]
+pub enum DirtyReason {
+    RustcChanged,
+    FeaturesChanged {
+        old: String,
+        new: String,
+    },
+    TargetConfigurationChanged,
+    PathToSourceChanged,
+    ProfileConfigurationChanged,
+    RustflagsChanged {
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    MetadataChanged,
+    ConfigSettingsChanged,
+    CompileKindChanged,
+    LocalLengthsChanged,
+    PrecalculatedComponentsChanged {
+        old: String,
+        new: String,
+    },
+    DepInfoOutputChanged {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    RerunIfChangedOutputFileChanged {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    RerunIfChangedOutputPathsChanged {
+        old: Vec<PathBuf>,
+        new: Vec<PathBuf>,
+    },
+    RerunIfChangedMatchesChanged {
+        old: Vec<PathBuf>,
+        new: Vec<PathBuf>,
+    },
+    RerunIfChangedFileChanged {
+        path: PathBuf,
+    },
+    RerunIfChangedChecksumsChanged,
+    EnvVarsChanged {
+        old: String,
+        new: String,
+    },
+    EnvVarChanged {
+        name: String,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    },
+    LocalFingerprintTypeChanged {
+        old: &'static str,
+        new: &'static str,
+    },
+    NumberOfDependenciesChanged {
+        old: usize,
+        new: usize,
+    },
+    UnitDependencyNameChanged {
+        old: InternedString,
+        new: InternedString,
+    },
+    UnitDependencyInfoChanged {
+        name: InternedString,
+    },
+    FsStatusOutdated(FsStatus),
+    NothingObvious,
+    /// The unit was rebuilt because a rebuild was requested, for example by
+    /// `cargo fix`.
+    Forced,
+    /// There is no usable fingerprint from a previous build.
+    FreshBuild,
+}
+
+impl DirtyReason {
+    /// Returns whether the unit is built for the first time, in which case
+    /// there isn't much to explain.
+    pub fn is_fresh_build(&self) -> bool {
+        matches!(self, DirtyReason::FreshBuild)
+    }
+
+    /// A stable identifier of this kind of reason, used in JSON messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DirtyReason::RustcChanged => "rustc-changed",
+            DirtyReason::FeaturesChanged { .. } => "features-changed",
+            DirtyReason::TargetConfigurationChanged => "target-configuration-changed",
+            DirtyReason::PathToSourceChanged => "path-to-source-changed",
+            DirtyReason::ProfileConfigurationChanged => "profile-configuration-changed",
+            DirtyReason::RustflagsChanged { .. } => "rustflags-changed",
+            DirtyReason::MetadataChanged => "metadata-changed",
+            DirtyReason::ConfigSettingsChanged => "config-settings-changed",
+            DirtyReason::CompileKindChanged => "compile-kind-changed",
+            DirtyReason::LocalLengthsChanged => "local-lengths-changed",
+            DirtyReason::PrecalculatedComponentsChanged { .. } => {
+                "precalculated-components-changed"
+            }
+            DirtyReason::DepInfoOutputChanged { .. } => "dep-info-output-changed",
+            DirtyReason::RerunIfChangedOutputFileChanged { .. } => {
+                "rerun-if-changed-output-file-changed"
+            }
+            DirtyReason::RerunIfChangedOutputPathsChanged { .. } => {
+                "rerun-if-changed-output-paths-changed"
+            }
+            DirtyReason::RerunIfChangedMatchesChanged { .. } => "rerun-if-changed-matches-changed",
+            DirtyReason::RerunIfChangedFileChanged { .. } => "rerun-if-changed-file-changed",
+            DirtyReason::RerunIfChangedChecksumsChanged => "rerun-if-changed-checksums-changed",
+            DirtyReason::EnvVarsChanged { .. } => "env-vars-changed",
+            DirtyReason::EnvVarChanged { .. } => "env-var-changed",
+            DirtyReason::LocalFingerprintTypeChanged { .. } => "local-fingerprint-type-changed",
+            DirtyReason::NumberOfDependenciesChanged { .. } => "number-of-dependencies-changed",
+            DirtyReason::UnitDependencyNameChanged { .. } => "unit-dependency-name-changed",
+            DirtyReason::UnitDependencyInfoChanged { .. } => "unit-dependency-info-changed",
+            DirtyReason::FsStatusOutdated(status) => match status {
+                FsStatus::StaleItem(StaleItem::MissingFile(_)) => "file-missing",
+                FsStatus::StaleItem(StaleItem::ChangedFile { .. }) => "file-changed",
+                FsStatus::StaleItem(StaleItem::ChangedEnv { .. }) => "env-var-changed",
+                FsStatus::StaleDependency { .. } => "dependency-rebuilt",
+                FsStatus::StaleDepFingerprint { .. } => "dependency-dirty",
+                FsStatus::Stale | FsStatus::UpToDate { .. } => "fs-status-outdated",
+            },
+            DirtyReason::NothingObvious => "nothing-obvious",
+            DirtyReason::Forced => "forced",
+            DirtyReason::FreshBuild => "fresh-build",
+        }
+    }
+
+    /// Describes the reason for humans. Paths inside of `root`, the root of
+    /// the package being rebuilt, are shown relative to it.
+    pub fn description(&self, root: &Path) -> String {
+        let path = |path: &Path| {
+            path.strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        match self {
+            DirtyReason::RustcChanged => "the toolchain changed".to_string(),
+            DirtyReason::FeaturesChanged { old, new } => {
+                format!("the list of features changed from {} to {}", old, new)
+            }
+            DirtyReason::TargetConfigurationChanged => {
+                "the target configuration changed".to_string()
+            }
+            DirtyReason::PathToSourceChanged => "the path to the source changed".to_string(),
+            DirtyReason::ProfileConfigurationChanged => {
+                "the profile configuration changed".to_string()
+            }
+            DirtyReason::RustflagsChanged { old, new } => {
+                format!("the rustflags changed from {:?} to {:?}", old, new)
+            }
+            DirtyReason::MetadataChanged => "the package metadata changed".to_string(),
+            DirtyReason::ConfigSettingsChanged => "the config settings changed".to_string(),
+            DirtyReason::CompileKindChanged => "the rustc compile kind changed".to_string(),
+            DirtyReason::LocalLengthsChanged => "the number of tracked inputs changed".to_string(),
+            DirtyReason::PrecalculatedComponentsChanged { old, new } => {
+                format!(
+                    "the precalculated components changed from `{}` to `{}`",
+                    old, new
+                )
+            }
+            DirtyReason::DepInfoOutputChanged { old, new } => format!(
+                "the dependency info output changed from `{}` to `{}`",
+                path(old),
+                path(new)
+            ),
+            DirtyReason::RerunIfChangedOutputFileChanged { old, new } => format!(
+                "the rerun-if-changed output file changed from `{}` to `{}`",
+                path(old),
+                path(new)
+            ),
+            DirtyReason::RerunIfChangedOutputPathsChanged { old, new } => {
+                let paths = |paths: &[PathBuf]| {
+                    paths.iter().map(|p| path(p)).collect::<Vec<_>>().join(", ")
+                };
+                format!(
+                    "the rerun-if-changed paths changed from [{}] to [{}]",
+                    paths(old),
+                    paths(new)
+                )
+            }
+            DirtyReason::RerunIfChangedMatchesChanged { old, new } => {
+                let paths = |paths: &[PathBuf]| {
+                    paths.iter().map(|p| path(p)).collect::<Vec<_>>().join(", ")
+                };
+                format!(
+                    "the files matching the rerun-if-changed patterns changed from [{}] to [{}]",
+                    paths(old),
+                    paths(new)
+                )
+            }
+            DirtyReason::RerunIfChangedFileChanged { path: file } => {
+                format!("the contents of `{}` changed", path(file))
+            }
+            DirtyReason::RerunIfChangedChecksumsChanged => {
+                "the rerun-if-changed files started or stopped being checksummed".to_string()
+            }
+            DirtyReason::EnvVarsChanged { old, new } => format!(
+                "the rerun-if-env-changed variables changed from `{}` to `{}`",
+                old, new
+            ),
+            DirtyReason::EnvVarChanged {
+                name,
+                old_value,
+                new_value,
+            } => {
+                let value = |v: &Option<String>| match v {
+                    Some(v) => format!("`{}`", v),
+                    None => "unset".to_string(),
+                };
+                format!(
+                    "the environment variable `{}` changed from {} to {}",
+                    name,
+                    value(old_value),
+                    value(new_value)
+                )
+            }
+            DirtyReason::LocalFingerprintTypeChanged { old, new } => {
+                format!("the way inputs are tracked changed from {} to {}", old, new)
+            }
+            DirtyReason::NumberOfDependenciesChanged { old, new } => {
+                format!("the number of dependencies changed from {} to {}", old, new)
+            }
+            DirtyReason::UnitDependencyNameChanged { old, new } => {
+                format!(
+                    "the name of a dependency changed from `{}` to `{}`",
+                    old, new
+                )
+            }
+            DirtyReason::UnitDependencyInfoChanged { name } => {
+                format!("the dependency `{}` changed", name)
+            }
+            DirtyReason::FsStatusOutdated(status) => match status {
+                FsStatus::StaleItem(StaleItem::MissingFile(missing)) => {
+                    format!("the file `{}` is missing", path(missing))
+                }
+                FsStatus::StaleItem(StaleItem::ChangedFile { stale, .. }) => {
+                    format!("the file `{}` has changed", path(stale))
+                }
+                FsStatus::StaleItem(StaleItem::ChangedEnv { var, .. }) => {
+                    format!("the environment variable `{}` changed", var)
+                }
+                FsStatus::StaleDependency { name, .. } => {
+                    format!("the dependency `{}` was rebuilt", name)
+                }
+                FsStatus::StaleDepFingerprint { name } => {
+                    format!("the fingerprint of the dependency `{}` changed", name)
+                }
+                FsStatus::Stale | FsStatus::UpToDate { .. } => {
+                    "the files on disk are outdated".to_string()
+                }
+            },
+            DirtyReason::NothingObvious => {
+                "the fingerprint comparison turned up nothing obvious".to_string()
+            }
+            DirtyReason::Forced => "the rebuild was forced".to_string(),
+            DirtyReason::FreshBuild => "the unit was never built before".to_string(),
+        }
+    }
+}
+
 /// Calculates the fingerprint for a `unit`.
 ///
 /// This fingerprint is used by Cargo to learn about when information such as:
@@ -1330,6 +1731,10 @@ fn calculate_normal(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Finger
     if let Some(allow_features) = &cx.bcx.config.cli_unstable().allow_features {
         allow_features.hash(&mut config);
     }
+    // The standard library is rebuilt in place when its settings change.
+    if let Some(sysroot) = cx.bcx.std_sysroots.get(&unit.kind) {
+        sysroot.hash(&mut config);
+    }
     let compile_kind = unit.kind.fingerprint_hash();
     Ok(Fingerprint {
         rustc: util::hash_u64(&cx.bcx.rustc().verbose_version),
@@ -1373,6 +1778,7 @@ fn calculate_run_custom_build(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoRes
                 )
             })
         }),
+        false,
     )?
     .unwrap();
     let output = deps.build_script_output.clone();
@@ -1435,6 +1841,10 @@ fn calculate_run_custom_build(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoRes
 /// "first class" and not an `Option` but something that can be sent between
 /// threads. In any case, it's a bug for now.
 ///
+/// The third argument is whether the build script just finished, in which
+/// case files it may have seen before they were modified aren't checksummed
+/// with `-Zchecksum-freshness`.
+///
 /// This isn't the greatest of interfaces, and if there's suggestions to
 /// improve please do so!
 ///
@@ -1447,6 +1857,7 @@ fn build_script_local_fingerprints(
         dyn FnOnce(
                 &BuildDeps,
                 Option<&dyn Fn() -> CargoResult<String>>,
+                bool,
             ) -> CargoResult<Option<Vec<LocalFingerprint>>>
             + Send,
     >,
@@ -1459,7 +1870,7 @@ fn build_script_local_fingerprints(
         debug!("override local fingerprints deps {}", unit.pkg);
         return (
             Box::new(
-                move |_: &BuildDeps, _: Option<&dyn Fn() -> CargoResult<String>>| {
+                move |_: &BuildDeps, _: Option<&dyn Fn() -> CargoResult<String>>, _: bool| {
                     Ok(Some(vec![fingerprint]))
                 },
             ),
@@ -1476,37 +1887,56 @@ fn build_script_local_fingerprints(
     // obvious.
     let pkg_root = unit.pkg.root().to_path_buf();
     let target_dir = target_root(cx);
-    let calculate =
-        move |deps: &BuildDeps, pkg_fingerprint: Option<&dyn Fn() -> CargoResult<String>>| {
-            if deps.rerun_if_changed.is_empty() && deps.rerun_if_env_changed.is_empty() {
-                match pkg_fingerprint {
-                    // FIXME: this is somewhat buggy with respect to docker and
-                    // weird filesystems. The `Precalculated` variant
-                    // constructed below will, for `path` dependencies, contain
-                    // a stringified version of the mtime for the local crate.
-                    // This violates one of the things we describe in this
-                    // module's doc comment, never hashing mtimes. We should
-                    // figure out a better scheme where a package fingerprint
-                    // may be a string (like for a registry) or a list of files
-                    // (like for a path dependency). Those list of files would
-                    // be stored here rather than the the mtime of them.
-                    Some(f) => {
-                        let s = f()?;
-                        debug!(
-                            "old local fingerprints deps {:?} precalculated={:?}",
-                            pkg_root, s
-                        );
-                        return Ok(Some(vec![LocalFingerprint::Precalculated(s)]));
-                    }
-                    None => return Ok(None),
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
+    let globs = cx.bcx.config.cli_unstable().rerun_if_changed_glob;
+    let calculate = move |deps: &BuildDeps,
+                          pkg_fingerprint: Option<&dyn Fn() -> CargoResult<String>>,
+                          after_run: bool| {
+        if deps.rerun_if_changed.is_empty() && deps.rerun_if_env_changed.is_empty() {
+            match pkg_fingerprint {
+                // FIXME: this is somewhat buggy with respect to docker and
+                // weird filesystems. The `Precalculated` variant
+                // constructed below will, for `path` dependencies, contain
+                // a stringified version of the mtime for the local crate.
+                // This violates one of the things we describe in this
+                // module's doc comment, never hashing mtimes. We should
+                // figure out a better scheme where a package fingerprint
+                // may be a string (like for a registry) or a list of files
+                // (like for a path dependency). Those list of files would
+                // be stored here rather than the the mtime of them.
+                Some(f) => {
+                    let s = f()?;
+                    debug!(
+                        "old local fingerprints deps {:?} precalculated={:?}",
+                        pkg_root, s
+                    );
+                    return Ok(Some(vec![LocalFingerprint::Precalculated(s)]));
                 }
+                None => return Ok(None),
             }
+        }
 
-            // Ok so now we're in "new mode" where we can have files listed as
-            // dependencies as well as env vars listed as dependencies. Process
-            // them all here.
-            Ok(Some(local_fingerprints_deps(deps, &target_dir, &pkg_root)))
+        // Ok so now we're in "new mode" where we can have files listed as
+        // dependencies as well as env vars listed as dependencies. Process
+        // them all here.
+        let checksums = match checksum_freshness {
+            // Files modified while the build script ran are recorded
+            // without a checksum, so that it runs again next time.
+            true if after_run => {
+                Some(paths::mtime(&deps.build_script_output).unwrap_or_else(|_| FileTime::zero()))
+            }
+            // Otherwise the files are checked as they are now.
+            true => Some(FileTime::from_unix_time(i64::MAX, 0)),
+            false => None,
         };
+        Ok(Some(local_fingerprints_deps(
+            deps,
+            &target_dir,
+            &pkg_root,
+            checksums,
+            globs,
+        )))
+    };
 
     // Note that `false` == "not overridden"
     (Box::new(calculate), false)
@@ -1534,10 +1964,17 @@ fn build_script_override_fingerprint(
 /// Compute the `LocalFingerprint` values for a `RunCustomBuild` unit for
 /// non-overridden new-style build scripts only. This is only used when `deps`
 /// is already known to have a nonempty `rerun-if-*` somewhere.
+///
+/// If `checksums_since` is set, the checksums of the `rerun-if-changed`
+/// files modified before then are recorded, see `-Zchecksum-freshness`. If
+/// `globs` is set, the paths which are glob patterns are expanded, see
+/// `-Zrerun-if-changed-glob`.
 fn local_fingerprints_deps(
     deps: &BuildDeps,
     target_root: &Path,
     pkg_root: &Path,
+    checksums_since: Option<FileTime>,
+    globs: bool,
 ) -> Vec<LocalFingerprint> {
     debug!("new local fingerprints deps {:?}", pkg_root);
     let mut local = Vec::new();
@@ -1551,12 +1988,21 @@ fn local_fingerprints_deps(
             .strip_prefix(target_root)
             .unwrap()
             .to_path_buf();
-        let paths = deps
+        let (patterns, paths): (Vec<PathBuf>, Vec<PathBuf>) = deps
             .rerun_if_changed
             .iter()
             .map(|p| p.strip_prefix(pkg_root).unwrap_or(p).to_path_buf())
-            .collect();
-        local.push(LocalFingerprint::RerunIfChanged { output, paths });
+            .partition(|p| globs && is_rerun_if_changed_glob(pkg_root, p));
+        let matches = rerun_if_changed_matches(pkg_root, &patterns);
+        let checksums = checksums_since
+            .map(|since| rerun_if_changed_checksums(pkg_root, paths.iter().chain(&matches), since));
+        local.push(LocalFingerprint::RerunIfChanged {
+            output,
+            paths,
+            patterns,
+            matches,
+            checksums,
+        });
     }
 
     for var in deps.rerun_if_env_changed.iter() {
@@ -1588,6 +2034,11 @@ fn write_fingerprint(loc: &Path, fingerprint: &Fingerprint) -> CargoResult<()> {
     Ok(())
 }
 
+/// Returns the hash of the fingerprint `unit` will have once it's built.
+pub fn fingerprint_hash(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<u64> {
+    Ok(calculate(cx, unit)?.hash_u64())
+}
+
 /// Prepare for work when a package starts to build
 pub fn prepare_init(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<()> {
     let new1 = cx.files().fingerprint_dir(unit);
@@ -1612,11 +2063,40 @@ fn target_root(cx: &Context<'_, '_>) -> PathBuf {
     cx.bcx.ws.target_dir().into_path_unlocked()
 }
 
+/// Returns why `unit` needs to be rebuilt, or `None` if it is up to date.
 fn compare_old_fingerprint(
+    unit: &Unit,
     loc: &Path,
     new_fingerprint: &Fingerprint,
     mtime_on_use: bool,
-) -> CargoResult<()> {
+) -> Option<DirtyReason> {
+    let compare = _compare_old_fingerprint(loc, new_fingerprint, mtime_on_use);
+    match compare {
+        Ok(None) => None,
+        Ok(Some(reason)) => {
+            info!(
+                "fingerprint dirty for {}/{:?}/{:?}",
+                unit.pkg, unit.mode, unit.target,
+            );
+            info!("    dirty: {:?}", reason);
+            Some(reason)
+        }
+        Err(e) => {
+            info!(
+                "fingerprint error for {}/{:?}/{:?}",
+                unit.pkg, unit.mode, unit.target,
+            );
+            info!("    err: {:?}", e);
+            Some(DirtyReason::FreshBuild)
+        }
+    }
+}
+
+fn _compare_old_fingerprint(
+    loc: &Path,
+    new_fingerprint: &Fingerprint,
+    mtime_on_use: bool,
+) -> CargoResult<Option<DirtyReason>> {
     let old_fingerprint_short = paths::read(loc)?;
 
     if mtime_on_use {
@@ -1629,7 +2109,7 @@ fn compare_old_fingerprint(
     let new_hash = new_fingerprint.hash_u64();
 
     if util::to_hex(new_hash) == old_fingerprint_short && new_fingerprint.fs_status.up_to_date() {
-        return Ok(());
+        return Ok(None);
     }
 
     let old_fingerprint_json = paths::read(&loc.with_extension("json"))?;
@@ -1642,21 +2122,7 @@ fn compare_old_fingerprint(
             old_fingerprint_short
         );
     }
-    let result = new_fingerprint.compare(&old_fingerprint);
-    assert!(result.is_err());
-    result
-}
-
-fn log_compare(unit: &Unit, compare: &CargoResult<()>) {
-    let ce = match compare {
-        Ok(..) => return,
-        Err(e) => e,
-    };
-    info!(
-        "fingerprint error for {}/{:?}/{:?}",
-        unit.pkg, unit.mode, unit.target,
-    );
-    info!("    err: {:?}", ce);
+    Ok(Some(new_fingerprint.compare(&old_fingerprint)))
 }
 
 /// Parses Cargo's internal `EncodedDepInfo` structure that was previously
@@ -1688,12 +2154,15 @@ pub fn parse_dep_info(
     };
     let mut ret = RustcDepInfo::default();
     ret.env = info.env;
-    for (ty, path) in info.files {
+    for (ty, path, checksum) in info.files {
         let path = match ty {
             DepInfoPathType::PackageRootRelative => pkg_root.join(path),
             // N.B. path might be absolute here in which case the join will have no effect
             DepInfoPathType::TargetRootRelative => target_root.join(path),
         };
+        if let Some(checksum) = checksum {
+            ret.checksums.insert(path.clone(), checksum);
+        }
         ret.files.push(path);
     }
     Ok(Some(ret))
@@ -1709,10 +2178,79 @@ fn pkg_fingerprint(bcx: &BuildContext<'_, '_>, pkg: &Package) -> CargoResult<Str
     source.fingerprint(pkg)
 }
 
+/// Whether a `rerun-if-changed` path is a glob pattern. Paths which exist, or
+/// aren't valid patterns, are always taken literally.
+fn is_rerun_if_changed_glob(pkg_root: &Path, path: &Path) -> bool {
+    match path.to_str() {
+        Some(p) => {
+            is_glob_pattern(p) && glob::Pattern::new(p).is_ok() && !pkg_root.join(path).exists()
+        }
+        None => false,
+    }
+}
+
+/// Expands the `rerun-if-changed` glob patterns, which are relative to
+/// `pkg_root`. Returns the sorted matches, relative to it too.
+fn rerun_if_changed_matches(pkg_root: &Path, patterns: &[PathBuf]) -> Vec<PathBuf> {
+    let root = match pkg_root.to_str() {
+        Some(root) => glob::Pattern::escape(root),
+        None => return Vec::new(),
+    };
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::new()
+    };
+    let mut matches = BTreeSet::new();
+    for pattern in patterns {
+        let pattern = Path::new(&root).join(pattern);
+        let entries = match glob::glob_with(&pattern.to_string_lossy(), options) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("invalid rerun-if-changed pattern {:?}: {}", pattern, e);
+                continue;
+            }
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let entry = match entry.strip_prefix(pkg_root) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => entry,
+            };
+            matches.insert(entry);
+        }
+    }
+    matches.into_iter().collect()
+}
+
+/// Computes the checksums of the files at the `rerun-if-changed` paths, which
+/// are relative to `pkg_root`, walking directories recursively. Files
+/// modified after `since` are recorded without a checksum.
+fn rerun_if_changed_checksums<'a>(
+    pkg_root: &Path,
+    paths: impl Iterator<Item = &'a PathBuf>,
+    since: FileTime,
+) -> BTreeMap<PathBuf, Option<FileChecksum>> {
+    let mut checksums = BTreeMap::new();
+    for path in paths {
+        let files = walkdir::WalkDir::new(pkg_root.join(path))
+            .follow_links(true)
+            .into_iter()
+            // Like `mtime_recursive`, ignore what can't be accessed.
+            .filter_map(Result::ok)
+            .filter(|e| !e.file_type().is_dir());
+        for file in files {
+            let checksum = FileChecksum::compute(file.path(), since);
+            let file = file.path().strip_prefix(pkg_root).unwrap_or(file.path());
+            checksums.insert(file.to_path_buf(), checksum);
+        }
+    }
+    checksums
+}
+
 fn find_stale_file<I>(
     mtime_cache: &mut HashMap<PathBuf, FileTime>,
     reference: &Path,
     paths: I,
+    checksums: &HashMap<PathBuf, FileChecksum>,
 ) -> Option<StaleItem>
 where
     I: IntoIterator,
@@ -1758,6 +2296,15 @@ where
             continue;
         }
 
+        // The mtime may only have changed because the file was checked out or
+        // restored again, so see if the contents are still the same.
+        if let Some(checksum) = checksums.get(path) {
+            if checksum.matches(path) {
+                debug!("{:?} is newer but its checksum is unchanged", path);
+                continue;
+            }
+        }
+
         return Some(StaleItem::ChangedFile {
             reference: reference.to_path_buf(),
             reference_mtime,
@@ -1802,6 +2349,11 @@ enum DepInfoPathType {
 /// all those files). See the module-level docs for the note about
 /// `-Zbinary-dep-depinfo` for more details on why this is done.
 ///
+/// If `checksum_since` is set, the size and hash of every file outside of the
+/// target directory which hasn't been modified after that time (the start of
+/// the compilation) is recorded as well. See the module-level docs about
+/// `-Zchecksum-freshness`.
+///
 /// The serialized Cargo format will contain a list of files, all of which are
 /// relative if they're under `root`. or absolute if they're elsewhere.
 pub fn translate_dep_info(
@@ -1812,6 +2364,7 @@ pub fn translate_dep_info(
     target_root: &Path,
     rustc_cmd: &ProcessBuilder,
     allow_package: bool,
+    checksum_since: Option<FileTime>,
 ) -> CargoResult<()> {
     let depinfo = parse_rustc_dep_info(rustc_dep_info)?;
 
@@ -1861,22 +2414,30 @@ pub fn translate_dep_info(
         // to point to non-existent paths.
         let canon_file = abs_file.canonicalize().unwrap_or_else(|_| abs_file.clone());
 
-        let (ty, path) = if let Ok(stripped) = canon_file.strip_prefix(&target_root) {
-            (DepInfoPathType::TargetRootRelative, stripped)
-        } else if let Ok(stripped) = canon_file.strip_prefix(&pkg_root) {
-            if !allow_package {
-                continue;
-            }
-            (DepInfoPathType::PackageRootRelative, stripped)
-        } else {
-            // It's definitely not target root relative, but this is an absolute path (since it was
-            // joined to rustc_cwd) and as such re-joining it later to the target root will have no
-            // effect.
-            (DepInfoPathType::TargetRootRelative, &*abs_file)
+        let (ty, path, checksum_allowed) =
+            if let Ok(stripped) = canon_file.strip_prefix(&target_root) {
+                (DepInfoPathType::TargetRootRelative, stripped, false)
+            } else if let Ok(stripped) = canon_file.strip_prefix(&pkg_root) {
+                if !allow_package {
+                    continue;
+                }
+                (DepInfoPathType::PackageRootRelative, stripped, true)
+            } else {
+                // It's definitely not target root relative, but this is an absolute path (since it was
+                // joined to rustc_cwd) and as such re-joining it later to the target root will have no
+                // effect.
+                (DepInfoPathType::TargetRootRelative, &*abs_file, true)
+            };
+        let checksum = match checksum_since {
+            Some(since) if checksum_allowed => FileChecksum::compute(&canon_file, since),
+            _ => None,
         };
-        on_disk_info.files.push((ty, path.to_owned()));
+        on_disk_info.files.push((ty, path.to_owned(), checksum));
     }
-    paths::write(cargo_dep_info, on_disk_info.serialize()?)?;
+    paths::write(
+        cargo_dep_info,
+        on_disk_info.serialize(checksum_since.is_some())?,
+    )?;
     Ok(())
 }
 
@@ -1892,6 +2453,45 @@ pub struct RustcDepInfo {
     /// means that the env var wasn't actually set and the compilation depends
     /// on it not being set.
     pub env: Vec<(String, Option<String>)>,
+    /// The recorded checksums of files in `files`, if any. Only Cargo's own
+    /// dep-info files contain these, see `-Zchecksum-freshness`.
+    pub checksums: HashMap<PathBuf, FileChecksum>,
+}
+
+/// The size and SHA-256 hash of a file's contents, used to tell whether a
+/// file with a newer mtime actually changed.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileChecksum {
+    size: u64,
+    hash: String,
+}
+
+impl FileChecksum {
+    /// Computes the checksum of the file at `path`, unless it was modified
+    /// after `since` or can't be read.
+    fn compute(path: &Path, since: FileTime) -> Option<FileChecksum> {
+        let meta = std::fs::metadata(path).ok()?;
+        if !meta.is_file() || FileTime::from_last_modification_time(&meta) > since {
+            return None;
+        }
+        let hash = Sha256::new().update_path(path).ok()?.finish_hex();
+        Some(FileChecksum {
+            size: meta.len(),
+            hash,
+        })
+    }
+
+    /// Returns whether the file at `path` still has these contents.
+    fn matches(&self, path: &Path) -> bool {
+        match std::fs::metadata(path) {
+            Ok(meta) if meta.len() == self.size => {}
+            _ => return false,
+        }
+        match Sha256::new().update_path(path) {
+            Ok(hasher) => hasher.finish_hex() == self.hash,
+            Err(_) => false,
+        }
+    }
 }
 
 // Same as `RustcDepInfo` except avoids absolute paths as much as possible to
@@ -1899,16 +2499,33 @@ pub struct RustcDepInfo {
 //
 // This is also stored in an optimized format to make parsing it fast because
 // Cargo will read it for crates on all future compilations.
+//
+// With `-Zchecksum-freshness`, the checksums of the files are stored too. The
+// file then starts with a marker which can't be mistaken for the number of
+// files in the original format, followed by a version. Otherwise the original
+// format is written, which older versions of Cargo sharing the target
+// directory can read.
 #[derive(Default)]
 struct EncodedDepInfo {
-    files: Vec<(DepInfoPathType, PathBuf)>,
+    files: Vec<(DepInfoPathType, PathBuf, Option<FileChecksum>)>,
     env: Vec<(String, Option<String>)>,
 }
 
+const ENCODED_DEP_INFO_MARKER: usize = u32::MAX as usize;
+const ENCODED_DEP_INFO_VERSION: u8 = 1;
+
 impl EncodedDepInfo {
     fn parse(mut bytes: &[u8]) -> Option<EncodedDepInfo> {
         let bytes = &mut bytes;
-        let nfiles = read_usize(bytes)?;
+        let (nfiles, with_checksums) = match read_usize(bytes)? {
+            ENCODED_DEP_INFO_MARKER => {
+                if read_u8(bytes)? != ENCODED_DEP_INFO_VERSION {
+                    return None;
+                }
+                (read_usize(bytes)?, true)
+            }
+            nfiles => (nfiles, false),
+        };
         let mut files = Vec::with_capacity(nfiles as usize);
         for _ in 0..nfiles {
             let ty = match read_u8(bytes)? {
@@ -1916,8 +2533,17 @@ impl EncodedDepInfo {
                 1 => DepInfoPathType::TargetRootRelative,
                 _ => return None,
             };
-            let bytes = read_bytes(bytes)?;
-            files.push((ty, paths::bytes2path(bytes).ok()?));
+            let path = paths::bytes2path(read_bytes(bytes)?).ok()?;
+            let checksum = match with_checksums.then(|| read_u8(bytes)).flatten() {
+                None | Some(0) => None,
+                Some(1) => {
+                    let size = read_u64(bytes)?;
+                    let hash = str::from_utf8(read_bytes(bytes)?).ok()?.to_string();
+                    Some(FileChecksum { size, hash })
+                }
+                Some(_) => return None,
+            };
+            files.push((ty, path, checksum));
         }
 
         let nenv = read_usize(bytes)?;
@@ -1939,6 +2565,12 @@ impl EncodedDepInfo {
             Some(u32::from_le_bytes(ret.try_into().unwrap()) as usize)
         }
 
+        fn read_u64(bytes: &mut &[u8]) -> Option<u64> {
+            let ret = bytes.get(..8)?;
+            *bytes = &bytes[8..];
+            Some(u64::from_le_bytes(ret.try_into().unwrap()))
+        }
+
         fn read_u8(bytes: &mut &[u8]) -> Option<u8> {
             let ret = *bytes.get(0)?;
             *bytes = &bytes[1..];
@@ -1953,16 +2585,33 @@ impl EncodedDepInfo {
         }
     }
 
-    fn serialize(&self) -> CargoResult<Vec<u8>> {
+    /// Serializes the dep-info, in the format with checksums if
+    /// `with_checksums` is set.
+    fn serialize(&self, with_checksums: bool) -> CargoResult<Vec<u8>> {
         let mut ret = Vec::new();
         let dst = &mut ret;
+        if with_checksums {
+            write_usize(dst, ENCODED_DEP_INFO_MARKER);
+            dst.push(ENCODED_DEP_INFO_VERSION);
+        }
         write_usize(dst, self.files.len());
-        for (ty, file) in self.files.iter() {
+        for (ty, file, checksum) in self.files.iter() {
             match ty {
                 DepInfoPathType::PackageRootRelative => dst.push(0),
                 DepInfoPathType::TargetRootRelative => dst.push(1),
             }
             write_bytes(dst, paths::path2bytes(file)?);
+            if !with_checksums {
+                continue;
+            }
+            match checksum {
+                None => dst.push(0),
+                Some(checksum) => {
+                    dst.push(1);
+                    dst.extend(&u64::to_le_bytes(checksum.size));
+                    write_bytes(dst, &checksum.hash);
+                }
+            }
         }
 
         write_usize(dst, self.env.len());
@@ -1,12 +1,15 @@
 use std::fmt;
 use std::mem;
 
+use super::fingerprint::DirtyReason;
 use super::job_queue::JobState;
 use crate::util::CargoResult;
 
 pub struct Job {
     work: Work,
     fresh: Freshness,
+    /// Why a dirty job needs to run, if known.
+    dirty_reason: Option<DirtyReason>,
 }
 
 /// Each proc should send its description before starting.
@@ -45,14 +48,16 @@ impl Job {
         Job {
             work: Work::noop(),
             fresh: Freshness::Fresh,
+            dirty_reason: None,
         }
     }
 
     /// Creates a new job representing a unit of work.
-    pub fn new_dirty(work: Work) -> Job {
+    pub fn new_dirty(work: Work, dirty_reason: Option<DirtyReason>) -> Job {
         Job {
             work,
             fresh: Freshness::Dirty,
+            dirty_reason,
         }
     }
 
@@ -69,6 +74,11 @@ impl Job {
         self.fresh
     }
 
+    /// Returns why this job is dirty, if it is and the reason is known.
+    pub fn dirty_reason(&self) -> Option<&DirtyReason> {
+        self.dirty_reason.as_ref()
+    }
+
     pub fn before(&mut self, next: Work) {
         let prev = mem::replace(&mut self.work, Work::noop());
         self.work = next.then(prev);
@@ -53,6 +53,7 @@ use std::cell::Cell;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::io;
 use std::marker;
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -63,11 +64,13 @@ use jobserver::{Acquired, Client, HelperThread};
 use log::{debug, info, trace};
 
 use super::context::OutputFile;
+use super::fingerprint::DirtyReason;
 use super::job::{
     Freshness::{self, Dirty, Fresh},
     Job,
 };
-use super::timings::Timings;
+use super::memory_budget::MemoryBudget;
+use super::timings::{Timings, UnitDurations};
 use super::{BuildContext, BuildPlan, CompileMode, Context, Unit};
 use crate::core::compiler::future_incompat::{
     FutureBreakageItem, FutureIncompatReportPackage, OnDiskReports,
@@ -89,6 +92,8 @@ pub struct JobQueue<'cfg> {
     queue: DependencyQueue<Unit, Artifact, Job>,
     counts: HashMap<PackageId, usize>,
     timings: Timings<'cfg>,
+    /// Durations of units in previous builds, with `-Zcritical-path-scheduling`.
+    unit_durations: Option<UnitDurations>,
 }
 
 /// This structure is backed by the `DependencyQueue` type and manages the
@@ -132,6 +137,13 @@ struct DrainState<'cfg> {
     progress: Progress<'cfg>,
     next_id: u32,
     timings: Timings<'cfg>,
+    unit_durations: Option<UnitDurations>,
+    /// The expected memory usage of units, with `build.max-memory`.
+    memory_budget: Option<MemoryBudget>,
+    /// Whether units are waiting to be started only because they would
+    /// exceed the memory budget. The tokens for them are then kept instead of
+    /// being dropped, so they can start as soon as memory is available.
+    held_back_for_memory: bool,
 
     /// Tokens that are currently owned by this Cargo, and may be "associated"
     /// with a rustc process. They may also be unused, though if so will be
@@ -202,6 +214,10 @@ pub struct JobState<'a> {
     /// sending a double message later on.
     rmeta_required: Cell<bool>,
 
+    /// Where to record the peak memory usage of the processes run for this
+    /// job, with `build.max-memory`.
+    peak_memory: Option<Arc<AtomicU64>>,
+
     // Historical versions of Cargo made use of the `'a` argument here, so to
     // leave the door open to future refactorings keep it here.
     _marker: marker::PhantomData<&'a ()>,
@@ -232,6 +248,7 @@ enum Message {
     BuildPlanMsg(String, ProcessBuilder, Arc<Vec<OutputFile>>),
     Stdout(String),
     Stderr(String),
+    Warning(String),
     FixDiagnostic(diagnostic_server::Message),
     Token(io::Result<Acquired>),
     Finish(JobId, Artifact, CargoResult<()>),
@@ -249,6 +266,14 @@ impl<'a> JobState<'a> {
         self.messages.push(Message::Run(self.id, cmd.to_string()));
     }
 
+    /// Arranges for the peak memory usage of `cmd` to be measured, if this
+    /// job's memory usage is tracked.
+    pub fn measure_memory(&self, cmd: &mut ProcessBuilder) {
+        if let Some(peak) = &self.peak_memory {
+            cmd.record_peak_memory(Arc::clone(peak));
+        }
+    }
+
     pub fn build_plan(
         &self,
         module_name: String,
@@ -279,6 +304,16 @@ impl<'a> JobState<'a> {
         Ok(())
     }
 
+    /// Shows a warning of Cargo about this job.
+    pub fn warning(&self, warning: String) -> CargoResult<()> {
+        if let Some(config) = self.output {
+            config.shell().warn(warning)?;
+        } else {
+            self.messages.push_bounded(Message::Warning(warning));
+        }
+        Ok(())
+    }
+
     /// A method used to signal to the coordinator thread that the rmeta file
     /// for an rlib has been produced. This is only called for some rmeta
     /// builds when required, and can be called at any time before a job ends.
@@ -318,6 +353,7 @@ impl<'cfg> JobQueue<'cfg> {
             queue: DependencyQueue::new(),
             counts: HashMap::new(),
             timings: Timings::new(bcx, &bcx.roots),
+            unit_durations: None,
         }
     }
 
@@ -383,11 +419,17 @@ impl<'cfg> JobQueue<'cfg> {
             }
         }
 
-        // For now we use a fixed placeholder value for the cost of each unit, but
-        // in the future this could be used to allow users to provide hints about
-        // relative expected costs of units, or this could be automatically set in
-        // a smarter way using timing data from a previous compilation.
-        self.queue.queue(unit.clone(), job, queue_deps, 100);
+        // By default we use a fixed placeholder value for the cost of each
+        // unit. With `-Zcritical-path-scheduling` the cost is how long the
+        // unit took in previous compilations.
+        let cost = if cx.bcx.config.cli_unstable().critical_path_scheduling {
+            self.unit_durations
+                .get_or_insert_with(|| UnitDurations::load(cx.files().host_dest()))
+                .cost(unit)
+        } else {
+            100
+        };
+        self.queue.queue(unit.clone(), job, queue_deps, cost);
         *self.counts.entry(unit.pkg.package_id()).or_insert(0) += 1;
         Ok(())
     }
@@ -399,7 +441,11 @@ impl<'cfg> JobQueue<'cfg> {
     /// possible along each dependency chain.
     pub fn execute(mut self, cx: &mut Context<'_, '_>, plan: &mut BuildPlan) -> CargoResult<()> {
         let _p = profile::start("executing the job graph");
-        self.queue.queue_finished();
+        if self.unit_durations.is_some() {
+            self.queue.queue_finished_by_critical_path();
+        } else {
+            self.queue.queue_finished();
+        }
 
         let progress = Progress::with_style("Building", ProgressStyle::Ratio, cx.bcx.config);
         let state = DrainState {
@@ -417,6 +463,13 @@ impl<'cfg> JobQueue<'cfg> {
             progress,
             next_id: 0,
             timings: self.timings,
+            unit_durations: self.unit_durations,
+            memory_budget: cx
+                .bcx
+                .build_config
+                .max_memory
+                .map(|max| MemoryBudget::load(max, cx.files().host_dest())),
+            held_back_for_memory: false,
             tokens: Vec::new(),
             rustc_tokens: HashMap::new(),
             to_send_clients: BTreeMap::new(),
@@ -480,16 +533,31 @@ impl<'cfg> DrainState<'cfg> {
 
         // Now that we've learned of all possible work that we can execute
         // try to spawn it so long as we've got a jobserver token which says
-        // we're able to perform some parallel work.
+        // we're able to perform some parallel work. With a memory budget, the
+        // first job that fits within it is started instead.
+        self.held_back_for_memory = false;
         while self.has_extra_tokens() && !self.pending_queue.is_empty() {
-            let (unit, job) = self.pending_queue.remove(0);
+            let next = match &self.memory_budget {
+                Some(budget) => self
+                    .pending_queue
+                    .iter()
+                    .position(|(unit, job)| job.freshness() == Fresh || budget.fits(unit)),
+                None => Some(0),
+            };
+            let (unit, job) = match next {
+                Some(i) => self.pending_queue.remove(i),
+                None => {
+                    self.held_back_for_memory = true;
+                    break;
+                }
+            };
             *self.counts.get_mut(&unit.pkg.package_id()).unwrap() -= 1;
             if !cx.bcx.build_config.build_plan {
                 // Print out some nice progress information.
                 // NOTE: An error here will drop the job without starting it.
                 // That should be OK, since we want to exit as soon as
                 // possible during an error.
-                self.note_working_on(cx.bcx.config, &unit, job.freshness())?;
+                self.note_working_on(cx.bcx, &unit, job.freshness(), job.dirty_reason())?;
             }
             self.run(&unit, job, cx, scope);
         }
@@ -563,6 +631,9 @@ impl<'cfg> DrainState<'cfg> {
                 shell.print_ansi_stderr(err.as_bytes())?;
                 shell.err().write_all(b"\n")?;
             }
+            Message::Warning(warning) => {
+                cx.bcx.config.shell().warn(warning)?;
+            }
             Message::FixDiagnostic(msg) => {
                 self.print.print(&msg)?;
             }
@@ -586,7 +657,11 @@ impl<'cfg> DrainState<'cfg> {
                             self.tokens.extend(rustc_tokens);
                         }
                         self.to_send_clients.remove(&id);
-                        self.active.remove(&id).unwrap()
+                        let unit = self.active.remove(&id).unwrap();
+                        if let Some(budget) = &mut self.memory_budget {
+                            budget.finish(id, &unit);
+                        }
+                        unit
                     }
                     // ... otherwise if it hasn't finished we leave it
                     // in there as we'll get another `Finish` later on.
@@ -670,7 +745,9 @@ impl<'cfg> DrainState<'cfg> {
         if events.is_empty() {
             loop {
                 self.tick_progress();
-                self.tokens.truncate(self.active.len() - 1);
+                if !self.held_back_for_memory {
+                    self.tokens.truncate(self.active.len() - 1);
+                }
                 match self.messages.pop(Duration::from_millis(500)) {
                     Some(message) => {
                         events.push(message);
@@ -767,6 +844,24 @@ impl<'cfg> DrainState<'cfg> {
                 return Some(e);
             }
         }
+        if let Some(unit_durations) = &mut self.unit_durations {
+            if let Err(e) = unit_durations.save(&self.timings) {
+                crate::display_warning_with_error(
+                    "failed to save unit durations",
+                    &e,
+                    &mut cx.bcx.config.shell(),
+                );
+            }
+        }
+        if let Some(memory_budget) = &mut self.memory_budget {
+            if let Err(e) = memory_budget.save() {
+                crate::display_warning_with_error(
+                    "failed to save the memory usage of units",
+                    &e,
+                    &mut cx.bcx.config.shell(),
+                );
+            }
+        }
         if cx.bcx.build_config.emit_json() {
             let mut shell = cx.bcx.config.shell();
             let msg = machine_message::BuildFinished {
@@ -994,17 +1089,23 @@ impl<'cfg> DrainState<'cfg> {
                     messages,
                     output: Some(cx.bcx.config),
                     rmeta_required: Cell::new(rmeta_required),
+                    peak_memory: None,
                     _marker: marker::PhantomData,
                 });
             }
             Freshness::Dirty => {
                 self.timings.add_dirty();
+                let peak_memory = self
+                    .memory_budget
+                    .as_mut()
+                    .map(|budget| budget.start(id, unit));
                 scope.spawn(move |_| {
                     doit(JobState {
                         id,
                         messages: messages.clone(),
                         output: None,
                         rmeta_required: Cell::new(rmeta_required),
+                        peak_memory,
                         _marker: marker::PhantomData,
                     })
                 });
@@ -1073,10 +1174,28 @@ impl<'cfg> DrainState<'cfg> {
     // out any more information for a package after we've printed it once.
     fn note_working_on(
         &mut self,
-        config: &Config,
+        bcx: &BuildContext<'_, '_>,
         unit: &Unit,
         fresh: Freshness,
+        dirty_reason: Option<&DirtyReason>,
     ) -> CargoResult<()> {
+        let config = bcx.config;
+        // Units built for the first time are not worth explaining.
+        let dirty_reason = dirty_reason.filter(|r| !r.is_fresh_build());
+        if let Some(dirty_reason) = dirty_reason {
+            if bcx.build_config.emit_json() {
+                let msg = machine_message::DirtyUnit {
+                    package_id: unit.pkg.package_id(),
+                    target: &unit.target,
+                    mode: unit.mode,
+                    dirty_reason: dirty_reason.kind(),
+                    message: dirty_reason.description(unit.pkg.root()),
+                }
+                .to_json_string();
+                crate::drop_println!(config, "{}", msg);
+            }
+        }
+
         if (self.compiled.contains(&unit.pkg.package_id()) && !unit.mode.is_doc())
             || (self.documented.contains(&unit.pkg.package_id()) && unit.mode.is_doc())
         {
@@ -1087,6 +1206,18 @@ impl<'cfg> DrainState<'cfg> {
             // Any dirty stage which runs at least one command gets printed as
             // being a compiled package.
             Dirty => {
+                if let Some(dirty_reason) = dirty_reason {
+                    config.shell().verbose(|s| {
+                        s.status(
+                            "Dirty",
+                            format!(
+                                "{}: {}",
+                                unit.pkg,
+                                dirty_reason.description(unit.pkg.root())
+                            ),
+                        )
+                    })?;
+                }
                 if unit.mode.is_doc() {
                     self.documented.insert(unit.pkg.package_id());
                     config.shell().status("Documenting", &unit.pkg)?;
@@ -0,0 +1,127 @@
+//! Keeping the units built at the same time within `build.max-memory`.
+//!
+//! The peak memory usage of every process run to build a unit (rustc,
+//! rustdoc, or a build script) is measured when it exits, and saved in the
+//! target directory after the build. Before starting a unit, the job queue
+//! checks that the peak memory those measurements predict for the unit,
+//! together with the predictions for the units that are already running,
+//! stays within the budget. If not, the unit waits for others to finish, and
+//! the jobserver token that would have been used for it is held back.
+//!
+//! Units that weren't built before are expected to need as much memory as
+//! the average unit. A unit is always started when nothing else is running,
+//! even if it is expected to need more than the whole budget.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use cargo_util::paths;
+
+use super::job_queue::JobId;
+use super::timings::unit_key;
+use super::Unit;
+use crate::util::CargoResult;
+
+pub struct MemoryBudget {
+    /// The budget, in bytes.
+    max: u64,
+    path: PathBuf,
+    /// The peak memory usage of units in previous builds, in bytes, by
+    /// `unit_key`.
+    peaks: BTreeMap<String, u64>,
+    /// The expected peak memory usage of units that weren't built before.
+    default_peak: u64,
+    /// The units that are running, with their expected peak memory usage,
+    /// and where the actual peak memory usage of their processes is
+    /// recorded.
+    running: HashMap<JobId, (u64, Arc<AtomicU64>)>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct OnDiskPeaks {
+    version: u32,
+    peaks: BTreeMap<String, u64>,
+}
+
+impl MemoryBudget {
+    const VERSION: u32 = 1;
+
+    /// Creates a budget of `max` bytes, with the peak memory usage saved in
+    /// the directory `dir`.
+    ///
+    /// Missing or unreadable measurements are treated as if nothing was
+    /// built before.
+    pub fn load(max: u64, dir: &Path) -> MemoryBudget {
+        let path = dir.join(".unit-memory.json");
+        let peaks = paths::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<OnDiskPeaks>(&contents).ok())
+            .filter(|on_disk| on_disk.version == MemoryBudget::VERSION)
+            .map(|on_disk| on_disk.peaks)
+            .unwrap_or_default();
+        let default_peak = if peaks.is_empty() {
+            0
+        } else {
+            peaks.values().sum::<u64>() / peaks.len() as u64
+        };
+        MemoryBudget {
+            max,
+            path,
+            peaks,
+            default_peak,
+            running: HashMap::new(),
+        }
+    }
+
+    /// The peak memory usage `unit` is expected to have, in bytes.
+    pub fn estimate(&self, unit: &Unit) -> u64 {
+        self.peaks
+            .get(&unit_key(unit))
+            .copied()
+            .unwrap_or(self.default_peak)
+    }
+
+    /// Whether `unit` can be started alongside the units already running
+    /// without exceeding the budget.
+    pub fn fits(&self, unit: &Unit) -> bool {
+        let running: u64 = self.running.values().map(|(estimate, _)| estimate).sum();
+        self.running.is_empty() || running + self.estimate(unit) <= self.max
+    }
+
+    /// Notes that `unit` is started as job `id`, returning where the peak
+    /// memory usage of its processes should be recorded.
+    pub fn start(&mut self, id: JobId, unit: &Unit) -> Arc<AtomicU64> {
+        let peak = Arc::new(AtomicU64::new(0));
+        self.running
+            .insert(id, (self.estimate(unit), Arc::clone(&peak)));
+        peak
+    }
+
+    /// Notes that job `id`, building `unit`, has finished, and keeps the peak
+    /// memory usage of its processes for the next build.
+    pub fn finish(&mut self, id: JobId, unit: &Unit) {
+        if let Some((_, peak)) = self.running.remove(&id) {
+            let peak = peak.load(Ordering::SeqCst);
+            // Nothing is measured for units that didn't run a process, or
+            // on platforms where it isn't supported.
+            if peak > 0 {
+                self.peaks.insert(unit_key(unit), peak);
+            }
+        }
+    }
+
+    /// Saves the peak memory usage of all units built so far.
+    pub fn save(&mut self) -> CargoResult<()> {
+        let on_disk = OnDiskPeaks {
+            version: MemoryBudget::VERSION,
+            peaks: std::mem::take(&mut self.peaks),
+        };
+        let result = serde_json::to_string_pretty(&on_disk)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| paths::write(&self.path, contents));
+        self.peaks = on_disk.peaks;
+        result
+    }
+}
@@ -1,6 +1,7 @@
 mod build_config;
 mod build_context;
 mod build_plan;
+pub mod build_sandbox;
 mod compilation;
 mod compile_kind;
 mod context;
@@ -13,8 +14,11 @@ mod job_queue;
 mod layout;
 mod links;
 mod lto;
+mod memory_budget;
 mod output_depinfo;
+pub mod remote_cache;
 pub mod rustdoc;
+mod shared_cache;
 pub mod standard_lib;
 mod timings;
 mod unit;
@@ -48,6 +52,7 @@ use self::job_queue::{JobQueue, JobState};
 pub(crate) use self::layout::Layout;
 pub use self::lto::Lto;
 use self::output_depinfo::output_depinfo;
+pub use self::timings::{Concurrency, TimingReport, UnitData};
 use self::unit_graph::UnitDep;
 use crate::core::compiler::future_incompat::FutureIncompatReport;
 pub use crate::core::compiler::unit::{Unit, UnitInterner};
@@ -160,7 +165,7 @@ fn compile<'cfg>(
         // We run these targets later, so this is just a no-op for now.
         Job::new_fresh()
     } else if build_plan {
-        Job::new_dirty(rustc(cx, unit, &exec.clone())?)
+        Job::new_dirty(rustc(cx, unit, &exec.clone())?, None)
     } else {
         let force = exec.force_rebuild(unit) || force_rebuild;
         let mut job = fingerprint::prepare_target(cx, unit, force)?;
@@ -170,6 +175,10 @@ fn compile<'cfg>(
             } else {
                 rustc(cx, unit, exec)?
             };
+            let work = match cx.shared_cache.clone() {
+                Some(cache) if !force => cache.fetch_or(cx, unit, work)?,
+                _ => work,
+            };
             work.then(link_targets(cx, unit, false)?)
         } else {
             // We always replay the output cache,
@@ -260,6 +269,7 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
     let fingerprint_dir = cx.files().fingerprint_dir(unit);
     let script_metadata = cx.find_build_script_metadata(unit);
     let is_local = unit.is_local();
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
 
     return Ok(Work::new(move |state| {
         // Only at runtime have we discovered what the extra -L and -l
@@ -311,6 +321,7 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
         }
 
         state.running(&rustc);
+        state.measure_memory(&mut rustc);
         let timestamp = paths::set_invocation_time(&fingerprint_dir)?;
         if build_plan {
             state.build_plan(buildkey, rustc.clone(), outputs.clone());
@@ -359,6 +370,11 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
                 &rustc,
                 // Do not track source files in the fingerprint for registry dependencies.
                 is_local,
+                if checksum_freshness {
+                    Some(timestamp)
+                } else {
+                    None
+                },
             )
             .with_context(|| {
                 internal(format!(
@@ -680,6 +696,7 @@ fn rustdoc(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Work> {
             paths::remove_dir_all(crate_dir)?;
         }
         state.running(&rustdoc);
+        state.measure_memory(&mut rustdoc);
 
         rustdoc
             .exec_with_streaming(
@@ -1027,6 +1044,15 @@ fn build_deps_args(
         });
     }
 
+    // The dependencies of the standard library, if it was built beforehand.
+    if let Some(sysroot) = bcx.std_sysroots.get(&unit.kind) {
+        cmd.arg("-L").arg(&{
+            let mut deps = OsString::from("dependency=");
+            deps.push(&sysroot.lib_dir);
+            deps
+        });
+    }
+
     let deps = cx.unit_deps(unit);
 
     // If there is not one linkable target but should, rustc fails later
@@ -1141,6 +1167,19 @@ pub fn extern_args(
             link_to(dep, dep.extern_crate_name, dep.noprelude)?;
         }
     }
+    // The standard library built beforehand takes the place of the one of
+    // `-Zbuild-std`, which is a dependency of every unit for the target.
+    if let Some(sysroot) = cx.bcx.std_sysroots.get(&unit.kind) {
+        if !unit.mode.is_run_custom_build() {
+            for (name, path) in &sysroot.externs {
+                let mut value = OsString::from(format!("noprelude:{}=", name));
+                value.push(path);
+                result.push(OsString::from("--extern"));
+                result.push(value);
+            }
+            *unstable_opts = true;
+        }
+    }
     if unit.target.proc_macro() {
         // Automatically import `proc_macro`.
         result.push(OsString::from("--extern"));
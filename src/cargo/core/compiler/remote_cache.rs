@@ -0,0 +1,202 @@
+//! The protocol for remote build caches, `build.remote-cache`.
+//!
+//! A remote cache is an external helper process, much like a
+//! `credential-process`, that shares the artifacts of units between machines,
+//! for example between CI and developers. For every request, Cargo runs the
+//! helper and writes a single line of JSON to its stdin:
+//!
+//! ```json
+//! {"v":1,"action":"get","key":"bar-1a2b3c4d5e6f7a8b","path":"/tmp/.tmpA1b2C3"}
+//! ```
+//!
+//! The helper answers with a single line of JSON on stdout:
+//!
+//! * For `get`, the helper copies the files it stored under `key` into the
+//!   existing, empty directory `path`, and answers `{"found":true}`, or
+//!   `{"found":false}` if it doesn't have them.
+//! * For `put`, the helper stores the files in the directory `path` under
+//!   `key`, and answers `{}`.
+//!
+//! If something goes wrong, the helper answers `{"error":"..."}`, or exits
+//! with a failure. Keys are file names, so they can be used as they are.
+//!
+//! Errors of the helper never fail the build; the unit is then built, or not
+//! stored, and a warning is shown. `cargo:fs DIR` is a reference helper built
+//! into Cargo that stores the artifacts in the directory `DIR`, see
+//! `ops::remote_cache_fs_maybe_exec`.
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context as _};
+use cargo_util::ProcessError;
+use serde::{Deserialize, Serialize};
+
+use crate::util::config::PathAndArgs;
+use crate::util::{CargoResult, Config};
+
+/// The environment variable that makes Cargo act as the `cargo:fs` helper,
+/// set to the directory to store artifacts in.
+pub const FS_HELPER_ENV: &str = "__CARGO_REMOTE_CACHE_FS";
+
+/// The version of the protocol.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoteCacheAction {
+    Get,
+    Put,
+}
+
+/// A request sent to a remote cache helper.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RemoteCacheRequest {
+    pub v: u32,
+    pub action: RemoteCacheAction,
+    pub key: String,
+    pub path: PathBuf,
+}
+
+/// The answer of a remote cache helper.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RemoteCacheResponse {
+    /// Whether the artifacts asked for with `get` were found.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub found: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A remote cache helper, as configured with `build.remote-cache`.
+#[derive(Clone)]
+pub struct RemoteCache {
+    program: PathBuf,
+    args: Vec<String>,
+    envs: Vec<(&'static str, PathBuf)>,
+    /// The keys of the artifacts that were fetched from the helper in this
+    /// build, which don't need to be stored again.
+    fetched: Arc<Mutex<HashSet<String>>>,
+}
+
+impl RemoteCache {
+    pub fn new(config: &Config, helper: &PathAndArgs) -> CargoResult<RemoteCache> {
+        let cargo = config.cargo_exe()?.to_path_buf();
+        let mut envs = vec![("CARGO", cargo.clone())];
+        let (program, args) = match helper.path.raw_value() {
+            "cargo:fs" => {
+                let dir = match helper.args.as_slice() {
+                    [dir] => config.cwd().join(dir),
+                    _ => bail!(
+                        "the `cargo:fs` remote cache helper expects the directory to \
+                         store artifacts in, like `cargo:fs /path/to/dir`"
+                    ),
+                };
+                envs.push((FS_HELPER_ENV, dir));
+                (cargo, Vec::new())
+            }
+            name if name.starts_with("cargo:") => {
+                bail!("unknown built-in remote cache helper `{}`", name)
+            }
+            _ => (helper.path.resolve_program(config), helper.args.clone()),
+        };
+        Ok(RemoteCache {
+            program,
+            args,
+            envs,
+            fetched: Arc::new(Mutex::new(HashSet::new())),
+        })
+    }
+
+    /// Asks the helper to copy the artifacts stored under `key` into `dir`,
+    /// returning whether it had them.
+    pub fn get(&self, key: &str, dir: &Path) -> CargoResult<bool> {
+        let found = self.request(RemoteCacheAction::Get, key, dir)?.found;
+        if found {
+            self.fetched.lock().unwrap().insert(key.to_string());
+        }
+        Ok(found)
+    }
+
+    /// Asks the helper to store the artifacts in `dir` under `key`.
+    pub fn put(&self, key: &str, dir: &Path) -> CargoResult<()> {
+        self.request(RemoteCacheAction::Put, key, dir)?;
+        Ok(())
+    }
+
+    /// Whether the artifacts stored under `key` were fetched in this build.
+    pub fn was_fetched(&self, key: &str) -> bool {
+        self.fetched.lock().unwrap().contains(key)
+    }
+
+    fn request(
+        &self,
+        action: RemoteCacheAction,
+        key: &str,
+        dir: &Path,
+    ) -> CargoResult<RemoteCacheResponse> {
+        let request = RemoteCacheRequest {
+            v: PROTOCOL_VERSION,
+            action,
+            key: key.to_string(),
+            path: dir.to_path_buf(),
+        };
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped());
+        for (key, val) in &self.envs {
+            cmd.env(key, val);
+        }
+        let mut child = cmd.spawn().with_context(|| {
+            format!(
+                "failed to execute remote cache helper `{}`",
+                self.program.display()
+            )
+        })?;
+        let mut stdout = String::new();
+        let result = writeln!(
+            child.stdin.take().unwrap(),
+            "{}",
+            serde_json::to_string(&request)?
+        )
+        .and_then(|()| child.stdout.take().unwrap().read_to_string(&mut stdout));
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(ProcessError::new(
+                &format!(
+                    "remote cache helper `{}` didn't exit successfully",
+                    self.program.display()
+                ),
+                Some(status),
+                None,
+            )
+            .into());
+        }
+        result.with_context(|| {
+            format!(
+                "failed to communicate with remote cache helper `{}`",
+                self.program.display()
+            )
+        })?;
+        let response: RemoteCacheResponse =
+            serde_json::from_str(stdout.trim_end()).with_context(|| {
+                format!(
+                    "remote cache helper `{}` returned an invalid response: {}",
+                    self.program.display(),
+                    stdout.trim_end()
+                )
+            })?;
+        if let Some(error) = response.error {
+            bail!(
+                "remote cache helper `{}` failed: {}",
+                self.program.display(),
+                error
+            );
+        }
+        Ok(response)
+    }
+}
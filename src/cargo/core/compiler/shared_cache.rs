@@ -0,0 +1,255 @@
+//! A cache of build artifacts shared between workspaces, `build.shared-cache`.
+//!
+//! Checkouts and worktrees of the same project often build the same registry
+//! and git dependencies with the same settings. With a shared cache, the
+//! files rustc produced for such a unit are copied into the cache after it is
+//! built, and copied back out instead of running rustc when another workspace
+//! needs the same unit.
+//!
+//! Units are only shared if they don't come from a path source, since local
+//! packages change all the time. The cache is keyed by the unit's `Metadata`
+//! hash, the version of rustc, and the hash of the fingerprint the unit will
+//! have once it's built, which includes the fingerprints of its dependencies.
+//! A unit restored from the cache is then fresh, like one that was built
+//! before in the same target directory.
+//!
+//! Each entry is a directory with the outputs of rustc, Cargo's copy of the
+//! dep-info, and the cached messages of rustc. Entries are written and read
+//! while holding a lock on a file next to the directory, and a marker file is
+//! written last so that partially written entries are never used.
+//!
+//! The cache may also, or instead, be a remote cache configured with
+//! `build.remote-cache`, see the `remote_cache` module. Entries are then
+//! fetched from the remote cache right before the unit would be built, in the
+//! job of the unit, and stored in it after the unit was built, unless they
+//! were fetched in the same build.
+
+use std::path::{Path, PathBuf};
+
+use cargo_util::paths;
+
+use super::job::Work;
+use super::remote_cache::RemoteCache;
+use super::{CompileMode, Context, FileFlavor, Unit};
+use crate::util::{self, CargoResult, Config, Filesystem};
+
+/// The file written last in a complete entry.
+const COMPLETE: &str = ".complete";
+
+#[derive(Clone)]
+pub struct SharedCache {
+    /// The directory of `build.shared-cache`.
+    local: Option<Filesystem>,
+    remote: Option<RemoteCache>,
+}
+
+/// A unit's entry in the cache, and where its files go in the target
+/// directory.
+pub struct CacheEntry {
+    name: String,
+    /// The files rustc produces for the unit, and whether the unit can't be
+    /// used without them.
+    outputs: Vec<(PathBuf, bool)>,
+    /// Cargo's own copy of the unit's dep-info.
+    dep_info: PathBuf,
+    /// The messages of rustc, replayed when the unit is fresh.
+    message_cache: PathBuf,
+}
+
+impl SharedCache {
+    /// Creates a cache from the local and remote caches that are configured,
+    /// if any.
+    pub fn new(local: Option<PathBuf>, remote: Option<RemoteCache>) -> Option<SharedCache> {
+        if local.is_none() && remote.is_none() {
+            return None;
+        }
+        Some(SharedCache {
+            local: local.map(Filesystem::new),
+            remote,
+        })
+    }
+
+    /// Returns the entry of `unit` in the cache, if it can be shared.
+    /// `fingerprint` is the hash of the fingerprint the unit will have once
+    /// it's built.
+    pub fn entry(
+        &self,
+        cx: &mut Context<'_, '_>,
+        unit: &Unit,
+        fingerprint: u64,
+    ) -> CargoResult<Option<CacheEntry>> {
+        if unit.is_local() || !(unit.mode == CompileMode::Build || unit.mode.is_check()) {
+            return Ok(None);
+        }
+        let key = (
+            cx.files().metadata(unit).to_string(),
+            &cx.bcx.rustc().verbose_version,
+            fingerprint,
+        );
+        let outputs = cx
+            .outputs(unit)?
+            .iter()
+            .map(|output| (output.path.clone(), output.flavor != FileFlavor::DebugInfo))
+            .collect();
+        Ok(Some(CacheEntry {
+            name: format!("{}-{}", unit.target.crate_name(), util::short_hash(&key)),
+            outputs,
+            dep_info: super::fingerprint::dep_info_loc(cx, unit),
+            message_cache: cx.files().message_cache_path(unit),
+        }))
+    }
+
+    /// Copies the files of `entry` from the local cache into the target
+    /// directory, returning whether the cache had them.
+    pub fn restore(&self, config: &Config, entry: &CacheEntry) -> CargoResult<bool> {
+        let root = match &self.local {
+            Some(root) => root,
+            None => return Ok(false),
+        };
+        let dir = root.as_path_unlocked().join(&entry.name);
+        if !dir.join(COMPLETE).exists() {
+            return Ok(false);
+        }
+        let _lock = root.open_ro(format!("{}.lock", entry.name), config, "shared build cache")?;
+        restore_from(&dir, entry)
+    }
+
+    /// Wraps `work`, which builds `unit`, so that the files of the unit are
+    /// fetched from the remote cache instead, if it has them.
+    ///
+    /// Failing to fetch them only prints a warning, and the unit is built.
+    pub fn fetch_or(&self, cx: &mut Context<'_, '_>, unit: &Unit, work: Work) -> CargoResult<Work> {
+        let remote = match &self.remote {
+            Some(remote) => remote.clone(),
+            None => return Ok(work),
+        };
+        let fingerprint = super::fingerprint::fingerprint_hash(cx, unit)?;
+        let entry = match self.entry(cx, unit, fingerprint)? {
+            Some(entry) => entry,
+            None => return Ok(work),
+        };
+        Ok(Work::new(move |state| {
+            match fetch(&remote, &entry) {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => state.stderr(format!(
+                    "warning: failed to fetch `{}` from the remote build cache: {:#}",
+                    entry.name, e
+                ))?,
+            }
+            work.call(state)
+        }))
+    }
+
+    /// Returns the work that copies the files of `entry` into the cache,
+    /// which must run after the unit is built.
+    ///
+    /// Failing to do so only prints a warning, as the build itself succeeded.
+    pub fn store(&self, entry: CacheEntry) -> Work {
+        let local = self.local.clone();
+        let remote = self.remote.clone();
+        Work::new(move |state| {
+            if let Some(root) = &local {
+                if let Err(e) = store(root, &entry) {
+                    state.stderr(format!(
+                        "warning: failed to store `{}` in the shared build cache: {:#}",
+                        entry.name, e
+                    ))?;
+                }
+            }
+            if let Some(remote) = &remote {
+                if let Err(e) = put(remote, &entry) {
+                    state.stderr(format!(
+                        "warning: failed to store `{}` in the remote build cache: {:#}",
+                        entry.name, e
+                    ))?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+fn store(root: &Filesystem, entry: &CacheEntry) -> CargoResult<()> {
+    let _lock = root.open_rw_quiet(format!("{}.lock", entry.name))?;
+    let dir = root.as_path_unlocked().join(&entry.name);
+    if dir.join(COMPLETE).exists() {
+        // Another build stored the same unit in the meantime.
+        return Ok(());
+    }
+    if dir.exists() {
+        // Left behind by a build that was interrupted.
+        paths::remove_dir_all(&dir)?;
+    }
+    write_entry(&dir, entry)
+}
+
+fn fetch(remote: &RemoteCache, entry: &CacheEntry) -> CargoResult<bool> {
+    let tmp = tempfile::tempdir()?;
+    Ok(remote.get(&entry.name, tmp.path())? && restore_from(tmp.path(), entry)?)
+}
+
+fn put(remote: &RemoteCache, entry: &CacheEntry) -> CargoResult<()> {
+    if remote.was_fetched(&entry.name) {
+        return Ok(());
+    }
+    let tmp = tempfile::tempdir()?;
+    write_entry(tmp.path(), entry)?;
+    remote.put(&entry.name, tmp.path())
+}
+
+/// Writes the files of `entry` into the empty or missing directory `dir`.
+fn write_entry(dir: &Path, entry: &CacheEntry) -> CargoResult<()> {
+    paths::create_dir_all(dir.join("out"))?;
+    for (path, _) in &entry.outputs {
+        // Outputs like `.dSYM` directories aren't shared.
+        if path.is_file() {
+            paths::copy(path, dir.join("out").join(path.file_name().unwrap()))?;
+        }
+    }
+    paths::copy(&entry.dep_info, dir.join("dep-info"))?;
+    if entry.message_cache.exists() {
+        paths::copy(&entry.message_cache, dir.join("output"))?;
+    }
+    paths::write(dir.join(COMPLETE), "")
+}
+
+/// Copies the files of `entry` from the directory `dir` into the target
+/// directory, returning whether `dir` is a complete entry.
+fn restore_from(dir: &Path, entry: &CacheEntry) -> CargoResult<bool> {
+    let cached = |path: &Path| dir.join("out").join(path.file_name().unwrap());
+    if !dir.join(COMPLETE).exists()
+        || !entry
+            .outputs
+            .iter()
+            .all(|(path, required)| !required || cached(path).is_file())
+    {
+        return Ok(false);
+    }
+    for (path, _) in &entry.outputs {
+        let src = cached(path);
+        if src.is_file() {
+            copy(&src, path)?;
+        }
+    }
+    copy(&dir.join("dep-info"), &entry.dep_info)?;
+    let messages = dir.join("output");
+    if messages.exists() {
+        copy(&messages, &entry.message_cache)?;
+    } else if entry.message_cache.exists() {
+        paths::remove_file(&entry.message_cache)?;
+    }
+    Ok(true)
+}
+
+/// Copies `src` to `dst`, replacing `dst` instead of writing to it, since it
+/// may be hard linked to an uplifted artifact.
+fn copy(src: &Path, dst: &Path) -> CargoResult<()> {
+    if dst.exists() {
+        paths::remove_file(dst)?;
+    } else {
+        paths::create_dir_all(dst.parent().unwrap())?;
+    }
+    paths::copy(src, dst)?;
+    Ok(())
+}
@@ -2,12 +2,15 @@
 
 use crate::core::compiler::UnitInterner;
 use crate::core::compiler::{CompileKind, CompileMode, RustcTargetData, Unit};
-use crate::core::profiles::{Profiles, UnitFor};
+use crate::core::profiles::{PanicStrategy, Profiles, UnitFor};
 use crate::core::resolver::features::{CliFeatures, FeaturesFor, ResolvedFeatures};
 use crate::core::resolver::HasDevUnits;
 use crate::core::{Dependency, PackageId, PackageSet, Resolve, SourceId, Workspace};
-use crate::ops::{self, Packages};
+use crate::ops::{self, CompileOptions, Packages};
+use crate::util::config::{CargoBuildStdConfig, Config};
 use crate::util::errors::CargoResult;
+use anyhow::bail;
+use cargo_util::paths;
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::path::PathBuf;
@@ -27,7 +30,92 @@ pub fn parse_unstable_flag(value: Option<&str>) -> Vec<String> {
     } else if crates.contains("core") {
         crates.insert("compiler_builtins");
     }
-    crates.into_iter().map(|s| s.to_string()).collect()
+    let mut crates: Vec<_> = crates.into_iter().map(|s| s.to_string()).collect();
+    crates.sort();
+    crates
+}
+
+/// What to build of the standard library for a target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StdOptions {
+    /// The crates to build, as expanded by `parse_unstable_flag`.
+    pub crates: Vec<String>,
+    /// The features to enable for `std`.
+    pub features: Vec<String>,
+    /// The panic strategy of everything built for the target, if set.
+    pub panic: Option<PanicStrategy>,
+}
+
+/// Whether the standard library should be built once into a sysroot that
+/// later builds reuse, set with `build-std.sysroot`.
+pub fn use_sysroot(config: &Config) -> CargoResult<bool> {
+    Ok(config
+        .get::<CargoBuildStdConfig>("build-std")?
+        .sysroot
+        .unwrap_or(false))
+}
+
+/// Returns what to build of the standard library for `kind`.
+///
+/// The `[build-std.target.<triple>]` config table takes precedence over the
+/// `[build-std]` table, which takes precedence over the default crates of
+/// `-Zbuild-std`. Crates and features passed on the command line take
+/// precedence over both.
+pub fn std_options(config: &Config, kind: CompileKind) -> CargoResult<StdOptions> {
+    let mut build_std = config.get::<CargoBuildStdConfig>("build-std")?;
+    let target = match kind {
+        CompileKind::Host => None,
+        CompileKind::Target(target) => build_std.target.remove(target.short_name()),
+    };
+    let (crates, features, panic_strategy) = match target {
+        Some(target) => (
+            target.crates.or(build_std.crates),
+            target.features.or(build_std.features),
+            target.panic_strategy.or(build_std.panic_strategy),
+        ),
+        None => (
+            build_std.crates,
+            build_std.features,
+            build_std.panic_strategy,
+        ),
+    };
+    let panic = match panic_strategy.as_deref() {
+        None => None,
+        Some("unwind") => Some(PanicStrategy::Unwind),
+        Some("abort") => Some(PanicStrategy::Abort),
+        Some(s) => bail!(
+            "`build-std.panic-strategy` must be `unwind` or `abort`, found `{}`",
+            s
+        ),
+    };
+    let default_crates = parse_unstable_flag(None);
+    let mut crates = match &config.cli_unstable().build_std {
+        Some(cli_crates) if *cli_crates != default_crates => cli_crates.clone(),
+        _ => match crates {
+            Some(crates) => parse_unstable_flag(Some(&crates.as_slice().join(","))),
+            None => default_crates,
+        },
+    };
+    if panic == Some(PanicStrategy::Abort) {
+        // `std` depends on `panic_abort` itself.
+        crates.retain(|c| c != "panic_unwind");
+    }
+    let features = match (&config.cli_unstable().build_std_features, features) {
+        (Some(list), _) => list.clone(),
+        (None, Some(list)) => list.as_slice().to_vec(),
+        (None, None) => {
+            let mut list = vec!["backtrace".to_string(), "default".to_string()];
+            if panic != Some(PanicStrategy::Abort) {
+                list.insert(0, "panic-unwind".to_string());
+            }
+            list
+        }
+    };
+    Ok(StdOptions {
+        crates,
+        features,
+        panic,
+    })
 }
 
 /// Resolve the standard library dependencies.
@@ -35,7 +123,7 @@ pub fn resolve_std<'cfg>(
     ws: &Workspace<'cfg>,
     target_data: &RustcTargetData<'cfg>,
     requested_targets: &[CompileKind],
-    crates: &[String],
+    options: &StdOptions,
 ) -> CargoResult<(PackageSet<'cfg>, Resolve, ResolvedFeatures)> {
     let src_path = detect_sysroot_src_path(target_data)?;
     let to_patch = [
@@ -95,20 +183,14 @@ pub fn resolve_std<'cfg>(
     std_ws.set_require_optional_deps(false);
     // `test` is not in the default set because it is optional, but it needs
     // to be part of the resolve in case we do need it.
-    let mut spec_pkgs = Vec::from(crates);
+    let mut spec_pkgs = options.crates.clone();
     spec_pkgs.push("test".to_string());
     let spec = Packages::Packages(spec_pkgs);
     let specs = spec.to_package_id_specs(&std_ws)?;
-    let features = match &config.cli_unstable().build_std_features {
-        Some(list) => list.clone(),
-        None => vec![
-            "panic-unwind".to_string(),
-            "backtrace".to_string(),
-            "default".to_string(),
-        ],
-    };
     let cli_features = CliFeatures::from_command_line(
-        &features, /*all_features*/ false, /*uses_default_features*/ false,
+        &options.features,
+        /*all_features*/ false,
+        /*uses_default_features*/ false,
     )?;
     let resolve = ops::resolve_ws_with_opts(
         &std_ws,
@@ -185,6 +267,111 @@ pub fn generate_std_roots(
     Ok(ret)
 }
 
+/// The standard library built for a target by `build_sysroot`, which later
+/// builds reuse instead of building it again.
+#[derive(Debug, Hash)]
+pub struct Sysroot {
+    /// A directory laid out like the sysroot of rustc, which can also be used
+    /// with `rustc --sysroot`.
+    pub path: PathBuf,
+    /// The directory within `path` with the libraries.
+    pub lib_dir: PathBuf,
+    /// The libraries of the crates that were requested, which are passed to
+    /// rustc with `--extern`, by crate name.
+    pub externs: Vec<(String, PathBuf)>,
+}
+
+/// The crates to build into a sysroot. `test` is always included with `std`,
+/// since it's only known later whether any tests are built with it.
+pub fn sysroot_crates(options: &StdOptions) -> Vec<String> {
+    let mut crates = options.crates.clone();
+    if crates.iter().any(|c| c == "std") && !crates.iter().any(|c| c == "test") {
+        crates.push("test".to_string());
+    }
+    crates
+}
+
+/// Builds the standard library for `kind` into a sysroot, or reuses the one
+/// built before if it's up to date.
+///
+/// The standard library is built by a separate build in
+/// `target/build-std/<triple>/<profile>`, with the settings of `std_options`
+/// for the target, and the profile of `options`. The libraries are copied
+/// into the sysroot next to it whenever one of them was rebuilt.
+pub fn build_sysroot(
+    ws: &Workspace<'_>,
+    options: &CompileOptions,
+    kind: CompileKind,
+) -> CargoResult<Sysroot> {
+    let config = ws.config();
+    let target = match kind {
+        CompileKind::Host => bail!("building a sysroot requires --target"),
+        CompileKind::Target(target) => target,
+    };
+    let triple = target.short_name();
+    let root = ws
+        .target_dir()
+        .join("build-std")
+        .join(triple)
+        .join(options.build_config.requested_profile.as_str());
+    let mut std_ws = Workspace::new(ws.root_manifest(), config)?;
+    std_ws.set_target_dir(root.clone());
+    let mut std_build = CompileOptions::new(config, CompileMode::Build)?;
+    std_build.build_config.requested_kinds = vec![kind];
+    std_build.build_config.requested_profile = options.build_config.requested_profile;
+    std_build.build_config.jobs = options.build_config.jobs;
+    std_build.build_config.message_format = options.build_config.message_format;
+    std_build.build_std_only = true;
+    let compilation = ops::compile(&std_ws, &std_build)?;
+
+    let path = root.as_path_unlocked().join("sysroot");
+    let lib_dir = path.join("lib").join("rustlib").join(triple).join("lib");
+    let stamp = path.join(".libs");
+    let file_name = |lib: &PathBuf| lib.file_name().unwrap().to_owned();
+    let mut names: Vec<_> = compilation
+        .std_libs
+        .iter()
+        .map(|lib| file_name(&lib.path).to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    let listing = names.join("\n");
+    let stamp_mtime = paths::mtime(&stamp).ok();
+    let up_to_date = paths::read(&stamp).ok().as_ref() == Some(&listing)
+        && compilation
+            .std_libs
+            .iter()
+            .all(|lib| paths::mtime(&lib.path).ok() <= stamp_mtime);
+    if !up_to_date {
+        // Libraries left over from other settings would be ambiguous.
+        if lib_dir.exists() {
+            paths::remove_dir_all(&lib_dir)?;
+        }
+        paths::create_dir_all(&lib_dir)?;
+        for lib in &compilation.std_libs {
+            paths::copy(&lib.path, lib_dir.join(file_name(&lib.path)))?;
+        }
+        paths::write(&stamp, listing)?;
+    }
+
+    let crates = sysroot_crates(&std_options(config, kind)?);
+    let externs = compilation
+        .std_libs
+        .iter()
+        .filter(|lib| crates.iter().any(|c| *c == lib.unit.target.crate_name()))
+        .map(|lib| {
+            (
+                lib.unit.target.crate_name(),
+                lib_dir.join(file_name(&lib.path)),
+            )
+        })
+        .collect();
+    Ok(Sysroot {
+        path,
+        lib_dir,
+        externs,
+    })
+}
+
 fn detect_sysroot_src_path(target_data: &RustcTargetData<'_>) -> CargoResult<PathBuf> {
     if let Some(s) = env::var_os("__CARGO_TESTS_ONLY_SRC_ROOT") {
         return Ok(s.into());
@@ -2,7 +2,11 @@
 //!
 //! This module implements some simple tracking information for timing of how
 //! long it takes for different units to compile.
-use super::{CompileMode, Unit};
+//!
+//! The information can be saved as an HTML report, as a JSON file with the
+//! same data, or in the Chrome trace event format, which can be loaded into
+//! Perfetto or `chrome://tracing`.
+use super::{CompileKind, CompileMode, Unit};
 use crate::core::compiler::job_queue::JobId;
 use crate::core::compiler::BuildContext;
 use crate::core::PackageId;
@@ -11,8 +15,9 @@ use crate::util::machine_message::{self, Message};
 use crate::util::{CargoResult, Config};
 use anyhow::Context as _;
 use cargo_util::paths;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant, SystemTime};
 
 pub struct Timings<'cfg> {
@@ -25,6 +30,10 @@ pub struct Timings<'cfg> {
     report_info: bool,
     /// If true, emits JSON information with timing information.
     report_json: bool,
+    /// If true, saves all of the timing information to a JSON file on disk.
+    report_file: bool,
+    /// If true, saves a Chrome trace to disk.
+    report_trace: bool,
     /// When Cargo started.
     start: Instant,
     /// A rendered string of when compilation started.
@@ -74,22 +83,101 @@ struct UnitTime {
     unlocked_rmeta_units: Vec<Unit>,
 }
 
+/// The data saved with `-Ztimings=file`.
+///
+/// This is what `cargo report timings` loads to compare builds, so changes
+/// to it should bump `TimingReport::VERSION`.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TimingReport {
+    pub version: u32,
+    /// A rendered string of when compilation started.
+    pub start: String,
+    /// Total time of the build in seconds.
+    pub duration: f64,
+    pub profile: String,
+    pub jobs: u32,
+    pub ncpu: usize,
+    /// The first line of `rustc -vV`.
+    pub rustc: String,
+    pub host: String,
+    pub targets: Vec<String>,
+    /// Tuples of `(package_description, target_descriptions)`.
+    pub root_targets: Vec<(String, Vec<String>)>,
+    pub total_fresh: u32,
+    pub total_dirty: u32,
+    /// The error the build failed with, if any.
+    pub error: Option<String>,
+    /// The dirty units, ordered by their start time.
+    pub units: Vec<UnitData>,
+    pub concurrency: Vec<Concurrency>,
+    /// Tuples of `(time, percentage usage of the system)`.
+    pub cpu_usage: Vec<(f64, f64)>,
+}
+
+impl TimingReport {
+    pub const VERSION: u32 = 1;
+
+    /// Loads a report saved with `-Ztimings=file`.
+    pub fn load(path: &Path) -> CargoResult<TimingReport> {
+        let contents = paths::read(path)?;
+        let report: TimingReport = serde_json::from_str(&contents).with_context(|| {
+            format!(
+                "failed to parse timing report `{}`, \
+                 expected a file saved with `-Ztimings=file`",
+                path.display()
+            )
+        })?;
+        if report.version != TimingReport::VERSION {
+            anyhow::bail!(
+                "timing report `{}` has version {}, but this version of Cargo \
+                 only understands version {}",
+                path.display(),
+                report.version,
+                TimingReport::VERSION
+            );
+        }
+        Ok(report)
+    }
+}
+
+/// A unit as saved in the reports.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct UnitData {
+    /// The index of the unit, used to refer to it in `unlocked_units`.
+    pub i: usize,
+    pub name: String,
+    pub version: String,
+    /// The target platform of the unit, or `host`.
+    pub kind: String,
+    /// The `CompileMode` of the unit, like `build` or `run-custom-build`.
+    pub mode: String,
+    /// The target as shown in the reports, like ` bin "foo" (test)`.
+    pub target: String,
+    pub start: f64,
+    pub duration: f64,
+    pub rmeta_time: Option<f64>,
+    /// Units that were freed to run after this unit finished.
+    pub unlocked_units: Vec<usize>,
+    /// Same as `unlocked_units`, but unlocked by rmeta.
+    pub unlocked_rmeta_units: Vec<usize>,
+}
+
 /// Periodic concurrency tracking information.
-#[derive(serde::Serialize)]
-struct Concurrency {
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct Concurrency {
     /// Time as an offset in seconds from `Timings::start`.
-    t: f64,
+    pub t: f64,
     /// Number of units currently running.
-    active: usize,
+    pub active: usize,
     /// Number of units that could run, but are waiting for a jobserver token.
-    waiting: usize,
+    pub waiting: usize,
     /// Number of units that are not yet ready, because they are waiting for
     /// dependencies to finish.
-    inactive: usize,
+    pub inactive: usize,
     /// Number of rustc "extra" threads -- i.e., how many tokens have been
     /// provided across all current rustc instances that are not the main thread
     /// tokens.
-    rustc_parallelism: usize,
+    pub rustc_parallelism: usize,
 }
 
 impl<'cfg> Timings<'cfg> {
@@ -104,7 +192,16 @@ impl<'cfg> Timings<'cfg> {
         let report_html = has_report("html");
         let report_info = has_report("info");
         let report_json = has_report("json");
-        let enabled = report_html | report_info | report_json;
+        let report_file = has_report("file");
+        let report_trace = has_report("trace");
+        // Durations are also needed to schedule units by their critical path
+        // in later builds.
+        let enabled = report_html
+            | report_info
+            | report_json
+            | report_file
+            | report_trace
+            | bcx.config.cli_unstable().critical_path_scheduling;
 
         let mut root_map: HashMap<PackageId, Vec<String>> = HashMap::new();
         for unit in root_units {
@@ -141,6 +238,8 @@ impl<'cfg> Timings<'cfg> {
             report_html,
             report_info,
             report_json,
+            report_file,
+            report_trace,
             start: bcx.config.creation_time(),
             start_str,
             root_targets,
@@ -161,26 +260,9 @@ impl<'cfg> Timings<'cfg> {
         if !self.enabled {
             return;
         }
-        let mut target = if unit.target.is_lib() && unit.mode == CompileMode::Build {
-            // Special case for brevity, since most dependencies hit
-            // this path.
-            "".to_string()
-        } else {
-            format!(" {}", unit.target.description_named())
-        };
-        match unit.mode {
-            CompileMode::Test => target.push_str(" (test)"),
-            CompileMode::Build => {}
-            CompileMode::Check { test: true } => target.push_str(" (check-test)"),
-            CompileMode::Check { test: false } => target.push_str(" (check)"),
-            CompileMode::Bench => target.push_str(" (bench)"),
-            CompileMode::Doc { .. } => target.push_str(" (doc)"),
-            CompileMode::Doctest => target.push_str(" (doc test)"),
-            CompileMode::RunCustomBuild => target.push_str(" (run)"),
-        }
         let unit_time = UnitTime {
+            target: target_description(&unit),
             unit,
-            target,
             start: self.start.elapsed().as_secs_f64(),
             duration: 0.0,
             rmeta_time: None,
@@ -327,6 +409,38 @@ impl<'cfg> Timings<'cfg> {
             self.report_html(bcx, error)
                 .with_context(|| "failed to save timing report")?;
         }
+        if self.report_file {
+            self.report_file(bcx, error)
+                .with_context(|| "failed to save timing report")?;
+        }
+        if self.report_trace {
+            self.report_trace()
+                .with_context(|| "failed to save timing trace")?;
+        }
+        Ok(())
+    }
+
+    /// The file name of a report saved with the given extension, with the
+    /// time the build started.
+    fn report_filename(&self, extension: &str) -> String {
+        let timestamp = self.start_str.replace(&['-', ':'][..], "");
+        format!("cargo-timing-{}.{}", timestamp, extension)
+    }
+
+    /// Links the report to a name without the timestamp, and tells the user
+    /// where it was saved.
+    fn report_saved(&self, filename: &str, extension: &str) -> CargoResult<()> {
+        let msg = format!(
+            "report saved to {}",
+            std::env::current_dir()
+                .unwrap_or_default()
+                .join(filename)
+                .display()
+        );
+        paths::link_or_copy(filename, format!("cargo-timing.{}", extension))?;
+        self.config
+            .shell()
+            .status_with_color("Timing", msg, termcolor::Color::Cyan)?;
         Ok(())
     }
 
@@ -337,8 +451,7 @@ impl<'cfg> Timings<'cfg> {
         error: &Option<anyhow::Error>,
     ) -> CargoResult<()> {
         let duration = self.start.elapsed().as_secs_f64();
-        let timestamp = self.start_str.replace(&['-', ':'][..], "");
-        let filename = format!("cargo-timing-{}.html", timestamp);
+        let filename = self.report_filename("html");
         let mut f = BufWriter::new(paths::create(&filename)?);
         let roots: Vec<&str> = self
             .root_targets
@@ -367,18 +480,155 @@ impl<'cfg> Timings<'cfg> {
             include_str!("timings.js")
         )?;
         drop(f);
-        let msg = format!(
-            "report saved to {}",
-            std::env::current_dir()
-                .unwrap_or_default()
-                .join(&filename)
-                .display()
-        );
-        paths::link_or_copy(&filename, "cargo-timing.html")?;
-        self.config
-            .shell()
-            .status_with_color("Timing", msg, termcolor::Color::Cyan)?;
-        Ok(())
+        self.report_saved(&filename, "html")
+    }
+
+    /// Save all of the data of the HTML report to disk as JSON.
+    fn report_file(
+        &self,
+        bcx: &BuildContext<'_, '_>,
+        error: &Option<anyhow::Error>,
+    ) -> CargoResult<()> {
+        let rustc = bcx
+            .rustc()
+            .verbose_version
+            .lines()
+            .next()
+            .expect("rustc version");
+        let report = TimingReport {
+            version: TimingReport::VERSION,
+            start: self.start_str.clone(),
+            duration: self.start.elapsed().as_secs_f64(),
+            profile: self.profile.clone(),
+            jobs: bcx.build_config.jobs,
+            ncpu: num_cpus::get(),
+            rustc: rustc.to_string(),
+            host: bcx.rustc().host.to_string(),
+            targets: bcx
+                .build_config
+                .requested_kinds
+                .iter()
+                .map(|kind| bcx.target_data.short_name(kind).to_string())
+                .collect(),
+            root_targets: self.root_targets.clone(),
+            total_fresh: self.total_fresh,
+            total_dirty: self.total_dirty,
+            error: error.as_ref().map(|e| e.to_string()),
+            units: self.unit_data(),
+            concurrency: self.concurrency.clone(),
+            cpu_usage: self.cpu_usage.clone(),
+        };
+        let filename = self.report_filename("json");
+        paths::write(&filename, serde_json::to_string_pretty(&report)?)?;
+        self.report_saved(&filename, "json")
+    }
+
+    /// Save the timings to disk in the Chrome trace event format.
+    ///
+    /// Every unit is a span with nested spans for the time until the
+    /// `.rmeta` file was generated and for the time of code generation.
+    /// Units are laid out on "threads" such that they don't overlap, like in
+    /// the HTML report. Concurrency and CPU usage are counters.
+    fn report_trace(&self) -> CargoResult<()> {
+        use serde_json::json;
+
+        // Trace timestamps are in microseconds.
+        let us = |t: f64| (t * 1_000_000.0).round() as u64;
+        let mut events = vec![json!({
+            "name": "process_name",
+            "ph": "M",
+            "pid": 1,
+            "args": {"name": format!("cargo ({})", self.profile)},
+        })];
+        // The end time of the last unit in each lane.
+        let mut lanes: Vec<f64> = Vec::new();
+        for ut in &self.unit_times {
+            let lane = match lanes.iter().position(|end| *end <= ut.start) {
+                Some(lane) => lane,
+                None => {
+                    lanes.push(0.0);
+                    events.push(json!({
+                        "name": "thread_name",
+                        "ph": "M",
+                        "pid": 1,
+                        "tid": lanes.len(),
+                        "args": {"name": format!("lane {}", lanes.len())},
+                    }));
+                    lanes.len() - 1
+                }
+            };
+            lanes[lane] = ut.start + ut.duration;
+            let tid = lane + 1;
+            events.push(json!({
+                "name": format!("{}{}", ut.name_ver(), ut.target),
+                "cat": "unit",
+                "ph": "X",
+                "pid": 1,
+                "tid": tid,
+                "ts": us(ut.start),
+                "dur": us(ut.duration),
+                "args": {
+                    "package": ut.unit.pkg.name().as_str(),
+                    "version": ut.unit.pkg.version().to_string(),
+                    "target": ut.unit.target.description_named(),
+                    "mode": ut.unit.mode,
+                    "features": &ut.unit.features,
+                    "unlocked_units": ut.unlocked_units.len(),
+                    "unlocked_rmeta_units": ut.unlocked_rmeta_units.len(),
+                },
+            }));
+            if let Some(rmeta_time) = ut.rmeta_time {
+                events.push(json!({
+                    "name": "metadata",
+                    "cat": "rmeta",
+                    "ph": "X",
+                    "pid": 1,
+                    "tid": tid,
+                    "ts": us(ut.start),
+                    "dur": us(rmeta_time),
+                }));
+                events.push(json!({
+                    "name": "codegen",
+                    "cat": "codegen",
+                    "ph": "X",
+                    "pid": 1,
+                    "tid": tid,
+                    "ts": us(ut.start + rmeta_time),
+                    "dur": us(ut.duration - rmeta_time),
+                }));
+            }
+        }
+        for c in &self.concurrency {
+            events.push(json!({
+                "name": "concurrency",
+                "ph": "C",
+                "pid": 1,
+                "ts": us(c.t),
+                "args": {
+                    "active": c.active,
+                    "waiting": c.waiting,
+                    "inactive": c.inactive,
+                    "rustc_parallelism": c.rustc_parallelism,
+                },
+            }));
+        }
+        for (t, usage) in &self.cpu_usage {
+            events.push(json!({
+                "name": "cpu usage",
+                "ph": "C",
+                "pid": 1,
+                "ts": us(*t),
+                "args": {"percent": usage},
+            }));
+        }
+        let trace = json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+            "otherData": {"start": self.start_str},
+        });
+        let filename = self.report_filename("trace.json");
+        paths::write(&filename, serde_json::to_string(&trace)?)?;
+        self.report_saved(&filename, "trace.json")
     }
 
     /// Render the summary table.
@@ -474,7 +724,8 @@ impl<'cfg> Timings<'cfg> {
         Ok(())
     }
 
-    fn write_js_data(&self, f: &mut impl Write) -> CargoResult<()> {
+    /// The units in the form used by the reports.
+    fn unit_data(&self) -> Vec<UnitData> {
         // Create a map to link indices of unlocked units.
         let unit_map: HashMap<Unit, usize> = self
             .unit_times
@@ -482,32 +733,11 @@ impl<'cfg> Timings<'cfg> {
             .enumerate()
             .map(|(i, ut)| (ut.unit.clone(), i))
             .collect();
-        #[derive(serde::Serialize)]
-        struct UnitData {
-            i: usize,
-            name: String,
-            version: String,
-            mode: String,
-            target: String,
-            start: f64,
-            duration: f64,
-            rmeta_time: Option<f64>,
-            unlocked_units: Vec<usize>,
-            unlocked_rmeta_units: Vec<usize>,
-        }
         let round = |x: f64| (x * 100.0).round() / 100.0;
-        let unit_data: Vec<UnitData> = self
-            .unit_times
+        self.unit_times
             .iter()
             .enumerate()
             .map(|(i, ut)| {
-                let mode = if ut.unit.mode.is_run_custom_build() {
-                    "run-custom-build"
-                } else {
-                    "todo"
-                }
-                .to_string();
-
                 // These filter on the unlocked units because not all unlocked
                 // units are actually "built". For example, Doctest mode units
                 // don't actually generate artifacts.
@@ -525,7 +755,11 @@ impl<'cfg> Timings<'cfg> {
                     i,
                     name: ut.unit.pkg.name().to_string(),
                     version: ut.unit.pkg.version().to_string(),
-                    mode,
+                    kind: match ut.unit.kind {
+                        CompileKind::Host => "host".to_string(),
+                        CompileKind::Target(target) => target.short_name().to_string(),
+                    },
+                    mode: ut.unit.mode.as_str().to_string(),
                     target: ut.target.clone(),
                     start: round(ut.start),
                     duration: round(ut.duration),
@@ -534,7 +768,11 @@ impl<'cfg> Timings<'cfg> {
                     unlocked_rmeta_units,
                 }
             })
-            .collect();
+            .collect()
+    }
+
+    fn write_js_data(&self, f: &mut impl Write) -> CargoResult<()> {
+        let unit_data = self.unit_data();
         writeln!(
             f,
             "const UNIT_DATA = {};",
@@ -618,6 +856,115 @@ impl UnitTime {
     }
 }
 
+/// Describes the target and mode of a unit, like ` bin "foo" (test)`.
+fn target_description(unit: &Unit) -> String {
+    let mut target = if unit.target.is_lib() && unit.mode == CompileMode::Build {
+        // Special case for brevity, since most dependencies hit
+        // this path.
+        "".to_string()
+    } else {
+        format!(" {}", unit.target.description_named())
+    };
+    match unit.mode {
+        CompileMode::Test => target.push_str(" (test)"),
+        CompileMode::Build => {}
+        CompileMode::Check { test: true } => target.push_str(" (check-test)"),
+        CompileMode::Check { test: false } => target.push_str(" (check)"),
+        CompileMode::Bench => target.push_str(" (bench)"),
+        CompileMode::Doc { .. } => target.push_str(" (doc)"),
+        CompileMode::Doctest => target.push_str(" (doc test)"),
+        CompileMode::RunCustomBuild => target.push_str(" (run)"),
+    }
+    target
+}
+
+/// How long units took to build in previous builds.
+///
+/// With `-Zcritical-path-scheduling`, these are saved in the target directory
+/// after every build, and used as the expected cost of units in the next one.
+pub struct UnitDurations {
+    path: PathBuf,
+    /// Durations in seconds, by `unit_key`.
+    durations: BTreeMap<String, f64>,
+    /// The expected duration of units that weren't built before, the
+    /// average of the known durations.
+    default_duration: f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct OnDiskUnitDurations {
+    version: u32,
+    durations: BTreeMap<String, f64>,
+}
+
+impl UnitDurations {
+    const VERSION: u32 = 1;
+
+    /// Loads the durations saved in the directory `dir`.
+    ///
+    /// Missing or unreadable durations are treated as if nothing was built
+    /// before, since they only affect the order units are built in.
+    pub fn load(dir: &Path) -> UnitDurations {
+        let path = dir.join(".unit-durations.json");
+        let durations = paths::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<OnDiskUnitDurations>(&contents).ok())
+            .filter(|on_disk| on_disk.version == UnitDurations::VERSION)
+            .map(|on_disk| on_disk.durations)
+            .unwrap_or_default();
+        let default_duration = if durations.is_empty() {
+            0.1
+        } else {
+            durations.values().sum::<f64>() / durations.len() as f64
+        };
+        UnitDurations {
+            path,
+            durations,
+            default_duration,
+        }
+    }
+
+    /// The expected cost of building `unit`, in milliseconds.
+    ///
+    /// Units that weren't built before are expected to take as long as the
+    /// average unit.
+    pub fn cost(&self, unit: &Unit) -> usize {
+        let secs = self
+            .durations
+            .get(&unit_key(unit))
+            .copied()
+            .unwrap_or(self.default_duration);
+        ((secs * 1000.0) as usize).max(1)
+    }
+
+    /// Adds the durations of the units built in this build, and saves all of
+    /// them.
+    pub fn save(&mut self, timings: &Timings<'_>) -> CargoResult<()> {
+        for ut in &timings.unit_times {
+            self.durations.insert(unit_key(&ut.unit), ut.duration);
+        }
+        let on_disk = OnDiskUnitDurations {
+            version: UnitDurations::VERSION,
+            durations: std::mem::take(&mut self.durations),
+        };
+        let result = serde_json::to_string_pretty(&on_disk)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| paths::write(&self.path, contents));
+        self.durations = on_disk.durations;
+        result
+    }
+}
+
+/// A name for the unit that stays the same across builds, even if the
+/// version of the package changes.
+pub(super) fn unit_key(unit: &Unit) -> String {
+    let kind = match unit.kind {
+        CompileKind::Host => String::new(),
+        CompileKind::Target(t) => format!(" for {}", t.short_name()),
+    };
+    format!("{}{}{}", unit.pkg.name(), target_description(unit), kind)
+}
+
 fn render_rustc_info(bcx: &BuildContext<'_, '_>) -> String {
     let version = bcx
         .rustc()
@@ -96,7 +96,10 @@ pub fn build_unit_dependencies<'a, 'cfg>(
 
     let std_unit_deps = calc_deps_of_std(&mut state, std_roots)?;
 
-    deps_of_roots(roots, &mut state)?;
+    // When only the standard library is built, its roots are among `roots`,
+    // and were handled above.
+    let roots: Vec<_> = roots.iter().filter(|unit| !unit.is_std).cloned().collect();
+    deps_of_roots(&roots, &mut state)?;
     super::links::validate_links(state.resolve(), &state.unit_dependencies)?;
     // Hopefully there aren't any links conflicts with the standard library?
 
@@ -44,6 +44,13 @@ struct Inner {
     // This dependency should be used only for this platform.
     // `None` means *all platforms*.
     platform: Option<Platform>,
+
+    /// The directory of the package within its git repository, as given by
+    /// the `subdir` key.
+    git_subdir: Option<InternedString>,
+    /// Whether the signature of the git commit or tag is verified, as asked
+    /// for by the `verify-signature` key.
+    verify_git_signature: bool,
 }
 
 #[derive(Serialize)]
@@ -159,6 +166,8 @@ impl Dependency {
                 specified_req: false,
                 platform: None,
                 explicit_name_in_toml: None,
+                git_subdir: None,
+                verify_git_signature: false,
             }),
         }
     }
@@ -254,6 +263,16 @@ impl Dependency {
         self.inner.platform.as_ref()
     }
 
+    /// The directory of the package within its git repository, if given.
+    pub fn git_subdir(&self) -> Option<InternedString> {
+        self.inner.git_subdir
+    }
+
+    /// Whether the signature of the git commit or tag must be verified.
+    pub fn verify_git_signature(&self) -> bool {
+        self.inner.verify_git_signature
+    }
+
     /// The renamed name of this dependency, if any.
     ///
     /// If the `package` key is used in `Cargo.toml` then this returns the same
@@ -309,6 +328,16 @@ impl Dependency {
         self
     }
 
+    pub fn set_git_subdir(&mut self, subdir: impl Into<InternedString>) -> &mut Dependency {
+        Rc::make_mut(&mut self.inner).git_subdir = Some(subdir.into());
+        self
+    }
+
+    pub fn set_verify_git_signature(&mut self, verify: bool) -> &mut Dependency {
+        Rc::make_mut(&mut self.inner).verify_git_signature = verify;
+        self
+    }
+
     pub fn set_explicit_name_in_toml(
         &mut self,
         name: impl Into<InternedString>,
@@ -403,6 +403,12 @@ features! {
 
     // Allow to specify per-package targets (compile kinds)
     (unstable, per_package_target, "", "reference/unstable.html#per-package-target"),
+
+    // Allow workspace members to inherit fields and dependencies.
+    (unstable, workspace_inheritance, "", "reference/unstable.html#workspace-inheritance"),
+
+    // Grant build scripts additional capabilities in the build script sandbox.
+    (unstable, build_capabilities, "", "reference/unstable.html#build-sandbox"),
 }
 
 pub struct Feature {
@@ -623,31 +629,43 @@ unstable_cli_options!(
     advanced_env: bool = (HIDDEN),
     avoid_dev_deps: bool = ("Avoid installing dev-dependencies if possible"),
     binary_dep_depinfo: bool = ("Track changes to dependency artifacts"),
+    build_sandbox: bool = ("Run build scripts in a sandbox that restricts their file system and network access"),
     #[serde(deserialize_with = "deserialize_build_std")]
     build_std: Option<Vec<String>>  = ("Enable Cargo to compile the standard library itself as part of a crate graph compilation"),
     build_std_features: Option<Vec<String>>  = ("Configure features enabled for the standard library itself when building the standard library"),
+    checksum_freshness: bool = ("Fall back to hashing source files when their mtimes indicate a rebuild"),
     config_include: bool = ("Enable the `include` key in config files"),
     configurable_env: bool = ("Enable the [env] section in the .cargo/config.toml file"),
     credential_process: bool = ("Add a config setting to fetch registry authentication tokens by calling an external process"),
+    critical_path_scheduling: bool = ("Build the longest chains of units first, using how long units took in previous builds"),
     doctest_in_workspace: bool = ("Compile doctests with paths relative to the workspace root"),
     doctest_xcompile: bool = ("Compile and run doctests for non-host target using runner config"),
     dual_proc_macros: bool = ("Build proc-macros for both the host and the target"),
     future_incompat_report: bool = ("Enable creation of a future-incompat report for all dependencies"),
+    gc: bool = ("Track last-use of the global package cache and enable `cargo gc`"),
+    git: Option<Vec<String>> = ("Fetch git dependencies or the registry index shallowly or partially, check out only the packages in use, verify signatures, use any reference, or select the git backend: shallow-deps, shallow-index, partial-deps, sparse-checkout, verify-signatures, refs, backend"),
     extra_link_arg: bool = ("Allow `cargo:rustc-link-arg` in build scripts"),
     features: Option<Vec<String>>  = (HIDDEN),
     jobserver_per_rustc: bool = (HIDDEN),
+    max_memory: bool = ("Limit how many units are built at once to stay within `build.max-memory`"),
     minimal_versions: bool = ("Resolve minimal dependency versions instead of maximum"),
+    msrv_policy: bool = ("Prefer dependency versions compatible with the workspace's `rust-version`"),
     mtime_on_use: bool = ("Configure Cargo to update the mtime of used files"),
     multitarget: bool = ("Allow passing multiple `--target` flags to the cargo subcommand selected"),
     named_profiles: bool = ("Allow defining custom profiles"),
+    namespaced_directives: bool = ("Allow the `cargo::` prefix for build script directives"),
     namespaced_features: bool = ("Allow features with `dep:` prefix"),
     no_index_update: bool = ("Do not update the registry index even if the cache is outdated"),
     panic_abort_tests: bool = ("Enable support to run tests with -Cpanic=abort"),
     host_config: bool = ("Enable the [host] section in the .cargo/config.toml file"),
     target_applies_to_host: bool = ("Enable the `target-applies-to-host` key in the .cargo/config.toml file"),
     patch_in_config: bool = ("Allow `[patch]` sections in .cargo/config.toml files"),
+    remote_cache: bool = ("Share the artifacts of non-path dependencies through a helper process configured with `build.remote-cache`"),
+    rerun_if_changed_glob: bool = ("Allow glob patterns in `cargo:rerun-if-changed`"),
     rustdoc_map: bool = ("Allow passing external documentation mappings to rustdoc"),
     separate_nightlies: bool = (HIDDEN),
+    shared_cache: bool = ("Share the artifacts of non-path dependencies between workspaces with `build.shared-cache`"),
+    sparse_registry: bool = ("Support plain-HTTP-based crate registries"),
     terminal_width: Option<Option<usize>>  = ("Provide a terminal width to rustc for error truncation"),
     timings: Option<Vec<String>>  = ("Display concurrency information"),
     unstable_options: bool = ("Allow the usage of unstable options"),
@@ -768,6 +786,39 @@ impl CliUnstable {
             Ok(true)
         }
 
+        fn parse_git(value: Option<&str>) -> CargoResult<Vec<String>> {
+            let modes = parse_features(value);
+            if modes.is_empty() {
+                bail!("-Zgit requires a comma-separated list of options");
+            }
+            for mode in &modes {
+                if mode == "partial-index" {
+                    bail!(
+                        "-Zgit=partial-index is not supported, as the contents of all files \
+                         of the index would be fetched right after"
+                    );
+                }
+                if !matches!(
+                    mode.as_str(),
+                    "shallow-deps"
+                        | "shallow-index"
+                        | "partial-deps"
+                        | "sparse-checkout"
+                        | "verify-signatures"
+                        | "refs"
+                        | "backend"
+                ) {
+                    bail!(
+                        "unknown -Zgit option `{}`, expected one of `shallow-deps`, \
+                         `shallow-index`, `partial-deps`, `sparse-checkout`, \
+                         `verify-signatures`, `refs` or `backend`",
+                        mode
+                    );
+                }
+            }
+            Ok(modes)
+        }
+
         fn parse_usize_opt(value: Option<&str>) -> CargoResult<Option<usize>> {
             Ok(match value {
                 Some(value) => match value.parse::<usize>() {
@@ -815,17 +866,21 @@ impl CliUnstable {
             "no-index-update" => self.no_index_update = parse_empty(k, v)?,
             "avoid-dev-deps" => self.avoid_dev_deps = parse_empty(k, v)?,
             "minimal-versions" => self.minimal_versions = parse_empty(k, v)?,
+            "max-memory" => self.max_memory = parse_empty(k, v)?,
             "advanced-env" => self.advanced_env = parse_empty(k, v)?,
             "config-include" => self.config_include = parse_empty(k, v)?,
+            "critical-path-scheduling" => self.critical_path_scheduling = parse_empty(k, v)?,
             "dual-proc-macros" => self.dual_proc_macros = parse_empty(k, v)?,
             // can also be set in .cargo/config or with and ENV
             "mtime-on-use" => self.mtime_on_use = parse_empty(k, v)?,
             "named-profiles" => self.named_profiles = parse_empty(k, v)?,
             "binary-dep-depinfo" => self.binary_dep_depinfo = parse_empty(k, v)?,
+            "checksum-freshness" => self.checksum_freshness = parse_empty(k, v)?,
             "build-std" => {
                 self.build_std = Some(crate::core::compiler::standard_lib::parse_unstable_flag(v))
             }
             "build-std-features" => self.build_std_features = Some(parse_features(v)),
+            "build-sandbox" => self.build_sandbox = parse_empty(k, v)?,
             "timings" => self.timings = Some(parse_timings(v)),
             "doctest-xcompile" => self.doctest_xcompile = parse_empty(k, v)?,
             "doctest-in-workspace" => self.doctest_in_workspace = parse_empty(k, v)?,
@@ -857,9 +912,17 @@ impl CliUnstable {
                 self.features = Some(feats);
             }
             "separate-nightlies" => self.separate_nightlies = parse_empty(k, v)?,
+            "remote-cache" => self.remote_cache = parse_empty(k, v)?,
+            "shared-cache" => self.shared_cache = parse_empty(k, v)?,
+            "sparse-registry" => self.sparse_registry = parse_empty(k, v)?,
+            "gc" => self.gc = parse_empty(k, v)?,
+            "git" => self.git = Some(parse_git(v)?),
+            "msrv-policy" => self.msrv_policy = parse_empty(k, v)?,
             "multitarget" => self.multitarget = parse_empty(k, v)?,
             "rustdoc-map" => self.rustdoc_map = parse_empty(k, v)?,
+            "rerun-if-changed-glob" => self.rerun_if_changed_glob = parse_empty(k, v)?,
             "terminal-width" => self.terminal_width = Some(parse_usize_opt(v)?),
+            "namespaced-directives" => self.namespaced_directives = parse_empty(k, v)?,
             "namespaced-features" => self.namespaced_features = parse_empty(k, v)?,
             "weak-dep-features" => self.weak_dep_features = parse_empty(k, v)?,
             "extra-link-arg" => self.extra_link_arg = parse_empty(k, v)?,
@@ -879,6 +942,11 @@ impl CliUnstable {
         Ok(())
     }
 
+    /// Whether `option` was passed to `-Zgit`.
+    pub fn git_option(&self, option: &str) -> bool {
+        self.git.iter().flatten().any(|o| o == option)
+    }
+
     /// Generates an error if `-Z unstable-options` was not used for a new,
     /// unstable command-line flag.
     pub fn fail_if_stable_opt(&self, flag: &str, issue: u32) -> CargoResult<()> {
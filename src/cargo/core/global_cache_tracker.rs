@@ -0,0 +1,254 @@
+//! Tracking of when the contents of the global package cache were last used.
+//!
+//! Cargo never cleans up `$CARGO_HOME/registry` or `$CARGO_HOME/git` on its
+//! own, so they grow forever. With `-Z gc`, every registry index, `.crate`
+//! file, extracted crate source, git database and git checkout that Cargo
+//! uses is recorded with a timestamp in a small database at
+//! `$CARGO_HOME/.global-cache`. `cargo gc` (see [`crate::ops::gc`]) then uses
+//! those timestamps to decide what to delete.
+//!
+//! Uses are collected in memory with [`Config::mark_cache_used`] and written
+//! out all at once right before the package cache lock is released, so the
+//! database is only ever read or modified while that lock is held.
+
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+use anyhow::Context as _;
+use cargo_util::paths;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the last-use database, relative to `CARGO_HOME`.
+pub const GLOBAL_CACHE_FILENAME: &str = ".global-cache";
+
+const DATABASE_VERSION: u32 = 1;
+
+/// The different kinds of entries tracked in the global cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CacheKind {
+    /// The index of a registry in `registry/index/`.
+    RegistryIndex,
+    /// A `.crate` file in `registry/cache/<registry>/`.
+    RegistryCrate,
+    /// An extracted crate in `registry/src/<registry>/`.
+    RegistrySrc,
+    /// A bare git database in `git/db/`.
+    GitDb,
+    /// A checkout of a git revision in `git/checkouts/<repo>/`.
+    GitCheckout,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 5] = [
+        CacheKind::RegistryIndex,
+        CacheKind::RegistryCrate,
+        CacheKind::RegistrySrc,
+        CacheKind::GitDb,
+        CacheKind::GitCheckout,
+    ];
+
+    /// Returns the directory (relative to `CARGO_HOME`) holding entries of
+    /// this kind, and how many levels below that directory an entry is.
+    fn location(self) -> (&'static [&'static str], usize) {
+        match self {
+            CacheKind::RegistryIndex => (&["registry", "index"], 1),
+            CacheKind::RegistryCrate => (&["registry", "cache"], 2),
+            CacheKind::RegistrySrc => (&["registry", "src"], 2),
+            CacheKind::GitDb => (&["git", "db"], 1),
+            CacheKind::GitCheckout => (&["git", "checkouts"], 2),
+        }
+    }
+
+    fn is_entry(self, path: &Path) -> bool {
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => return false,
+        };
+        if name.starts_with('.') {
+            return false;
+        }
+        match self {
+            CacheKind::RegistryCrate => name.ends_with(".crate") && path.is_file(),
+            _ => path.is_dir(),
+        }
+    }
+}
+
+/// An entry of the global cache, along with when it was last used.
+#[derive(Debug)]
+pub struct CacheEntry {
+    pub kind: CacheKind,
+    /// Absolute path to the file or directory.
+    pub path: PathBuf,
+    /// Seconds since the Unix epoch.
+    pub last_use: u64,
+    key: String,
+}
+
+/// The on-disk format of the database.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Database {
+    version: u32,
+    /// Maps paths relative to `CARGO_HOME` (always using `/` as separator)
+    /// to the time they were last used, in seconds since the Unix epoch.
+    last_use: BTreeMap<String, u64>,
+}
+
+impl Default for Database {
+    fn default() -> Database {
+        Database {
+            version: DATABASE_VERSION,
+            last_use: BTreeMap::new(),
+        }
+    }
+}
+
+/// A handle to the last-use database.
+pub struct GlobalCacheTracker<'cfg> {
+    config: &'cfg Config,
+    db: Database,
+}
+
+impl<'cfg> GlobalCacheTracker<'cfg> {
+    /// Loads the database from disk.
+    ///
+    /// The package cache lock must be held for as long as this is used.
+    pub fn load(config: &'cfg Config) -> CargoResult<GlobalCacheTracker<'cfg>> {
+        let db_path = config.home().join(GLOBAL_CACHE_FILENAME);
+        let path = config.assert_package_cache_locked(&db_path);
+        let db = if path.exists() {
+            let contents = paths::read_bytes(path)?;
+            match serde_json::from_slice::<Database>(&contents) {
+                Ok(db) if db.version == DATABASE_VERSION => db,
+                // Losing the last-use data only means that entries get a
+                // fresh grace period, so don't make this a hard error.
+                Ok(_) | Err(_) => {
+                    log::warn!(
+                        "ignoring invalid global cache database `{}`",
+                        path.display()
+                    );
+                    Database::default()
+                }
+            }
+        } else {
+            Database::default()
+        };
+        Ok(GlobalCacheTracker { config, db })
+    }
+
+    /// Writes the database back to disk.
+    pub fn save(&self) -> CargoResult<()> {
+        let db_path = self.config.home().join(GLOBAL_CACHE_FILENAME);
+        let path = self.config.assert_package_cache_locked(&db_path);
+        let contents = serde_json::to_vec(&self.db)?;
+        // Write to a temporary file first so that an interrupted write
+        // doesn't leave a truncated database behind.
+        let tmp = path.with_extension("tmp");
+        paths::write(&tmp, contents)?;
+        fs::rename(&tmp, path).with_context(|| format!("failed to rename `{}`", tmp.display()))?;
+        Ok(())
+    }
+
+    /// Records that `path` was used at `now`.
+    ///
+    /// Paths that aren't an entry of the global cache (for example, `.crate`
+    /// files of local registries) are ignored.
+    pub fn mark_used(&mut self, path: &Path, now: u64) {
+        if let Some(key) = self.key(path) {
+            self.db.last_use.insert(key, now);
+        }
+    }
+
+    /// Scans the global cache for all entries that are currently on disk.
+    ///
+    /// Entries which were never recorded in the database (for example, ones
+    /// created by a Cargo without `-Z gc`) are treated as used at `now`, so
+    /// they aren't deleted before they had a chance to be tracked. Entries in
+    /// the database which no longer exist are forgotten.
+    pub fn entries(&mut self, now: u64) -> CargoResult<Vec<CacheEntry>> {
+        let home = self.config.home().clone();
+        let home = self.config.assert_package_cache_locked(&home);
+        let mut entries = Vec::new();
+        for &kind in CacheKind::ALL.iter() {
+            let (dir, depth) = kind.location();
+            let mut candidates = vec![dir.iter().fold(home.to_path_buf(), |p, c| p.join(c))];
+            for _ in 0..depth {
+                candidates = candidates
+                    .iter()
+                    .filter_map(|parent| fs::read_dir(parent).ok())
+                    .flatten()
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .collect();
+            }
+            for path in candidates {
+                if !kind.is_entry(&path) {
+                    continue;
+                }
+                let key = match self.key(&path) {
+                    Some(key) => key,
+                    None => continue,
+                };
+                let last_use = *self.db.last_use.entry(key.clone()).or_insert(now);
+                entries.push(CacheEntry {
+                    kind,
+                    path,
+                    last_use,
+                    key,
+                });
+            }
+        }
+        let on_disk: HashSet<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+        self.db
+            .last_use
+            .retain(|key, _| on_disk.contains(key.as_str()));
+        entries.sort_by(|a, b| (a.last_use, &a.path).cmp(&(b.last_use, &b.path)));
+        Ok(entries)
+    }
+
+    /// Forgets about an entry, after it has been deleted.
+    pub fn remove(&mut self, entry: &CacheEntry) {
+        self.db.last_use.remove(&entry.key);
+    }
+
+    /// Returns the database key of `path`, if it is an entry of the global
+    /// cache.
+    fn key(&self, path: &Path) -> Option<String> {
+        let rel = path
+            .strip_prefix(self.config.home().as_path_unlocked())
+            .ok()?;
+        let components = rel
+            .components()
+            .map(|c| c.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()?;
+        CacheKind::ALL.iter().find_map(|kind| {
+            let (dir, depth) = kind.location();
+            if components.len() == dir.len() + depth && components.starts_with(dir) {
+                Some(components.join("/"))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// The current time in seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Saves the uses recorded with [`Config::mark_cache_used`].
+pub(crate) fn save_uses(config: &Config, uses: &HashSet<PathBuf>) -> CargoResult<()> {
+    let mut tracker = GlobalCacheTracker::load(config)?;
+    let now = now();
+    for path in uses {
+        tracker.mark_used(path, now);
+    }
+    tracker.save()
+}
@@ -53,6 +53,20 @@ pub struct Manifest {
     default_run: Option<String>,
     metabuild: Option<Vec<String>>,
     resolve_behavior: Option<ResolveBehavior>,
+    build_capabilities: BuildCapabilities,
+}
+
+/// What the build script of a package may do when it's run in the sandbox of
+/// `-Zbuild-sandbox`, besides the access it always has, as granted in the
+/// `[package.build-capabilities]` table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BuildCapabilities {
+    /// Whether the build script may access the network.
+    pub network: bool,
+    /// Additional paths within the package the build script may read.
+    pub read: Vec<PathBuf>,
+    /// Additional paths within the package the build script may write.
+    pub write: Vec<PathBuf>,
 }
 
 /// When parsing `Cargo.toml`, some warnings should silenced
@@ -389,6 +403,7 @@ impl Manifest {
         original: Rc<TomlManifest>,
         metabuild: Option<Vec<String>>,
         resolve_behavior: Option<ResolveBehavior>,
+        build_capabilities: BuildCapabilities,
     ) -> Manifest {
         Manifest {
             summary,
@@ -414,6 +429,7 @@ impl Manifest {
             default_run,
             metabuild,
             resolve_behavior,
+            build_capabilities,
         }
     }
 
@@ -556,6 +572,10 @@ impl Manifest {
         self.metabuild.as_ref()
     }
 
+    pub fn build_capabilities(&self) -> &BuildCapabilities {
+        &self.build_capabilities
+    }
+
     pub fn metabuild_path(&self, target_dir: Filesystem) -> PathBuf {
         let hash = short_hash(&self.package_id());
         target_dir
@@ -10,11 +10,13 @@ pub use self::resolver::{Resolve, ResolveVersion};
 pub use self::shell::{Shell, Verbosity};
 pub use self::source::{GitReference, Source, SourceId, SourceMap};
 pub use self::summary::{FeatureMap, FeatureValue, Summary};
+pub(crate) use self::workspace::{find_root_iter, read_root_pointer};
 pub use self::workspace::{MaybePackage, Workspace, WorkspaceConfig, WorkspaceRootConfig};
 
 pub mod compiler;
 pub mod dependency;
 pub mod features;
+pub mod global_cache_tracker;
 pub mod manifest;
 pub mod package;
 pub mod package_id;
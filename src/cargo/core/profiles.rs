@@ -26,6 +26,9 @@ pub struct Profiles {
     requested_profile: InternedString,
     /// The host target for rustc being used by this `Profiles`.
     rustc_host: InternedString,
+    /// The panic strategy of everything built for some targets, set with
+    /// `build-std.panic-strategy`.
+    panic_overrides: HashMap<CompileKind, PanicStrategy>,
 }
 
 impl Profiles {
@@ -46,6 +49,7 @@ impl Profiles {
                 by_name: HashMap::new(),
                 requested_profile,
                 rustc_host,
+                panic_overrides: HashMap::new(),
             };
 
             profile_makers.by_name.insert(
@@ -99,6 +103,7 @@ impl Profiles {
             by_name: HashMap::new(),
             requested_profile,
             rustc_host,
+            panic_overrides: HashMap::new(),
         };
 
         Self::add_root_profiles(&mut profile_makers, &profiles);
@@ -345,6 +350,11 @@ impl Profiles {
                 }
             }
         }
+        if !matches!(unit_for.panic_setting(), PanicSetting::AlwaysUnwind) {
+            if let Some(panic) = self.panic_overrides.get(&kind) {
+                profile.panic = *panic;
+            }
+        }
 
         // Default macOS debug information to being stored in the "unpacked"
         // split-debuginfo format. At the time of this writing that's the only
@@ -381,6 +391,12 @@ impl Profiles {
         profile
     }
 
+    /// Sets the panic strategy of everything built for `kind`, overriding
+    /// the profiles.
+    pub fn override_panic(&mut self, kind: CompileKind, panic: PanicStrategy) {
+        self.panic_overrides.insert(kind, panic);
+    }
+
     /// The profile for *running* a `build.rs` script is only used for setting
     /// a few environment variables. To ensure proper de-duplication of the
     /// running `Unit`, this uses a stripped-down profile (so that unrelated
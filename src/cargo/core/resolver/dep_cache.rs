@@ -11,7 +11,9 @@
 
 use crate::core::resolver::context::Context;
 use crate::core::resolver::errors::describe_path;
-use crate::core::resolver::types::{ConflictReason, DepInfo, FeaturesSet};
+use crate::core::resolver::types::{
+    ConflictReason, DepInfo, FeaturesSet, IncompatibleRustVersions, RustVersionPolicy,
+};
 use crate::core::resolver::{
     ActivateError, ActivateResult, CliFeatures, RequestedFeatures, ResolveOpts,
 };
@@ -29,6 +31,9 @@ pub struct RegistryQueryer<'a> {
     pub registry: &'a mut (dyn Registry + 'a),
     replacements: &'a [(PackageIdSpec, Dependency)],
     try_to_use: &'a HashSet<PackageId>,
+    /// If set, candidates compatible with the workspace's `rust-version` are
+    /// sorted first, or are the only candidates, depending on the policy.
+    pub rust_version: Option<&'a RustVersionPolicy>,
     /// If set the list of dependency candidates will be sorted by minimal
     /// versions first. That allows `cargo update -Z minimal-versions` which will
     /// specify minimum dependency versions to be used.
@@ -49,12 +54,14 @@ impl<'a> RegistryQueryer<'a> {
         registry: &'a mut dyn Registry,
         replacements: &'a [(PackageIdSpec, Dependency)],
         try_to_use: &'a HashSet<PackageId>,
+        rust_version: Option<&'a RustVersionPolicy>,
         minimal_versions: bool,
     ) -> Self {
         RegistryQueryer {
             registry,
             replacements,
             try_to_use,
+            rust_version,
             minimal_versions,
             registry_cache: HashMap::new(),
             summary_cache: HashMap::new(),
@@ -164,15 +171,27 @@ impl<'a> RegistryQueryer<'a> {
             }
         }
 
+        if let Some(rust_version) = self.rust_version {
+            if rust_version.incompatible() == IncompatibleRustVersions::Deny {
+                ret.retain(|s| rust_version.is_compatible(s));
+            }
+        }
+
         // When we attempt versions for a package we'll want to do so in a
         // sorted fashion to pick the "best candidates" first. Currently we try
-        // prioritized summaries (those in `try_to_use`) and failing that we
-        // list everything from the maximum version to the lowest version.
+        // prioritized summaries (those in `try_to_use`), then those compatible
+        // with the workspace's `rust-version`, and failing that we list
+        // everything from the maximum version to the lowest version.
+        let rust_version = self.rust_version;
         ret.sort_unstable_by(|a, b| {
             let a_in_previous = self.try_to_use.contains(&a.package_id());
             let b_in_previous = self.try_to_use.contains(&b.package_id());
             let previous_cmp = a_in_previous.cmp(&b_in_previous).reverse();
-            match previous_cmp {
+            let compatible_cmp = || match rust_version {
+                Some(rv) => rv.is_compatible(a).cmp(&rv.is_compatible(b)).reverse(),
+                None => Ordering::Equal,
+            };
+            match previous_cmp.then_with(compatible_cmp) {
                 Ordering::Equal => {
                     let cmp = a.version().cmp(b.version());
                     if self.minimal_versions {
@@ -183,7 +202,7 @@ impl<'a> RegistryQueryer<'a> {
                         cmp.reverse()
                     }
                 }
-                _ => previous_cmp,
+                cmp => cmp,
             }
         });
 
@@ -6,7 +6,7 @@ use crate::util::{Config, VersionExt};
 use anyhow::Error;
 
 use super::context::Context;
-use super::types::{ConflictMap, ConflictReason};
+use super::types::{ConflictMap, ConflictReason, IncompatibleRustVersions, RustVersionPolicy};
 
 /// Error during resolution providing a path of `PackageId`s.
 pub struct ResolveError {
@@ -74,6 +74,7 @@ pub(super) fn activation_error(
     dep: &Dependency,
     conflicting_activations: &ConflictMap,
     candidates: &[Summary],
+    rust_version: Option<&RustVersionPolicy>,
     config: Option<&Config>,
 ) -> ResolveError {
     let to_resolve_err = |err| {
@@ -209,6 +210,50 @@ pub(super) fn activation_error(
         return to_resolve_err(anyhow::format_err!("{}", msg));
     }
 
+    // All candidates may have been filtered out because they need a newer
+    // Rust than the workspace supports, so explain that instead of claiming
+    // that nothing matched.
+    if let Some(rust_version) = rust_version {
+        if rust_version.incompatible() == IncompatibleRustVersions::Deny {
+            let mut incompatible = match registry.query_vec(dep, false) {
+                Ok(candidates) => candidates,
+                Err(e) => return to_resolve_err(e),
+            };
+            incompatible.retain(|s| !rust_version.is_compatible(s));
+            if !incompatible.is_empty() {
+                incompatible.sort_unstable_by(|a, b| b.version().cmp(a.version()));
+                let mut msg = format!(
+                    "failed to select a version for the requirement `{} = \"{}\"`\n\
+                     all candidate versions require a newer Rust than the \
+                     workspace's `rust-version` of {}:",
+                    dep.package_name(),
+                    dep.version_req(),
+                    rust_version.rust_version(),
+                );
+                for summary in incompatible.iter().take(3) {
+                    msg.push_str(&format!(
+                        "\n  {} v{} requires rustc {}",
+                        summary.name(),
+                        summary.version(),
+                        summary.rust_version().as_deref().unwrap_or_default(),
+                    ));
+                }
+                if incompatible.len() > 3 {
+                    msg.push_str("\n  ...");
+                }
+                msg.push_str("\nrequired by ");
+                msg.push_str(&describe_path(
+                    &cx.parents.path_to_bottom(&parent.package_id()),
+                ));
+                msg.push_str(
+                    "\nconsider raising the `rust-version`, or setting \
+                     `resolver.incompatible-rust-versions = \"fallback\"`",
+                );
+                return to_resolve_err(anyhow::format_err!("{}", msg));
+            }
+        }
+    }
+
     // We didn't actually find any candidates, so we need to
     // give an error message that nothing was found.
     //
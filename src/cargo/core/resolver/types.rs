@@ -2,6 +2,7 @@ use super::features::{CliFeatures, RequestedFeatures};
 use crate::core::{Dependency, PackageId, Summary};
 use crate::util::errors::CargoResult;
 use crate::util::interning::InternedString;
+use crate::util::to_semver::parse_rust_version;
 use crate::util::Config;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
@@ -127,6 +128,80 @@ impl ResolveBehavior {
     }
 }
 
+/// How the resolver treats candidates whose `rust-version` is newer than the
+/// workspace's, set with the `resolver.incompatible-rust-versions` config.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IncompatibleRustVersions {
+    /// Ignore `rust-version` when picking versions.
+    Allow,
+    /// Prefer compatible versions, but pick an incompatible one if that's the
+    /// only way to satisfy the requirements.
+    Fallback,
+    /// Never pick an incompatible version.
+    Deny,
+}
+
+/// The minimum `rust-version` of the workspace, and how the resolver should
+/// treat registry packages which need a newer Rust.
+#[derive(Clone, Debug)]
+pub struct RustVersionPolicy {
+    /// The `rust-version` as written in the manifest, such as `1.56`.
+    rust_version: InternedString,
+    /// `rust_version` with missing components filled with zeros.
+    version: semver::Version,
+    incompatible: IncompatibleRustVersions,
+}
+
+impl RustVersionPolicy {
+    /// Returns `None` if `rust_version` isn't a valid `rust-version`.
+    pub fn new(
+        rust_version: &str,
+        incompatible: IncompatibleRustVersions,
+    ) -> Option<RustVersionPolicy> {
+        Some(RustVersionPolicy {
+            rust_version: InternedString::new(rust_version),
+            version: parse_rust_version(rust_version).ok()?,
+            incompatible,
+        })
+    }
+
+    pub fn rust_version(&self) -> InternedString {
+        self.rust_version
+    }
+
+    /// The version of Rust this policy is for, used to pick the lowest
+    /// `rust-version` among workspace members.
+    pub fn version(&self) -> &semver::Version {
+        &self.version
+    }
+
+    pub fn incompatible(&self) -> IncompatibleRustVersions {
+        self.incompatible
+    }
+
+    /// Returns whether `summary` can be built with the workspace's
+    /// `rust-version`.
+    ///
+    /// Only registry packages are checked, as there is no other version to
+    /// pick for path or git dependencies anyway. A `rust-version` which fails
+    /// to parse is treated as compatible.
+    pub fn is_compatible(&self, summary: &Summary) -> bool {
+        if self.incompatible == IncompatibleRustVersions::Allow
+            || !summary.source_id().is_registry()
+        {
+            return true;
+        }
+        match summary.rust_version() {
+            Some(rust_version) => match parse_rust_version(&rust_version) {
+                Ok(version) => version <= self.version,
+                Err(_) => true,
+            },
+            None => true,
+        }
+    }
+}
+
 /// Options for how the resolve should work.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ResolveOpts {
@@ -67,6 +67,8 @@ pub enum GitReference {
     Rev(String),
     /// The default branch of the repository, the reference named `HEAD`.
     DefaultBranch,
+    /// From any fully qualified reference, such as `refs/pull/123/head`.
+    Ref(String),
 }
 
 impl SourceId {
@@ -117,7 +119,10 @@ impl SourceId {
                 let mut reference = GitReference::DefaultBranch;
                 for (k, v) in url.query_pairs() {
                     match &k[..] {
-                        // Map older 'ref' to branch.
+                        // Map older 'ref' to branch, unless it's fully qualified.
+                        "ref" if v.starts_with("refs/") => {
+                            reference = GitReference::Ref(v.into_owned())
+                        }
                         "branch" | "ref" => reference = GitReference::Branch(v.into_owned()),
 
                         "rev" => reference = GitReference::Rev(v.into_owned()),
@@ -135,6 +140,12 @@ impl SourceId {
                 Ok(SourceId::new(SourceKind::Registry, url)?
                     .with_precise(Some("locked".to_string())))
             }
+            "sparse" => {
+                // The `sparse+` prefix is kept as part of the registry URL.
+                let url = string.into_url()?;
+                Ok(SourceId::new(SourceKind::Registry, url)?
+                    .with_precise(Some("locked".to_string())))
+            }
             "path" => {
                 let url = url.into_url()?;
                 SourceId::new(SourceKind::Path, url)
@@ -164,6 +175,9 @@ impl SourceId {
     }
 
     /// Creates a SourceId from a registry URL.
+    ///
+    /// URLs prefixed with `sparse+` refer to a registry whose index is
+    /// fetched file-by-file over HTTP instead of being cloned with git.
     pub fn for_registry(url: &Url) -> CargoResult<SourceId> {
         SourceId::new(SourceKind::Registry, url.clone())
     }
@@ -262,6 +276,13 @@ impl SourceId {
         matches!(self.inner.kind, SourceKind::Registry)
     }
 
+    /// Returns `true` if this source is a remote registry whose index is
+    /// served over plain HTTP (a `sparse+` URL) instead of git.
+    pub fn is_sparse(self) -> bool {
+        matches!(self.inner.kind, SourceKind::Registry)
+            && self.inner.url.scheme().starts_with("sparse+")
+    }
+
     /// Returns `true` if this source from a Git repository.
     pub fn is_git(self) -> bool {
         matches!(self.inner.kind, SourceKind::Git(_))
@@ -287,7 +308,7 @@ impl SourceId {
                 self,
                 yanked_whitelist,
                 config,
-            ))),
+            )?)),
             SourceKind::LocalRegistry => {
                 let path = match self.inner.url.to_file_path() {
                     Ok(p) => p,
@@ -619,7 +640,14 @@ impl<'a> fmt::Display for SourceIdAsUrl<'a> {
                 kind: SourceKind::Registry,
                 ref url,
                 ..
-            } => write!(f, "registry+{}", url),
+            } => {
+                // Sparse registry URLs already carry their `sparse+` prefix.
+                if url.scheme().starts_with("sparse+") {
+                    write!(f, "{}", url)
+                } else {
+                    write!(f, "registry+{}", url)
+                }
+            }
             SourceIdInner {
                 kind: SourceKind::LocalRegistry,
                 ref url,
@@ -656,6 +684,7 @@ impl<'a> fmt::Display for PrettyRef<'a> {
             GitReference::Branch(ref b) => write!(f, "branch={}", b),
             GitReference::Tag(ref s) => write!(f, "tag={}", s),
             GitReference::Rev(ref s) => write!(f, "rev={}", s),
+            GitReference::Ref(ref s) => write!(f, "ref={}", s),
             GitReference::DefaultBranch => unreachable!(),
         }
     }
@@ -681,4 +710,31 @@ mod tests {
         let s3 = SourceId::new(foo, loc).unwrap();
         assert_ne!(s1, s3);
     }
+
+    #[test]
+    fn sparse_registry_url_roundtrip() {
+        let url = "sparse+https://index.example.com/";
+        let sid = SourceId::from_url(url).unwrap();
+        assert!(sid.is_sparse());
+        assert!(sid.is_remote_registry());
+        assert_eq!(sid.as_url().to_string(), url);
+
+        let git = SourceId::from_url("registry+https://example.com/index").unwrap();
+        assert!(!git.is_sparse());
+        assert_ne!(sid, git);
+    }
+
+    #[test]
+    fn git_ref_url_roundtrip() {
+        let url = "git+https://example.com/foo?ref=refs/pull/1/head#0123abcd";
+        let sid = SourceId::from_url(url).unwrap();
+        let reference = GitReference::Ref("refs/pull/1/head".to_string());
+        assert_eq!(sid.git_reference(), Some(&reference));
+        assert_eq!(sid.as_url().to_string(), url);
+
+        // The older `ref` for branches.
+        let sid = SourceId::from_url("git+https://example.com/foo?ref=main").unwrap();
+        let reference = GitReference::Branch("main".to_string());
+        assert_eq!(sid.git_reference(), Some(&reference));
+    }
 }
@@ -27,6 +27,7 @@ struct Inner {
     has_overlapping_features: Option<InternedString>,
     checksum: Option<String>,
     links: Option<InternedString>,
+    rust_version: Option<InternedString>,
 }
 
 impl Summary {
@@ -62,6 +63,7 @@ impl Summary {
                 features: Rc::new(feature_map),
                 checksum: None,
                 links: links.map(|l| l.into()),
+                rust_version: None,
                 has_namespaced_features,
                 has_overlapping_features,
             }),
@@ -133,6 +135,10 @@ impl Summary {
     pub fn links(&self) -> Option<InternedString> {
         self.inner.links
     }
+    /// The `rust-version` of the package, if it declares one.
+    pub fn rust_version(&self) -> Option<InternedString> {
+        self.inner.rust_version
+    }
 
     pub fn override_id(mut self, id: PackageId) -> Summary {
         Rc::make_mut(&mut self.inner).package_id = id;
@@ -143,6 +149,10 @@ impl Summary {
         Rc::make_mut(&mut self.inner).checksum = Some(cksum);
     }
 
+    pub fn set_rust_version(&mut self, rust_version: Option<InternedString>) {
+        Rc::make_mut(&mut self.inner).rust_version = rust_version;
+    }
+
     pub fn map_dependencies<F>(mut self, f: F) -> Summary
     where
         F: FnMut(Dependency) -> Dependency,
@@ -583,16 +583,6 @@ impl<'cfg> Workspace<'cfg> {
     /// Returns an error if `manifest_path` isn't actually a valid manifest or
     /// if some other transient error happens.
     fn find_root(&mut self, manifest_path: &Path) -> CargoResult<Option<PathBuf>> {
-        fn read_root_pointer(member_manifest: &Path, root_link: &str) -> PathBuf {
-            let path = member_manifest
-                .parent()
-                .unwrap()
-                .join(root_link)
-                .join("Cargo.toml");
-            debug!("find_root - pointer {}", path.display());
-            paths::normalize_path(&path)
-        }
-
         {
             let current = self.packages.load(manifest_path)?;
             match *current.workspace_config() {
@@ -607,39 +597,23 @@ impl<'cfg> Workspace<'cfg> {
             }
         }
 
-        for path in paths::ancestors(manifest_path, None).skip(2) {
-            if path.ends_with("target/package") {
-                break;
-            }
-
-            let ances_manifest_path = path.join("Cargo.toml");
+        for ances_manifest_path in find_root_iter(manifest_path, self.config) {
             debug!("find_root - trying {}", ances_manifest_path.display());
-            if ances_manifest_path.exists() {
-                match *self.packages.load(&ances_manifest_path)?.workspace_config() {
-                    WorkspaceConfig::Root(ref ances_root_config) => {
-                        debug!("find_root - found a root checking exclusion");
-                        if !ances_root_config.is_excluded(manifest_path) {
-                            debug!("find_root - found!");
-                            return Ok(Some(ances_manifest_path));
-                        }
-                    }
-                    WorkspaceConfig::Member {
-                        root: Some(ref path_to_root),
-                    } => {
-                        debug!("find_root - found pointer");
-                        return Ok(Some(read_root_pointer(&ances_manifest_path, path_to_root)));
+            match *self.packages.load(&ances_manifest_path)?.workspace_config() {
+                WorkspaceConfig::Root(ref ances_root_config) => {
+                    debug!("find_root - found a root checking exclusion");
+                    if !ances_root_config.is_excluded(manifest_path) {
+                        debug!("find_root - found!");
+                        return Ok(Some(ances_manifest_path));
                     }
-                    WorkspaceConfig::Member { .. } => {}
                 }
-            }
-
-            // Don't walk across `CARGO_HOME` when we're looking for the
-            // workspace root. Sometimes a package will be organized with
-            // `CARGO_HOME` pointing inside of the workspace root or in the
-            // current package, but we don't want to mistakenly try to put
-            // crates.io crates into the workspace by accident.
-            if self.config.home() == path {
-                break;
+                WorkspaceConfig::Member {
+                    root: Some(ref path_to_root),
+                } => {
+                    debug!("find_root - found pointer");
+                    return Ok(Some(read_root_pointer(&ances_manifest_path, path_to_root)));
+                }
+                WorkspaceConfig::Member { .. } => {}
             }
         }
 
@@ -1585,7 +1559,7 @@ impl WorkspaceRootConfig {
     /// Checks the path against the `excluded` list.
     ///
     /// This method does **not** consider the `members` list.
-    fn is_excluded(&self, manifest_path: &Path) -> bool {
+    pub(crate) fn is_excluded(&self, manifest_path: &Path) -> bool {
         let excluded = self
             .exclude
             .iter()
@@ -1645,3 +1619,41 @@ impl WorkspaceRootConfig {
         Ok(res)
     }
 }
+
+/// Returns the path of the root manifest that the `package.workspace` key
+/// `root_link` of the manifest at `member_manifest` points to.
+pub(crate) fn read_root_pointer(member_manifest: &Path, root_link: &str) -> PathBuf {
+    let path = member_manifest
+        .parent()
+        .unwrap()
+        .join(root_link)
+        .join("Cargo.toml");
+    debug!("find_root - pointer {}", path.display());
+    paths::normalize_path(&path)
+}
+
+/// Returns the existing manifests in the parent directories of the package
+/// whose manifest is at `manifest_path`, which may be the root of its
+/// workspace, nearest first.
+pub(crate) fn find_root_iter<'a>(
+    manifest_path: &'a Path,
+    config: &'a Config,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    let mut crossed_home = false;
+    paths::ancestors(manifest_path, None)
+        .skip(2)
+        .take_while(move |path| {
+            if crossed_home || path.ends_with("target/package") {
+                return false;
+            }
+            // Don't walk across `CARGO_HOME` when we're looking for the
+            // workspace root. Sometimes a package will be organized with
+            // `CARGO_HOME` pointing inside of the workspace root or in the
+            // current package, but we don't want to mistakenly try to put
+            // crates.io crates into the workspace by accident.
+            crossed_home = config.home() == *path;
+            true
+        })
+        .map(|path| path.join("Cargo.toml"))
+        .filter(|ances_manifest_path| ances_manifest_path.exists())
+}
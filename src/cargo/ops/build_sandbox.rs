@@ -0,0 +1,271 @@
+//! Running build scripts in the sandbox of `-Zbuild-sandbox`.
+//!
+//! Cargo runs itself as the sandbox of a build script, with
+//! `__CARGO_BUILD_SANDBOX` set to its policy, see the
+//! `core::compiler::build_sandbox` module. On Linux, the network is cut off
+//! with a new user and network namespace, and the file system access is
+//! restricted with Landlock. If the kernel doesn't support one of them, the
+//! sandbox reports it to Cargo, and only runs the build script without that
+//! restriction if `build.allow-incomplete-sandbox` is set.
+
+use std::env;
+use std::ffi::OsString;
+
+use anyhow::{bail, Context as _};
+
+use crate::core::compiler::build_sandbox::{SandboxPolicy, SandboxReport, SANDBOX_ENV};
+use crate::util::{CargoResult, Config};
+
+/// Runs the build script given as arguments in the sandbox, if Cargo was run
+/// as the sandbox of a build script. Returns `false` otherwise.
+pub fn build_sandbox_maybe_exec(config: &Config) -> CargoResult<bool> {
+    let policy = match env::var(SANDBOX_ENV) {
+        Ok(policy) => policy,
+        Err(_) => return Ok(false),
+    };
+    // The build script shouldn't act as a sandbox when it runs `cargo`.
+    env::remove_var(SANDBOX_ENV);
+    let policy: SandboxPolicy = serde_json::from_str(&policy)
+        .with_context(|| format!("invalid `{}` environment variable", SANDBOX_ENV))?;
+    let mut args = env::args_os().skip(1);
+    let script = match args.next() {
+        Some(script) => script,
+        None => bail!("no build script given to run in the sandbox"),
+    };
+    let args: Vec<OsString> = args.collect();
+    exec(config, &policy, script, &args)
+        .with_context(|| format!("failed to sandbox the build script of `{}`", policy.package))?;
+    Ok(true)
+}
+
+#[cfg(target_os = "linux")]
+fn exec(
+    config: &Config,
+    policy: &SandboxPolicy,
+    script: OsString,
+    args: &[OsString],
+) -> CargoResult<()> {
+    use std::fs::File;
+    use std::io::Write;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    // Opened before the file system is restricted.
+    let mut report_file = File::create(&policy.report)
+        .with_context(|| format!("failed to create `{}`", policy.report.display()))?;
+    let mut report = SandboxReport::default();
+    let network_error = match policy.network {
+        true => None,
+        false => imp::isolate_network().err(),
+    };
+    let tcp_blocked = imp::restrict(policy, network_error.is_some(), &mut report.incomplete)?;
+    if let Some(e) = network_error {
+        if !tcp_blocked {
+            report
+                .incomplete
+                .push(format!("{:#}, the network is accessible", e));
+        }
+    }
+    report_file.write_all(serde_json::to_string(&report)?.as_bytes())?;
+    drop(report_file);
+    if !report.incomplete.is_empty() && !policy.allow_incomplete {
+        // Cargo reports why from the report.
+        bail!("the build script sandbox is incomplete");
+    }
+
+    let mut cmd = Command::new(&script);
+    cmd.args(args);
+    if let Some(client) = config.jobserver_from_env() {
+        // The file descriptors of the jobserver were closed on exec when
+        // Cargo started.
+        client.configure(&mut cmd);
+    }
+    let error = cmd.exec();
+    Err(error).with_context(|| format!("failed to execute `{}`", script.to_string_lossy()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exec(
+    _config: &Config,
+    _policy: &SandboxPolicy,
+    _script: OsString,
+    _args: &[OsString],
+) -> CargoResult<()> {
+    bail!("sandboxing build scripts is only supported on Linux")
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::unix::prelude::*;
+    use std::path::Path;
+
+    use anyhow::Context as _;
+    use cargo_util::paths;
+
+    use crate::core::compiler::build_sandbox::SandboxPolicy;
+    use crate::util::CargoResult;
+
+    // The Landlock ABI, from `linux/landlock.h`.
+    const CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+    const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+    const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+    /// `REMOVE_DIR` up to `MAKE_SYM`, which only apply to directories.
+    const ACCESS_FS_MAKE_AND_REMOVE: u64 = 0x1ff0;
+    const ACCESS_FS_REFER: u64 = 1 << 13;
+    const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+    const ACCESS_NET_BIND_TCP: u64 = 1 << 0;
+    const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+        handled_access_net: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: RawFd,
+    }
+
+    /// Moves the process into new user and network namespaces, which have no
+    /// network interfaces but loopback.
+    pub fn isolate_network() -> CargoResult<()> {
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+            return Err(io::Error::last_os_error()).context("failed to create a network namespace");
+        }
+        // Keep the same user and group within the user namespace.
+        paths::write("/proc/self/setgroups", "deny")?;
+        paths::write("/proc/self/uid_map", format!("{} {} 1", uid, uid))?;
+        paths::write("/proc/self/gid_map", format!("{} {} 1", gid, gid))?;
+        Ok(())
+    }
+
+    /// Restricts the file system access of the process to the paths of the
+    /// policy with Landlock. If `block_tcp` is set, TCP connections are
+    /// blocked too if possible, returning whether they are.
+    pub fn restrict(
+        policy: &SandboxPolicy,
+        block_tcp: bool,
+        incomplete: &mut Vec<String>,
+    ) -> CargoResult<bool> {
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            incomplete.push(format!(
+                "Landlock is not available ({}), the file system is accessible",
+                io::Error::last_os_error()
+            ));
+            return Ok(false);
+        }
+        let mut handled_access_fs = ACCESS_FS_EXECUTE
+            | ACCESS_FS_WRITE_FILE
+            | ACCESS_FS_READ_FILE
+            | ACCESS_FS_READ_DIR
+            | ACCESS_FS_MAKE_AND_REMOVE;
+        if abi >= 2 {
+            handled_access_fs |= ACCESS_FS_REFER;
+        }
+        if abi >= 3 {
+            handled_access_fs |= ACCESS_FS_TRUNCATE;
+        }
+        let mut attr = RulesetAttr {
+            handled_access_fs,
+            handled_access_net: 0,
+        };
+        // Older kernels only know the first field.
+        let mut attr_size = std::mem::size_of::<u64>();
+        let tcp_blocked = block_tcp && abi >= 4;
+        if tcp_blocked {
+            attr.handled_access_net = ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP;
+            attr_size = std::mem::size_of::<RulesetAttr>();
+        }
+        let ruleset = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                attr_size,
+                0,
+            )
+        };
+        if ruleset < 0 {
+            return Err(io::Error::last_os_error()).context("failed to create a Landlock ruleset");
+        }
+        let ruleset = unsafe { File::from_raw_fd(ruleset as RawFd) };
+
+        let read = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+        let write = read
+            | ACCESS_FS_WRITE_FILE
+            | ACCESS_FS_MAKE_AND_REMOVE
+            | ACCESS_FS_REFER
+            | ACCESS_FS_TRUNCATE;
+        for path in &policy.read {
+            allow(&ruleset, path, read & handled_access_fs)?;
+        }
+        for path in &policy.write {
+            allow(&ruleset, path, write & handled_access_fs)?;
+        }
+
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                return Err(io::Error::last_os_error()).context("failed to set no_new_privs");
+            }
+            if libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0) != 0 {
+                return Err(io::Error::last_os_error())
+                    .context("failed to enforce the Landlock ruleset");
+            }
+        }
+        Ok(tcp_blocked)
+    }
+
+    /// Allows `access` beneath `path`, if it exists.
+    fn allow(ruleset: &File, path: &Path, mut access: u64) -> CargoResult<()> {
+        let file = match OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_PATH | libc::O_CLOEXEC)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to open `{}`", path.display()))
+            }
+        };
+        if !file.metadata()?.is_dir() {
+            // The other rights only apply to directories.
+            access &=
+                ACCESS_FS_EXECUTE | ACCESS_FS_WRITE_FILE | ACCESS_FS_READ_FILE | ACCESS_FS_TRUNCATE;
+        }
+        let attr = PathBeneathAttr {
+            allowed_access: access,
+            parent_fd: file.as_raw_fd(),
+        };
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                RULE_PATH_BENEATH,
+                &attr as *const PathBeneathAttr,
+                0,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("failed to allow access to `{}`", path.display()));
+        }
+        Ok(())
+    }
+}
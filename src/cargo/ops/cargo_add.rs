@@ -0,0 +1,325 @@
+//! Implementation of `cargo add`.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context as _};
+use cargo_util::paths;
+
+use crate::core::dependency::DepKind;
+use crate::core::registry::PackageRegistry;
+use crate::core::{
+    Dependency, GitReference, Package, PackageIdSpec, Registry, SourceId, Summary, Workspace,
+};
+use crate::util::errors::CargoResult;
+use crate::util::toml_mut::{DepSource, DepTable, LocalManifest, ManifestDependency};
+use crate::util::{validate_package_name, Config, IntoUrl};
+
+pub struct AddOptions<'a> {
+    pub config: &'a Config,
+    /// The package whose manifest is edited.
+    pub spec: &'a Package,
+    pub dependencies: Vec<DepOp>,
+    /// The table to add the dependencies to.
+    pub section: DepTable,
+    /// Only report what would be added.
+    pub dry_run: bool,
+}
+
+/// A dependency to add, as given on the command line.
+#[derive(Clone, Debug, Default)]
+pub struct DepOp {
+    /// `name` or `name@version-req`. May be omitted for `--path` and
+    /// `--git`, in which case the name of the package found there is used.
+    pub crate_spec: Option<String>,
+    pub rename: Option<String>,
+    pub features: Vec<String>,
+    pub default_features: Option<bool>,
+    pub optional: Option<bool>,
+    /// The name of an alternative registry.
+    pub registry: Option<String>,
+    /// Absolute path to a local package.
+    pub path: Option<PathBuf>,
+    pub git: Option<String>,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+    pub rev: Option<String>,
+}
+
+/// Adds dependencies to the manifest of `opts.spec`.
+///
+/// Packages are looked up in their source to check that they exist, and to
+/// pick the latest version of registry dependencies. The manifest keeps its
+/// formatting and comments.
+pub fn add(opts: &AddOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    if opts.section.kind() == DepKind::Development
+        && opts
+            .dependencies
+            .iter()
+            .any(|dep| dep.optional == Some(true))
+    {
+        bail!("dev-dependencies cannot be optional");
+    }
+
+    let mut manifest = LocalManifest::try_new(opts.spec.manifest_path())?;
+    let manifest_dir = opts.spec.root();
+
+    let deps = {
+        let _lock = config.acquire_package_cache_lock()?;
+        let mut registry = PackageRegistry::new(config)?;
+        registry.lock_patches();
+        opts.dependencies
+            .iter()
+            .map(|op| resolve_dependency(config, &mut registry, manifest_dir, op))
+            .collect::<CargoResult<Vec<_>>>()?
+    };
+
+    for (dep, summary) in &deps {
+        let mut msg = match &dep.source {
+            DepSource::Registry { .. } => format!("{} v{}", dep.name, summary.version()),
+            DepSource::Path { .. } => format!("{} (local)", dep.name),
+            DepSource::Git { .. } => format!("{} (git)", dep.name),
+        };
+        let optional = dep.optional.unwrap_or_else(|| {
+            manifest
+                .get_dependency(&opts.section, &dep.name)
+                .map_or(false, crate::util::toml_mut::is_optional)
+        });
+        msg.push_str(if optional { " to optional " } else { " to " });
+        msg.push_str(&opts.section.to_string());
+        if !dep.features.is_empty() {
+            msg.push_str(&format!(" with features: {}", dep.features.join(", ")));
+        }
+        config.shell().status("Adding", msg)?;
+        manifest.insert_dependency(&opts.section, dep)?;
+    }
+
+    if opts.dry_run {
+        config.shell().warn("aborting add due to dry run")?;
+    } else {
+        manifest.write()?;
+    }
+    Ok(())
+}
+
+/// Returns the workspace member whose manifest `cargo add` or `cargo remove`
+/// should edit: the one selected with `--package`, or else the current one.
+pub fn manifest_package<'a>(
+    ws: &'a Workspace<'_>,
+    spec: Option<&str>,
+    command: &str,
+) -> CargoResult<&'a Package> {
+    match spec {
+        Some(spec) => {
+            let spec = PackageIdSpec::parse(spec)?;
+            let mut matches = ws.members().filter(|p| spec.matches(p.package_id()));
+            match (matches.next(), matches.next()) {
+                (Some(pkg), None) => Ok(pkg),
+                (Some(_), Some(_)) => bail!("`{}` matches multiple workspace members", spec),
+                (None, _) => bail!("package `{}` is not a member of the workspace", spec),
+            }
+        }
+        None => match ws.current_opt() {
+            Some(pkg) => Ok(pkg),
+            None => {
+                let mut names: Vec<_> = ws.members().map(|p| p.name().as_str()).collect();
+                names.sort_unstable();
+                bail!(
+                    "`cargo {}` could not determine which package to modify, \
+                     use the `--package` option to select one\n\
+                     available packages: {}",
+                    command,
+                    names.join(", ")
+                )
+            }
+        },
+    }
+}
+
+/// Finds the package a [`DepOp`] refers to, and works out how to write it
+/// into the manifest.
+fn resolve_dependency(
+    config: &Config,
+    registry: &mut PackageRegistry<'_>,
+    manifest_dir: &Path,
+    op: &DepOp,
+) -> CargoResult<(ManifestDependency, Summary)> {
+    let (name, req) = match &op.crate_spec {
+        Some(spec) => {
+            let (name, req) = parse_crate_spec(spec)?;
+            (Some(name), req)
+        }
+        None => (None, None),
+    };
+    if let Some(rename) = &op.rename {
+        validate_package_name(rename, "dependency name", "")?;
+    }
+
+    let source_id = if let Some(path) = &op.path {
+        SourceId::for_path(path)?
+    } else if let Some(git) = &op.git {
+        let reference = match (&op.branch, &op.tag, &op.rev) {
+            (Some(branch), _, _) => GitReference::Branch(branch.clone()),
+            (_, Some(tag), _) => GitReference::Tag(tag.clone()),
+            (_, _, Some(rev)) => GitReference::Rev(rev.clone()),
+            _ => GitReference::DefaultBranch,
+        };
+        SourceId::for_git(&git.into_url()?, reference)?
+    } else if let Some(registry) = &op.registry {
+        SourceId::alt_registry(config, registry)?
+    } else {
+        SourceId::crates_io(config)?
+    };
+
+    let summary = match name {
+        Some(name) => {
+            let dep = Dependency::parse(name, req, source_id)?;
+            let candidates = registry.query_vec(&dep, false)?;
+            // Prefer stable releases unless a requirement asks for something
+            // else.
+            let best = candidates
+                .iter()
+                .filter(|s| req.is_some() || s.version().pre.is_empty())
+                .max_by_key(|s| s.version())
+                .or_else(|| candidates.iter().max_by_key(|s| s.version()));
+            match best {
+                Some(summary) => summary.clone(),
+                None if req.is_some() => bail!(
+                    "could not find `{}` in {} with version `{}`",
+                    name,
+                    source_id,
+                    dep.version_req()
+                ),
+                None => bail!("could not find `{}` in {}", name, source_id),
+            }
+        }
+        None => {
+            if source_id.is_registry() {
+                bail!("a crate name is required unless `--path` or `--git` is given");
+            }
+            // Path and git sources list all of their packages for a fuzzy
+            // query, regardless of the name.
+            let dep = Dependency::parse("*", None, source_id)?;
+            let mut candidates = registry.query_vec(&dep, true)?;
+            match candidates.len() {
+                0 => bail!("no package found in {}", source_id),
+                1 => candidates.remove(0),
+                _ => {
+                    let mut names: Vec<_> = candidates.iter().map(|s| s.name()).collect();
+                    names.sort();
+                    bail!(
+                        "multiple packages found in {}, specify one by name: {}",
+                        source_id,
+                        names
+                            .iter()
+                            .map(|n| n.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                }
+            }
+        }
+    };
+
+    for feature in &op.features {
+        // Features of the dependency's own dependencies can't be checked
+        // without loading them, so leave that to the resolver.
+        if !feature.contains('/') && !summary.features().contains_key(feature.as_str()) {
+            let mut available: Vec<_> = summary.features().keys().map(|f| f.as_str()).collect();
+            available.sort_unstable();
+            let available = if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            };
+            bail!(
+                "unrecognized feature `{}` for `{}`\navailable features: {}",
+                feature,
+                summary.package_id(),
+                available
+            );
+        }
+    }
+
+    let version = req.map(|req| req.to_string());
+    let source = if let Some(path) = &op.path {
+        DepSource::Path {
+            path: relative_path(manifest_dir, path),
+            version,
+        }
+    } else if let Some(git) = &op.git {
+        DepSource::Git {
+            url: git.clone(),
+            branch: op.branch.clone(),
+            tag: op.tag.clone(),
+            rev: op.rev.clone(),
+            version,
+        }
+    } else {
+        DepSource::Registry {
+            version: version.unwrap_or_else(|| summary.version().to_string()),
+            registry: op.registry.clone(),
+        }
+    };
+    let (dep_name, package) = match &op.rename {
+        Some(rename) => (rename.clone(), Some(summary.name().to_string())),
+        None => (summary.name().to_string(), None),
+    };
+    let dep = ManifestDependency {
+        name: dep_name,
+        package,
+        source,
+        features: op.features.clone(),
+        default_features: op.default_features,
+        optional: op.optional,
+    };
+    Ok((dep, summary))
+}
+
+/// Splits `name@version-req` into its parts.
+fn parse_crate_spec(spec: &str) -> CargoResult<(&str, Option<&str>)> {
+    let (name, req) = match spec.split_once('@') {
+        Some((name, req)) => (name, Some(req)),
+        None => (spec, None),
+    };
+    validate_package_name(name, "crate name", "")?;
+    if let Some(req) = req {
+        semver::VersionReq::parse(req).with_context(|| {
+            format!(
+                "invalid version requirement `{}` in `{}` (expected something like `{}@1.2`)",
+                req, spec, name
+            )
+        })?;
+    }
+    Ok((name, req))
+}
+
+/// Returns `path` relative to `base`, with `/` as separator so that the
+/// manifest works on every platform.
+fn relative_path(base: &Path, path: &Path) -> String {
+    let base = paths::normalize_path(base);
+    let path = paths::normalize_path(path);
+    // Paths on different drives can't be made relative.
+    if base.components().next() != path.components().next() {
+        return path.to_string_lossy().replace('\\', "/");
+    }
+    let base: Vec<Component<'_>> = base.components().collect();
+    let path: Vec<Component<'_>> = path.components().collect();
+    let common = base
+        .iter()
+        .zip(path.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts: Vec<String> = Vec::new();
+    parts.extend(base[common..].iter().map(|_| "..".to_string()));
+    parts.extend(
+        path[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned()),
+    );
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
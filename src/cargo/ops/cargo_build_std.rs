@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+
+use crate::core::compiler::standard_lib;
+use crate::core::compiler::{CompileKind, CompileTarget};
+use crate::core::Workspace;
+use crate::ops::CompileOptions;
+use crate::util::CargoResult;
+
+/// Builds the standard library into a sysroot for each requested target, or
+/// for the host if none is, returning the paths of the sysroots.
+pub fn build_std(ws: &Workspace<'_>, options: &CompileOptions) -> CargoResult<Vec<PathBuf>> {
+    let host = ws.config().load_global_rustc(Some(ws))?.host;
+    options
+        .build_config
+        .requested_kinds
+        .iter()
+        .map(|kind| {
+            let kind = match kind {
+                CompileKind::Host => CompileKind::Target(CompileTarget::new(&host)?),
+                kind => *kind,
+            };
+            Ok(standard_lib::build_sysroot(ws, options, kind)?.path)
+        })
+        .collect()
+}
@@ -43,7 +43,7 @@ use crate::ops::resolve::WorkspaceResolve;
 use crate::util::config::Config;
 use crate::util::interning::InternedString;
 use crate::util::restricted_names::is_glob_pattern;
-use crate::util::{closest_msg, profile, CargoResult, StableHasher};
+use crate::util::{closest_msg, hash_u64, profile, CargoResult, StableHasher};
 
 use anyhow::Context as _;
 
@@ -79,6 +79,12 @@ pub struct CompileOptions {
     /// Whether the build process should check the minimum Rust version
     /// defined in the cargo metadata for a crate.
     pub honor_rust_version: bool,
+    /// Only build a deterministic subset of the root units, so that the
+    /// work can be split across several machines.
+    pub partition: Option<Partition>,
+    /// Only build the standard library, into a sysroot, instead of the
+    /// packages of the workspace. See `standard_lib::build_sysroot`.
+    pub build_std_only: bool,
 }
 
 impl<'a> CompileOptions {
@@ -95,6 +101,8 @@ impl<'a> CompileOptions {
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             honor_rust_version: true,
+            partition: None,
+            build_std_only: false,
         })
     }
 }
@@ -257,6 +265,90 @@ pub enum CompileFilter {
     },
 }
 
+/// A deterministic share of the root units, selected with `--partition`.
+///
+/// Every runner given the same arguments and workspace computes the same
+/// partitions, so `M/N` for each `M` in `1..=N` covers every unit exactly once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Partition {
+    pub kind: PartitionKind,
+    /// The 1-based index of the selected partition.
+    pub shard: u64,
+    pub total: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartitionKind {
+    /// Units are assigned by a hash of their name, so adding a target only
+    /// moves that target.
+    Hash,
+    /// Units are sorted and dealt out in turn, so every partition has nearly
+    /// the same number of units.
+    Count,
+}
+
+impl Partition {
+    /// Parses a `hash:M/N` or `count:M/N` argument.
+    pub fn parse(s: &str) -> CargoResult<Partition> {
+        let invalid = || {
+            anyhow::format_err!(
+                "invalid partition `{}`, expected `hash:M/N` or `count:M/N`, for example `hash:1/3`",
+                s
+            )
+        };
+        let (kind, rest) = s.split_once(':').ok_or_else(invalid)?;
+        let kind = match kind {
+            "hash" => PartitionKind::Hash,
+            "count" => PartitionKind::Count,
+            _ => return Err(invalid()),
+        };
+        let (shard, total) = rest.split_once('/').ok_or_else(invalid)?;
+        let shard: u64 = shard.parse().map_err(|_| invalid())?;
+        let total: u64 = total.parse().map_err(|_| invalid())?;
+        if shard == 0 || total == 0 || shard > total {
+            anyhow::bail!(
+                "invalid partition `{}`, the partition index must be between 1 and {}",
+                s,
+                total.max(1)
+            );
+        }
+        Ok(Partition { kind, shard, total })
+    }
+
+    /// Removes the units that don't belong to this partition.
+    fn retain(&self, units: &mut Vec<Unit>) {
+        // The key must be the same on every machine, so it can't include
+        // anything like the path of the package.
+        let key = |unit: &Unit| {
+            let kind = match unit.kind {
+                CompileKind::Host => "host".to_string(),
+                CompileKind::Target(t) => t.short_name().to_string(),
+            };
+            format!(
+                "{} {} {} {:?} {}",
+                unit.pkg.name(),
+                unit.pkg.version(),
+                unit.target.description_named(),
+                unit.mode,
+                kind
+            )
+        };
+        let mut keyed: Vec<_> = units.drain(..).map(|u| (key(&u), u)).collect();
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+        let shard = self.shard - 1;
+        units.extend(
+            keyed
+                .into_iter()
+                .enumerate()
+                .filter(|(i, (key, _))| match self.kind {
+                    PartitionKind::Hash => hash_u64(key) % self.total == shard,
+                    PartitionKind::Count => *i as u64 % self.total == shard,
+                })
+                .map(|(_, (_, unit))| unit),
+        );
+    }
+}
+
 pub fn compile<'a>(ws: &Workspace<'a>, options: &CompileOptions) -> CargoResult<Compilation<'a>> {
     let exec: Arc<dyn Executor> = Arc::new(DefaultExecutor);
     compile_with_exec(ws, options, &exec)
@@ -335,6 +427,8 @@ pub fn create_bcx<'a, 'cfg>(
         ref local_rustdoc_args,
         rustdoc_document_private_items,
         honor_rust_version,
+        ref partition,
+        build_std_only,
     } = *options;
     let config = ws.config();
 
@@ -385,7 +479,12 @@ pub fn create_bcx<'a, 'cfg>(
         resolved_features,
     } = resolve;
 
-    let std_resolve_features = if let Some(crates) = &config.cli_unstable().build_std {
+    // What to build of the standard library for each target, and the
+    // sysroots it was built into beforehand with `build-std.sysroot`.
+    let build_std = build_std_only || config.cli_unstable().build_std.is_some();
+    let mut std_options = HashMap::new();
+    let mut std_sysroots = HashMap::new();
+    let std_resolve_features = if build_std {
         if build_config.build_plan {
             config
                 .shell()
@@ -397,10 +496,32 @@ pub fn create_bcx<'a, 'cfg>(
             // requested_target to an enum, or some other approach.
             anyhow::bail!("-Zbuild-std requires --target");
         }
-        let (std_package_set, std_resolve, std_features) =
-            standard_lib::resolve_std(ws, &target_data, &build_config.requested_kinds, crates)?;
-        pkg_set.add_set(std_package_set);
-        Some((std_resolve, std_features))
+        for kind in &build_config.requested_kinds {
+            std_options.insert(*kind, standard_lib::std_options(config, *kind)?);
+        }
+        if !build_std_only && standard_lib::use_sysroot(config)? {
+            for kind in &build_config.requested_kinds {
+                let sysroot = standard_lib::build_sysroot(ws, options, *kind)?;
+                std_sysroots.insert(*kind, sysroot);
+            }
+            None
+        } else {
+            let options = &std_options[&build_config.requested_kinds[0]];
+            if std_options.values().any(|other| other != options) {
+                anyhow::bail!(
+                    "building the standard library with different settings for each \
+                     target requires `build-std.sysroot`"
+                );
+            }
+            let (std_package_set, std_resolve, std_features) = standard_lib::resolve_std(
+                ws,
+                &target_data,
+                &build_config.requested_kinds,
+                options,
+            )?;
+            pkg_set.add_set(std_package_set);
+            Some((std_resolve, std_features))
+        }
     } else {
         None
     };
@@ -447,7 +568,12 @@ pub fn create_bcx<'a, 'cfg>(
         );
     }
 
-    let profiles = Profiles::new(ws, build_config.requested_profile)?;
+    let mut profiles = Profiles::new(ws, build_config.requested_profile)?;
+    for (kind, options) in &std_options {
+        if let Some(panic) = options.panic {
+            profiles.override_panic(*kind, panic);
+        }
+    }
     profiles.validate_packages(
         ws.profiles(),
         &mut config.shell(),
@@ -472,35 +598,48 @@ pub fn create_bcx<'a, 'cfg>(
     // its own special handling of `CompileKind::Host`. It will
     // internally replace the host kind by the `explicit_host_kind`
     // before setting as a unit.
-    let mut units = generate_targets(
-        ws,
-        &to_builds,
-        filter,
-        &build_config.requested_kinds,
-        explicit_host_kind,
-        build_config.mode,
-        &resolve,
-        &workspace_resolve,
-        &resolved_features,
-        &pkg_set,
-        &profiles,
-        interner,
-    )?;
+    let mut units = if build_std_only {
+        Vec::new()
+    } else {
+        generate_targets(
+            ws,
+            &to_builds,
+            filter,
+            &build_config.requested_kinds,
+            explicit_host_kind,
+            build_config.mode,
+            &resolve,
+            &workspace_resolve,
+            &resolved_features,
+            &pkg_set,
+            &profiles,
+            interner,
+        )?
+    };
 
-    let std_roots = if let Some(crates) = &config.cli_unstable().build_std {
-        // Only build libtest if it looks like it is needed.
-        let mut crates = crates.clone();
-        if !crates.iter().any(|c| c == "test")
-            && units
-                .iter()
-                .any(|unit| unit.mode.is_rustc_test() && unit.target.harness())
-        {
-            // Only build libtest when libstd is built (libtest depends on libstd)
-            if crates.iter().any(|c| c == "std") {
-                crates.push("test".to_string());
+    if let Some(partition) = partition {
+        partition.retain(&mut units);
+    }
+
+    let std_roots = if let Some((std_resolve, std_features)) = &std_resolve_features {
+        let options = &std_options[&build_config.requested_kinds[0]];
+        let crates = if build_std_only {
+            standard_lib::sysroot_crates(options)
+        } else {
+            // Only build libtest if it looks like it is needed.
+            let mut crates = options.crates.clone();
+            if !crates.iter().any(|c| c == "test")
+                && units
+                    .iter()
+                    .any(|unit| unit.mode.is_rustc_test() && unit.target.harness())
+            {
+                // Only build libtest when libstd is built (libtest depends on libstd)
+                if crates.iter().any(|c| c == "std") {
+                    crates.push("test".to_string());
+                }
             }
-        }
-        let (std_resolve, std_features) = std_resolve_features.as_ref().unwrap();
+            crates
+        };
         standard_lib::generate_std_roots(
             &crates,
             std_resolve,
@@ -514,6 +653,11 @@ pub fn create_bcx<'a, 'cfg>(
         Default::default()
     };
 
+    if build_std_only {
+        units = std_roots.values().flatten().cloned().collect();
+        units.sort();
+    }
+
     let mut unit_graph = build_unit_dependencies(
         ws,
         &pkg_set,
@@ -612,7 +756,7 @@ pub fn create_bcx<'a, 'cfg>(
         }
     }
 
-    let bcx = BuildContext::new(
+    let mut bcx = BuildContext::new(
         ws,
         pkg_set,
         build_config,
@@ -622,6 +766,7 @@ pub fn create_bcx<'a, 'cfg>(
         units,
         unit_graph,
     )?;
+    bcx.std_sysroots = std_sysroots;
 
     Ok(bcx)
 }
@@ -0,0 +1,171 @@
+//! Implementation of `cargo gc`, which deletes unused entries of the
+//! global package cache.
+
+use crate::core::global_cache_tracker::{self, CacheEntry, CacheKind, GlobalCacheTracker};
+use crate::util::errors::CargoResult;
+use crate::util::Config;
+use anyhow::{bail, Context as _};
+use bytesize::ByteSize;
+use cargo_util::paths;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use walkdir::WalkDir;
+
+/// Entries unused for longer than this are deleted when neither a maximum
+/// age nor a maximum size is given.
+const DEFAULT_MAX_AGE: &str = "3 months";
+
+pub struct GcOptions {
+    /// Delete entries which haven't been used for longer than this.
+    pub max_age: Option<Duration>,
+    /// Delete the least recently used entries until the cache takes up at
+    /// most this many bytes.
+    pub max_size: Option<u64>,
+    /// Only report what would be deleted.
+    pub dry_run: bool,
+}
+
+/// The `[gc]` table in config files.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct GcConfig {
+    max_age: Option<String>,
+    max_size: Option<String>,
+}
+
+/// Parses a duration like `30 days` or `3 months`.
+pub fn parse_max_age(s: &str) -> CargoResult<Duration> {
+    humantime::parse_duration(s).with_context(|| {
+        format!(
+            "failed to parse `{}` as a duration (expected something like `30 days`)",
+            s
+        )
+    })
+}
+
+/// Parses a size like `500 MiB` or `2GB`.
+pub fn parse_max_size(s: &str) -> CargoResult<u64> {
+    match s.parse::<ByteSize>() {
+        Ok(size) => Ok(size.as_u64()),
+        Err(e) => bail!(
+            "failed to parse `{}` as a size (expected something like `2 GiB`): {}",
+            s,
+            e
+        ),
+    }
+}
+
+/// Deletes entries of the global package cache which are either older than
+/// the maximum age, or which push the cache over the maximum size.
+///
+/// Limits not given in `opts` are taken from the `[gc]` config table.
+pub fn gc(config: &Config, opts: &GcOptions) -> CargoResult<()> {
+    let mut max_age = opts.max_age;
+    let mut max_size = opts.max_size;
+    if max_age.is_none() && max_size.is_none() {
+        let gc_config = config.get::<Option<GcConfig>>("gc")?.unwrap_or_default();
+        max_age = gc_config
+            .max_age
+            .as_deref()
+            .map(parse_max_age)
+            .transpose()?;
+        max_size = gc_config
+            .max_size
+            .as_deref()
+            .map(parse_max_size)
+            .transpose()?;
+        if max_age.is_none() && max_size.is_none() {
+            max_age = Some(parse_max_age(DEFAULT_MAX_AGE)?);
+        }
+    }
+
+    let _lock = config.acquire_package_cache_lock()?;
+    let mut tracker = GlobalCacheTracker::load(config)?;
+    let now = global_cache_tracker::now();
+    let mut entries = tracker.entries(now)?;
+
+    let mut to_delete = Vec::new();
+    if let Some(max_age) = max_age {
+        let cutoff = now.saturating_sub(max_age.as_secs());
+        // Entries are sorted from least to most recently used.
+        let n = entries.iter().take_while(|e| e.last_use < cutoff).count();
+        to_delete.extend(entries.drain(..n));
+    }
+    if let Some(max_size) = max_size {
+        let sizes: Vec<u64> = entries.iter().map(|e| entry_size(&e.path)).collect();
+        let mut total: u64 = sizes.iter().sum();
+        let mut n = 0;
+        while total > max_size && n < entries.len() {
+            total -= sizes[n];
+            n += 1;
+        }
+        to_delete.extend(entries.drain(..n));
+    }
+
+    let mut removed_size = 0;
+    for entry in &to_delete {
+        removed_size += entry_size(&entry.path);
+        if opts.dry_run {
+            config
+                .shell()
+                .verbose(|s| s.status("Removing", format!("{} (dry run)", entry.path.display())))?;
+            continue;
+        }
+        config
+            .shell()
+            .verbose(|s| s.status("Removing", entry.path.display()))?;
+        remove_entry(entry)?;
+        tracker.remove(entry);
+    }
+
+    let summary = format!(
+        "{} cache {}, {} total",
+        to_delete.len(),
+        if to_delete.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        },
+        ByteSize(removed_size)
+    );
+    if opts.dry_run {
+        config
+            .shell()
+            .status("Summary", format!("{} would be removed", summary))?;
+    } else {
+        tracker.save()?;
+        config.shell().status("Removed", summary)?;
+    }
+    Ok(())
+}
+
+fn remove_entry(entry: &CacheEntry) -> CargoResult<()> {
+    match entry.kind {
+        CacheKind::RegistryCrate => paths::remove_file(&entry.path)?,
+        CacheKind::RegistryIndex
+        | CacheKind::RegistrySrc
+        | CacheKind::GitDb
+        | CacheKind::GitCheckout => paths::remove_dir_all(&entry.path)?,
+    }
+    // Clean up the per-registry or per-repository directory if this was its
+    // last entry. This fails if it isn't empty, which is fine.
+    if !matches!(entry.kind, CacheKind::RegistryIndex | CacheKind::GitDb) {
+        if let Some(parent) = entry.path.parent() {
+            drop(fs::remove_dir(parent));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the size on disk of a file, or of all files in a directory.
+fn entry_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
@@ -702,6 +702,8 @@ fn run_verify(ws: &Workspace<'_>, tar: &FileLock, opts: &PackageOpts<'_>) -> Car
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             honor_rust_version: true,
+            partition: None,
+            build_std_only: false,
         },
         &exec,
     )?;
@@ -0,0 +1,67 @@
+//! Implementation of `cargo remove`.
+
+use anyhow::bail;
+
+use crate::core::Package;
+use crate::util::errors::CargoResult;
+use crate::util::toml_mut::{self, DepTable, LocalManifest};
+use crate::util::Config;
+
+pub struct RemoveOptions<'a> {
+    pub config: &'a Config,
+    /// The package whose manifest is edited.
+    pub spec: &'a Package,
+    /// Names of the dependencies to remove, as written in the manifest.
+    pub dependencies: Vec<String>,
+    /// The table to remove the dependencies from.
+    pub section: DepTable,
+    /// Only report what would be removed.
+    pub dry_run: bool,
+}
+
+/// Removes dependencies from the manifest of `opts.spec`.
+///
+/// Features referring to a removed dependency are removed as well, unless
+/// the dependency is still listed in another table.
+pub fn remove(opts: &RemoveOptions<'_>) -> CargoResult<()> {
+    let mut manifest = LocalManifest::try_new(opts.spec.manifest_path())?;
+
+    for name in &opts.dependencies {
+        opts.config
+            .shell()
+            .status("Removing", format!("{} from {}", name, opts.section))?;
+        let removed = match manifest.remove_dependency(&opts.section, name) {
+            Ok(removed) => removed,
+            Err(e) => {
+                let others: Vec<String> = manifest
+                    .dep_tables()
+                    .iter()
+                    .filter(|table| manifest.get_dependency(table, name).is_some())
+                    .map(|table| format!("`{}`", table))
+                    .collect();
+                if others.is_empty() {
+                    return Err(e);
+                }
+                bail!(
+                    "{}\n\nhelp: a dependency with the same name exists in {}",
+                    e,
+                    others.join(", ")
+                );
+            }
+        };
+        let still_used = manifest
+            .dep_tables()
+            .iter()
+            .any(|table| manifest.get_dependency(table, name).is_some());
+        if !still_used {
+            manifest.remove_feature_references(name, toml_mut::is_optional(&removed));
+        }
+    }
+
+    if opts.dry_run {
+        opts.config.shell().warn("aborting remove due to dry run")?;
+    } else {
+        manifest.write()?;
+    }
+    Ok(())
+}
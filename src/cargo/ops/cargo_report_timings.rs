@@ -0,0 +1,482 @@
+//! Comparing two builds timed with `-Ztimings=file`, for `cargo report timings`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use cargo_util::paths;
+
+use crate::core::compiler::{TimingReport, UnitData};
+use crate::drop_println;
+use crate::util::{CargoResult, Config};
+
+pub struct CompareTimingsOptions {
+    /// The report of the build to compare against.
+    pub old: PathBuf,
+    pub new: PathBuf,
+    /// If true, saves the comparison as an HTML file instead of printing it.
+    pub html: bool,
+}
+
+/// Compares two timing reports, printing the differences as a table or
+/// saving them as an HTML file.
+pub fn compare_timings(config: &Config, opts: &CompareTimingsOptions) -> CargoResult<()> {
+    let old = TimingReport::load(&opts.old)?;
+    let new = TimingReport::load(&opts.new)?;
+    let comparison = Comparison::new(&old, &new);
+    if opts.html {
+        let filename = "cargo-timing-compare.html";
+        paths::write(filename, comparison.to_html(&opts.old, &opts.new))?;
+        let msg = format!(
+            "comparison saved to {}",
+            config.cwd().join(filename).display()
+        );
+        config
+            .shell()
+            .status_with_color("Timing", msg, termcolor::Color::Cyan)?;
+    } else {
+        drop_println!(config, "{}", comparison.to_text());
+    }
+    Ok(())
+}
+
+/// Numbers describing a whole build.
+struct Summary {
+    duration: f64,
+    units: usize,
+    /// The number of units running on average.
+    avg_concurrency: f64,
+    /// Time during which fewer units than allowed by `-j` were running.
+    underutilized: f64,
+    /// The average CPU usage in percent, if it could be measured.
+    cpu_usage: Option<f64>,
+    /// Indices of the units on the critical path, in build order.
+    critical_path: Vec<usize>,
+    /// The time the last unit on the critical path finished.
+    critical_path_time: f64,
+}
+
+impl Summary {
+    fn new(report: &TimingReport) -> Summary {
+        let mut avg_concurrency = 0.0;
+        let mut underutilized = 0.0;
+        for w in report.concurrency.windows(2) {
+            let dt = w[1].t - w[0].t;
+            avg_concurrency += w[0].active as f64 * dt;
+            if w[0].active < report.jobs as usize {
+                underutilized += dt;
+            }
+        }
+        let span = match (report.concurrency.first(), report.concurrency.last()) {
+            (Some(first), Some(last)) => last.t - first.t,
+            _ => 0.0,
+        };
+        if span > 0.0 {
+            avg_concurrency /= span;
+        }
+        let cpu_usage = if report.cpu_usage.is_empty() {
+            None
+        } else {
+            let total: f64 = report.cpu_usage.iter().map(|(_, usage)| usage).sum();
+            Some(total / report.cpu_usage.len() as f64)
+        };
+        let (critical_path, critical_path_time) = critical_path(&report.units);
+        Summary {
+            duration: report.duration,
+            units: report.units.len(),
+            avg_concurrency,
+            underutilized,
+            cpu_usage,
+            critical_path,
+            critical_path_time,
+        }
+    }
+}
+
+/// Finds the chain of units that determined when the last unit finished.
+///
+/// Each unit records the units it unlocked, that is the units whose last
+/// dependency it was, so walking back from the last unit to finish gives the
+/// chain of units that each had to wait for the previous one.
+fn critical_path(units: &[UnitData]) -> (Vec<usize>, f64) {
+    let mut unlocked_by = HashMap::new();
+    for unit in units {
+        for &i in unit.unlocked_units.iter().chain(&unit.unlocked_rmeta_units) {
+            unlocked_by.insert(i, unit.i);
+        }
+    }
+    let end = |u: &UnitData| u.start + u.duration;
+    let last = match units.iter().max_by(|a, b| end(a).total_cmp(&end(b))) {
+        Some(last) => last,
+        None => return (Vec::new(), 0.0),
+    };
+    let mut path = vec![last.i];
+    let mut seen = HashSet::new();
+    while let Some(&prev) = unlocked_by.get(path.last().unwrap()) {
+        if !seen.insert(prev) {
+            break;
+        }
+        path.push(prev);
+    }
+    path.reverse();
+    (path, end(last))
+}
+
+/// What identifies a unit between two builds, apart from its version.
+fn unit_key(u: &UnitData) -> (&str, &str, &str, &str) {
+    (&u.name, &u.target, &u.kind, &u.mode)
+}
+
+fn by_unit_key<'a>(
+    units: impl Iterator<Item = &'a UnitData>,
+) -> HashMap<(&'a str, &'a str, &'a str, &'a str), Vec<&'a UnitData>> {
+    let mut map: HashMap<_, Vec<_>> = HashMap::new();
+    for u in units {
+        map.entry(unit_key(u)).or_default().push(u);
+    }
+    map
+}
+
+/// A unit in one or both of the builds.
+struct Row {
+    name: String,
+    old: Option<f64>,
+    new: Option<f64>,
+}
+
+impl Row {
+    /// How much longer the unit took in the new build, counting missing
+    /// units as taking no time.
+    fn delta(&self) -> f64 {
+        self.new.unwrap_or(0.0) - self.old.unwrap_or(0.0)
+    }
+}
+
+struct Comparison {
+    old: Summary,
+    new: Summary,
+    rows: Vec<Row>,
+    /// The names of the units on the critical path of the new build, with
+    /// whether they were on the critical path of the old build.
+    critical_path: Vec<(String, bool)>,
+    /// The names of the units that are no longer on the critical path.
+    left_critical_path: Vec<String>,
+}
+
+impl Comparison {
+    fn new(old: &TimingReport, new: &TimingReport) -> Comparison {
+        // Units are matched by name, version, target, kind and mode. Units
+        // whose version changed are then matched by the others, if that is
+        // unambiguous.
+        let key = |u| (unit_key(u), u.version.as_str());
+        let old_by_key: HashMap<_, &UnitData> = old.units.iter().map(|u| (key(u), u)).collect();
+        let mut matched: HashMap<usize, &UnitData> = HashMap::new();
+        for unit in &new.units {
+            if let Some(old_unit) = old_by_key.get(&key(unit)) {
+                matched.insert(unit.i, old_unit);
+            }
+        }
+        let matched_old: HashSet<usize> = matched.values().map(|u| u.i).collect();
+        let old_rest = by_unit_key(old.units.iter().filter(|u| !matched_old.contains(&u.i)));
+        let new_rest = by_unit_key(new.units.iter().filter(|u| !matched.contains_key(&u.i)));
+        for (unit_key, new_units) in &new_rest {
+            if let (Some(old_units), [new_unit]) = (old_rest.get(unit_key), &new_units[..]) {
+                if let [old_unit] = &old_units[..] {
+                    matched.insert(new_unit.i, old_unit);
+                }
+            }
+        }
+
+        // Units built for the host are told apart from the same units built
+        // for `--target`.
+        let cross = old.units.iter().chain(&new.units).any(|u| u.kind != "host");
+        let target = |u: &UnitData| {
+            if cross && u.kind == "host" {
+                format!("{} (host)", u.target)
+            } else {
+                u.target.clone()
+            }
+        };
+        let unit_name = |u: &UnitData| format!("{} v{}{}", u.name, u.version, target(u));
+
+        let mut rows = Vec::new();
+        let mut old_names = HashMap::new();
+        for unit in &new.units {
+            let (name, old_duration) = match matched.get(&unit.i) {
+                Some(old_unit) if old_unit.version != unit.version => {
+                    let name = format!(
+                        "{} v{} -> v{}{}",
+                        unit.name,
+                        old_unit.version,
+                        unit.version,
+                        target(unit)
+                    );
+                    old_names.insert(old_unit.i, name.clone());
+                    (name, Some(old_unit.duration))
+                }
+                Some(old_unit) => {
+                    old_names.insert(old_unit.i, unit_name(unit));
+                    (unit_name(unit), Some(old_unit.duration))
+                }
+                None => (unit_name(unit), None),
+            };
+            rows.push(Row {
+                name,
+                old: old_duration,
+                new: Some(unit.duration),
+            });
+        }
+        for unit in &old.units {
+            if !old_names.contains_key(&unit.i) {
+                old_names.insert(unit.i, unit_name(unit));
+                rows.push(Row {
+                    name: unit_name(unit),
+                    old: Some(unit.duration),
+                    new: None,
+                });
+            }
+        }
+        rows.sort_by(|a, b| {
+            b.delta()
+                .total_cmp(&a.delta())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        let old = Summary::new(old);
+        let new_summary = Summary::new(new);
+        let old_critical: HashSet<&String> =
+            old.critical_path.iter().map(|i| &old_names[i]).collect();
+        let mut new_critical = HashSet::new();
+        let critical_path = new_summary
+            .critical_path
+            .iter()
+            .map(|i| {
+                let unit = &new.units[*i];
+                let name = match matched.get(i) {
+                    Some(old_unit) => old_names[&old_unit.i].clone(),
+                    None => unit_name(unit),
+                };
+                new_critical.insert(name.clone());
+                let was_critical = old_critical.contains(&name);
+                (name, was_critical)
+            })
+            .collect();
+        let left_critical_path = old
+            .critical_path
+            .iter()
+            .map(|i| old_names[i].clone())
+            .filter(|name| !new_critical.contains(name))
+            .collect();
+
+        Comparison {
+            old,
+            new: new_summary,
+            rows,
+            critical_path,
+            left_critical_path,
+        }
+    }
+
+    /// Rows of the summary table, as `(label, old, new, delta)`.
+    fn summary_rows(&self) -> Vec<(&'static str, String, String, String)> {
+        let (old, new) = (&self.old, &self.new);
+        let mut rows = vec![
+            (
+                "Total time",
+                secs(old.duration),
+                secs(new.duration),
+                delta(Some(old.duration), Some(new.duration)),
+            ),
+            (
+                "Critical path",
+                secs(old.critical_path_time),
+                secs(new.critical_path_time),
+                delta(Some(old.critical_path_time), Some(new.critical_path_time)),
+            ),
+            (
+                "Units built",
+                old.units.to_string(),
+                new.units.to_string(),
+                format!("{:+}", new.units as i64 - old.units as i64),
+            ),
+            (
+                "Avg concurrency",
+                format!("{:.1}", old.avg_concurrency),
+                format!("{:.1}", new.avg_concurrency),
+                format!("{:+.1}", new.avg_concurrency - old.avg_concurrency),
+            ),
+            (
+                "Underutilized time",
+                secs(old.underutilized),
+                secs(new.underutilized),
+                delta(Some(old.underutilized), Some(new.underutilized)),
+            ),
+        ];
+        if let (Some(old_cpu), Some(new_cpu)) = (old.cpu_usage, new.cpu_usage) {
+            rows.push((
+                "Avg CPU usage",
+                format!("{:.0}%", old_cpu),
+                format!("{:.0}%", new_cpu),
+                format!("{:+.0}%", new_cpu - old_cpu),
+            ));
+        }
+        rows
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        let summary = self.summary_rows();
+        let label_width = summary.iter().map(|r| r.0.len()).max().unwrap_or(0);
+        for (label, old, new, delta) in &summary {
+            writeln!(
+                out,
+                "{:<width$}  {:>9} -> {:<9} {}",
+                format!("{}:", label),
+                old,
+                new,
+                delta,
+                width = label_width + 1
+            )
+            .unwrap();
+        }
+
+        out.push_str("\nCritical path:\n");
+        for (name, was_critical) in &self.critical_path {
+            let marker = if *was_critical { ' ' } else { '+' };
+            writeln!(out, "  {} {}", marker, name).unwrap();
+        }
+        for name in &self.left_critical_path {
+            writeln!(out, "  - {}", name).unwrap();
+        }
+
+        let name_width = self
+            .rows
+            .iter()
+            .map(|r| r.name.len())
+            .chain(Some("Unit".len()))
+            .max()
+            .unwrap();
+        writeln!(
+            out,
+            "\n{:<width$}  {:>9}  {:>9}  {}",
+            "Unit",
+            "Old",
+            "New",
+            "Delta",
+            width = name_width
+        )
+        .unwrap();
+        for row in &self.rows {
+            writeln!(
+                out,
+                "{:<width$}  {:>9}  {:>9}  {}",
+                row.name,
+                row.old.map_or_else(|| "-".to_string(), secs),
+                row.new.map_or_else(|| "-".to_string(), secs),
+                delta(row.old, row.new),
+                width = name_width
+            )
+            .unwrap();
+        }
+        out.truncate(out.trim_end().len());
+        out
+    }
+
+    fn to_html(&self, old_path: &Path, new_path: &Path) -> String {
+        let mut out = String::new();
+        write!(
+            out,
+            r#"<html>
+<head>
+  <title>Cargo Build Timing Comparison</title>
+  <meta charset="utf-8">
+<style type="text/css">
+html {{ font-family: sans-serif; }}
+table {{ border-collapse: collapse; margin-bottom: 20px; }}
+td, th {{ border: 1px solid #ddd; padding: 4px 8px; text-align: right; }}
+td:first-child, th:first-child {{ text-align: left; }}
+.slower {{ color: #c0392b; }}
+.faster {{ color: #27ae60; }}
+.added {{ background-color: #fdecea; }}
+.removed {{ background-color: #eafaf1; text-decoration: line-through; }}
+</style>
+</head>
+<body>
+<h1>Cargo Build Timing Comparison</h1>
+<p>Old: {}<br>New: {}</p>
+<table>
+  <tr><th></th><th>Old</th><th>New</th><th>Delta</th></tr>
+"#,
+            escape_html(&old_path.display().to_string()),
+            escape_html(&new_path.display().to_string()),
+        )
+        .unwrap();
+        for (label, old, new, delta) in self.summary_rows() {
+            writeln!(
+                out,
+                "  <tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                label, old, new, delta
+            )
+            .unwrap();
+        }
+        out.push_str("</table>\n<h2>Critical path</h2>\n<ol>\n");
+        for (name, was_critical) in &self.critical_path {
+            let class = if *was_critical {
+                ""
+            } else {
+                " class=\"added\""
+            };
+            writeln!(out, "  <li{}>{}</li>", class, escape_html(name)).unwrap();
+        }
+        for name in &self.left_critical_path {
+            writeln!(out, "  <li class=\"removed\">{}</li>", escape_html(name)).unwrap();
+        }
+        out.push_str(
+            "</ol>\n<h2>Units</h2>\n<table>\n  \
+             <tr><th>Unit</th><th>Old</th><th>New</th><th>Delta</th></tr>\n",
+        );
+        for row in &self.rows {
+            let class = if row.delta() > 0.0 {
+                "slower"
+            } else if row.delta() < 0.0 {
+                "faster"
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "  <tr><td>{}</td><td>{}</td><td>{}</td><td class=\"{}\">{}</td></tr>",
+                escape_html(&row.name),
+                row.old.map_or_else(|| "-".to_string(), secs),
+                row.new.map_or_else(|| "-".to_string(), secs),
+                class,
+                delta(row.old, row.new)
+            )
+            .unwrap();
+        }
+        out.push_str("</table>\n</body>\n</html>\n");
+        out
+    }
+}
+
+fn secs(t: f64) -> String {
+    format!("{:.1}s", t)
+}
+
+/// Renders the change from `old` to `new`, with the relative change if there
+/// was an old value.
+fn delta(old: Option<f64>, new: Option<f64>) -> String {
+    match (old, new) {
+        (Some(old), Some(new)) if old > 0.0 => {
+            format!("{:+.1}s ({:+.0}%)", new - old, (new - old) / old * 100.0)
+        }
+        (old, new) => format!("{:+.1}s", new.unwrap_or(0.0) - old.unwrap_or(0.0)),
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
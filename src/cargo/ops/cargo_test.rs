@@ -1,16 +1,29 @@
-use crate::core::compiler::{Compilation, CompileKind, Doctest, UnitOutput};
+use crate::core::compiler::{Compilation, CompileKind, Doctest, Unit, UnitOutput};
 use crate::core::shell::Verbosity;
 use crate::core::{TargetKind, Workspace};
 use crate::ops;
+use crate::ops::test_report::{
+    LibtestParser, TestBinaryKind, TestBinaryResult, TestReport, TestReportOutput,
+};
 use crate::util::errors::CargoResult;
 use crate::util::{add_path_args, CargoTestError, Config, Test};
-use cargo_util::ProcessError;
+use anyhow::Context as _;
+use cargo_util::{ProcessBuilder, ProcessError};
+use jobserver::Client;
+use std::cell::RefCell;
 use std::ffi::OsString;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
 pub struct TestOptions {
     pub compile_opts: ops::CompileOptions,
     pub no_run: bool,
     pub no_fail_fast: bool,
+    /// The number of test binaries to run at the same time.
+    pub test_jobs: usize,
+    /// Reports of the test results to write once all tests ran.
+    pub reports: Vec<TestReportOutput>,
 }
 
 pub fn run_tests(
@@ -23,14 +36,24 @@ pub fn run_tests(
     if options.no_run {
         return Ok(None);
     }
-    let (test, mut errors) = run_unit_tests(ws.config(), options, test_args, &compilation)?;
+    let mut report = new_report(ws.config(), options);
+    let (test, mut errors) = run_unit_tests(
+        ws.config(),
+        options,
+        test_args,
+        &compilation,
+        report.as_mut(),
+    )?;
 
     // If we have an error and want to fail fast, then return.
     if !errors.is_empty() && !options.no_fail_fast {
+        write_reports(options, report.as_ref())?;
         return Ok(Some(CargoTestError::new(test, errors)));
     }
 
-    let (doctest, docerrors) = run_doc_tests(ws, options, test_args, &compilation)?;
+    let (doctest, docerrors) =
+        run_doc_tests(ws, options, test_args, &compilation, report.as_mut())?;
+    write_reports(options, report.as_ref())?;
     let test = if docerrors.is_empty() { test } else { doctest };
     errors.extend(docerrors);
     if errors.is_empty() {
@@ -54,7 +77,10 @@ pub fn run_benches(
     let mut args = args.to_vec();
     args.push("--bench");
 
-    let (test, errors) = run_unit_tests(ws.config(), options, &args, &compilation)?;
+    let mut report = new_report(ws.config(), options);
+    let (test, errors) =
+        run_unit_tests(ws.config(), options, &args, &compilation, report.as_mut())?;
+    write_reports(options, report.as_ref())?;
 
     match errors.len() {
         0 => Ok(None),
@@ -62,6 +88,27 @@ pub fn run_benches(
     }
 }
 
+/// Returns a report to collect the test results into, if they are needed
+/// for `--report` or JSON messages.
+fn new_report(config: &Config, options: &TestOptions) -> Option<TestReport> {
+    let emit_json =
+        options.compile_opts.build_config.emit_json() && config.cli_unstable().unstable_options;
+    if emit_json || !options.reports.is_empty() {
+        Some(TestReport::new(emit_json))
+    } else {
+        None
+    }
+}
+
+fn write_reports(options: &TestOptions, report: Option<&TestReport>) -> CargoResult<()> {
+    if let Some(report) = report {
+        for output in &options.reports {
+            report.write(output)?;
+        }
+    }
+    Ok(())
+}
+
 fn compile_tests<'a>(ws: &Workspace<'a>, options: &TestOptions) -> CargoResult<Compilation<'a>> {
     let mut compilation = ops::compile(ws, &options.compile_opts)?;
     compilation.tests.sort();
@@ -74,9 +121,12 @@ fn run_unit_tests(
     options: &TestOptions,
     test_args: &[&str],
     compilation: &Compilation<'_>,
+    mut report: Option<&mut TestReport>,
 ) -> CargoResult<(Test, Vec<ProcessError>)> {
     let cwd = config.cwd();
-    let mut errors = Vec::new();
+    let mut runs = Vec::new();
+    let captured = report.is_some() || (options.test_jobs > 1 && compilation.tests.len() > 1);
+    let color = color_arg(config, test_args, captured);
 
     for UnitOutput {
         unit,
@@ -84,8 +134,6 @@ fn run_unit_tests(
         script_meta,
     } in compilation.tests.iter()
     {
-        let test = unit.target.name().to_string();
-
         let test_path = unit.target.src_path().path().unwrap();
         let exe_display = if let TargetKind::Test = unit.target.kind() {
             format!(
@@ -105,50 +153,201 @@ fn run_unit_tests(
 
         let mut cmd = compilation.target_process(path, unit.kind, &unit.pkg, *script_meta)?;
         cmd.args(test_args);
-        if unit.target.harness() && config.shell().verbosity() == Verbosity::Quiet {
-            cmd.arg("--quiet");
+        if unit.target.harness() {
+            if config.shell().verbosity() == Verbosity::Quiet {
+                cmd.arg("--quiet");
+            }
+            if let Some(color) = color {
+                cmd.arg(color);
+            }
         }
-        config
-            .shell()
-            .concise(|shell| shell.status("Running", &exe_display))?;
-        config
-            .shell()
-            .verbose(|shell| shell.status("Running", &cmd))?;
-
-        let result = cmd.exec();
-
-        match result {
-            Err(e) => {
-                let e = e.downcast::<ProcessError>()?;
-                errors.push((
-                    unit.target.kind().clone(),
-                    test.clone(),
-                    unit.pkg.name().to_string(),
-                    e,
-                ));
+        runs.push(TestRun {
+            unit,
+            exe_display,
+            cmd,
+        });
+    }
+
+    let mut errors = Vec::new();
+    if options.test_jobs > 1 && runs.len() > 1 {
+        run_unit_tests_concurrently(config, options, &mut runs, report, &mut errors)?;
+    } else {
+        for run in &runs {
+            run.show_running(config)?;
+            let result = exec_test(
+                config,
+                &run.cmd,
+                run.unit,
+                TestBinaryKind::Test,
+                report.as_deref_mut(),
+            );
+            if let Err(e) = result {
+                errors.push((run.unit, e.downcast::<ProcessError>()?));
                 if !options.no_fail_fast {
                     break;
                 }
             }
-            Ok(()) => {}
         }
     }
 
     if errors.len() == 1 {
-        let (kind, name, pkg_name, e) = errors.pop().unwrap();
+        let (unit, e) = errors.pop().unwrap();
         Ok((
             Test::UnitTest {
-                kind,
-                name,
-                pkg_name,
+                kind: unit.target.kind().clone(),
+                name: unit.target.name().to_string(),
+                pkg_name: unit.pkg.name().to_string(),
             },
             vec![e],
         ))
     } else {
-        Ok((
-            Test::Multiple,
-            errors.into_iter().map(|(_, _, _, e)| e).collect(),
-        ))
+        Ok((Test::Multiple, errors.into_iter().map(|(_, e)| e).collect()))
+    }
+}
+
+/// A test binary to run.
+struct TestRun<'a> {
+    unit: &'a Unit,
+    exe_display: String,
+    cmd: ProcessBuilder,
+}
+
+impl TestRun<'_> {
+    fn show_running(&self, config: &Config) -> CargoResult<()> {
+        config
+            .shell()
+            .concise(|shell| shell.status("Running", &self.exe_display))?;
+        config
+            .shell()
+            .verbose(|shell| shell.status("Running", &self.cmd))
+    }
+}
+
+/// The buffered output and outcome of a test binary run on another thread.
+struct BufferedRun {
+    /// Lines in the order they were printed, with whether they were printed
+    /// to stdout.
+    lines: Vec<(bool, String)>,
+    duration: Duration,
+    result: CargoResult<()>,
+}
+
+/// Runs the test binaries with `--test-jobs` threads.
+///
+/// Each running binary holds a token of the jobserver, which is inherited
+/// from the environment if Cargo itself runs under one. The output of each
+/// binary is buffered and shown once it exits, so that the output of binaries
+/// running at the same time isn't interleaved. Binaries are shown in the
+/// order they finish.
+///
+/// Unless `--no-fail-fast` is given, no more binaries are started once one
+/// failed, but those still running are waited for.
+fn run_unit_tests_concurrently<'a>(
+    config: &Config,
+    options: &TestOptions,
+    runs: &mut [TestRun<'a>],
+    mut report: Option<&mut TestReport>,
+    errors: &mut Vec<(&'a Unit, ProcessError)>,
+) -> CargoResult<()> {
+    let jobserver = match config.jobserver_from_env() {
+        Some(client) => client.clone(),
+        None => Client::new(options.test_jobs).with_context(|| "failed to create jobserver")?,
+    };
+    for run in runs.iter_mut() {
+        run.cmd.inherit_jobserver(&jobserver);
+    }
+    // `Unit`s can't be sent to other threads, only the commands are.
+    let cmds: Vec<&ProcessBuilder> = runs.iter().map(|run| &run.cmd).collect();
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (tx, rx) = mpsc::channel();
+
+    crossbeam_utils::thread::scope(|scope| -> CargoResult<()> {
+        for _ in 0..options.test_jobs.min(runs.len()) {
+            let tx = tx.clone();
+            let (cmds, next, stop, jobserver) = (&cmds, &next, &stop, &jobserver);
+            scope.spawn(move |_| loop {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                let cmd = match cmds.get(i) {
+                    Some(cmd) => cmd,
+                    None => break,
+                };
+                let run = match jobserver.acquire() {
+                    Ok(_token) => exec_buffered(cmd),
+                    Err(e) => BufferedRun {
+                        lines: Vec::new(),
+                        duration: Duration::new(0, 0),
+                        result: Err(
+                            anyhow::Error::from(e).context("failed to acquire jobserver token")
+                        ),
+                    },
+                };
+                if tx.send((i, run)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+
+        for (i, buffered) in rx {
+            let run = &runs[i];
+            run.show_running(config)?;
+            let mut parser = report.as_ref().map(|_| LibtestParser::default());
+            for (is_stdout, line) in &buffered.lines {
+                if *is_stdout {
+                    if let Some(parser) = &mut parser {
+                        parser.line(line);
+                    }
+                    writeln!(config.shell().out(), "{}", line)?;
+                } else {
+                    writeln!(config.shell().err(), "{}", line)?;
+                }
+            }
+            if let (Some(report), Some(parser)) = (report.as_deref_mut(), parser) {
+                add_to_report(
+                    config,
+                    report,
+                    run.unit,
+                    TestBinaryKind::Test,
+                    buffered.duration,
+                    parser,
+                    buffered.result.is_ok(),
+                );
+            }
+            if let Err(e) = buffered.result {
+                errors.push((run.unit, e.downcast::<ProcessError>()?));
+                if !options.no_fail_fast {
+                    stop.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+        Ok(())
+    })
+    .expect("child threads shouldn't panic")
+}
+
+/// Runs a test binary, collecting its output instead of showing it.
+fn exec_buffered(cmd: &ProcessBuilder) -> BufferedRun {
+    let start = Instant::now();
+    let lines = RefCell::new(Vec::new());
+    let result = cmd.exec_with_streaming(
+        &mut |line| {
+            lines.borrow_mut().push((true, line.to_string()));
+            Ok(())
+        },
+        &mut |line| {
+            lines.borrow_mut().push((false, line.to_string()));
+            Ok(())
+        },
+        false,
+    );
+    BufferedRun {
+        lines: lines.into_inner(),
+        duration: start.elapsed(),
+        result: result.map(drop),
     }
 }
 
@@ -157,6 +356,7 @@ fn run_doc_tests(
     options: &TestOptions,
     test_args: &[&str],
     compilation: &Compilation<'_>,
+    mut report: Option<&mut TestReport>,
 ) -> CargoResult<(Test, Vec<ProcessError>)> {
     let config = ws.config();
     let mut errors = Vec::new();
@@ -235,6 +435,9 @@ fn run_doc_tests(
         for arg in test_args {
             p.arg("--test-args").arg(arg);
         }
+        if let Some(color) = color_arg(config, test_args, report.is_some()) {
+            p.arg("--test-args").arg(color);
+        }
 
         p.args(args);
 
@@ -245,7 +448,13 @@ fn run_doc_tests(
         config
             .shell()
             .verbose(|shell| shell.status("Running", p.to_string()))?;
-        if let Err(e) = p.exec() {
+        if let Err(e) = exec_test(
+            config,
+            &p,
+            unit,
+            TestBinaryKind::Doctest,
+            report.as_deref_mut(),
+        ) {
             let e = e.downcast::<ProcessError>()?;
             errors.push(e);
             if !options.no_fail_fast {
@@ -255,3 +464,89 @@ fn run_doc_tests(
     }
     Ok((Test::Doc, errors))
 }
+
+/// Returns the argument keeping libtest's colors if its output is `captured`
+/// by Cargo, since libtest only colors the output of a terminal by default.
+fn color_arg(config: &Config, test_args: &[&str], captured: bool) -> Option<&'static str> {
+    let given = test_args.iter().any(|arg| arg.starts_with("--color"));
+    if captured && !given && config.shell().out_supports_color() {
+        Some("--color=always")
+    } else {
+        None
+    }
+}
+
+/// Runs a test binary, or rustdoc to run doctests.
+///
+/// If a report is given, the output is captured and parsed to add the
+/// results to the report, while still being shown as usual.
+fn exec_test(
+    config: &Config,
+    cmd: &ProcessBuilder,
+    unit: &Unit,
+    kind: TestBinaryKind,
+    report: Option<&mut TestReport>,
+) -> CargoResult<()> {
+    let report = match report {
+        Some(report) => report,
+        None => return cmd.exec(),
+    };
+
+    let start = Instant::now();
+    let mut parser = LibtestParser::default();
+    let result = cmd.exec_with_streaming(
+        &mut |line| {
+            parser.line(line);
+            writeln!(config.shell().out(), "{}", line)?;
+            Ok(())
+        },
+        &mut |line| {
+            writeln!(config.shell().err(), "{}", line)?;
+            Ok(())
+        },
+        false,
+    );
+    add_to_report(
+        config,
+        report,
+        unit,
+        kind,
+        start.elapsed(),
+        parser,
+        result.is_ok(),
+    );
+    result.map(drop)
+}
+
+fn add_to_report(
+    config: &Config,
+    report: &mut TestReport,
+    unit: &Unit,
+    kind: TestBinaryKind,
+    duration: Duration,
+    parser: LibtestParser,
+    success: bool,
+) {
+    let mut tests = parser.finish();
+    if kind == TestBinaryKind::Doctest {
+        // rustdoc names doctests after the path of the source file, which is
+        // absolute. Keep names stable across machines.
+        let root = format!("{}{}", unit.pkg.root().display(), std::path::MAIN_SEPARATOR);
+        for test in &mut tests {
+            if let Some(name) = test.name.strip_prefix(&root) {
+                test.name = name.to_string();
+            }
+        }
+    }
+    report.add(
+        config,
+        TestBinaryResult {
+            package_id: unit.pkg.package_id(),
+            target: unit.target.clone(),
+            kind,
+            duration,
+            success,
+            tests,
+        },
+    );
+}
@@ -1,10 +1,16 @@
+pub use self::build_sandbox::build_sandbox_maybe_exec;
+pub use self::cargo_add::{add, manifest_package, AddOptions, DepOp};
+pub use self::cargo_build_std::build_std;
 pub use self::cargo_clean::{clean, CleanOptions};
 pub use self::cargo_compile::{
     compile, compile_with_exec, compile_ws, create_bcx, print, resolve_all_features, CompileOptions,
 };
-pub use self::cargo_compile::{CompileFilter, FilterRule, LibRule, Packages};
+pub use self::cargo_compile::{
+    CompileFilter, FilterRule, LibRule, Packages, Partition, PartitionKind,
+};
 pub use self::cargo_doc::{doc, DocOptions};
 pub use self::cargo_fetch::{fetch, FetchOptions};
+pub use self::cargo_gc::{gc, parse_max_age, parse_max_size, GcOptions};
 pub use self::cargo_generate_lockfile::generate_lockfile;
 pub use self::cargo_generate_lockfile::update_lockfile;
 pub use self::cargo_generate_lockfile::UpdateOptions;
@@ -14,26 +20,35 @@ pub use self::cargo_output_metadata::{output_metadata, ExportInfo, OutputMetadat
 pub use self::cargo_package::{package, PackageOpts};
 pub use self::cargo_pkgid::pkgid;
 pub use self::cargo_read_manifest::{read_package, read_packages};
+pub use self::cargo_remove::{remove, RemoveOptions};
+pub use self::cargo_report_timings::{compare_timings, CompareTimingsOptions};
 pub use self::cargo_run::run;
 pub use self::cargo_test::{run_benches, run_tests, TestOptions};
 pub use self::cargo_uninstall::uninstall;
 pub use self::fix::{fix, fix_maybe_exec_rustc, FixOptions};
 pub use self::lockfile::{load_pkg_lockfile, resolve_to_string, write_pkg_lockfile};
+pub use self::registry::http_multi_handle;
 pub use self::registry::HttpTimeout;
 pub use self::registry::{configure_http_handle, http_handle, http_handle_and_timeout};
 pub use self::registry::{modify_owners, yank, OwnersOptions, PublishOpts};
 pub use self::registry::{needs_custom_http_transport, registry_login, registry_logout, search};
 pub use self::registry::{publish, registry_configuration, RegistryConfig};
+pub use self::remote_cache_fs::remote_cache_fs_maybe_exec;
 pub use self::resolve::{
     add_overrides, get_resolved_packages, resolve_with_previous, resolve_ws, resolve_ws_with_opts,
 };
+pub use self::test_report::TestReportOutput;
 pub use self::vendor::{vendor, VendorOptions};
 
+mod build_sandbox;
+mod cargo_add;
+mod cargo_build_std;
 mod cargo_clean;
 mod cargo_compile;
 pub mod cargo_config;
 mod cargo_doc;
 mod cargo_fetch;
+mod cargo_gc;
 mod cargo_generate_lockfile;
 mod cargo_install;
 mod cargo_new;
@@ -41,6 +56,8 @@ mod cargo_output_metadata;
 mod cargo_package;
 mod cargo_pkgid;
 mod cargo_read_manifest;
+mod cargo_remove;
+mod cargo_report_timings;
 mod cargo_run;
 mod cargo_test;
 mod cargo_uninstall;
@@ -48,7 +65,9 @@ mod common_for_install_and_uninstall;
 mod fix;
 mod lockfile;
 mod registry;
+mod remote_cache_fs;
 mod resolve;
+mod test_report;
 pub mod tree;
 mod vendor;
 
@@ -11,6 +11,7 @@ use anyhow::{bail, format_err, Context as _};
 use cargo_util::paths;
 use crates_io::{self, NewCrate, NewCrateDependency, Registry};
 use curl::easy::{Easy, InfoType, SslOpt, SslVersion};
+use curl::multi::Multi;
 use log::{log, Level};
 use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 
@@ -282,6 +283,7 @@ fn transmit(
                 license_file: license_file.clone(),
                 badges: badges.clone(),
                 links: links.clone(),
+                rust_version: pkg.rust_version().map(|s| s.to_string()),
                 v: None,
             },
             tarball,
@@ -425,7 +427,7 @@ fn registry(
     }
     let api_host = {
         let _lock = config.acquire_package_cache_lock()?;
-        let mut src = RegistrySource::remote(sid, &HashSet::new(), config);
+        let mut src = RegistrySource::remote(sid, &HashSet::new(), config)?;
         // Only update the index if the config is not available or `force` is set.
         let cfg = src.config();
         let mut updated_cfg = || {
@@ -515,6 +517,32 @@ pub fn http_handle_and_timeout(config: &Config) -> CargoResult<(Easy, HttpTimeou
     Ok((handle, timeout))
 }
 
+/// Creates a new curl `Multi` handle for performing many transfers in
+/// parallel, along with whether or not HTTP/2 multiplexing is enabled.
+///
+/// This is shared between crate downloads and sparse registry index fetches.
+pub fn http_multi_handle(config: &Config) -> CargoResult<(Multi, bool)> {
+    // We've enabled the `http2` feature of `curl` in Cargo, so treat
+    // failures here as fatal as it would indicate a build-time problem.
+    //
+    // Note that the multiplexing support is pretty new so we're having it
+    // off-by-default temporarily.
+    //
+    // Also note that pipelining is disabled as curl authors have indicated
+    // that it's buggy, and we've empirically seen that it's buggy with HTTP
+    // proxies.
+    let mut multi = Multi::new();
+    let multiplexing = config.http_config()?.multiplexing.unwrap_or(true);
+    multi
+        .pipelining(false, multiplexing)
+        .with_context(|| "failed to enable multiplexing/pipelining in curl")?;
+
+    // let's not flood crates.io with connections
+    multi.set_max_host_connections(2)?;
+
+    Ok((multi, multiplexing))
+}
+
 pub fn needs_custom_http_transport(config: &Config) -> CargoResult<bool> {
     Ok(http_proxy_exists(config)?
         || *config.http_config()? != Default::default()
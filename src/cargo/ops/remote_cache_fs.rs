@@ -0,0 +1,96 @@
+//! The `cargo:fs` remote cache helper, which stores artifacts in a directory.
+//!
+//! Cargo runs itself as this helper, with `__CARGO_REMOTE_CACHE_FS` set to
+//! the directory, the same way it runs itself as rustc for `cargo fix`. It
+//! serves as the reference implementation of the protocol described in the
+//! `core::compiler::remote_cache` module, and to share artifacts through a
+//! network file system.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use cargo_util::paths;
+
+use crate::core::compiler::remote_cache::{
+    RemoteCacheAction, RemoteCacheRequest, RemoteCacheResponse, FS_HELPER_ENV, PROTOCOL_VERSION,
+};
+use crate::util::CargoResult;
+
+/// Answers a single request of the remote cache protocol on stdin, if Cargo
+/// was run as the `cargo:fs` helper. Returns `false` otherwise.
+pub fn remote_cache_fs_maybe_exec() -> CargoResult<bool> {
+    let root = match env::var_os(FS_HELPER_ENV) {
+        Some(root) => PathBuf::from(root),
+        None => return Ok(false),
+    };
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    let response = match serde_json::from_str(&line)
+        .map_err(anyhow::Error::from)
+        .and_then(|request| answer(&root, request))
+    {
+        Ok(response) => response,
+        Err(e) => RemoteCacheResponse {
+            error: Some(format!("{:#}", e)),
+            ..Default::default()
+        },
+    };
+    writeln!(io::stdout(), "{}", serde_json::to_string(&response)?)?;
+    Ok(true)
+}
+
+fn answer(root: &Path, request: RemoteCacheRequest) -> CargoResult<RemoteCacheResponse> {
+    if request.v != PROTOCOL_VERSION {
+        bail!("unsupported protocol version {}", request.v);
+    }
+    let key = &request.key;
+    if key.is_empty() || key.starts_with('.') || key.contains(&['/', '\\'][..]) {
+        bail!("invalid key `{}`", key);
+    }
+    let entry = root.join(key);
+    match request.action {
+        RemoteCacheAction::Get => {
+            if !entry.is_dir() {
+                return Ok(RemoteCacheResponse::default());
+            }
+            copy_dir(&entry, &request.path)?;
+            Ok(RemoteCacheResponse {
+                found: true,
+                ..Default::default()
+            })
+        }
+        RemoteCacheAction::Put => {
+            if entry.exists() {
+                return Ok(RemoteCacheResponse::default());
+            }
+            // Copied next to the entry first, so that other builds never see
+            // a partially written entry.
+            let tmp = root.join(format!(".{}.{}", key, std::process::id()));
+            copy_dir(&request.path, &tmp)?;
+            if std::fs::rename(&tmp, &entry).is_err() {
+                // Another build stored the same entry in the meantime.
+                paths::remove_dir_all(&tmp)?;
+                if !entry.exists() {
+                    bail!("failed to store `{}` in `{}`", key, root.display());
+                }
+            }
+            Ok(RemoteCacheResponse::default())
+        }
+    }
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> CargoResult<()> {
+    paths::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let dst = dst.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dst)?;
+        } else {
+            paths::copy(entry.path(), dst)?;
+        }
+    }
+    Ok(())
+}
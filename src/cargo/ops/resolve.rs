@@ -15,7 +15,10 @@ use crate::core::registry::{LockedPatchDependency, PackageRegistry};
 use crate::core::resolver::features::{
     CliFeatures, FeatureOpts, FeatureResolver, ForceAllTargets, RequestedFeatures, ResolvedFeatures,
 };
-use crate::core::resolver::{self, HasDevUnits, Resolve, ResolveOpts, ResolveVersion};
+use crate::core::resolver::{
+    self, HasDevUnits, IncompatibleRustVersions, Resolve, ResolveOpts, ResolveVersion,
+    RustVersionPolicy,
+};
 use crate::core::summary::Summary;
 use crate::core::Feature;
 use crate::core::{
@@ -420,12 +423,15 @@ pub fn resolve_with_previous<'cfg>(
         None => root_replace.to_vec(),
     };
 
+    let rust_version = rust_version_policy(ws)?;
+
     ws.preload(registry);
     let mut resolved = resolver::resolve(
         &summaries,
         &replace,
         registry,
         &try_to_use,
+        rust_version.as_ref(),
         Some(ws.config()),
         ws.unstable_features()
             .require(Feature::public_dependency())
@@ -450,12 +456,68 @@ pub fn resolve_with_previous<'cfg>(
             ))?;
         }
     }
+    if let Some(rust_version) = &rust_version {
+        warn_incompatible_rust_versions(ws, &resolved, previous, rust_version)?;
+    }
     if let Some(previous) = previous {
         resolved.merge_from(previous)?;
     }
     Ok(resolved)
 }
 
+/// Determines how the resolver should treat packages requiring a newer Rust
+/// than the workspace's `rust-version`.
+///
+/// This is the lowest `rust-version` of all workspace members, combined
+/// with the `resolver.incompatible-rust-versions` config. Returns `None` if
+/// `-Z msrv-policy` isn't enabled, no member sets a `rust-version`, or
+/// incompatible versions are simply allowed.
+fn rust_version_policy(ws: &Workspace<'_>) -> CargoResult<Option<RustVersionPolicy>> {
+    let config = ws.config();
+    if !config.cli_unstable().msrv_policy {
+        return Ok(None);
+    }
+    let incompatible = config
+        .get::<Option<IncompatibleRustVersions>>("resolver.incompatible-rust-versions")?
+        .unwrap_or(IncompatibleRustVersions::Fallback);
+    if incompatible == IncompatibleRustVersions::Allow {
+        return Ok(None);
+    }
+    Ok(ws
+        .members()
+        .filter_map(|pkg| pkg.manifest().rust_version())
+        .filter_map(|rv| RustVersionPolicy::new(rv, incompatible))
+        .min_by(|a, b| a.version().cmp(b.version())))
+}
+
+/// Warns about newly selected packages which need a newer Rust than the
+/// workspace's `rust-version`, because no compatible version was available.
+fn warn_incompatible_rust_versions(
+    ws: &Workspace<'_>,
+    resolved: &Resolve,
+    previous: Option<&Resolve>,
+    rust_version: &RustVersionPolicy,
+) -> CargoResult<()> {
+    let mut incompatible: Vec<_> = resolved
+        .iter()
+        .filter(|id| previous.map_or(true, |prev| !prev.contains(id)))
+        .map(|id| resolved.summary(id))
+        .filter(|summary| !rust_version.is_compatible(summary))
+        .collect();
+    incompatible.sort_by_key(|summary| summary.package_id());
+    for summary in incompatible {
+        ws.config().shell().warn(format!(
+            "package `{}` requires rustc {}, which is newer than the \
+             workspace's `rust-version` of {}\n\
+             no version compatible with the `rust-version` matches the requirement",
+            summary.package_id(),
+            summary.rust_version().as_deref().unwrap_or_default(),
+            rust_version.rust_version(),
+        ))?;
+    }
+    Ok(())
+}
+
 /// Read the `paths` configuration variable to discover all path overrides that
 /// have been configured.
 pub fn add_overrides<'a>(
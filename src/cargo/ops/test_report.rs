@@ -0,0 +1,374 @@
+//! Collecting the results of `cargo test`.
+//!
+//! When results are requested (with `--report`, or as JSON messages), the
+//! output of each test binary and of rustdoc's doctests is captured and
+//! parsed line by line as it is forwarded to the user. libtest has no stable
+//! machine-readable output, so this understands its human-readable format:
+//!
+//! ```text
+//! test tests::it_works ... ok
+//! test tests::it_fails ... FAILED
+//!
+//! failures:
+//!
+//! ---- tests::it_fails stdout ----
+//! thread 'tests::it_fails' panicked at 'explicit panic', src/lib.rs:9:9
+//! ```
+//!
+//! Durations of individual tests are only known if the binary was asked to
+//! print them with libtest's unstable `--report-time` flag. Binaries that
+//! don't use the libtest harness are reported as a single test.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::bail;
+use cargo_util::paths;
+
+use crate::core::{PackageId, Target};
+use crate::util::errors::CargoResult;
+use crate::util::machine_message::{self, Message};
+use crate::util::Config;
+
+/// Where to write a report of the test results, given with `--report`.
+#[derive(Clone, Debug)]
+pub enum TestReportOutput {
+    /// A JUnit XML file.
+    Junit(PathBuf),
+}
+
+impl TestReportOutput {
+    /// Parses a `FORMAT=PATH` argument. Relative paths are relative to `cwd`.
+    pub fn parse(s: &str, cwd: &Path) -> CargoResult<TestReportOutput> {
+        match s.split_once('=') {
+            Some(("junit", path)) if !path.is_empty() => {
+                Ok(TestReportOutput::Junit(cwd.join(path)))
+            }
+            Some((format, _)) if format != "junit" => bail!(
+                "unsupported test report format `{}`, the only supported format is `junit`",
+                format
+            ),
+            _ => bail!(
+                "invalid test report `{}`, expected `FORMAT=PATH`, for example `junit=report.xml`",
+                s
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestOutcome {
+    Ok,
+    Failed,
+    Ignored,
+}
+
+impl TestOutcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            TestOutcome::Ok => "ok",
+            TestOutcome::Failed => "failed",
+            TestOutcome::Ignored => "ignored",
+        }
+    }
+}
+
+/// The result of a single test.
+#[derive(Debug)]
+pub struct TestCase {
+    pub name: String,
+    pub outcome: TestOutcome,
+    pub duration: Option<Duration>,
+    /// The output libtest captured for the test, shown for failed tests.
+    pub stdout: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestBinaryKind {
+    /// A test binary built from a target with `--test`.
+    Test,
+    /// The doctests of a library, run by rustdoc.
+    Doctest,
+}
+
+impl TestBinaryKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            TestBinaryKind::Test => "test",
+            TestBinaryKind::Doctest => "doctest",
+        }
+    }
+}
+
+/// The results of running one test binary, or the doctests of one target.
+#[derive(Debug)]
+pub struct TestBinaryResult {
+    pub package_id: PackageId,
+    pub target: Target,
+    pub kind: TestBinaryKind,
+    pub duration: Duration,
+    /// Whether the process exited successfully.
+    pub success: bool,
+    pub tests: Vec<TestCase>,
+}
+
+impl TestBinaryResult {
+    /// A name for the binary within the whole report, such as
+    /// `foo (test "integration")`.
+    fn suite_name(&self) -> String {
+        let what = match self.kind {
+            TestBinaryKind::Test => self.target.description_named(),
+            TestBinaryKind::Doctest => "doctests".to_string(),
+        };
+        format!("{} ({})", self.package_id.name(), what)
+    }
+
+    fn count(&self, outcome: TestOutcome) -> usize {
+        self.tests.iter().filter(|t| t.outcome == outcome).count()
+    }
+}
+
+/// All results collected while running tests.
+#[derive(Debug)]
+pub struct TestReport {
+    emit_json: bool,
+    binaries: Vec<TestBinaryResult>,
+}
+
+impl TestReport {
+    /// Creates an empty report. If `emit_json` is set, a JSON message is
+    /// printed for every result as it is added.
+    pub fn new(emit_json: bool) -> TestReport {
+        TestReport {
+            emit_json,
+            binaries: Vec::new(),
+        }
+    }
+
+    /// Adds the results of a test binary.
+    ///
+    /// If the binary doesn't seem to use the libtest harness, its exit status
+    /// is recorded as the result of a single test named after the target.
+    pub fn add(&mut self, config: &Config, mut binary: TestBinaryResult) {
+        if binary.tests.is_empty() && !binary.target.harness() {
+            binary.tests.push(TestCase {
+                name: binary.target.name().to_string(),
+                outcome: if binary.success {
+                    TestOutcome::Ok
+                } else {
+                    TestOutcome::Failed
+                },
+                duration: Some(binary.duration),
+                stdout: None,
+            });
+        }
+        if self.emit_json {
+            for test in &binary.tests {
+                let msg = machine_message::TestResult {
+                    package_id: binary.package_id,
+                    target: &binary.target,
+                    kind: binary.kind.as_str(),
+                    name: &test.name,
+                    outcome: test.outcome.as_str(),
+                    duration: test.duration.map(|d| d.as_secs_f64()),
+                    stdout: test.stdout.as_deref(),
+                }
+                .to_json_string();
+                crate::drop_println!(config, "{}", msg);
+            }
+            let msg = machine_message::TestSummary {
+                package_id: binary.package_id,
+                target: &binary.target,
+                kind: binary.kind.as_str(),
+                success: binary.success,
+                passed: binary.count(TestOutcome::Ok),
+                failed: binary.count(TestOutcome::Failed),
+                ignored: binary.count(TestOutcome::Ignored),
+                duration: binary.duration.as_secs_f64(),
+            }
+            .to_json_string();
+            crate::drop_println!(config, "{}", msg);
+        }
+        self.binaries.push(binary);
+    }
+
+    /// Writes the report to `output`.
+    pub fn write(&self, output: &TestReportOutput) -> CargoResult<()> {
+        match output {
+            TestReportOutput::Junit(path) => {
+                if let Some(parent) = path.parent() {
+                    paths::create_dir_all(parent)?;
+                }
+                paths::write(path, self.to_junit())
+            }
+        }
+    }
+
+    fn to_junit(&self) -> String {
+        let mut total = Duration::new(0, 0);
+        let (mut tests, mut failures, mut skipped) = (0, 0, 0);
+        for binary in &self.binaries {
+            total += binary.duration;
+            tests += binary.tests.len();
+            failures += binary.count(TestOutcome::Failed);
+            skipped += binary.count(TestOutcome::Ignored);
+        }
+
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        writeln!(
+            xml,
+            "<testsuites name=\"cargo test\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            tests,
+            failures,
+            skipped,
+            total.as_secs_f64()
+        )
+        .unwrap();
+        for binary in &self.binaries {
+            let suite = escape_xml(&binary.suite_name());
+            writeln!(
+                xml,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+                suite,
+                binary.tests.len(),
+                binary.count(TestOutcome::Failed),
+                binary.count(TestOutcome::Ignored),
+                binary.duration.as_secs_f64()
+            )
+            .unwrap();
+            for test in &binary.tests {
+                write!(
+                    xml,
+                    "    <testcase classname=\"{}\" name=\"{}\"",
+                    suite,
+                    escape_xml(&test.name)
+                )
+                .unwrap();
+                if let Some(duration) = test.duration {
+                    write!(xml, " time=\"{:.3}\"", duration.as_secs_f64()).unwrap();
+                }
+                match test.outcome {
+                    TestOutcome::Ok => xml.push_str("/>\n"),
+                    TestOutcome::Ignored => xml.push_str(">\n      <skipped/>\n    </testcase>\n"),
+                    TestOutcome::Failed => {
+                        xml.push_str(">\n      <failure message=\"test failed\"");
+                        match &test.stdout {
+                            Some(stdout) => {
+                                writeln!(xml, ">{}</failure>", escape_xml(stdout)).unwrap()
+                            }
+                            None => xml.push_str("/>\n"),
+                        }
+                        xml.push_str("    </testcase>\n");
+                    }
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '"' => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&apos;"),
+            // Control characters other than whitespace aren't allowed in XML.
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+/// Parses the human-readable output of libtest, one line at a time.
+#[derive(Debug, Default)]
+pub struct LibtestParser {
+    tests: Vec<TestCase>,
+    /// The index of the test whose captured output is being read, and the
+    /// output so far.
+    capturing: Option<(usize, String)>,
+}
+
+impl LibtestParser {
+    pub fn line(&mut self, line: &str) {
+        // The output is colored if Cargo passed `--color=always`. Strip only
+        // fails if the Writer fails, which is Cursor on a Vec, which should
+        // never fail.
+        let line = strip_ansi_escapes::strip(line)
+            .map(|v| String::from_utf8(v).expect("utf8"))
+            .expect("strip should never fail");
+        let line = line.as_str();
+        if let Some(name) = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" stdout ----"))
+        {
+            self.finish_capture();
+            if let Some(idx) = self.tests.iter().rposition(|t| t.name == name) {
+                self.capturing = Some((idx, String::new()));
+            }
+            return;
+        }
+
+        if let Some((_, output)) = &mut self.capturing {
+            if line == "failures:" || line.starts_with("test result: ") {
+                self.finish_capture();
+            } else {
+                output.push_str(line);
+                output.push('\n');
+            }
+            return;
+        }
+
+        let (name, result) = match line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ... "))
+        {
+            Some(test) => test,
+            None => return,
+        };
+        let name = name.strip_suffix(" - should panic").unwrap_or(name);
+        let mut result = result.split_whitespace();
+        let outcome = match result.next() {
+            Some("ok") | Some("bench:") => TestOutcome::Ok,
+            Some("FAILED") => TestOutcome::Failed,
+            Some("ignored") | Some("ignored,") => TestOutcome::Ignored,
+            _ => return,
+        };
+        // With `--report-time`, libtest appends the duration as `<0.012s>`.
+        let duration = result
+            .last()
+            .and_then(|s| s.strip_prefix('<'))
+            .and_then(|s| s.strip_suffix("s>"))
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(Duration::from_secs_f64);
+        self.tests.push(TestCase {
+            name: name.to_string(),
+            outcome,
+            duration,
+            stdout: None,
+        });
+    }
+
+    /// Returns the tests seen so far.
+    pub fn finish(mut self) -> Vec<TestCase> {
+        self.finish_capture();
+        self.tests
+    }
+
+    fn finish_capture(&mut self) {
+        if let Some((idx, output)) = self.capturing.take() {
+            let output = output.trim_end();
+            if !output.is_empty() {
+                self.tests[idx].stdout = Some(format!("{}\n", output));
+            }
+        }
+    }
+}
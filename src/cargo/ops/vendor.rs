@@ -62,6 +62,8 @@ enum VendorSource {
         branch: Option<String>,
         tag: Option<String>,
         rev: Option<String>,
+        #[serde(rename = "ref")]
+        git_ref: Option<String>,
         #[serde(rename = "replace-with")]
         replace_with: String,
     },
@@ -268,11 +270,13 @@ fn sync(
             let mut branch = None;
             let mut tag = None;
             let mut rev = None;
+            let mut git_ref = None;
             if let Some(reference) = source_id.git_reference() {
                 match *reference {
                     GitReference::Branch(ref b) => branch = Some(b.clone()),
                     GitReference::Tag(ref t) => tag = Some(t.clone()),
                     GitReference::Rev(ref r) => rev = Some(r.clone()),
+                    GitReference::Ref(ref r) => git_ref = Some(r.clone()),
                     GitReference::DefaultBranch => {}
                 }
             }
@@ -281,6 +285,7 @@ fn sync(
                 branch,
                 tag,
                 rev,
+                git_ref,
                 replace_with: merged_source_name.to_string(),
             }
         } else {
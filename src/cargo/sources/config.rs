@@ -43,6 +43,9 @@ struct SourceConfigDef {
     tag: OptValue<String>,
     /// The git revision.
     rev: OptValue<String>,
+    /// Any fully qualified git reference.
+    #[serde(rename = "ref")]
+    git_ref: OptValue<String>,
 }
 
 /// Configuration for a particular source, found in TOML looking like:
@@ -225,7 +228,10 @@ restore the source replacement configuration to continue the build
                     Some(b) => GitReference::Tag(b.val),
                     None => match def.rev {
                         Some(b) => GitReference::Rev(b.val),
-                        None => GitReference::DefaultBranch,
+                        None => match def.git_ref {
+                            Some(b) => GitReference::Ref(b.val),
+                            None => GitReference::DefaultBranch,
+                        },
                     },
                 },
             };
@@ -246,6 +252,7 @@ restore the source replacement configuration to continue the build
             check_not_set("branch", def.branch)?;
             check_not_set("tag", def.tag)?;
             check_not_set("rev", def.rev)?;
+            check_not_set("ref", def.git_ref)?;
         }
         if name == "crates-io" && srcs.is_empty() {
             srcs.push(SourceId::crates_io(self.config)?);
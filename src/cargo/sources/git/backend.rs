@@ -0,0 +1,367 @@
+//! The backends doing the git operations of Cargo.
+//!
+//! Fetching is done with libgit2 by default, or with the `git` command line
+//! with `net.git-fetch-with-cli`. With `-Zgit=backend`, the `git.backend`
+//! config selects the backend by name instead. Working with the local
+//! repositories is shared by both, and done with libgit2.
+
+use anyhow::{bail, Context as _};
+use cargo_util::ProcessBuilder;
+use git2::ErrorClass;
+use log::{debug, info};
+
+use super::utils::{backfill, fetch, init, reinitialize, with_fetch_options, GitHistory};
+use crate::core::GitReference;
+use crate::util::config::CargoGitConfig;
+use crate::util::{CargoResult, Config, Progress};
+
+/// The git operations Cargo needs to fetch and check out repositories.
+pub trait GitBackend {
+    /// The name of the backend in the `git.backend` config.
+    fn name(&self) -> &'static str;
+
+    /// Whether the backend can fetch shallowly or partially, see
+    /// `GitHistory`.
+    fn fetches_history(&self) -> bool {
+        false
+    }
+
+    /// Fetches `refspecs` from `url` into `repo`, along with all tags if
+    /// `tags` is set. `reference` is what the refspecs are fetched for.
+    fn fetch(
+        &self,
+        repo: &mut git2::Repository,
+        url: &str,
+        reference: &GitReference,
+        refspecs: &[String],
+        tags: bool,
+        history: GitHistory,
+        config: &Config,
+    ) -> CargoResult<()>;
+
+    /// Resolves `reference` to a commit of `repo`, after it was fetched.
+    fn resolve(&self, repo: &git2::Repository, reference: &GitReference) -> CargoResult<git2::Oid> {
+        reference.resolve(repo)
+    }
+
+    /// Resets `repo` to `rev`, only checking out the paths matching
+    /// `pathspecs` if given.
+    fn checkout(
+        &self,
+        repo: &git2::Repository,
+        rev: git2::Oid,
+        pathspecs: Option<&[String]>,
+        config: &Config,
+    ) -> CargoResult<()> {
+        let obj = repo.find_object(rev, None)?;
+        let mut pb = Progress::new("Checkout", config);
+        let mut opts = git2::build::CheckoutBuilder::new();
+        opts.progress(|_, cur, max| {
+            drop(pb.tick(cur, max, ""));
+        });
+        if let Some(pathspecs) = pathspecs {
+            for pathspec in pathspecs {
+                opts.path(pathspec);
+            }
+        }
+        debug!("doing reset");
+        repo.reset(&obj, git2::ResetType::Hard, Some(&mut opts))?;
+        debug!("reset done");
+        Ok(())
+    }
+
+    /// Fetches and checks out the submodules of `repo` whose path matches
+    /// `filter`, and their submodules.
+    fn update_submodules(
+        &self,
+        repo: &git2::Repository,
+        filter: &dyn Fn(&str) -> bool,
+        config: &Config,
+    ) -> CargoResult<()> {
+        info!("update submodules for: {:?}", repo.workdir().unwrap());
+
+        for mut child in repo.submodules()? {
+            if !filter(child.path().to_str().unwrap_or("")) {
+                continue;
+            }
+            update_submodule(self, repo, &mut child, config).with_context(|| {
+                format!(
+                    "failed to update submodule `{}`",
+                    child.name().unwrap_or("")
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn update_submodule<B: GitBackend + ?Sized>(
+    backend: &B,
+    parent: &git2::Repository,
+    child: &mut git2::Submodule<'_>,
+    config: &Config,
+) -> CargoResult<()> {
+    child.init(false)?;
+    let url = child
+        .url()
+        .ok_or_else(|| anyhow::format_err!("non-utf8 url for submodule {:?}?", child.path()))?;
+
+    // A submodule which is listed in .gitmodules but not actually
+    // checked out will not have a head id, so we should ignore it.
+    let head = match child.head_id() {
+        Some(head) => head,
+        None => return Ok(()),
+    };
+
+    // If the submodule hasn't been checked out yet, we need to
+    // clone it. If it has been checked out and the head is the same
+    // as the submodule's head, then we can skip an update and keep
+    // recursing.
+    let head_and_repo = child.open().and_then(|repo| {
+        let target = repo.head()?.target();
+        Ok((target, repo))
+    });
+    let mut repo = match head_and_repo {
+        Ok((head, repo)) => {
+            if child.head_id() == head {
+                return backend.update_submodules(&repo, &|_| true, config);
+            }
+            repo
+        }
+        Err(..) => {
+            let path = parent.workdir().unwrap().join(child.path());
+            let _ = cargo_util::paths::remove_dir_all(&path);
+            init(&path, false)?
+        }
+    };
+    // Fetch data from origin and reset to the head commit
+    let reference = GitReference::Rev(head.to_string());
+    config
+        .shell()
+        .status("Updating", format!("git submodule `{}`", url))?;
+    fetch(
+        &mut repo,
+        url,
+        &reference,
+        GitHistory::default(),
+        backend,
+        config,
+    )
+    .with_context(|| {
+        format!(
+            "failed to fetch submodule `{}` from {}",
+            child.name().unwrap_or(""),
+            url
+        )
+    })?;
+
+    backend.checkout(&repo, head, None, config)?;
+    backend.update_submodules(&repo, &|_| true, config)
+}
+
+/// The backend selected by the config.
+pub fn from_config(config: &Config) -> CargoResult<Box<dyn GitBackend>> {
+    let name = if config.cli_unstable().git_option("backend") {
+        config.get::<CargoGitConfig>("git")?.backend
+    } else {
+        None
+    };
+    let name = match name {
+        Some(name) => name,
+        None if config.net_config()?.git_fetch_with_cli == Some(true) => "git-cli".to_string(),
+        None => "libgit2".to_string(),
+    };
+    match name.as_str() {
+        "libgit2" => Ok(Box::new(LibGit2)),
+        "git-cli" => Ok(Box::new(GitCli)),
+        name => bail!(
+            "unknown git backend `{}` in `git.backend`, expected `libgit2` or `git-cli`",
+            name
+        ),
+    }
+}
+
+/// Fetches with libgit2, which is built into Cargo, as `libgit2`.
+pub struct LibGit2;
+
+impl GitBackend for LibGit2 {
+    fn name(&self) -> &'static str {
+        "libgit2"
+    }
+
+    fn fetch(
+        &self,
+        repo: &mut git2::Repository,
+        url: &str,
+        _reference: &GitReference,
+        refspecs: &[String],
+        tags: bool,
+        _history: GitHistory,
+        config: &Config,
+    ) -> CargoResult<()> {
+        debug!("doing a fetch for {}", url);
+        let git_config = git2::Config::open_default()?;
+        with_fetch_options(&git_config, url, config, &mut |mut opts| {
+            if tags {
+                opts.download_tags(git2::AutotagOption::All);
+            }
+            // The `fetch` operation here may fail spuriously due to a corrupt
+            // repository. It could also fail, however, for a whole slew of other
+            // reasons (aka network related reasons). We want Cargo to automatically
+            // recover from corrupt repositories, but we don't want Cargo to stomp
+            // over other legitimate errors.
+            //
+            // Consequently we save off the error of the `fetch` operation and if it
+            // looks like a "corrupt repo" error then we blow away the repo and try
+            // again. If it looks like any other kind of error, or if we've already
+            // blown away the repository, then we want to return the error as-is.
+            let mut repo_reinitialized = false;
+            loop {
+                debug!("initiating fetch of {:?} from {}", refspecs, url);
+                let res = repo
+                    .remote_anonymous(url)?
+                    .fetch(refspecs, Some(&mut opts), None);
+                let err = match res {
+                    Ok(()) => break,
+                    Err(e) => e,
+                };
+                debug!("fetch failed: {}", err);
+
+                if !repo_reinitialized
+                    && matches!(err.class(), ErrorClass::Reference | ErrorClass::Odb)
+                {
+                    repo_reinitialized = true;
+                    debug!(
+                        "looks like this is a corrupt repository, reinitializing \
+                         and trying again"
+                    );
+                    if reinitialize(repo).is_ok() {
+                        continue;
+                    }
+                }
+
+                return Err(err.into());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Fetches with the `git` command line, as `git-cli`.
+///
+/// Unfortunately `libgit2` is notably lacking in the realm of authentication
+/// when compared to the `git` command line. As a result, allow an escape
+/// hatch for users that would prefer to use `git`-the-CLI for fetching
+/// repositories instead of `libgit2`-the-library. This should make more
+/// flavors of authentication possible while also still giving us all the
+/// speed and portability of using `libgit2`.
+pub struct GitCli;
+
+impl GitBackend for GitCli {
+    fn name(&self) -> &'static str {
+        "git-cli"
+    }
+
+    fn fetches_history(&self) -> bool {
+        true
+    }
+
+    fn fetch(
+        &self,
+        repo: &mut git2::Repository,
+        url: &str,
+        reference: &GitReference,
+        refspecs: &[String],
+        tags: bool,
+        history: GitHistory,
+        config: &Config,
+    ) -> CargoResult<()> {
+        if history.is_full() {
+            return fetch_with_cli(repo, url, refspecs, tags, &[], config);
+        }
+        fetch_history_with_cli(self, repo, url, reference, refspecs, tags, history, config)
+    }
+}
+
+/// Fetches `refspecs` shallowly or partially as `history` asks for. If the
+/// shallow fetch doesn't have `reference`, the full history is fetched. For
+/// partial fetches, the contents of `reference` are fetched as well.
+fn fetch_history_with_cli(
+    backend: &GitCli,
+    repo: &mut git2::Repository,
+    url: &str,
+    reference: &GitReference,
+    refspecs: &[String],
+    tags: bool,
+    history: GitHistory,
+    config: &Config,
+) -> CargoResult<()> {
+    let filter = if history.partial {
+        &["--filter=blob:none"][..]
+    } else {
+        &[]
+    };
+    let mut args = filter.to_vec();
+    if history.shallow {
+        args.push("--depth=1");
+    }
+    let result = fetch_with_cli(repo, url, refspecs, tags, &args, config);
+    if history.shallow && (result.is_err() || backend.resolve(repo, reference).is_err()) {
+        // The revision may not be the tip of a branch or tag, or the server
+        // may not allow fetching a commit by itself, so look for it in the
+        // history of all of them.
+        debug!("deepening shallow fetch of {}: {:?}", url, result.err());
+        let refspecs = [
+            String::from("refs/heads/*:refs/remotes/origin/*"),
+            String::from("HEAD:refs/remotes/origin/HEAD"),
+        ];
+        let mut args = filter.to_vec();
+        if repo.is_shallow() {
+            args.push("--unshallow");
+        }
+        fetch_with_cli(repo, url, &refspecs, true, &args, config)?;
+    } else {
+        result?;
+    }
+    if let Ok(rev) = backend.resolve(repo, reference) {
+        backfill(repo, url, rev, &|_| true, config)?;
+    }
+    Ok(())
+}
+
+pub(super) fn fetch_with_cli(
+    repo: &git2::Repository,
+    url: &str,
+    refspecs: &[String],
+    tags: bool,
+    args: &[&str],
+    config: &Config,
+) -> CargoResult<()> {
+    let mut cmd = ProcessBuilder::new("git");
+    cmd.arg("fetch");
+    if tags {
+        cmd.arg("--tags");
+    }
+    cmd.args(args);
+    cmd.arg("--force") // handle force pushes
+        .arg("--update-head-ok") // see discussion in #2078
+        .arg(url)
+        .args(refspecs)
+        // If cargo is run by git (for example, the `exec` command in `git
+        // rebase`), the GIT_DIR is set by git and will point to the wrong
+        // location (this takes precedence over the cwd). Make sure this is
+        // unset so git will look at cwd for the repo.
+        .env_remove("GIT_DIR")
+        // The reset of these may not be necessary, but I'm including them
+        // just to be extra paranoid and avoid any issues.
+        .env_remove("GIT_WORK_TREE")
+        .env_remove("GIT_INDEX_FILE")
+        .env_remove("GIT_OBJECT_DIRECTORY")
+        .env_remove("GIT_ALTERNATE_OBJECT_DIRECTORIES")
+        .cwd(repo.path());
+    config
+        .shell()
+        .verbose(|s| s.status("Running", &cmd.to_string()))?;
+    cmd.exec_with_output()?;
+    Ok(())
+}
@@ -1,4 +1,6 @@
 pub use self::source::GitSource;
-pub use self::utils::{fetch, GitCheckout, GitDatabase, GitRemote};
+pub use self::utils::{GitCheckout, GitDatabase, GitHistory, GitRemote};
+mod backend;
+mod signature;
 mod source;
 mod utils;
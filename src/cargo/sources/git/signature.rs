@@ -0,0 +1,165 @@
+//! Verification of the signatures of git dependencies, see
+//! `-Zgit=verify-signatures`.
+//!
+//! Like `git verify-commit` and `git verify-tag`, OpenPGP signatures are
+//! checked with `gpgv` against the keys in `git.keyring`, and SSH signatures
+//! with `ssh-keygen` against the keys in `git.allowed-signers`.
+
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{bail, Context as _};
+use cargo_util::{paths, ProcessBuilder};
+use url::Url;
+
+use crate::core::GitReference;
+use crate::util::config::CargoGitConfig;
+use crate::util::{CargoResult, Config};
+
+const PGP_SIGNATURE: &str = "-----BEGIN PGP SIGNATURE-----";
+const SSH_SIGNATURE: &str = "-----BEGIN SSH SIGNATURE-----";
+
+/// Checks the signature of the tag `reference` names if it's a signed tag,
+/// or of the commit `rev` otherwise.
+pub fn verify(
+    repo: &git2::Repository,
+    url: &Url,
+    reference: &GitReference,
+    rev: git2::Oid,
+    config: &Config,
+) -> CargoResult<()> {
+    let (object, signed) = match signed_tag(repo, reference, rev)? {
+        Some((name, tag, signed)) => (format!("tag `{}` ({})", name, tag), Some(signed)),
+        None => (
+            format!("commit `{}`", rev),
+            match repo.extract_signature(&rev, None) {
+                Ok((signature, payload)) => Some((signature.to_vec(), payload.to_vec())),
+                Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+                Err(e) => return Err(e.into()),
+            },
+        ),
+    };
+    let (signature, payload) = match signed {
+        Some(signed) => signed,
+        None => bail!(
+            "{} of git repository `{}` is not signed, but git dependencies must be signed",
+            object,
+            url
+        ),
+    };
+
+    let git_config = config.get::<CargoGitConfig>("git")?;
+    let keys = if signature.starts_with(SSH_SIGNATURE.as_bytes()) {
+        ("git.allowed-signers", git_config.allowed_signers)
+    } else {
+        ("git.keyring", git_config.keyring)
+    };
+    let keys = match keys {
+        (_, Some(path)) => path.resolve_path(config),
+        (key, None) => bail!(
+            "cannot verify the signature of {} of git repository `{}`: `{}` is not set",
+            object,
+            url,
+            key
+        ),
+    };
+
+    let tmp = tempfile::tempdir()?;
+    let signature_path = tmp.path().join("signature");
+    let payload_path = tmp.path().join("payload");
+    paths::write(&signature_path, &signature)?;
+    paths::write(&payload_path, &payload)?;
+    let result = if signature.starts_with(SSH_SIGNATURE.as_bytes()) {
+        verify_ssh(&keys, &signature_path, &payload_path)
+    } else {
+        verify_pgp(&keys, &signature_path, &payload_path)
+    };
+    result.with_context(|| {
+        format!(
+            "{} of git repository `{}` is not signed by a key in `{}`",
+            object,
+            url,
+            keys.display()
+        )
+    })
+}
+
+/// The name, ID, signature and signed contents of the annotated tag
+/// `reference` names, if it's a signed tag of `rev`.
+fn signed_tag(
+    repo: &git2::Repository,
+    reference: &GitReference,
+    rev: git2::Oid,
+) -> CargoResult<Option<(String, git2::Oid, (Vec<u8>, Vec<u8>))>> {
+    let name = match reference {
+        GitReference::Tag(name) => name,
+        _ => return Ok(None),
+    };
+    // Where `fetch` puts tags. The tag may have moved since `rev` was locked.
+    let id = match repo.refname_to_id(&format!("refs/remotes/origin/tags/{}", name)) {
+        Ok(id) => id,
+        Err(_) => return Ok(None),
+    };
+    let tag = repo.find_object(id, None)?;
+    if tag.kind() != Some(git2::ObjectType::Tag) || tag.peel_to_commit()?.id() != rev {
+        return Ok(None);
+    }
+    let odb = repo.odb()?;
+    let object = odb.read(id)?;
+    // The signature of a tag is appended to its message.
+    let data = object.data();
+    let start = [PGP_SIGNATURE, SSH_SIGNATURE]
+        .iter()
+        .filter_map(|marker| {
+            let marker = format!("\n{}", marker);
+            data.windows(marker.len())
+                .rposition(|window| window == marker.as_bytes())
+        })
+        .max();
+    Ok(start.map(|start| {
+        let (payload, signature) = data.split_at(start + 1);
+        (name.clone(), id, (signature.to_vec(), payload.to_vec()))
+    }))
+}
+
+fn verify_pgp(keyring: &Path, signature: &Path, payload: &Path) -> CargoResult<()> {
+    ProcessBuilder::new("gpgv")
+        .arg("--keyring")
+        .arg(keyring)
+        .arg(signature)
+        .arg(payload)
+        .exec_with_output()?;
+    Ok(())
+}
+
+fn verify_ssh(allowed_signers: &Path, signature: &Path, payload: &Path) -> CargoResult<()> {
+    let output = ProcessBuilder::new("ssh-keygen")
+        .args(&["-Y", "find-principals", "-f"])
+        .arg(allowed_signers)
+        .arg("-s")
+        .arg(signature)
+        .exec_with_output()?;
+    let principals = String::from_utf8_lossy(&output.stdout);
+    let principal = principals.lines().next().unwrap_or_default();
+    let mut cmd = ProcessBuilder::new("ssh-keygen");
+    cmd.args(&["-Y", "verify", "-n", "git", "-f"])
+        .arg(allowed_signers)
+        .arg("-I")
+        .arg(principal)
+        .arg("-s")
+        .arg(signature);
+    // `ssh-keygen` reads the signed contents from stdin.
+    let output = cmd
+        .build_command()
+        .stdin(File::open(payload)?)
+        .output()
+        .with_context(|| format!("could not execute process {}", cmd))?;
+    if !output.status.success() {
+        bail!(
+            "process didn't exit successfully: {}\n{}",
+            cmd,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
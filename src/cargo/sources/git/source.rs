@@ -1,14 +1,18 @@
 use crate::core::source::{MaybePackage, Source, SourceId};
 use crate::core::GitReference;
 use crate::core::{Dependency, Package, PackageId, Summary};
-use crate::sources::git::utils::GitRemote;
+use crate::ops;
+use crate::sources::git::utils::{GitDatabase, GitHistory, GitRemote};
 use crate::sources::PathSource;
+use crate::util::config::CargoGitConfig;
 use crate::util::errors::CargoResult;
 use crate::util::hex::short_hash;
 use crate::util::Config;
 use anyhow::Context;
 use log::trace;
+use std::collections::HashSet;
 use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
 use url::Url;
 
 pub struct GitSource<'cfg> {
@@ -17,15 +21,36 @@ pub struct GitSource<'cfg> {
     locked_rev: Option<git2::Oid>,
     source_id: SourceId,
     path_source: Option<PathSource<'cfg>>,
+    /// With `-Zgit=sparse-checkout`, the packages are checked out as they
+    /// are queried instead of with `path_source`.
+    sparse: Option<SparseCheckout>,
+    /// The database of the repository, once it's updated.
+    db_path: Option<PathBuf>,
+    /// Whether the signature of `locked_rev` was verified, see
+    /// `-Zgit=verify-signatures`.
+    signature_verified: bool,
     ident: String,
     config: &'cfg Config,
 }
 
+/// The packages of a git source checked out so far with
+/// `-Zgit=sparse-checkout`. Each package is checked out along with the
+/// packages it depends on by `path`, in a directory of its own.
+struct SparseCheckout {
+    db: GitDatabase,
+    rev: git2::Oid,
+    /// Where the checkouts of `rev` go, with a suffix for the packages.
+    path: PathBuf,
+    /// The directories in the repository of the packages checked out.
+    dirs: HashSet<String>,
+    packages: Vec<Package>,
+}
+
 impl<'cfg> GitSource<'cfg> {
     pub fn new(source_id: SourceId, config: &'cfg Config) -> CargoResult<GitSource<'cfg>> {
         assert!(source_id.is_git(), "id is not git, id={}", source_id);
 
-        let remote = GitRemote::new(source_id.url());
+        let remote = GitRemote::new(source_id.url(), config)?;
         let ident = ident(&source_id);
 
         let source = GitSource {
@@ -39,6 +64,9 @@ impl<'cfg> GitSource<'cfg> {
             },
             source_id,
             path_source: None,
+            sparse: None,
+            db_path: None,
+            signature_verified: false,
             ident,
             config,
         };
@@ -51,11 +79,101 @@ impl<'cfg> GitSource<'cfg> {
     }
 
     pub fn read_packages(&mut self) -> CargoResult<Vec<Package>> {
-        if self.path_source.is_none() {
+        if self.path_source.is_none() && self.sparse.is_none() {
             self.update()?;
         }
+        if let Some(sparse) = self.sparse.take() {
+            // All of the packages are needed.
+            sparse
+                .db
+                .copy_to(sparse.rev, &sparse.path, None, self.config)?;
+            self.config.mark_cache_used(&sparse.path);
+            let source_id = self.source_id.with_precise(Some(sparse.rev.to_string()));
+            let mut path_source = PathSource::new_recursive(&sparse.path, source_id, self.config);
+            path_source.update()?;
+            self.path_source = Some(path_source);
+        }
         self.path_source.as_mut().unwrap().read_packages()
     }
+
+    /// Fails if the signature of the commit or tag in use can't be verified.
+    fn verify_signature(&mut self) -> CargoResult<()> {
+        if self.signature_verified {
+            return Ok(());
+        }
+        let db_path = self
+            .db_path
+            .as_ref()
+            .expect("BUG: `update()` must be called before `verify_signature()`");
+        let db = self.remote.db_at(db_path)?;
+        db.verify_signature(
+            &self.manifest_reference,
+            self.locked_rev.unwrap(),
+            self.config,
+        )?;
+        self.signature_verified = true;
+        Ok(())
+    }
+
+    /// Checks out the package `dep` refers to, if it isn't checked out yet
+    /// with `-Zgit=sparse-checkout`. It's located by its `subdir`, or by the
+    /// package name otherwise.
+    fn check_out_package(&mut self, dep_subdir: Option<&str>, name: &str) -> CargoResult<()> {
+        let sparse = match &mut self.sparse {
+            Some(sparse) => sparse,
+            None => return Ok(()),
+        };
+        let dir = match dep_subdir {
+            Some(dir) => dir.to_string(),
+            None if sparse.packages.iter().any(|pkg| pkg.name() == name) => return Ok(()),
+            None => match sparse.db.find_package(sparse.rev, name, self.config)? {
+                Some(dir) => dir,
+                // The query just comes up empty.
+                None => return Ok(()),
+            },
+        };
+        if sparse.dirs.contains(&dir) {
+            return Ok(());
+        }
+
+        let dirs = sparse.db.package_closure(sparse.rev, &dir, self.config)?;
+        if !dirs.contains(&dir) {
+            anyhow::bail!(
+                "no `Cargo.toml` found in `{}` of git repository `{}`",
+                dir,
+                self.remote.url()
+            );
+        }
+        let checkout_path = if dirs.iter().any(|dir| dir.is_empty()) {
+            // The root package needs all of the repository.
+            sparse
+                .db
+                .copy_to(sparse.rev, &sparse.path, None, self.config)?;
+            sparse.path.clone()
+        } else {
+            let path = sparse.path.with_file_name(format!(
+                "{}-{}",
+                sparse.path.file_name().unwrap().to_str().unwrap(),
+                short_hash(&dirs)
+            ));
+            sparse
+                .db
+                .copy_to(sparse.rev, &path, Some(&dirs), self.config)?;
+            path
+        };
+        self.config.mark_cache_used(&checkout_path);
+
+        let source_id = self.source_id.with_precise(Some(sparse.rev.to_string()));
+        for dir in dirs {
+            if !sparse.dirs.insert(dir.clone()) {
+                continue;
+            }
+            let manifest_path = checkout_path.join(&dir).join("Cargo.toml");
+            let (pkg, _) = ops::read_package(&manifest_path, source_id, self.config)?;
+            sparse.packages.push(pkg);
+        }
+        Ok(())
+    }
 }
 
 fn ident(id: &SourceId) -> String {
@@ -84,6 +202,21 @@ impl<'cfg> Debug for GitSource<'cfg> {
 
 impl<'cfg> Source for GitSource<'cfg> {
     fn query(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        if dep.verify_git_signature() {
+            self.verify_signature()?;
+        }
+        if self.path_source.is_none() {
+            let subdir = dep.git_subdir();
+            self.check_out_package(subdir.as_deref(), &dep.package_name())?;
+            if let Some(sparse) = &self.sparse {
+                for pkg in &sparse.packages {
+                    if dep.matches(pkg.summary()) {
+                        f(pkg.summary().clone());
+                    }
+                }
+                return Ok(());
+            }
+        }
         let src = self
             .path_source
             .as_mut()
@@ -92,6 +225,12 @@ impl<'cfg> Source for GitSource<'cfg> {
     }
 
     fn fuzzy_query(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        if let (None, Some(sparse)) = (&self.path_source, &self.sparse) {
+            for pkg in &sparse.packages {
+                f(pkg.summary().clone());
+            }
+            return Ok(());
+        }
         let src = self
             .path_source
             .as_mut()
@@ -112,9 +251,21 @@ impl<'cfg> Source for GitSource<'cfg> {
     }
 
     fn update(&mut self) -> CargoResult<()> {
+        let require_signed = self.config.get::<CargoGitConfig>("git")?.require_signed == Some(true);
+        let verify = self.config.cli_unstable().git_option("verify-signatures");
+        if require_signed && !verify {
+            anyhow::bail!(
+                "`git.require-signed` is set in cargo config, but the signatures of git \
+                 dependencies are only verified with `-Zgit=verify-signatures`"
+            );
+        }
+
         let git_path = self.config.git_path();
         let git_path = self.config.assert_package_cache_locked(&git_path);
-        let db_path = git_path.join("db").join(&self.ident);
+        let history = GitHistory::for_deps(self.config);
+        let db_path = git_path
+            .join("db")
+            .join(format!("{}{}", self.ident, history.dir_suffix()));
 
         let db = self.remote.db_at(&db_path).ok();
         let (db, actual_rev) = match (self.locked_rev, db) {
@@ -156,6 +307,7 @@ impl<'cfg> Source for GitSource<'cfg> {
                     db,
                     &self.manifest_reference,
                     locked_rev,
+                    history,
                     self.config,
                 )?
             }
@@ -173,13 +325,37 @@ impl<'cfg> Source for GitSource<'cfg> {
             .join("checkouts")
             .join(&self.ident)
             .join(short_id.as_str());
-        db.copy_to(actual_rev, &checkout_path, self.config)?;
+        self.config.mark_cache_used(&db_path);
+        self.locked_rev = Some(actual_rev);
+        self.db_path = Some(db_path);
+        // Verified before anything is checked out.
+        if verify
+            && (require_signed
+                || self
+                    .config
+                    .signed_git_sources()
+                    .contains(&self.source_id.with_precise(None)))
+        {
+            self.verify_signature()?;
+        }
+        if self.config.cli_unstable().git_option("sparse-checkout") {
+            // The packages are checked out as they are queried.
+            self.sparse = Some(SparseCheckout {
+                db,
+                rev: actual_rev,
+                path: checkout_path,
+                dirs: HashSet::new(),
+                packages: Vec::new(),
+            });
+            return Ok(());
+        }
+        db.copy_to(actual_rev, &checkout_path, None, self.config)?;
+        self.config.mark_cache_used(&checkout_path);
 
         let source_id = self.source_id.with_precise(Some(actual_rev.to_string()));
         let path_source = PathSource::new_recursive(&checkout_path, source_id, self.config);
 
         self.path_source = Some(path_source);
-        self.locked_rev = Some(actual_rev);
         self.path_source.as_mut().unwrap().update()
     }
 
@@ -189,6 +365,14 @@ impl<'cfg> Source for GitSource<'cfg> {
             id,
             self.remote
         );
+        if self.path_source.is_none() && self.sparse.is_some() {
+            self.check_out_package(None, &id.name())?;
+            let sparse = self.sparse.as_ref().unwrap();
+            return match sparse.packages.iter().find(|pkg| pkg.package_id() == id) {
+                Some(pkg) => Ok(MaybePackage::Ready(pkg.clone())),
+                None => anyhow::bail!("failed to find {} in git repository", id),
+            };
+        }
         self.path_source
             .as_mut()
             .expect("BUG: `update()` must be called before `get()`")
@@ -1,20 +1,23 @@
 //! Utilities for handling git repositories, mainly around
 //! authentication/cloning.
 
+use super::backend::{self, GitBackend};
 use crate::core::GitReference;
 use crate::util::errors::CargoResult;
 use crate::util::{network, Config, IntoUrl, MetricsCounter, Progress};
 use anyhow::{anyhow, Context as _};
-use cargo_util::{paths, ProcessBuilder};
+use cargo_util::paths;
 use curl::easy::List;
 use git2::{self, ErrorClass, ObjectType};
 use log::{debug, info};
 use serde::ser;
 use serde::Serialize;
+use std::collections::BTreeSet;
 use std::env;
 use std::fmt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 use url::Url;
 
@@ -36,10 +39,14 @@ impl GitShortID {
 
 /// `GitRemote` represents a remote repository. It gets cloned into a local
 /// `GitDatabase`.
-#[derive(PartialEq, Clone, Debug, Serialize)]
+#[derive(Clone, Serialize)]
 pub struct GitRemote {
     #[serde(serialize_with = "serialize_str")]
     url: Url,
+    /// The backend selected by the config, shared with the databases and
+    /// checkouts of this remote.
+    #[serde(skip_serializing)]
+    backend: Rc<dyn GitBackend>,
 }
 
 /// `GitDatabase` is a local clone of a remote repository's database. Multiple
@@ -63,21 +70,102 @@ pub struct GitCheckout<'a> {
     revision: git2::Oid,
     #[serde(skip_serializing)]
     repo: git2::Repository,
+    /// The package directories checked out, or `None` for the whole tree.
+    sparse: Option<Vec<String>>,
+}
+
+/// How much of a repository is fetched, see `-Zgit`.
+///
+/// libgit2 can't fetch shallowly or partially, so this is only done with a
+/// backend that can, like `git-cli`, but it can read the repositories created
+/// this way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GitHistory {
+    /// Only the fetched commits are fetched, not their history.
+    pub shallow: bool,
+    /// The contents of files are left out, except for the commits in use.
+    pub partial: bool,
 }
 
 // Implementations
 
+impl GitHistory {
+    /// How git dependencies are fetched.
+    pub fn for_deps(config: &Config) -> GitHistory {
+        let unstable = config.cli_unstable();
+        GitHistory {
+            shallow: unstable.git_option("shallow-deps"),
+            partial: unstable.git_option("partial-deps"),
+        }
+    }
+
+    /// How the index of a registry is fetched. It's never fetched partially,
+    /// as the contents of all of its files would be fetched right after.
+    pub fn for_index(config: &Config) -> GitHistory {
+        GitHistory {
+            shallow: config.cli_unstable().git_option("shallow-index"),
+            partial: false,
+        }
+    }
+
+    /// The suffix of the directories of repositories fetched this way. They
+    /// are kept apart from complete repositories, which libgit2 may fetch
+    /// into.
+    pub fn dir_suffix(self) -> &'static str {
+        match (self.shallow, self.partial) {
+            (false, false) => "",
+            (true, false) => "-shallow",
+            (false, true) => "-partial",
+            (true, true) => "-shallow-partial",
+        }
+    }
+
+    pub(super) fn is_full(self) -> bool {
+        !self.shallow && !self.partial
+    }
+}
+
+impl fmt::Debug for GitRemote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitRemote")
+            .field("url", &self.url)
+            .field("backend", &self.backend.name())
+            .finish()
+    }
+}
+
 impl GitRemote {
-    pub fn new(url: &Url) -> GitRemote {
-        GitRemote { url: url.clone() }
+    pub fn new(url: &Url, config: &Config) -> CargoResult<GitRemote> {
+        Ok(GitRemote {
+            url: url.clone(),
+            backend: backend::from_config(config)?.into(),
+        })
     }
 
     pub fn url(&self) -> &Url {
         &self.url
     }
 
+    /// Fetches `reference` from this remote into `repo`, see [`fetch`].
+    pub fn fetch(
+        &self,
+        repo: &mut git2::Repository,
+        reference: &GitReference,
+        history: GitHistory,
+        cargo_config: &Config,
+    ) -> CargoResult<()> {
+        fetch(
+            repo,
+            self.url.as_str(),
+            reference,
+            history,
+            &*self.backend,
+            cargo_config,
+        )
+    }
+
     pub fn rev_for(&self, path: &Path, reference: &GitReference) -> CargoResult<git2::Oid> {
-        reference.resolve(&self.db_at(path)?.repo)
+        self.db_at(path)?.resolve(reference)
     }
 
     pub fn checkout(
@@ -86,6 +174,7 @@ impl GitRemote {
         db: Option<GitDatabase>,
         reference: &GitReference,
         locked_rev: Option<git2::Oid>,
+        history: GitHistory,
         cargo_config: &Config,
     ) -> CargoResult<(GitDatabase, git2::Oid)> {
         // If we have a previous instance of `GitDatabase` then fetch into that
@@ -93,16 +182,18 @@ impl GitRemote {
         // populated the database with the latest version of `reference`, so
         // return that database and the rev we resolve to.
         if let Some(mut db) = db {
-            fetch(&mut db.repo, self.url.as_str(), reference, cargo_config)
+            self.fetch(&mut db.repo, reference, history, cargo_config)
                 .context(format!("failed to fetch into: {}", into.display()))?;
             match locked_rev {
                 Some(rev) => {
+                    self.fetch_locked_rev(&mut db.repo, rev, history, cargo_config)
+                        .context(format!("failed to fetch into: {}", into.display()))?;
                     if db.contains(rev) {
                         return Ok((db, rev));
                     }
                 }
                 None => {
-                    if let Ok(rev) = reference.resolve(&db.repo) {
+                    if let Ok(rev) = db.resolve(reference) {
                         return Ok((db, rev));
                     }
                 }
@@ -117,11 +208,15 @@ impl GitRemote {
         }
         paths::create_dir_all(into)?;
         let mut repo = init(into, true)?;
-        fetch(&mut repo, self.url.as_str(), reference, cargo_config)
+        self.fetch(&mut repo, reference, history, cargo_config)
             .context(format!("failed to clone into: {}", into.display()))?;
         let rev = match locked_rev {
-            Some(rev) => rev,
-            None => reference.resolve(&repo)?,
+            Some(rev) => {
+                self.fetch_locked_rev(&mut repo, rev, history, cargo_config)
+                    .context(format!("failed to clone into: {}", into.display()))?;
+                rev
+            }
+            None => self.backend.resolve(&repo, reference)?,
         };
 
         Ok((
@@ -134,6 +229,22 @@ impl GitRemote {
         ))
     }
 
+    /// A shallow fetch only has the commit the reference points to, so the
+    /// locked revision is fetched by itself if it's another one.
+    fn fetch_locked_rev(
+        &self,
+        repo: &mut git2::Repository,
+        rev: git2::Oid,
+        history: GitHistory,
+        cargo_config: &Config,
+    ) -> CargoResult<()> {
+        if !history.shallow || repo.find_commit(rev).is_ok() {
+            return Ok(());
+        }
+        let reference = GitReference::Rev(rev.to_string());
+        self.fetch(repo, &reference, history, cargo_config)
+    }
+
     pub fn db_at(&self, db_path: &Path) -> CargoResult<GitDatabase> {
         let repo = git2::Repository::open(db_path)?;
         Ok(GitDatabase {
@@ -145,15 +256,26 @@ impl GitRemote {
 }
 
 impl GitDatabase {
+    /// Checks out `rev` to `dest`. Only the package directories in `sparse`
+    /// and the manifests above them are checked out, if given.
     pub fn copy_to(
         &self,
         rev: git2::Oid,
         dest: &Path,
+        sparse: Option<&[String]>,
         cargo_config: &Config,
     ) -> CargoResult<GitCheckout<'_>> {
+        let sparse = sparse.map(|dirs| dirs.to_vec());
+        backfill(
+            &self.repo,
+            self.remote.url.as_str(),
+            rev,
+            &|path| in_sparse_checkout(sparse.as_deref(), path),
+            cargo_config,
+        )?;
         let mut checkout = None;
         if let Ok(repo) = git2::Repository::open(dest) {
-            let mut co = GitCheckout::new(dest, self, rev, repo);
+            let mut co = GitCheckout::new(dest, self, rev, repo, sparse.clone());
             if !co.is_fresh() {
                 // After a successful fetch operation the subsequent reset can
                 // fail sometimes for corrupt repositories where the fetch
@@ -175,7 +297,7 @@ impl GitDatabase {
         };
         let checkout = match checkout {
             Some(c) => c,
-            None => GitCheckout::clone_into(dest, self, rev, cargo_config)?,
+            None => GitCheckout::clone_into(dest, self, rev, sparse, cargo_config)?,
         };
         checkout.update_submodules(cargo_config)?;
         Ok(checkout)
@@ -191,8 +313,208 @@ impl GitDatabase {
     }
 
     pub fn resolve(&self, r: &GitReference) -> CargoResult<git2::Oid> {
-        r.resolve(&self.repo)
+        self.remote.backend.resolve(&self.repo, r)
+    }
+
+    /// Checks the signature of `rev`, or of the tag `reference` names, with
+    /// `-Zgit=verify-signatures`.
+    pub fn verify_signature(
+        &self,
+        reference: &GitReference,
+        rev: git2::Oid,
+        config: &Config,
+    ) -> CargoResult<()> {
+        super::signature::verify(&self.repo, &self.remote.url, reference, rev, config)
+    }
+
+    /// Finds the directory of the package `name` at `rev` by reading the
+    /// manifests in the tree, without checking anything out.
+    pub fn find_package(
+        &self,
+        rev: git2::Oid,
+        name: &str,
+        config: &Config,
+    ) -> CargoResult<Option<String>> {
+        let tree = self.repo.find_commit(rev)?.tree()?;
+        let mut dirs = Vec::new();
+        tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+            match (entry.kind(), entry.name()) {
+                // Like `PathSource`, skip hidden directories.
+                (Some(ObjectType::Tree), Some(dir)) if dir.starts_with('.') => {
+                    git2::TreeWalkResult::Skip
+                }
+                (Some(ObjectType::Blob), Some("Cargo.toml")) => {
+                    dirs.push(root.trim_end_matches('/').to_string());
+                    git2::TreeWalkResult::Ok
+                }
+                _ => git2::TreeWalkResult::Ok,
+            }
+        })?;
+        backfill(
+            &self.repo,
+            self.remote.url.as_str(),
+            rev,
+            &|path| path.ends_with("Cargo.toml"),
+            config,
+        )?;
+        for dir in dirs {
+            let manifest = match self.read_manifest(&tree, &dir, config) {
+                Ok(Some(manifest)) => manifest,
+                // Malformed manifests are skipped, as when loading packages.
+                Ok(None) | Err(_) => continue,
+            };
+            let package = manifest.get("package").or_else(|| manifest.get("project"));
+            let found = package.and_then(|p| p.get("name")).and_then(|n| n.as_str());
+            if found == Some(name) {
+                return Ok(Some(dir));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The directories of the package in `dir` at `rev` and of the packages
+    /// it depends on through `path` dependencies, transitively. Development
+    /// dependencies aren't followed, as they aren't built for dependencies.
+    pub fn package_closure(
+        &self,
+        rev: git2::Oid,
+        dir: &str,
+        config: &Config,
+    ) -> CargoResult<Vec<String>> {
+        let tree = self.repo.find_commit(rev)?.tree()?;
+        let mut dirs = BTreeSet::new();
+        let mut pending = vec![dir.to_string()];
+        while let Some(dir) = pending.pop() {
+            if dirs.contains(&dir) {
+                continue;
+            }
+            let manifest = match self.read_manifest(&tree, &dir, config)? {
+                Some(manifest) => manifest,
+                None => continue,
+            };
+            let mut tables = Vec::new();
+            for key in &["dependencies", "build-dependencies", "build_dependencies"] {
+                tables.extend(manifest.get(key));
+                if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+                    tables.extend(targets.values().filter_map(|t| t.get(key)));
+                }
+            }
+            for (name, dep) in tables.iter().filter_map(|t| t.as_table()).flatten() {
+                if let Some(path) = dep.get("path").and_then(|p| p.as_str()) {
+                    pending.extend(join_repo_path(&dir, path));
+                } else if dep.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                    pending.extend(self.workspace_dependency_dir(&tree, &dir, name, config)?);
+                }
+            }
+            dirs.insert(dir);
+        }
+        Ok(dirs.into_iter().collect())
+    }
+
+    /// The directory of the `path` dependency `name` that the package in
+    /// `dir` inherits from the closest workspace root above it.
+    fn workspace_dependency_dir(
+        &self,
+        tree: &git2::Tree<'_>,
+        dir: &str,
+        name: &str,
+        config: &Config,
+    ) -> CargoResult<Option<String>> {
+        for root in repo_ancestors(dir).skip(1) {
+            let workspace = match self.read_manifest(tree, root, config)? {
+                Some(manifest) => manifest.get("workspace").cloned(),
+                None => continue,
+            };
+            if let Some(workspace) = workspace {
+                let path = workspace
+                    .get("dependencies")
+                    .and_then(|deps| deps.get(name))
+                    .and_then(|dep| dep.get("path"))
+                    .and_then(|path| path.as_str());
+                return Ok(path.and_then(|path| join_repo_path(root, path)));
+            }
+        }
+        Ok(None)
     }
+
+    /// Reads the manifest of the package in `dir` of `tree`, if there is one.
+    fn read_manifest(
+        &self,
+        tree: &git2::Tree<'_>,
+        dir: &str,
+        config: &Config,
+    ) -> CargoResult<Option<toml::Value>> {
+        let path = Path::new(dir).join("Cargo.toml");
+        let entry = match tree.get_path(&path) {
+            Ok(entry) => entry,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let blob = entry.to_object(&self.repo)?.peel_to_blob()?;
+        let contents = std::str::from_utf8(blob.content())
+            .with_context(|| format!("`{}` is not UTF-8", path.display()))?;
+        crate::util::toml::parse(contents, &path, config).map(Some)
+    }
+}
+
+/// Joins `path` to the directory `dir` of a repository, or returns `None` if
+/// the result lies outside of it.
+fn join_repo_path(dir: &str, path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in path.split(|c| c == '/' || c == '\\') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// The directory `dir` of a repository followed by the directories above it,
+/// up to the root as an empty string.
+fn repo_ancestors(dir: &str) -> impl Iterator<Item = &str> {
+    let parents = dir.match_indices('/').rev().map(move |(i, _)| &dir[..i]);
+    let root = if dir.is_empty() { None } else { Some("") };
+    std::iter::once(dir).chain(parents).chain(root)
+}
+
+/// The pathspecs matching the package directories `dirs` and the manifests
+/// above them, see `in_sparse_checkout`.
+fn sparse_pathspecs(dirs: &[String]) -> Vec<String> {
+    let mut pathspecs = BTreeSet::new();
+    for dir in dirs {
+        pathspecs.insert(dir.clone());
+        for ancestor in repo_ancestors(dir).skip(1) {
+            pathspecs.insert(match ancestor {
+                "" => "Cargo.toml".to_string(),
+                _ => format!("{}/Cargo.toml", ancestor),
+            });
+        }
+    }
+    pathspecs.into_iter().collect()
+}
+
+/// Whether the file at `path` in a repository is part of a checkout of the
+/// package directories `sparse`, which includes the manifests above them.
+fn in_sparse_checkout(sparse: Option<&[String]>, path: &str) -> bool {
+    let dirs = match sparse {
+        Some(dirs) => dirs,
+        None => return true,
+    };
+    dirs.iter().any(|dir| {
+        let in_dir = dir.is_empty()
+            || path
+                .strip_prefix(dir.as_str())
+                .map_or(false, |rest| rest.starts_with('/'));
+        in_dir
+            || repo_ancestors(dir).any(|ancestor| match ancestor {
+                "" => path == "Cargo.toml",
+                _ => path.strip_prefix(ancestor) == Some("/Cargo.toml"),
+            })
+    })
 }
 
 impl GitReference {
@@ -235,6 +557,14 @@ impl GitReference {
                     None => obj.id(),
                 }
             }
+
+            // Fetched into the same name below.
+            GitReference::Ref(s) => (|| -> CargoResult<git2::Oid> {
+                let id = repo.refname_to_id(s)?;
+                let obj = repo.find_object(id, None)?;
+                Ok(obj.peel(ObjectType::Commit)?.id())
+            })()
+            .with_context(|| format!("failed to find reference `{}`", s))?,
         };
         Ok(id)
     }
@@ -246,12 +576,14 @@ impl<'a> GitCheckout<'a> {
         database: &'a GitDatabase,
         revision: git2::Oid,
         repo: git2::Repository,
+        sparse: Option<Vec<String>>,
     ) -> GitCheckout<'a> {
         GitCheckout {
             location: path.to_path_buf(),
             database,
             revision,
             repo,
+            sparse,
         }
     }
 
@@ -259,6 +591,7 @@ impl<'a> GitCheckout<'a> {
         into: &Path,
         database: &'a GitDatabase,
         revision: git2::Oid,
+        sparse: Option<Vec<String>>,
         config: &Config,
     ) -> CargoResult<GitCheckout<'a>> {
         let dirname = into.parent().unwrap();
@@ -296,7 +629,7 @@ impl<'a> GitCheckout<'a> {
         })?;
         let repo = repo.unwrap();
 
-        let checkout = GitCheckout::new(into, database, revision, repo);
+        let checkout = GitCheckout::new(into, database, revision, repo, sparse);
         checkout.reset(config)?;
         Ok(checkout)
     }
@@ -315,7 +648,14 @@ impl<'a> GitCheckout<'a> {
         info!("fetch {}", self.repo.path().display());
         let url = self.database.path.into_url()?;
         let reference = GitReference::Rev(self.revision.to_string());
-        fetch(&mut self.repo, url.as_str(), &reference, cargo_config)?;
+        fetch(
+            &mut self.repo,
+            url.as_str(),
+            &reference,
+            GitHistory::default(),
+            &*self.database.remote.backend,
+            cargo_config,
+        )?;
         Ok(())
     }
 
@@ -337,84 +677,24 @@ impl<'a> GitCheckout<'a> {
             git_config.set_bool("core.autocrlf", false)?;
         }
 
-        let object = self.repo.find_object(self.revision, None)?;
-        reset(&self.repo, &object, config)?;
+        let pathspecs = self.sparse.as_ref().map(|dirs| sparse_pathspecs(dirs));
+        self.database.remote.backend.checkout(
+            &self.repo,
+            self.revision,
+            pathspecs.as_deref(),
+            config,
+        )?;
         paths::create(ok_file)?;
         Ok(())
     }
 
     fn update_submodules(&self, cargo_config: &Config) -> CargoResult<()> {
-        return update_submodules(&self.repo, cargo_config);
-
-        fn update_submodules(repo: &git2::Repository, cargo_config: &Config) -> CargoResult<()> {
-            info!("update submodules for: {:?}", repo.workdir().unwrap());
-
-            for mut child in repo.submodules()? {
-                update_submodule(repo, &mut child, cargo_config).with_context(|| {
-                    format!(
-                        "failed to update submodule `{}`",
-                        child.name().unwrap_or("")
-                    )
-                })?;
-            }
-            Ok(())
-        }
-
-        fn update_submodule(
-            parent: &git2::Repository,
-            child: &mut git2::Submodule<'_>,
-            cargo_config: &Config,
-        ) -> CargoResult<()> {
-            child.init(false)?;
-            let url = child.url().ok_or_else(|| {
-                anyhow::format_err!("non-utf8 url for submodule {:?}?", child.path())
-            })?;
-
-            // A submodule which is listed in .gitmodules but not actually
-            // checked out will not have a head id, so we should ignore it.
-            let head = match child.head_id() {
-                Some(head) => head,
-                None => return Ok(()),
-            };
-
-            // If the submodule hasn't been checked out yet, we need to
-            // clone it. If it has been checked out and the head is the same
-            // as the submodule's head, then we can skip an update and keep
-            // recursing.
-            let head_and_repo = child.open().and_then(|repo| {
-                let target = repo.head()?.target();
-                Ok((target, repo))
-            });
-            let mut repo = match head_and_repo {
-                Ok((head, repo)) => {
-                    if child.head_id() == head {
-                        return update_submodules(&repo, cargo_config);
-                    }
-                    repo
-                }
-                Err(..) => {
-                    let path = parent.workdir().unwrap().join(child.path());
-                    let _ = paths::remove_dir_all(&path);
-                    init(&path, false)?
-                }
-            };
-            // Fetch data from origin and reset to the head commit
-            let reference = GitReference::Rev(head.to_string());
-            cargo_config
-                .shell()
-                .status("Updating", format!("git submodule `{}`", url))?;
-            fetch(&mut repo, url, &reference, cargo_config).with_context(|| {
-                format!(
-                    "failed to fetch submodule `{}` from {}",
-                    child.name().unwrap_or(""),
-                    url
-                )
-            })?;
-
-            let obj = repo.find_object(head, None)?;
-            reset(&repo, &obj, cargo_config)?;
-            update_submodules(&repo, cargo_config)
-        }
+        // Submodules outside of a sparse checkout aren't needed.
+        self.database.remote.backend.update_submodules(
+            &self.repo,
+            &|path| in_sparse_checkout(self.sparse.as_deref(), path),
+            cargo_config,
+        )
     }
 }
 
@@ -674,18 +954,6 @@ where
     Err(err)
 }
 
-fn reset(repo: &git2::Repository, obj: &git2::Object<'_>, config: &Config) -> CargoResult<()> {
-    let mut pb = Progress::new("Checkout", config);
-    let mut opts = git2::build::CheckoutBuilder::new();
-    opts.progress(|_, cur, max| {
-        drop(pb.tick(cur, max, ""));
-    });
-    debug!("doing reset");
-    repo.reset(obj, git2::ResetType::Hard, Some(&mut opts))?;
-    debug!("reset done");
-    Ok(())
-}
-
 pub fn with_fetch_options(
     git_config: &git2::Config,
     url: &str,
@@ -751,10 +1019,14 @@ pub fn with_fetch_options(
     })
 }
 
-pub fn fetch(
+/// Fetches `reference` from `url` into `repo` with `backend`, as much of its
+/// history as `history` asks for if the backend can.
+pub(super) fn fetch<B: GitBackend + ?Sized>(
     repo: &mut git2::Repository,
     url: &str,
     reference: &GitReference,
+    mut history: GitHistory,
+    backend: &B,
     config: &Config,
 ) -> CargoResult<()> {
     if config.frozen() {
@@ -781,6 +1053,16 @@ pub fn fetch(
     // request we're about to issue.
     maybe_gc_repo(repo)?;
 
+    if !history.is_full() && !backend.fetches_history() {
+        config.shell().warn(format!(
+            "shallow and partial fetches need a git backend that can fetch \
+             history, such as `git-cli`, but `{}` is in use, fetching all of `{}`",
+            backend.name(),
+            url
+        ))?;
+        history = GitHistory::default();
+    }
+
     // Translate the reference desired here into an actual list of refspecs
     // which need to get fetched. Additionally record if we're fetching tags.
     let mut refspecs = Vec::new();
@@ -799,6 +1081,18 @@ pub fn fetch(
             refspecs.push(String::from("HEAD:refs/remotes/origin/HEAD"));
         }
 
+        // Any other reference is fetched as is, forced like the others as it
+        // may be rewritten, such as the head of a pull request.
+        GitReference::Ref(r) => {
+            refspecs.push(format!("+{0}:{0}", r));
+        }
+
+        // A shallow fetch of a commit ID only fetches that commit, if the
+        // server allows it.
+        GitReference::Rev(rev) if history.shallow && is_commit_id(rev) => {
+            refspecs.push(format!("+{0}:refs/commit/{0}", rev));
+        }
+
         // For `rev` dependencies we don't know what the rev will point to. To
         // handle this situation we fetch all branches and tags, and then we
         // pray it's somewhere in there.
@@ -809,97 +1103,57 @@ pub fn fetch(
         }
     }
 
-    // Unfortunately `libgit2` is notably lacking in the realm of authentication
-    // when compared to the `git` command line. As a result, allow an escape
-    // hatch for users that would prefer to use `git`-the-CLI for fetching
-    // repositories instead of `libgit2`-the-library. This should make more
-    // flavors of authentication possible while also still giving us all the
-    // speed and portability of using `libgit2`.
-    if let Some(true) = config.net_config()?.git_fetch_with_cli {
-        return fetch_with_cli(repo, url, &refspecs, tags, config);
-    }
-
-    debug!("doing a fetch for {}", url);
-    let git_config = git2::Config::open_default()?;
-    with_fetch_options(&git_config, url, config, &mut |mut opts| {
-        if tags {
-            opts.download_tags(git2::AutotagOption::All);
-        }
-        // The `fetch` operation here may fail spuriously due to a corrupt
-        // repository. It could also fail, however, for a whole slew of other
-        // reasons (aka network related reasons). We want Cargo to automatically
-        // recover from corrupt repositories, but we don't want Cargo to stomp
-        // over other legitimate errors.
-        //
-        // Consequently we save off the error of the `fetch` operation and if it
-        // looks like a "corrupt repo" error then we blow away the repo and try
-        // again. If it looks like any other kind of error, or if we've already
-        // blown away the repository, then we want to return the error as-is.
-        let mut repo_reinitialized = false;
-        loop {
-            debug!("initiating fetch of {:?} from {}", refspecs, url);
-            let res = repo
-                .remote_anonymous(url)?
-                .fetch(&refspecs, Some(&mut opts), None);
-            let err = match res {
-                Ok(()) => break,
-                Err(e) => e,
-            };
-            debug!("fetch failed: {}", err);
-
-            if !repo_reinitialized && matches!(err.class(), ErrorClass::Reference | ErrorClass::Odb)
-            {
-                repo_reinitialized = true;
-                debug!(
-                    "looks like this is a corrupt repository, reinitializing \
-                     and trying again"
-                );
-                if reinitialize(repo).is_ok() {
-                    continue;
-                }
-            }
-
-            return Err(err.into());
-        }
-        Ok(())
-    })
+    backend.fetch(repo, url, reference, &refspecs, tags, history, config)
 }
 
-fn fetch_with_cli(
-    repo: &mut git2::Repository,
+/// Fetches the contents of the files of `rev` whose path matches `filter`
+/// that a partial fetch left out, as libgit2 can't fetch them when it needs
+/// them like `git` does.
+pub(super) fn backfill(
+    repo: &git2::Repository,
     url: &str,
-    refspecs: &[String],
-    tags: bool,
+    rev: git2::Oid,
+    filter: &dyn Fn(&str) -> bool,
     config: &Config,
 ) -> CargoResult<()> {
-    let mut cmd = ProcessBuilder::new("git");
-    cmd.arg("fetch");
-    if tags {
-        cmd.arg("--tags");
+    // `git` marks the remotes it fetched partially from.
+    let partial = repo
+        .config()
+        .and_then(|c| c.get_bool(&format!("remote.{}.promisor", url)))
+        .unwrap_or(false);
+    if !partial {
+        return Ok(());
+    }
+    let tree = repo.find_commit(rev)?.tree()?;
+    let odb = repo.odb()?;
+    let mut missing = Vec::new();
+    tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() == Some(ObjectType::Blob)
+            && filter(&format!("{}{}", root, entry.name().unwrap_or("")))
+            && !odb.exists(entry.id())
+        {
+            missing.push(entry.id().to_string());
+        }
+        git2::TreeWalkResult::Ok
+    })?;
+    debug!(
+        "backfilling {} blobs of {} from {}",
+        missing.len(),
+        rev,
+        url
+    );
+    // Keep the command lines at a reasonable length.
+    for blobs in missing.chunks(1000) {
+        backend::fetch_with_cli(repo, url, blobs, false, &["--filter=blob:none"], config)?;
     }
-    cmd.arg("--force") // handle force pushes
-        .arg("--update-head-ok") // see discussion in #2078
-        .arg(url)
-        .args(refspecs)
-        // If cargo is run by git (for example, the `exec` command in `git
-        // rebase`), the GIT_DIR is set by git and will point to the wrong
-        // location (this takes precedence over the cwd). Make sure this is
-        // unset so git will look at cwd for the repo.
-        .env_remove("GIT_DIR")
-        // The reset of these may not be necessary, but I'm including them
-        // just to be extra paranoid and avoid any issues.
-        .env_remove("GIT_WORK_TREE")
-        .env_remove("GIT_INDEX_FILE")
-        .env_remove("GIT_OBJECT_DIRECTORY")
-        .env_remove("GIT_ALTERNATE_OBJECT_DIRECTORIES")
-        .cwd(repo.path());
-    config
-        .shell()
-        .verbose(|s| s.status("Running", &cmd.to_string()))?;
-    cmd.exec_with_output()?;
     Ok(())
 }
 
+/// Whether `rev` is a full commit ID, which can be fetched by itself.
+fn is_commit_id(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
 /// Cargo has a bunch of long-lived git repositories in its global cache and
 /// some, like the index, are updated very frequently. Right now each update
 /// creates a new "pack file" inside the git database, and over time this can
@@ -961,7 +1215,7 @@ fn maybe_gc_repo(repo: &mut git2::Repository) -> CargoResult<()> {
     reinitialize(repo)
 }
 
-fn reinitialize(repo: &mut git2::Repository) -> CargoResult<()> {
+pub(super) fn reinitialize(repo: &mut git2::Repository) -> CargoResult<()> {
     // Here we want to drop the current repository object pointed to by `repo`,
     // so we initialize temporary repository in a sub-folder, blow away the
     // existing git folder, and then recreate the git repo. Finally we blow away
@@ -984,7 +1238,7 @@ fn reinitialize(repo: &mut git2::Repository) -> CargoResult<()> {
     Ok(())
 }
 
-fn init(path: &Path, bare: bool) -> CargoResult<git2::Repository> {
+pub(super) fn init(path: &Path, bare: bool) -> CargoResult<git2::Repository> {
     let mut opts = git2::RepositoryInitOptions::new();
     // Skip anything related to templates, they just call all sorts of issues as
     // we really don't want to use them yet they insist on being used. See #6240
@@ -1029,6 +1283,10 @@ fn github_up_to_date(
             debug!("can't use github fast path with `rev`");
             return Ok(false);
         }
+        GitReference::Ref(_) => {
+            debug!("can't use github fast path with `ref`");
+            return Ok(false);
+        }
     };
 
     // This expects GitHub urls in the form `github.com/user/repo` and nothing
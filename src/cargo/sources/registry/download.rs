@@ -0,0 +1,124 @@
+//! Shared download logic between [`HttpRegistry`] and [`RemoteRegistry`].
+//!
+//! Both registry kinds store `.crate` files in the same on-disk cache and
+//! generate download URLs from the `dl` template in the index `config.json`,
+//! only the way the index itself is fetched differs.
+//!
+//! [`HttpRegistry`]: super::http_remote::HttpRegistry
+//! [`RemoteRegistry`]: super::remote::RemoteRegistry
+
+use crate::core::PackageId;
+use crate::sources::registry::{
+    MaybeLock, RegistryConfig, CRATE_TEMPLATE, LOWER_PREFIX_TEMPLATE, PREFIX_TEMPLATE,
+    VERSION_TEMPLATE,
+};
+use crate::util::errors::CargoResult;
+use crate::util::{Config, Filesystem};
+use anyhow::Context as _;
+use cargo_util::{registry::make_dep_path, Sha256};
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::io::SeekFrom;
+
+/// Filename of a crate file in the `.crate` cache.
+pub(super) fn filename(pkg: PackageId) -> String {
+    format!("{}-{}.crate", pkg.name(), pkg.version())
+}
+
+/// Checks the `.crate` cache for `pkg`, returning the file if it's already
+/// downloaded or the URL to download it from otherwise.
+pub(super) fn download(
+    cache_path: &Filesystem,
+    config: &Config,
+    pkg: PackageId,
+    registry_config: RegistryConfig,
+) -> CargoResult<MaybeLock> {
+    let filename = filename(pkg);
+
+    // Attempt to open an read-only copy first to avoid an exclusive write
+    // lock and also work with read-only filesystems. Note that we check the
+    // length of the file like below to handle interrupted downloads.
+    //
+    // If this fails then we fall through to the exclusive path where we may
+    // have to redownload the file.
+    let path = cache_path.join(&filename);
+    let path = config.assert_package_cache_locked(&path);
+    if let Ok(dst) = File::open(&path) {
+        let meta = dst.metadata()?;
+        if meta.len() > 0 {
+            config.mark_cache_used(path);
+            return Ok(MaybeLock::Ready(dst));
+        }
+    }
+
+    let mut url = registry_config.dl;
+    if !url.contains(CRATE_TEMPLATE)
+        && !url.contains(VERSION_TEMPLATE)
+        && !url.contains(PREFIX_TEMPLATE)
+        && !url.contains(LOWER_PREFIX_TEMPLATE)
+    {
+        write!(url, "/{}/{}/download", CRATE_TEMPLATE, VERSION_TEMPLATE).unwrap();
+    }
+    let prefix = make_dep_path(&*pkg.name(), true);
+    let url = url
+        .replace(CRATE_TEMPLATE, &*pkg.name())
+        .replace(VERSION_TEMPLATE, &pkg.version().to_string())
+        .replace(PREFIX_TEMPLATE, &prefix)
+        .replace(LOWER_PREFIX_TEMPLATE, &prefix.to_lowercase());
+
+    Ok(MaybeLock::Download {
+        url,
+        descriptor: pkg.to_string(),
+    })
+}
+
+/// Verifies the checksum of a freshly downloaded `.crate` file and saves it
+/// into the `.crate` cache.
+pub(super) fn finish_download(
+    cache_path: &Filesystem,
+    config: &Config,
+    pkg: PackageId,
+    checksum: &str,
+    data: &[u8],
+) -> CargoResult<File> {
+    // Verify what we just downloaded
+    let actual = Sha256::new().update(data).finish_hex();
+    if actual != checksum {
+        anyhow::bail!("failed to verify the checksum of `{}`", pkg)
+    }
+
+    let filename = filename(pkg);
+    cache_path.create_dir()?;
+    let path = cache_path.join(&filename);
+    let path = config.assert_package_cache_locked(&path);
+    let mut dst = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("failed to open `{}`", path.display()))?;
+    config.mark_cache_used(path);
+    let meta = dst.metadata()?;
+    if meta.len() > 0 {
+        return Ok(dst);
+    }
+
+    dst.write_all(data)?;
+    dst.seek(SeekFrom::Start(0))?;
+    Ok(dst)
+}
+
+/// Returns whether or not the `.crate` file for `pkg` is in the cache.
+pub(super) fn is_crate_downloaded(
+    cache_path: &Filesystem,
+    config: &Config,
+    pkg: PackageId,
+) -> bool {
+    let path = cache_path.join(filename(pkg));
+    let path = config.assert_package_cache_locked(&path);
+    if let Ok(meta) = fs::metadata(path) {
+        return meta.len() > 0;
+    }
+    false
+}
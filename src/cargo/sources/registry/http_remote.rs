@@ -0,0 +1,430 @@
+//! Access to a registry whose index is served over plain HTTP.
+//!
+//! See [`HttpRegistry`] for details.
+
+use crate::core::{PackageId, SourceId};
+use crate::ops;
+use crate::sources::registry::download;
+use crate::sources::registry::{MaybeLock, RegistryConfig, RegistryData};
+use crate::util::errors::{CargoResult, HttpNot200};
+use crate::util::interning::InternedString;
+use crate::util::network::Retry;
+use crate::util::{Config, Filesystem};
+use anyhow::Context as _;
+use cargo_util::paths;
+use curl::easy::{Easy, HttpVersion, List};
+use curl::multi::{EasyHandle, Multi};
+use log::{debug, trace};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as FmtWrite;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Directory (relative to the index root) holding the cache validators of
+/// each index file, stored at the same relative path as the file itself.
+const HEADERS_DIR: &str = ".headers";
+const ETAG: &str = "ETag";
+const LAST_MODIFIED: &str = "Last-Modified";
+
+/// A remote registry whose index is fetched file-by-file over HTTP, selected
+/// with a `sparse+` URL such as `sparse+https://index.example.com/`.
+///
+/// Instead of cloning the entire git index, each index file is requested on
+/// demand from `<url>/<path>`, where `<path>` follows the same layout as the
+/// git index (see `make_dep_path`). Files are cached in the index directory
+/// and, once the index has been asked to update, revalidated at most once per
+/// session with a conditional request based on the `ETag` and
+/// `Last-Modified` headers of the previous response.
+///
+/// Files needed by the resolver are fetched in parallel through
+/// [`RegistryData::prefetch`]. `.crate` files are downloaded and cached
+/// exactly like for a git-based registry.
+pub struct HttpRegistry<'cfg> {
+    index_path: Filesystem,
+    /// Path to the cache of `.crate` files (`$CARGO_HOME/registry/path/$REG-HASH`).
+    cache_path: Filesystem,
+    source_id: SourceId,
+    config: &'cfg Config,
+    /// Base URL of the index, without the `sparse+` prefix or trailing slash.
+    url: String,
+    /// Whether the index has been asked to update, in which case cached index
+    /// files need to be revalidated with the server before they are used.
+    requested_update: bool,
+    /// Index files (relative to the index root) which have already been
+    /// fetched or revalidated in this session.
+    fresh: RefCell<HashSet<PathBuf>>,
+    /// Handle used to run the requests for index files in parallel.
+    multi: Multi,
+    /// Whether or not to use curl HTTP/2 multiplexing.
+    multiplexing: bool,
+}
+
+/// An in-flight request for an index file.
+struct Download<'cfg> {
+    /// Path of the index file, relative to the index root.
+    path: PathBuf,
+    /// The URL being fetched, cached here for error messages.
+    url: String,
+    /// Response body and cache validators, filled in by the curl callbacks.
+    response: Arc<Mutex<Response>>,
+    /// Logic used to track retrying this request if it's a spurious failure.
+    retry: Retry<'cfg>,
+}
+
+#[derive(Default)]
+struct Response {
+    body: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl<'cfg> HttpRegistry<'cfg> {
+    pub fn new(
+        source_id: SourceId,
+        config: &'cfg Config,
+        name: &str,
+    ) -> CargoResult<HttpRegistry<'cfg>> {
+        if !config.cli_unstable().sparse_registry {
+            anyhow::bail!(
+                "usage of sparse registries requires `-Z sparse-registry`\n\
+                 (found `{}`)",
+                source_id.url()
+            );
+        }
+        let url = source_id
+            .url()
+            .as_str()
+            .trim_start_matches("sparse+")
+            .trim_end_matches('/')
+            .to_string();
+        let (multi, multiplexing) = ops::http_multi_handle(config)?;
+        Ok(HttpRegistry {
+            index_path: config.registry_index_path().join(name),
+            cache_path: config.registry_cache_path().join(name),
+            source_id,
+            config,
+            url,
+            requested_update: false,
+            fresh: RefCell::new(HashSet::new()),
+            multi,
+            multiplexing,
+        })
+    }
+
+    /// Returns whether the index file at `path` has to be fetched from the
+    /// server before it can be loaded.
+    fn needs_fetch(&self, root: &Path, path: &Path) -> bool {
+        if self.config.offline() || self.fresh.borrow().contains(path) {
+            return false;
+        }
+        self.requested_update || !root.join(path).exists()
+    }
+
+    /// Fetches all of the index files in `paths` that need it, in parallel.
+    fn fetch(&self, paths: &[PathBuf]) -> CargoResult<()> {
+        let root = self.config.assert_package_cache_locked(&self.index_path);
+        let mut pending = HashMap::new();
+        let mut started = HashSet::new();
+        for path in paths {
+            if !self.needs_fetch(root, path) || !started.insert(path) {
+                continue;
+            }
+            let token = pending.len();
+            let (dl, handle) = self.start(root, path)?;
+            let mut handle = self.multi.add(handle)?;
+            handle.set_token(token)?;
+            pending.insert(token, (dl, handle));
+        }
+        if pending.is_empty() {
+            return Ok(());
+        }
+        debug!("fetching {} index files from {}", pending.len(), self.url);
+
+        let mut results = Vec::new();
+        while !pending.is_empty() {
+            self.multi
+                .perform()
+                .with_context(|| "failed to perform http requests")?;
+            self.multi.messages(|msg| {
+                let token = msg.token().expect("failed to read token");
+                let handle: &EasyHandle = &pending[&token].1;
+                if let Some(result) = msg.result_for(handle) {
+                    results.push((token, result));
+                } else {
+                    debug!("message without a result (?)");
+                }
+            });
+
+            if results.is_empty() {
+                let timeout = self
+                    .multi
+                    .get_timeout()?
+                    .unwrap_or_else(|| Duration::new(5, 0));
+                self.multi
+                    .wait(&mut [], timeout)
+                    .with_context(|| "failed to wait on curl `Multi`")?;
+                continue;
+            }
+
+            for (token, result) in results.drain(..) {
+                let (mut dl, handle) = pending.remove(&token).unwrap();
+                let handle = self.multi.remove(handle)?;
+                let url = &dl.url;
+                let ret = dl
+                    .retry
+                    .r#try(|| {
+                        result?;
+                        match handle.response_code()? {
+                            code @ (200 | 304 | 404 | 410) => Ok(code),
+                            code => Err(HttpNot200 {
+                                code,
+                                url: url.to_string(),
+                            }
+                            .into()),
+                        }
+                    })
+                    .with_context(|| format!("failed to fetch `{}`", dl.url))?;
+                match ret {
+                    Some(code) => self.finish(root, &dl, code)?,
+                    None => {
+                        *dl.response.lock().unwrap() = Response::default();
+                        let mut handle = self.multi.add(handle)?;
+                        handle.set_token(token)?;
+                        pending.insert(token, (dl, handle));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Prepares the request for the index file at `path`, revalidating the
+    /// copy on disk if there is one.
+    fn start(&self, root: &Path, path: &Path) -> CargoResult<(Download<'cfg>, Easy)> {
+        let relative = path
+            .iter()
+            .map(|c| c.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let url = format!("{}/{}", self.url, relative);
+        trace!("fetching {}", url);
+
+        let mut handle = ops::http_handle(self.config)?;
+        handle.get(true)?;
+        handle.url(&url)?;
+        handle.follow_location(true)?;
+        if self.multiplexing {
+            try_old_curl!(handle.http_version(HttpVersion::V2), "HTTP2");
+        } else {
+            handle.http_version(HttpVersion::V11)?;
+        }
+        try_old_curl!(handle.pipewait(true), "pipewait");
+
+        if root.join(path).exists() {
+            if let Ok(validators) = paths::read(&root.join(HEADERS_DIR).join(path)) {
+                let mut headers = List::new();
+                for line in validators.lines() {
+                    if let Some(etag) = header_value(line, ETAG) {
+                        headers.append(&format!("If-None-Match: {}", etag))?;
+                    } else if let Some(last_modified) = header_value(line, LAST_MODIFIED) {
+                        headers.append(&format!("If-Modified-Since: {}", last_modified))?;
+                    }
+                }
+                handle.http_headers(headers)?;
+            }
+        }
+
+        let response = Arc::new(Mutex::new(Response::default()));
+        let body = Arc::clone(&response);
+        handle.write_function(move |buf| {
+            body.lock().unwrap().body.extend_from_slice(buf);
+            Ok(buf.len())
+        })?;
+        let headers = Arc::clone(&response);
+        handle.header_function(move |line| {
+            if let Ok(line) = str::from_utf8(line) {
+                let mut response = headers.lock().unwrap();
+                if let Some(etag) = header_value(line, ETAG) {
+                    response.etag = Some(etag.to_string());
+                } else if let Some(last_modified) = header_value(line, LAST_MODIFIED) {
+                    response.last_modified = Some(last_modified.to_string());
+                }
+            }
+            true
+        })?;
+
+        let dl = Download {
+            path: path.to_path_buf(),
+            url,
+            response,
+            retry: Retry::new(self.config)?,
+        };
+        Ok((dl, handle))
+    }
+
+    /// Updates the on-disk copy of an index file after its request finished
+    /// with the HTTP status `code`.
+    fn finish(&self, root: &Path, dl: &Download<'_>, code: u32) -> CargoResult<()> {
+        debug!("fetched {} with status {}", dl.url, code);
+        let path = root.join(&dl.path);
+        let headers_path = root.join(HEADERS_DIR).join(&dl.path);
+        match code {
+            200 => {
+                let response = dl.response.lock().unwrap();
+                paths::create_dir_all(path.parent().unwrap())?;
+                paths::write(&path, &response.body)?;
+
+                let mut validators = String::new();
+                if let Some(etag) = &response.etag {
+                    writeln!(validators, "{}: {}", ETAG, etag).unwrap();
+                }
+                if let Some(last_modified) = &response.last_modified {
+                    writeln!(validators, "{}: {}", LAST_MODIFIED, last_modified).unwrap();
+                }
+                paths::create_dir_all(headers_path.parent().unwrap())?;
+                paths::write(&headers_path, validators)?;
+            }
+            // Not modified, our copy is still up to date.
+            304 => {}
+            // The file is gone, so make sure we don't keep using a stale copy.
+            _ => {
+                for path in &[path, headers_path] {
+                    if path.exists() {
+                        paths::remove_file(path)?;
+                    }
+                }
+            }
+        }
+        self.fresh.borrow_mut().insert(dl.path.clone());
+        Ok(())
+    }
+}
+
+/// Returns the value of `line` if it is an HTTP header named `name`.
+fn header_value<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let (key, value) = line.split_once(':')?;
+    if key.trim().eq_ignore_ascii_case(name) {
+        Some(value.trim())
+    } else {
+        None
+    }
+}
+
+impl<'cfg> RegistryData for HttpRegistry<'cfg> {
+    fn prepare(&self) -> CargoResult<()> {
+        Ok(())
+    }
+
+    fn index_path(&self) -> &Filesystem {
+        &self.index_path
+    }
+
+    fn assert_index_locked<'a>(&self, path: &'a Filesystem) -> &'a Path {
+        self.config.assert_package_cache_locked(path)
+    }
+
+    fn current_version(&self) -> Option<InternedString> {
+        None
+    }
+
+    /// Each index file is versioned by the cache validators of the response
+    /// it was fetched with. A file that has to be revalidated with the server
+    /// has no version until then.
+    fn file_version(&self, root: &Path, path: &Path) -> Option<InternedString> {
+        if self.needs_fetch(root, path) || !root.join(path).exists() {
+            return None;
+        }
+        let validators = paths::read(&root.join(HEADERS_DIR).join(path)).ok()?;
+        let version = validators
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if version.is_empty() {
+            None
+        } else {
+            Some(InternedString::new(&version))
+        }
+    }
+
+    fn load(
+        &self,
+        root: &Path,
+        path: &Path,
+        data: &mut dyn FnMut(&[u8]) -> CargoResult<()>,
+    ) -> CargoResult<()> {
+        if self.needs_fetch(root, path) {
+            self.fetch(&[path.to_path_buf()])?;
+        }
+        data(&paths::read_bytes(&root.join(path))?)
+    }
+
+    fn config(&mut self) -> CargoResult<Option<RegistryConfig>> {
+        debug!("loading config");
+        let root = self.config.assert_package_cache_locked(&self.index_path);
+        let mut config = None;
+        self.load(root, Path::new("config.json"), &mut |json| {
+            config = Some(serde_json::from_slice(json)?);
+            Ok(())
+        })?;
+        trace!("config loaded");
+        Ok(config)
+    }
+
+    fn update_index(&mut self) -> CargoResult<()> {
+        if self.config.offline() {
+            return Ok(());
+        }
+        if self.config.cli_unstable().no_index_update {
+            return Ok(());
+        }
+        // Only revalidate the cached index files once per session, even if
+        // this registry is loaded several times (such as during `cargo
+        // publish`).
+        if self.requested_update || self.config.updated_sources().contains(&self.source_id) {
+            return Ok(());
+        }
+
+        // Ensure that we'll actually be able to acquire an HTTP handle later
+        // on, see `RemoteRegistry::update_index`.
+        self.config.http()?;
+
+        self.config
+            .shell()
+            .status("Updating", self.source_id.display_index())?;
+        self.requested_update = true;
+        self.config.updated_sources().insert(self.source_id);
+        Ok(())
+    }
+
+    fn supports_prefetch(&self) -> bool {
+        true
+    }
+
+    fn prefetch(&mut self, paths: &[PathBuf]) -> CargoResult<()> {
+        self.fetch(paths)
+    }
+
+    fn download(&mut self, pkg: PackageId, _checksum: &str) -> CargoResult<MaybeLock> {
+        let registry_config = self.config()?.unwrap();
+        download::download(&self.cache_path, self.config, pkg, registry_config)
+    }
+
+    fn finish_download(
+        &mut self,
+        pkg: PackageId,
+        checksum: &str,
+        data: &[u8],
+    ) -> CargoResult<File> {
+        download::finish_download(&self.cache_path, self.config, pkg, checksum, data)
+    }
+
+    fn is_crate_downloaded(&self, pkg: PackageId) -> bool {
+        download::is_crate_downloaded(&self.cache_path, self.config, pkg)
+    }
+}
@@ -66,7 +66,7 @@
 //! details like invalidating caches and whatnot which are handled below, but
 //! hopefully those are more obvious inline in the code itself.
 
-use crate::core::dependency::Dependency;
+use crate::core::dependency::{DepKind, Dependency};
 use crate::core::{PackageId, SourceId, Summary};
 use crate::sources::registry::{RegistryData, RegistryPackage, INDEX_V_MAX};
 use crate::util::interning::InternedString;
@@ -78,7 +78,7 @@ use semver::Version;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str;
 
 /// Crates.io treats hyphen and underscores as interchangeable, but the index and old Cargo do not.
@@ -187,6 +187,8 @@ pub struct RegistryIndex<'cfg> {
     /// to JSON files from the index, and the creates the optimized on-disk
     /// summary cache.
     summaries_cache: HashMap<InternedString, Summaries>,
+    /// Requirements which [`RegistryIndex::prefetch`] has already walked.
+    prefetched: HashSet<(InternedString, OptVersionReq)>,
     /// [`Config`] reference for convenience.
     config: &'cfg Config,
 }
@@ -258,6 +260,7 @@ impl<'cfg> RegistryIndex<'cfg> {
             source_id,
             path: path.clone(),
             summaries_cache: HashMap::new(),
+            prefetched: HashSet::new(),
             config,
         }
     }
@@ -365,15 +368,8 @@ impl<'cfg> RegistryIndex<'cfg> {
         // let root = self.config.assert_package_cache_locked(&self.path);
         let root = load.assert_index_locked(&self.path);
         let cache_root = root.join(".cache");
-        let index_version = load.current_version();
 
-        // See module comment in `registry/mod.rs` for why this is structured
-        // the way it is.
-        let fs_name = name
-            .chars()
-            .flat_map(|c| c.to_lowercase())
-            .collect::<String>();
-        let raw_path = make_dep_path(&fs_name, false);
+        let raw_path = index_file_path(&name);
 
         // Attempt to handle misspellings by searching for a chain of related
         // names to the original `raw_path` name. Only return summaries
@@ -382,7 +378,6 @@ impl<'cfg> RegistryIndex<'cfg> {
         // along the way produce helpful "did you mean?" suggestions.
         for path in UncanonicalizedIter::new(&raw_path).take(1024) {
             let summaries = Summaries::parse(
-                index_version.as_deref(),
                 root,
                 &cache_root,
                 path.as_ref(),
@@ -402,6 +397,48 @@ impl<'cfg> RegistryIndex<'cfg> {
         Ok(self.summaries_cache.get_mut(&name).unwrap())
     }
 
+    /// Walks the part of the dependency graph reachable from `dep` within
+    /// this registry, asking `load` to fetch each layer of index files in
+    /// one parallel batch.
+    ///
+    /// Only versions matching each requirement are followed. Optional and
+    /// target-specific dependencies are included since the resolver may need
+    /// them, but dev-dependencies of registry packages never are.
+    pub fn prefetch(&mut self, dep: &Dependency, load: &mut dyn RegistryData) -> CargoResult<()> {
+        let mut queue = Vec::new();
+        let key = (dep.package_name(), dep.version_req().clone());
+        if self.prefetched.insert(key.clone()) {
+            queue.push(key);
+        }
+        while !queue.is_empty() {
+            let paths = queue
+                .iter()
+                .filter(|(name, _)| !self.summaries_cache.contains_key(name))
+                .map(|(name, _)| PathBuf::from(index_file_path(name)))
+                .collect::<Vec<_>>();
+            load.prefetch(&paths)?;
+
+            let mut next = Vec::new();
+            for (name, req) in queue.drain(..) {
+                let deps = self
+                    .summaries(name, &req, load)?
+                    .flat_map(|s| s.summary.dependencies().iter().cloned())
+                    .collect::<Vec<_>>();
+                for dep in deps {
+                    if dep.source_id() != self.source_id || dep.kind() == DepKind::Development {
+                        continue;
+                    }
+                    let key = (dep.package_name(), dep.version_req().clone());
+                    if self.prefetched.insert(key.clone()) {
+                        next.push(key);
+                    }
+                }
+            }
+            queue = next;
+        }
+        Ok(())
+    }
+
     pub fn query_inner(
         &mut self,
         dep: &Dependency,
@@ -409,6 +446,7 @@ impl<'cfg> RegistryIndex<'cfg> {
         yanked_whitelist: &HashSet<PackageId>,
         f: &mut dyn FnMut(Summary),
     ) -> CargoResult<()> {
+        self.config.mark_cache_used(self.path.as_path_unlocked());
         if self.config.offline()
             && self.query_inner_with_online(dep, load, yanked_whitelist, f, false)? != 0
         {
@@ -492,6 +530,18 @@ impl<'cfg> RegistryIndex<'cfg> {
     }
 }
 
+/// Returns the path of the index file for the package `name`, relative to
+/// the root of the index.
+fn index_file_path(name: &str) -> String {
+    // See module comment in `registry/mod.rs` for why this is structured
+    // the way it is.
+    let fs_name = name
+        .chars()
+        .flat_map(|c| c.to_lowercase())
+        .collect::<String>();
+    make_dep_path(&fs_name, false)
+}
+
 impl Summaries {
     /// Parse out a `Summaries` instances from on-disk state.
     ///
@@ -502,9 +552,6 @@ impl Summaries {
     /// for `relative` from the underlying index (aka typically libgit2 with
     /// crates.io) and then parse everything in there.
     ///
-    /// * `index_version` - a version string to describe the current state of
-    ///   the index which for remote registries is the current git sha and
-    ///   for local registries is not available.
     /// * `root` - this is the root argument passed to `load`
     /// * `cache_root` - this is the root on the filesystem itself of where to
     ///   store cache files.
@@ -515,7 +562,6 @@ impl Summaries {
     /// * `load` - the actual index implementation which may be very slow to
     ///   call. We avoid this if we can.
     pub fn parse(
-        index_version: Option<&str>,
         root: &Path,
         cache_root: &Path,
         relative: &Path,
@@ -526,11 +572,16 @@ impl Summaries {
         // First up, attempt to load the cache. This could fail for all manner
         // of reasons, but consider all of them non-fatal and just log their
         // occurrence in case anyone is debugging anything.
+        //
+        // The version describes the current state of the index file, which
+        // for remote registries is the current git sha, for sparse registries
+        // comes from the HTTP headers of the file, and for local registries
+        // is not available.
         let cache_path = cache_root.join(relative);
         let mut cache_contents = None;
-        if let Some(index_version) = index_version {
+        if let Some(index_version) = load.file_version(root, relative) {
             match fs::read(&cache_path) {
-                Ok(contents) => match Summaries::parse_cache(contents, index_version) {
+                Ok(contents) => match Summaries::parse_cache(contents, &index_version) {
                     Ok(s) => {
                         log::debug!("fast path for registry cache of {:?}", relative);
                         if cfg!(debug_assertions) {
@@ -553,10 +604,9 @@ impl Summaries {
         log::debug!("slow path for {:?}", relative);
         let mut ret = Summaries::default();
         let mut hit_closure = false;
-        let mut cache_bytes = None;
+        let mut cache_lines = Vec::new();
         let err = load.load(root, relative, &mut |contents| {
             ret.raw_data = contents.to_vec();
-            let mut cache = SummariesCache::default();
             hit_closure = true;
             for line in split(contents, b'\n') {
                 // Attempt forwards-compatibility on the index by ignoring
@@ -580,12 +630,9 @@ impl Summaries {
                     }
                 };
                 let version = summary.summary.package_id().version().clone();
-                cache.versions.push((version.clone(), line));
+                cache_lines.push((version.clone(), line.to_vec()));
                 ret.versions.insert(version, summary.into());
             }
-            if let Some(index_version) = index_version {
-                cache_bytes = Some(cache.serialize(index_version));
-            }
             Ok(())
         });
 
@@ -598,6 +645,18 @@ impl Summaries {
         }
         err?;
 
+        // Loading may have fetched a new copy of the file, so its version is
+        // only known now.
+        let cache_bytes = load.file_version(root, relative).map(|index_version| {
+            let cache = SummariesCache {
+                versions: cache_lines
+                    .iter()
+                    .map(|(version, line)| (version.clone(), &line[..]))
+                    .collect(),
+            };
+            cache.serialize(&index_version)
+        });
+
         // If we've got debug assertions enabled and the cache was previously
         // present and considered fresh this is where the debug assertions
         // actually happens to verify that our cache is indeed fresh and
@@ -816,6 +875,7 @@ impl IndexSummary {
             features2,
             yanked,
             links,
+            rust_version,
             v,
         } = serde_json::from_slice(line)?;
         let v = v.unwrap_or(1);
@@ -832,6 +892,7 @@ impl IndexSummary {
         }
         let mut summary = Summary::new(config, pkgid, deps, &features, links)?;
         summary.set_checksum(cksum);
+        summary.set_rust_version(rust_version);
         Ok(IndexSummary {
             summary,
             yanked: yanked.unwrap_or(false),
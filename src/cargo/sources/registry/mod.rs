@@ -280,6 +280,11 @@ pub struct RegistryPackage<'a> {
     /// Added early 2018 (see <https://github.com/rust-lang/cargo/pull/4978>),
     /// can be `None` if published before then.
     links: Option<InternedString>,
+    /// The `rust-version` from the package's manifest.
+    ///
+    /// Can be `None` if the package doesn't declare one, or if it was
+    /// published by a Cargo that didn't send it to the registry.
+    rust_version: Option<InternedString>,
     /// The schema version for this entry.
     ///
     /// If this is None, it defaults to version 1. Entries with unknown
@@ -502,9 +507,36 @@ pub trait RegistryData {
     /// For local registries, this returns `None` because there is no
     /// versioning. For remote registries, this returns the SHA hash of the
     /// git index on disk (or None if the index hasn't been downloaded yet).
+    /// Sparse registries version each file separately, see
+    /// [`Self::file_version`], and also return `None`.
     ///
     /// This is used by index caching to check if the cache is out of date.
     fn current_version(&self) -> Option<InternedString>;
+
+    /// Returns the current "version" of the index file at `path`, or `None`
+    /// if it isn't known.
+    ///
+    /// Defaults to the [`Self::current_version`] of the whole index.
+    fn file_version(&self, _root: &Path, _path: &Path) -> Option<InternedString> {
+        self.current_version()
+    }
+
+    /// Returns whether this registry benefits from [`Self::prefetch`].
+    ///
+    /// Registries which already have the whole index on disk return `false`,
+    /// which lets [`RegistrySource`] skip walking the dependency graph.
+    fn supports_prefetch(&self) -> bool {
+        false
+    }
+
+    /// Fetches the index files at `paths` (relative to the index root) in
+    /// parallel, so that later calls to [`Self::load`] can be served from
+    /// disk.
+    ///
+    /// Files that are already up to date are skipped.
+    fn prefetch(&mut self, _paths: &[PathBuf]) -> CargoResult<()> {
+        Ok(())
+    }
 }
 
 /// The status of [`RegistryData::download`] which indicates if a `.crate`
@@ -520,6 +552,8 @@ pub enum MaybeLock {
     Download { url: String, descriptor: String },
 }
 
+mod download;
+mod http_remote;
 mod index;
 mod local;
 mod remote;
@@ -531,14 +565,26 @@ fn short_name(id: SourceId) -> String {
 }
 
 impl<'cfg> RegistrySource<'cfg> {
+    /// Creates a source for a remote registry, either backed by a git index
+    /// or by a sparse index fetched over HTTP depending on the URL.
     pub fn remote(
         source_id: SourceId,
         yanked_whitelist: &HashSet<PackageId>,
         config: &'cfg Config,
-    ) -> RegistrySource<'cfg> {
+    ) -> CargoResult<RegistrySource<'cfg>> {
         let name = short_name(source_id);
-        let ops = remote::RemoteRegistry::new(source_id, config, &name);
-        RegistrySource::new(source_id, config, &name, Box::new(ops), yanked_whitelist)
+        let ops: Box<dyn RegistryData + 'cfg> = if source_id.is_sparse() {
+            Box::new(http_remote::HttpRegistry::new(source_id, config, &name)?)
+        } else {
+            Box::new(remote::RemoteRegistry::new(source_id, config, &name))
+        };
+        Ok(RegistrySource::new(
+            source_id,
+            config,
+            &name,
+            ops,
+            yanked_whitelist,
+        ))
     }
 
     pub fn local(
@@ -658,10 +704,23 @@ impl<'cfg> RegistrySource<'cfg> {
         Ok(())
     }
 
+    /// Fetches the index entries that resolving `dep` may need.
+    ///
+    /// Registries which fetch index files lazily over the network would
+    /// otherwise issue one request at a time as the resolver walks the
+    /// graph, so this grabs everything up front in parallel.
+    fn prefetch(&mut self, dep: &Dependency) -> CargoResult<()> {
+        if self.ops.supports_prefetch() {
+            self.index.prefetch(dep, &mut *self.ops)?;
+        }
+        Ok(())
+    }
+
     fn get_pkg(&mut self, package: PackageId, path: &File) -> CargoResult<Package> {
         let path = self
             .unpack_package(package, path)
             .with_context(|| format!("failed to unpack package `{}`", package))?;
+        self.config.mark_cache_used(&path);
         let mut src = PathSource::new(&path, self.source_id, self.config);
         src.update()?;
         let mut pkg = match src.download(package)? {
@@ -690,6 +749,8 @@ impl<'cfg> RegistrySource<'cfg> {
 
 impl<'cfg> Source for RegistrySource<'cfg> {
     fn query(&mut self, dep: &Dependency, f: &mut dyn FnMut(Summary)) -> CargoResult<()> {
+        self.prefetch(dep)?;
+
         // If this is a precise dependency, then it came from a lock file and in
         // theory the registry is known to contain this version. If, however, we
         // come back with no summaries, then our registry may need to be
@@ -709,6 +770,7 @@ impl<'cfg> Source for RegistrySource<'cfg> {
             } else {
                 debug!("falling back to an update");
                 self.do_update()?;
+                self.prefetch(dep)?;
             }
         }
 
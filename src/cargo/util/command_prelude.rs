@@ -515,6 +515,8 @@ pub trait ArgMatchesExt {
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
             honor_rust_version: !self._is_present("ignore-rust-version"),
+            partition: None,
+            build_std_only: false,
         };
 
         if !opts.honor_rust_version {
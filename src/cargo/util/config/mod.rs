@@ -173,9 +173,15 @@ pub struct Config {
     upper_case_env: HashMap<String, String>,
     /// Tracks which sources have been updated to avoid multiple updates.
     updated_sources: LazyCell<RefCell<HashSet<SourceId>>>,
+    /// Git sources that a dependency requires to be signed
+    /// (`-Zgit=verify-signatures`).
+    signed_git_sources: LazyCell<RefCell<HashSet<SourceId>>>,
     /// Lock, if held, of the global package cache along with the number of
     /// acquisitions so far.
     package_cache_lock: RefCell<Option<(Option<FileLock>, usize)>>,
+    /// Paths in the global package cache used by this process which haven't
+    /// been saved to the last-use database yet (`-Z gc`).
+    cache_uses: RefCell<HashSet<PathBuf>>,
     /// Cached configuration parsed by Cargo
     http_config: LazyCell<CargoHttpConfig>,
     net_config: LazyCell<CargoNetConfig>,
@@ -273,7 +279,9 @@ impl Config {
             env,
             upper_case_env,
             updated_sources: LazyCell::new(),
+            signed_git_sources: LazyCell::new(),
             package_cache_lock: RefCell::new(None),
+            cache_uses: RefCell::new(HashSet::new()),
             http_config: LazyCell::new(),
             net_config: LazyCell::new(),
             build_config: LazyCell::new(),
@@ -421,6 +429,14 @@ impl Config {
             .borrow_mut()
     }
 
+    /// The git sources, without a precise revision, whose signatures a
+    /// dependency with `verify-signature = true` requires to be verified.
+    pub fn signed_git_sources(&self) -> RefMut<'_, HashSet<SourceId>> {
+        self.signed_git_sources
+            .borrow_with(|| RefCell::new(HashSet::new()))
+            .borrow_mut()
+    }
+
     /// Gets all config values from disk.
     ///
     /// This will lazy-load the values as necessary. Callers are responsible
@@ -1607,6 +1623,33 @@ impl Config {
     }
 
     pub fn release_package_cache_lock(&self) {}
+
+    /// Records that `path` in the global package cache was just used.
+    ///
+    /// With `-Z gc` this is saved to the last-use database when the package
+    /// cache lock is released, and is otherwise ignored. See
+    /// [`crate::core::global_cache_tracker`] for more.
+    pub fn mark_cache_used(&self, path: &Path) {
+        if self.cli_unstable().gc {
+            self.cache_uses.borrow_mut().insert(path.to_path_buf());
+        }
+    }
+
+    /// Saves the uses recorded with `mark_cache_used`, while the package
+    /// cache lock is still held.
+    fn save_cache_uses(&self) {
+        let uses = std::mem::take(&mut *self.cache_uses.borrow_mut());
+        if uses.is_empty() {
+            return;
+        }
+        if let Err(e) = crate::core::global_cache_tracker::save_uses(self, &uses) {
+            crate::display_warning_with_error(
+                "failed to save last-use data of the package cache",
+                &e,
+                &mut self.shell(),
+            );
+        }
+    }
 }
 
 /// Internal error for serde errors.
@@ -2011,6 +2054,10 @@ pub struct PackageCacheLock<'a>(&'a Config);
 
 impl Drop for PackageCacheLock<'_> {
     fn drop(&mut self) {
+        let releasing = matches!(*self.0.package_cache_lock.borrow(), Some((Some(_), 1)));
+        if releasing {
+            self.0.save_cache_uses();
+        }
         let mut slot = self.0.package_cache_lock.borrow_mut();
         let (_, cnt) = slot.as_mut().unwrap();
         *cnt -= 1;
@@ -2077,6 +2124,7 @@ pub struct CargoBuildConfig {
     pub incremental: Option<bool>,
     pub target: Option<ConfigRelativePath>,
     pub jobs: Option<u32>,
+    pub max_memory: Option<String>,
     pub rustflags: Option<StringList>,
     pub rustdocflags: Option<StringList>,
     pub rustc_wrapper: Option<ConfigRelativePath>,
@@ -2084,6 +2132,48 @@ pub struct CargoBuildConfig {
     pub rustc: Option<ConfigRelativePath>,
     pub rustdoc: Option<ConfigRelativePath>,
     pub out_dir: Option<ConfigRelativePath>,
+    pub shared_cache: Option<ConfigRelativePath>,
+    pub remote_cache: Option<PathAndArgs>,
+    pub allow_incomplete_sandbox: Option<bool>,
+}
+
+/// The `[git]` table, used with `-Zgit=verify-signatures` and `-Zgit=backend`.
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct CargoGitConfig {
+    /// Whether the signatures of all git dependencies are verified.
+    pub require_signed: Option<bool>,
+    /// The OpenPGP keys which may sign git dependencies.
+    pub keyring: Option<ConfigRelativePath>,
+    /// The SSH keys which may sign git dependencies, in the format of
+    /// `ssh-keygen`'s allowed signers files.
+    pub allowed_signers: Option<ConfigRelativePath>,
+    /// The backend doing the git operations, see `sources::git::backend`.
+    pub backend: Option<String>,
+}
+
+/// The `[build-std]` table, used with `-Zbuild-std`.
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct CargoBuildStdConfig {
+    pub crates: Option<StringList>,
+    pub features: Option<StringList>,
+    pub panic_strategy: Option<String>,
+    /// Whether to build the standard library once into a sysroot that is
+    /// reused by later builds, instead of as part of every build.
+    pub sysroot: Option<bool>,
+    /// The `[build-std.target.<triple>]` tables, overriding the settings
+    /// above for specific targets.
+    #[serde(default)]
+    pub target: HashMap<String, CargoBuildStdTargetConfig>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct CargoBuildStdTargetConfig {
+    pub crates: Option<StringList>,
+    pub features: Option<StringList>,
+    pub panic_strategy: Option<String>,
 }
 
 #[derive(Deserialize, Default)]
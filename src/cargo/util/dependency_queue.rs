@@ -145,6 +145,51 @@ impl<N: Hash + Eq + Clone, E: Eq + Hash + Clone, V> DependencyQueue<N, E, V> {
         }
     }
 
+    /// Like `queue_finished`, but prioritizes each node by the total cost of
+    /// the most expensive chain of nodes that starts with it, instead of the
+    /// total cost of all nodes that depend on it.
+    ///
+    /// If the costs are good estimates of how long nodes take to build, this
+    /// starts the nodes on the critical path first.
+    pub fn queue_finished_by_critical_path(&mut self) {
+        let mut out = HashMap::new();
+        for key in self.dep_map.keys() {
+            longest_path(key, &self.reverse_dep_map, &self.cost, &mut out);
+        }
+        self.priority = out
+            .into_iter()
+            .map(|(n, cost)| (n, cost.expect("cycle in DependencyQueue")))
+            .collect();
+
+        /// Finds the total cost of the most expensive chain of nodes starting
+        /// with `key`. A `None` marks the nodes being visited.
+        fn longest_path<N: Hash + Eq + Clone, E: Hash + Eq + Clone>(
+            key: &N,
+            map: &HashMap<N, HashMap<E, HashSet<N>>>,
+            cost: &HashMap<N, usize>,
+            results: &mut HashMap<N, Option<usize>>,
+        ) -> usize {
+            if let Some(total) = results.get(key) {
+                return total.expect("cycle in DependencyQueue");
+            }
+            results.insert(key.clone(), None);
+
+            let mut max = 0;
+            for dep in map
+                .get(key)
+                .into_iter()
+                .flat_map(|it| it.values())
+                .flatten()
+            {
+                max = max.max(longest_path(dep, map, cost, results));
+            }
+
+            let total = cost[key] + max;
+            results.insert(key.clone(), Some(total));
+            total
+        }
+    }
+
     /// Dequeues a package that is ready to be built.
     ///
     /// A package is ready to be built when it has 0 un-built dependencies. If
@@ -256,4 +301,33 @@ mod test {
         q.finish(&4, &());
         assert_eq!(q.dequeue(), None);
     }
+
+    #[test]
+    fn sort_by_critical_path() {
+        let mut q = DependencyQueue::new();
+
+        // 1 has more dependents, but 3 is on the most expensive chain.
+        q.queue(1, (), vec![], 1);
+        q.queue(2, (), vec![(1, ())], 1);
+        q.queue(3, (), vec![], 3);
+        q.queue(4, (), vec![(2, ()), (3, ())], 1);
+        for n in 5..9 {
+            q.queue(n, (), vec![(1, ())], 1);
+        }
+        q.queue_finished();
+        assert_eq!(q.dequeue(), Some((1, ())));
+
+        let mut q = DependencyQueue::new();
+        q.queue(1, (), vec![], 1);
+        q.queue(2, (), vec![(1, ())], 1);
+        q.queue(3, (), vec![], 3);
+        q.queue(4, (), vec![(2, ()), (3, ())], 1);
+        for n in 5..9 {
+            q.queue(n, (), vec![(1, ())], 1);
+        }
+        q.queue_finished_by_critical_path();
+        assert_eq!(q.dequeue(), Some((3, ())));
+        assert_eq!(q.dequeue(), Some((1, ())));
+        assert_eq!(q.dequeue(), None);
+    }
 }
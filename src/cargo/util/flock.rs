@@ -175,8 +175,22 @@ impl Filesystem {
             path.as_ref(),
             OpenOptions::new().read(true).write(true).create(true),
             State::Exclusive,
-            config,
-            msg,
+            Some((config, msg)),
+        )
+    }
+
+    /// Like `open_rw`, but for threads that don't have access to the
+    /// `Config`, such as the ones building units. Nothing is printed if the
+    /// process must block waiting for the lock.
+    pub fn open_rw_quiet<P>(&self, path: P) -> CargoResult<FileLock>
+    where
+        P: AsRef<Path>,
+    {
+        self.open(
+            path.as_ref(),
+            OpenOptions::new().read(true).write(true).create(true),
+            State::Exclusive,
+            None,
         )
     }
 
@@ -197,8 +211,7 @@ impl Filesystem {
             path.as_ref(),
             OpenOptions::new().read(true),
             State::Shared,
-            config,
-            msg,
+            Some((config, msg)),
         )
     }
 
@@ -207,8 +220,7 @@ impl Filesystem {
         path: &Path,
         opts: &OpenOptions,
         state: State,
-        config: &Config,
-        msg: &str,
+        report: Option<(&Config, &str)>,
     ) -> CargoResult<FileLock> {
         let path = self.root.join(path);
 
@@ -228,14 +240,12 @@ impl Filesystem {
             .with_context(|| format!("failed to open: {}", path.display()))?;
         match state {
             State::Exclusive => {
-                acquire(config, msg, &path, &|| try_lock_exclusive(&f), &|| {
+                acquire(report, &path, &|| try_lock_exclusive(&f), &|| {
                     lock_exclusive(&f)
                 })?;
             }
             State::Shared => {
-                acquire(config, msg, &path, &|| try_lock_shared(&f), &|| {
-                    lock_shared(&f)
-                })?;
+                acquire(report, &path, &|| try_lock_shared(&f), &|| lock_shared(&f))?;
             }
             State::Unlocked => {}
         }
@@ -269,14 +279,13 @@ impl PartialEq<Filesystem> for Path {
 /// This function will acquire the lock on a `path`, printing out a nice message
 /// to the console if we have to wait for it. It will first attempt to use `try`
 /// to acquire a lock on the crate, and in the case of contention it will emit a
-/// status message based on `msg` to `config`'s shell, and then use `block` to
-/// block waiting to acquire a lock.
+/// status message based on `msg` to `config`'s shell, if given in `report`,
+/// and then use `block` to block waiting to acquire a lock.
 ///
 /// Returns an error if the lock could not be acquired or if any error other
 /// than a contention error happens.
 fn acquire(
-    config: &Config,
-    msg: &str,
+    report: Option<(&Config, &str)>,
     path: &Path,
     lock_try: &dyn Fn() -> io::Result<()>,
     lock_block: &dyn Fn() -> io::Result<()>,
@@ -311,8 +320,10 @@ fn acquire(
             }
         }
     }
-    let msg = format!("waiting for file lock on {}", msg);
-    config.shell().status_with_color("Blocking", &msg, Cyan)?;
+    if let Some((config, msg)) = report {
+        let msg = format!("waiting for file lock on {}", msg);
+        config.shell().status_with_color("Blocking", &msg, Cyan)?;
+    }
 
     lock_block().with_context(|| format!("failed to lock file: {}", path.display()))?;
     return Ok(());
@@ -93,6 +93,60 @@ impl<'a> Message for TimingInfo<'a> {
     }
 }
 
+#[derive(Serialize)]
+pub struct DirtyUnit<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    pub mode: CompileMode,
+    /// A stable identifier of why the unit is rebuilt.
+    pub dirty_reason: &'static str,
+    /// The reason in a human-readable form.
+    pub message: String,
+}
+
+impl<'a> Message for DirtyUnit<'a> {
+    fn reason(&self) -> &str {
+        "dirty-unit"
+    }
+}
+
+#[derive(Serialize)]
+pub struct TestResult<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    /// Either `test` or `doctest`.
+    pub kind: &'static str,
+    pub name: &'a str,
+    /// One of `ok`, `failed` or `ignored`.
+    pub outcome: &'static str,
+    pub duration: Option<f64>,
+    pub stdout: Option<&'a str>,
+}
+
+impl<'a> Message for TestResult<'a> {
+    fn reason(&self) -> &str {
+        "test-result"
+    }
+}
+
+#[derive(Serialize)]
+pub struct TestSummary<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    pub kind: &'static str,
+    pub success: bool,
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub duration: f64,
+}
+
+impl<'a> Message for TestSummary<'a> {
+    fn reason(&self) -> &str {
+        "test-summary"
+    }
+}
+
 #[derive(Serialize)]
 pub struct BuildFinished {
     pub success: bool,
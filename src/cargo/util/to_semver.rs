@@ -31,3 +31,22 @@ impl<'a> ToSemver for &'a Version {
         Ok(self.clone())
     }
 }
+
+/// Parses a `rust-version` like `1.56` or `1.56.1` into the version it
+/// stands for, with the missing components being zero.
+pub fn parse_rust_version(rust_version: &str) -> CargoResult<Version> {
+    let mut parts = rust_version.trim().split('.');
+    let mut next = || -> CargoResult<u64> {
+        match parts.next() {
+            Some(part) => part.parse().map_err(|_| {
+                anyhow::format_err!("cannot parse '{}' as a rust-version", rust_version)
+            }),
+            None => Ok(0),
+        }
+    };
+    let version = Version::new(next()?, next()?, next()?);
+    if parts.next().is_some() {
+        anyhow::bail!("cannot parse '{}' as a rust-version", rust_version);
+    }
+    Ok(version)
+}
@@ -8,6 +8,7 @@ use std::str;
 use anyhow::{anyhow, bail, Context as _};
 use cargo_platform::Platform;
 use cargo_util::paths;
+use lazycell::LazyCell;
 use log::{debug, trace};
 use semver::{self, VersionReq};
 use serde::de;
@@ -17,8 +18,9 @@ use url::Url;
 
 use crate::core::compiler::{CompileKind, CompileTarget};
 use crate::core::dependency::DepKind;
-use crate::core::manifest::{ManifestMetadata, TargetSourcePath, Warnings};
+use crate::core::manifest::{BuildCapabilities, ManifestMetadata, TargetSourcePath, Warnings};
 use crate::core::resolver::ResolveBehavior;
+use crate::core::{find_root_iter, read_root_pointer};
 use crate::core::{Dependency, Manifest, PackageId, Summary, Target};
 use crate::core::{Edition, EitherManifest, Feature, Features, VirtualManifest, Workspace};
 use crate::core::{GitReference, PackageIdSpec, SourceId, WorkspaceConfig, WorkspaceRootConfig};
@@ -286,6 +288,13 @@ pub struct DetailedTomlDependency<P = String> {
     branch: Option<String>,
     tag: Option<String>,
     rev: Option<String>,
+    /// Any fully qualified reference of the `git` repository.
+    #[serde(rename = "ref")]
+    git_ref: Option<String>,
+    /// The directory of the package within the `git` repository.
+    subdir: Option<String>,
+    /// Whether the signature of the `git` commit or tag must be verified.
+    verify_signature: Option<bool>,
     features: Option<Vec<String>>,
     optional: Option<bool>,
     default_features: Option<bool>,
@@ -293,6 +302,8 @@ pub struct DetailedTomlDependency<P = String> {
     default_features2: Option<bool>,
     package: Option<String>,
     public: Option<bool>,
+    /// Inherit this dependency from `[workspace.dependencies]`.
+    workspace: Option<bool>,
 }
 
 // Explicit implementation so we avoid pulling in P: Default
@@ -307,12 +318,16 @@ impl<P> Default for DetailedTomlDependency<P> {
             branch: Default::default(),
             tag: Default::default(),
             rev: Default::default(),
+            git_ref: Default::default(),
+            subdir: Default::default(),
+            verify_signature: Default::default(),
             features: Default::default(),
             optional: Default::default(),
             default_features: Default::default(),
             default_features2: Default::default(),
             package: Default::default(),
             public: Default::default(),
+            workspace: Default::default(),
         }
     }
 }
@@ -813,12 +828,12 @@ where
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "kebab-case")]
 pub struct TomlProject {
-    edition: Option<String>,
-    rust_version: Option<String>,
+    edition: Option<MaybeWorkspace<String>>,
+    rust_version: Option<MaybeWorkspace<String>>,
     name: InternedString,
     #[serde(deserialize_with = "version_trim_whitespace")]
     version: semver::Version,
-    authors: Option<Vec<String>>,
+    authors: Option<MaybeWorkspace<Vec<String>>>,
     build: Option<StringOrBool>,
     metabuild: Option<StringOrVec>,
     #[serde(rename = "default-target")]
@@ -828,7 +843,7 @@ pub struct TomlProject {
     links: Option<String>,
     exclude: Option<Vec<String>>,
     include: Option<Vec<String>>,
-    publish: Option<VecStringOrBool>,
+    publish: Option<MaybeWorkspace<VecStringOrBool>>,
     workspace: Option<String>,
     im_a_teapot: Option<bool>,
     autobins: Option<bool>,
@@ -838,36 +853,395 @@ pub struct TomlProject {
     default_run: Option<String>,
 
     // Package metadata.
-    description: Option<String>,
-    homepage: Option<String>,
-    documentation: Option<String>,
+    description: Option<MaybeWorkspace<String>>,
+    homepage: Option<MaybeWorkspace<String>>,
+    documentation: Option<MaybeWorkspace<String>>,
     readme: Option<StringOrBool>,
-    keywords: Option<Vec<String>>,
-    categories: Option<Vec<String>>,
-    license: Option<String>,
+    keywords: Option<MaybeWorkspace<Vec<String>>>,
+    categories: Option<MaybeWorkspace<Vec<String>>>,
+    license: Option<MaybeWorkspace<String>>,
     license_file: Option<String>,
-    repository: Option<String>,
+    repository: Option<MaybeWorkspace<String>>,
     resolver: Option<String>,
+    build_capabilities: Option<TomlBuildCapabilities>,
 
     // Note that this field must come last due to the way toml serialization
     // works which requires tables to be emitted after all values.
     metadata: Option<toml::Value>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// The `[package.build-capabilities]` table, granting the build script more
+/// access in the build script sandbox.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TomlBuildCapabilities {
+    network: Option<bool>,
+    read: Option<Vec<String>>,
+    write: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TomlWorkspace {
     members: Option<Vec<String>>,
     #[serde(rename = "default-members")]
     default_members: Option<Vec<String>>,
     exclude: Option<Vec<String>>,
     resolver: Option<String>,
+    /// Package fields that members can inherit with `field.workspace = true`.
+    package: Option<TomlWorkspacePackage>,
+    /// Dependencies that members can inherit with `workspace = true`.
+    dependencies: Option<BTreeMap<String, TomlDependency>>,
 
     // Note that this field must come last due to the way toml serialization
     // works which requires tables to be emitted after all values.
     metadata: Option<toml::Value>,
 }
 
+/// The `[workspace.package]` table, holding the values of package fields
+/// that members can inherit.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TomlWorkspacePackage {
+    edition: Option<String>,
+    rust_version: Option<String>,
+    authors: Option<Vec<String>>,
+    publish: Option<VecStringOrBool>,
+    description: Option<String>,
+    homepage: Option<String>,
+    documentation: Option<String>,
+    keywords: Option<Vec<String>>,
+    categories: Option<Vec<String>>,
+    license: Option<String>,
+    repository: Option<String>,
+}
+
+/// A package field that is either given directly, or inherited from the
+/// workspace root with `field.workspace = true`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum MaybeWorkspace<T> {
+    Defined(T),
+    Workspace(TomlWorkspaceField),
+}
+
+/// The `{ workspace = true }` marker of an inherited package field.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TomlWorkspaceField {
+    workspace: bool,
+}
+
+impl<'de, T: Deserialize<'de>> de::Deserialize<'de> for MaybeWorkspace<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct MaybeWorkspaceVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> de::Visitor<'de> for MaybeWorkspaceVisitor<T> {
+            type Value = MaybeWorkspace<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a value or `{ workspace = true }`")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                T::deserialize(de::value::StrDeserializer::new(s)).map(MaybeWorkspace::Defined)
+            }
+
+            fn visit_bool<E>(self, b: bool) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                T::deserialize(de::value::BoolDeserializer::new(b)).map(MaybeWorkspace::Defined)
+            }
+
+            fn visit_seq<V>(self, seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                let seq = de::value::SeqAccessDeserializer::new(seq);
+                T::deserialize(seq).map(MaybeWorkspace::Defined)
+            }
+
+            fn visit_map<V>(self, map: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::MapAccess<'de>,
+            {
+                let map = de::value::MapAccessDeserializer::new(map);
+                TomlWorkspaceField::deserialize(map).map(MaybeWorkspace::Workspace)
+            }
+        }
+
+        deserializer.deserialize_any(MaybeWorkspaceVisitor(PhantomData))
+    }
+}
+
+impl<T> MaybeWorkspace<T> {
+    /// Returns the value of the field, looking it up in the workspace with
+    /// `get_ws_inheritable` if it is inherited.
+    fn resolve(
+        self,
+        label: &str,
+        get_ws_inheritable: impl FnOnce() -> CargoResult<T>,
+    ) -> CargoResult<T> {
+        match self {
+            MaybeWorkspace::Defined(value) => Ok(value),
+            MaybeWorkspace::Workspace(TomlWorkspaceField { workspace: true }) => {
+                get_ws_inheritable().with_context(|| {
+                    format!(
+                        "error inheriting `{}` from workspace root manifest's `workspace.package.{}`",
+                        label, label
+                    )
+                })
+            }
+            MaybeWorkspace::Workspace(TomlWorkspaceField { workspace: false }) => {
+                bail!("`workspace = false` is unsupported for `package.{}`", label)
+            }
+        }
+    }
+
+    /// Returns the value if it was given directly.
+    fn as_defined(&self) -> Option<&T> {
+        match self {
+            MaybeWorkspace::Defined(value) => Some(value),
+            MaybeWorkspace::Workspace(_) => None,
+        }
+    }
+}
+
+/// The values a workspace member can inherit, as found in the root
+/// manifest of its workspace.
+struct InheritableFields {
+    package: Option<TomlWorkspacePackage>,
+    dependencies: Option<BTreeMap<String, TomlDependency>>,
+    /// The directory of the workspace root manifest.
+    ws_root: PathBuf,
+}
+
+impl InheritableFields {
+    fn new(workspace: &TomlWorkspace, ws_root: &Path) -> InheritableFields {
+        InheritableFields {
+            package: workspace.package.clone(),
+            dependencies: workspace.dependencies.clone(),
+            ws_root: ws_root.to_path_buf(),
+        }
+    }
+
+    /// Finds the workspace root of the package at `package_root` and reads
+    /// what it offers for inheritance.
+    fn load(
+        me: &TomlManifest,
+        project: &TomlProject,
+        package_root: &Path,
+        config: &Config,
+    ) -> CargoResult<InheritableFields> {
+        if let Some(workspace) = &me.workspace {
+            return Ok(InheritableFields::new(workspace, package_root));
+        }
+        let manifest_path = package_root.join("Cargo.toml");
+        match find_workspace_root(&manifest_path, project.workspace.as_deref(), config)? {
+            Some((root_path, workspace)) => Ok(InheritableFields::new(
+                &workspace,
+                root_path.parent().unwrap(),
+            )),
+            None => bail!(
+                "failed to find a workspace root for `{}` to inherit from",
+                manifest_path.display()
+            ),
+        }
+    }
+
+    fn package_field<T: Clone>(
+        &self,
+        label: &str,
+        get: impl FnOnce(&TomlWorkspacePackage) -> &Option<T>,
+    ) -> CargoResult<T> {
+        self.package
+            .as_ref()
+            .and_then(|package| get(package).clone())
+            .ok_or_else(|| anyhow!("`workspace.package.{}` was not defined", label))
+    }
+
+    /// Merges the member's `{ workspace = true, ... }` entry for `name` with
+    /// the one in `[workspace.dependencies]`.
+    fn dependency(
+        &self,
+        name: &str,
+        member: &DetailedTomlDependency,
+    ) -> CargoResult<TomlDependency> {
+        let ws_dep = self
+            .dependencies
+            .as_ref()
+            .and_then(|deps| deps.get(name))
+            .ok_or_else(|| {
+                anyhow!(
+                    "dependency `{}` was not found in `workspace.dependencies`",
+                    name
+                )
+            })?;
+        let mut dep = match ws_dep {
+            TomlDependency::Simple(version) => DetailedTomlDependency {
+                version: Some(version.clone()),
+                ..Default::default()
+            },
+            TomlDependency::Detailed(d) => d.clone(),
+        };
+        // Paths in the workspace root are relative to the root.
+        if let Some(path) = &dep.path {
+            let path = paths::normalize_path(&self.ws_root.join(path));
+            dep.path = Some(path.to_string_lossy().into_owned());
+        }
+        if let Some(features) = &member.features {
+            let all = dep.features.get_or_insert_with(Vec::new);
+            for feature in features {
+                if !all.contains(feature) {
+                    all.push(feature.clone());
+                }
+            }
+        }
+        dep.optional = member.optional;
+        Ok(TomlDependency::Detailed(dep))
+    }
+}
+
+/// Looks for the workspace root of the package whose manifest is at
+/// `manifest_path`, without loading any of the manifests on the way as
+/// packages, since the member's own manifest is still being read. Otherwise
+/// this follows the same rules as `Workspace::find_root`.
+///
+/// Returns the path of the root manifest and its `[workspace]` table.
+fn find_workspace_root(
+    manifest_path: &Path,
+    root_link: Option<&str>,
+    config: &Config,
+) -> CargoResult<Option<(PathBuf, TomlWorkspace)>> {
+    #[derive(Deserialize)]
+    struct WorkspaceProbe {
+        workspace: Option<TomlWorkspace>,
+        package: Option<PackageProbe>,
+        project: Option<PackageProbe>,
+    }
+
+    #[derive(Deserialize)]
+    struct PackageProbe {
+        workspace: Option<String>,
+    }
+
+    fn probe(path: &Path, config: &Config) -> CargoResult<WorkspaceProbe> {
+        let contents = paths::read(path)?;
+        let toml = parse(&contents, path, config)?;
+        WorkspaceProbe::deserialize(toml)
+            .with_context(|| format!("failed to parse manifest at `{}`", path.display()))
+    }
+
+    fn follow_link(
+        member_manifest: &Path,
+        root_link: &str,
+        config: &Config,
+    ) -> CargoResult<Option<(PathBuf, TomlWorkspace)>> {
+        let path = read_root_pointer(member_manifest, root_link);
+        Ok(probe(&path, config)?.workspace.map(|ws| (path, ws)))
+    }
+
+    if let Some(root_link) = root_link {
+        return follow_link(manifest_path, root_link, config);
+    }
+
+    for ances_manifest_path in find_root_iter(manifest_path, config) {
+        let found = probe(&ances_manifest_path, config)?;
+        if let Some(workspace) = found.workspace {
+            let root_config = WorkspaceRootConfig::new(
+                ances_manifest_path.parent().unwrap(),
+                &workspace.members,
+                &workspace.default_members,
+                &workspace.exclude,
+                &None,
+            );
+            if !root_config.is_excluded(manifest_path) {
+                return Ok(Some((ances_manifest_path, workspace)));
+            }
+        } else if let Some(root_link) = found
+            .package
+            .or(found.project)
+            .and_then(|package| package.workspace)
+        {
+            return follow_link(&ances_manifest_path, &root_link, config);
+        }
+    }
+
+    Ok(None)
+}
+
+impl TomlWorkspace {
+    /// Checks the parts of `[workspace]` that members inherit from.
+    fn validate(&self, features: &Features) -> CargoResult<()> {
+        if self.package.is_some() || self.dependencies.is_some() {
+            features.require(Feature::workspace_inheritance())?;
+        }
+        for (name, dep) in self.dependencies.iter().flatten() {
+            if let TomlDependency::Detailed(d) = dep {
+                if d.workspace.is_some() {
+                    bail!(
+                        "`workspace.dependencies.{}` cannot itself inherit from the workspace",
+                        name
+                    );
+                }
+                if d.optional.is_some() {
+                    bail!(
+                        "`workspace.dependencies.{}` cannot specify `optional`, \
+                         set it on the member's dependency instead",
+                        name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl TomlProject {
+    /// Returns a copy of this `[package]` table with every inherited field
+    /// replaced by the value from the workspace.
+    fn resolve_inherited<'a>(
+        &self,
+        inherit: &dyn Fn() -> CargoResult<&'a InheritableFields>,
+    ) -> CargoResult<TomlProject> {
+        fn resolve<'a, T: Clone>(
+            field: &Option<MaybeWorkspace<T>>,
+            label: &str,
+            inherit: &dyn Fn() -> CargoResult<&'a InheritableFields>,
+            get: impl FnOnce(&TomlWorkspacePackage) -> &Option<T>,
+        ) -> CargoResult<Option<MaybeWorkspace<T>>> {
+            field
+                .clone()
+                .map(|field| field.resolve(label, || inherit()?.package_field(label, get)))
+                .transpose()
+                .map(|value| value.map(MaybeWorkspace::Defined))
+        }
+
+        let mut project = self.clone();
+        project.edition = resolve(&self.edition, "edition", inherit, |p| &p.edition)?;
+        project.rust_version = resolve(&self.rust_version, "rust-version", inherit, |p| {
+            &p.rust_version
+        })?;
+        project.authors = resolve(&self.authors, "authors", inherit, |p| &p.authors)?;
+        project.publish = resolve(&self.publish, "publish", inherit, |p| &p.publish)?;
+        project.description = resolve(&self.description, "description", inherit, |p| {
+            &p.description
+        })?;
+        project.homepage = resolve(&self.homepage, "homepage", inherit, |p| &p.homepage)?;
+        project.documentation = resolve(&self.documentation, "documentation", inherit, |p| {
+            &p.documentation
+        })?;
+        project.keywords = resolve(&self.keywords, "keywords", inherit, |p| &p.keywords)?;
+        project.categories = resolve(&self.categories, "categories", inherit, |p| &p.categories)?;
+        project.license = resolve(&self.license, "license", inherit, |p| &p.license)?;
+        project.repository = resolve(&self.repository, "repository", inherit, |p| &p.repository)?;
+        Ok(project)
+    }
+
     pub fn to_package_id(&self, source_id: SourceId) -> CargoResult<PackageId> {
         PackageId::new(self.name, self.version.clone(), source_id)
     }
@@ -1014,6 +1388,9 @@ impl TomlManifest {
                     d.branch.take();
                     d.tag.take();
                     d.rev.take();
+                    d.git_ref.take();
+                    d.subdir.take();
+                    d.verify_signature.take();
                     // registry specifications are elaborated to the index URL
                     if let Some(registry) = d.registry.take() {
                         let src = SourceId::alt_registry(config, &registry)?;
@@ -1047,6 +1424,19 @@ impl TomlManifest {
         let project = me.project.as_ref().or_else(|| me.package.as_ref());
         let project = project.ok_or_else(|| anyhow!("no `package` section found"))?;
 
+        if let Some(workspace) = &me.workspace {
+            workspace.validate(&features)?;
+        }
+        // The workspace root is only looked up if something is inherited.
+        let inheritable = LazyCell::new();
+        let inherit = || {
+            inheritable.try_borrow_with(|| {
+                features.require(Feature::workspace_inheritance())?;
+                InheritableFields::load(me, project, package_root, config)
+            })
+        };
+        let project = &project.resolve_inherited(&inherit)?;
+
         let package_name = project.name.trim();
         if package_name.is_empty() {
             bail!("package name cannot be an empty string")
@@ -1056,7 +1446,7 @@ impl TomlManifest {
 
         let pkgid = project.to_package_id(source_id)?;
 
-        let edition = if let Some(ref edition) = project.edition {
+        let edition = if let Some(edition) = project.edition.as_ref().and_then(|e| e.as_defined()) {
             features
                 .require(Feature::edition())
                 .with_context(|| "editions are unstable")?;
@@ -1076,7 +1466,9 @@ impl TomlManifest {
             )));
         }
 
-        let rust_version = if let Some(rust_version) = &project.rust_version {
+        let rust_version = if let Some(rust_version) =
+            project.rust_version.as_ref().and_then(|r| r.as_defined())
+        {
             if features.require(Feature::rust_version()).is_err() {
                 let mut msg =
                     "`rust-version` is not supported on this version of Cargo and will be ignored"
@@ -1125,6 +1517,24 @@ impl TomlManifest {
             features.require(Feature::metabuild())?;
         }
 
+        let build_capabilities = match &project.build_capabilities {
+            Some(capabilities) => {
+                features.require(Feature::build_capabilities())?;
+                let paths = |key: &str, list: &Option<Vec<String>>| {
+                    list.iter()
+                        .flatten()
+                        .map(|path| build_capability_path(package_root, key, path))
+                        .collect::<CargoResult<Vec<_>>>()
+                };
+                BuildCapabilities {
+                    network: capabilities.network.unwrap_or(false),
+                    read: paths("read", &capabilities.read)?,
+                    write: paths("write", &capabilities.write)?,
+                }
+            }
+            None => BuildCapabilities::default(),
+        };
+
         if project.resolver.is_some()
             || me
                 .workspace
@@ -1182,6 +1592,54 @@ impl TomlManifest {
             }
         }
 
+        let dependencies = resolve_dependencies(me.dependencies.as_ref(), &inherit)?;
+        let dev_dependencies = resolve_dependencies(
+            me.dev_dependencies
+                .as_ref()
+                .or_else(|| me.dev_dependencies2.as_ref()),
+            &inherit,
+        )?;
+        let build_dependencies = resolve_dependencies(
+            me.build_dependencies
+                .as_ref()
+                .or_else(|| me.build_dependencies2.as_ref()),
+            &inherit,
+        )?;
+        let target = me
+            .target
+            .as_ref()
+            .map(|target| {
+                target
+                    .iter()
+                    .map(|(name, platform)| {
+                        let platform = TomlPlatform {
+                            dependencies: resolve_dependencies(
+                                platform.dependencies.as_ref(),
+                                &inherit,
+                            )?,
+                            build_dependencies: resolve_dependencies(
+                                platform
+                                    .build_dependencies
+                                    .as_ref()
+                                    .or_else(|| platform.build_dependencies2.as_ref()),
+                                &inherit,
+                            )?,
+                            build_dependencies2: None,
+                            dev_dependencies: resolve_dependencies(
+                                platform
+                                    .dev_dependencies
+                                    .as_ref()
+                                    .or_else(|| platform.dev_dependencies2.as_ref()),
+                                &inherit,
+                            )?,
+                            dev_dependencies2: None,
+                        };
+                        Ok((name.clone(), platform))
+                    })
+                    .collect::<CargoResult<BTreeMap<_, _>>>()
+            })
+            .transpose()?;
+
         let mut deps = Vec::new();
         let replace;
         let patch;
@@ -1217,35 +1675,31 @@ impl TomlManifest {
             }
 
             // Collect the dependencies.
-            process_dependencies(&mut cx, me.dependencies.as_ref(), None)?;
-            let dev_deps = me
-                .dev_dependencies
-                .as_ref()
-                .or_else(|| me.dev_dependencies2.as_ref());
-            process_dependencies(&mut cx, dev_deps, Some(DepKind::Development))?;
-            let build_deps = me
-                .build_dependencies
-                .as_ref()
-                .or_else(|| me.build_dependencies2.as_ref());
-            process_dependencies(&mut cx, build_deps, Some(DepKind::Build))?;
-
-            for (name, platform) in me.target.iter().flatten() {
+            process_dependencies(&mut cx, dependencies.as_ref(), None)?;
+            process_dependencies(
+                &mut cx,
+                dev_dependencies.as_ref(),
+                Some(DepKind::Development),
+            )?;
+            process_dependencies(&mut cx, build_dependencies.as_ref(), Some(DepKind::Build))?;
+
+            for (name, platform) in target.iter().flatten() {
                 cx.platform = {
                     let platform: Platform = name.parse()?;
                     platform.check_cfg_attributes(&mut cx.warnings);
                     Some(platform)
                 };
                 process_dependencies(&mut cx, platform.dependencies.as_ref(), None)?;
-                let build_deps = platform
-                    .build_dependencies
-                    .as_ref()
-                    .or_else(|| platform.build_dependencies2.as_ref());
-                process_dependencies(&mut cx, build_deps, Some(DepKind::Build))?;
-                let dev_deps = platform
-                    .dev_dependencies
-                    .as_ref()
-                    .or_else(|| platform.dev_dependencies2.as_ref());
-                process_dependencies(&mut cx, dev_deps, Some(DepKind::Development))?;
+                process_dependencies(
+                    &mut cx,
+                    platform.build_dependencies.as_ref(),
+                    Some(DepKind::Build),
+                )?;
+                process_dependencies(
+                    &mut cx,
+                    platform.dev_dependencies.as_ref(),
+                    Some(DepKind::Development),
+                )?;
             }
 
             replace = me.replace(&mut cx)?;
@@ -1272,27 +1726,28 @@ impl TomlManifest {
         let include = project.include.clone().unwrap_or_default();
         let empty_features = BTreeMap::new();
 
-        let summary = Summary::new(
+        let mut summary = Summary::new(
             config,
             pkgid,
             deps,
             me.features.as_ref().unwrap_or(&empty_features),
             project.links.as_deref(),
         )?;
+        summary.set_rust_version(rust_version.as_deref().map(InternedString::new));
         let unstable = config.cli_unstable();
         summary.unstable_gate(unstable.namespaced_features, unstable.weak_dep_features)?;
 
         let metadata = ManifestMetadata {
-            description: project.description.clone(),
-            homepage: project.homepage.clone(),
-            documentation: project.documentation.clone(),
+            description: defined(&project.description),
+            homepage: defined(&project.homepage),
+            documentation: defined(&project.documentation),
             readme: readme_for_project(package_root, project),
-            authors: project.authors.clone().unwrap_or_default(),
-            license: project.license.clone(),
+            authors: defined(&project.authors).unwrap_or_default(),
+            license: defined(&project.license),
             license_file: project.license_file.clone(),
-            repository: project.repository.clone(),
-            keywords: project.keywords.clone().unwrap_or_default(),
-            categories: project.categories.clone().unwrap_or_default(),
+            repository: defined(&project.repository),
+            keywords: defined(&project.keywords).unwrap_or_default(),
+            categories: defined(&project.categories).unwrap_or_default(),
             badges: me.badges.clone().unwrap_or_default(),
             links: project.links.clone(),
         };
@@ -1317,8 +1772,8 @@ impl TomlManifest {
         if let Some(profiles) = &profiles {
             profiles.validate(&features, &mut warnings)?;
         }
-        let publish = match project.publish {
-            Some(VecStringOrBool::VecString(ref vecstring)) => Some(vecstring.clone()),
+        let publish = match defined(&project.publish) {
+            Some(VecStringOrBool::VecString(vecstring)) => Some(vecstring),
             Some(VecStringOrBool::Bool(false)) => Some(vec![]),
             None | Some(VecStringOrBool::Bool(true)) => None,
         };
@@ -1357,6 +1812,30 @@ impl TomlManifest {
             .map(CompileKind::Target);
 
         let custom_metadata = project.metadata.clone();
+        // The manifest with everything inherited filled in, which is what
+        // `cargo package` publishes.
+        let resolved_toml = TomlManifest {
+            cargo_features: me.cargo_features.clone(),
+            package: Some(Box::new(project.clone())),
+            project: None,
+            profile: me.profile.clone(),
+            lib: me.lib.clone(),
+            bin: me.bin.clone(),
+            example: me.example.clone(),
+            test: me.test.clone(),
+            bench: me.bench.clone(),
+            dependencies,
+            dev_dependencies,
+            dev_dependencies2: None,
+            build_dependencies,
+            build_dependencies2: None,
+            features: me.features.clone(),
+            target,
+            replace: me.replace.clone(),
+            patch: me.patch.clone(),
+            workspace: me.workspace.clone(),
+            badges: me.badges.clone(),
+        };
         let mut manifest = Manifest::new(
             summary,
             default_kind,
@@ -1377,9 +1856,10 @@ impl TomlManifest {
             rust_version,
             project.im_a_teapot,
             project.default_run.clone(),
-            Rc::clone(me),
+            Rc::new(resolved_toml),
             project.metabuild.clone().map(|sov| sov.0),
             resolve_behavior,
+            build_capabilities,
         );
         if project.license_file.is_some() && project.license.is_some() {
             manifest.warnings_mut().add_warning(
@@ -1477,6 +1957,9 @@ impl TomlManifest {
         {
             features.require(Feature::resolver())?;
         }
+        if let Some(workspace) = &me.workspace {
+            workspace.validate(&features)?;
+        }
         let resolve_behavior = me
             .workspace
             .as_ref()
@@ -1605,6 +2088,50 @@ impl TomlManifest {
     }
 }
 
+/// Returns the value of a package field that has been through
+/// [`TomlProject::resolve_inherited`].
+fn defined<T: Clone>(field: &Option<MaybeWorkspace<T>>) -> Option<T> {
+    field.as_ref().and_then(MaybeWorkspace::as_defined).cloned()
+}
+
+/// Replaces every `workspace = true` dependency in `deps` with the entry it
+/// inherits from `[workspace.dependencies]`.
+fn resolve_dependencies<'a>(
+    deps: Option<&BTreeMap<String, TomlDependency>>,
+    inherit: &dyn Fn() -> CargoResult<&'a InheritableFields>,
+) -> CargoResult<Option<BTreeMap<String, TomlDependency>>> {
+    let deps = match deps {
+        Some(deps) => deps,
+        None => return Ok(None),
+    };
+    let deps = deps
+        .iter()
+        .map(|(name, dep)| {
+            let dep = match dep {
+                TomlDependency::Detailed(d) if d.workspace == Some(true) => {
+                    d.check_inherited(name)?;
+                    inherit()?.dependency(name, d).with_context(|| {
+                        format!(
+                            "error inheriting `{}` from workspace root manifest's \
+                             `workspace.dependencies.{}`",
+                            name, name
+                        )
+                    })?
+                }
+                TomlDependency::Detailed(d) if d.workspace == Some(false) => {
+                    bail!(
+                        "`workspace = false` is unsupported for dependency `{}`",
+                        name
+                    )
+                }
+                dep => dep.clone(),
+            };
+            Ok((name.clone(), dep))
+        })
+        .collect::<CargoResult<BTreeMap<_, _>>>()?;
+    Ok(Some(deps))
+}
+
 /// Returns the name of the README file for a `TomlProject`.
 fn readme_for_project(package_root: &Path, project: &TomlProject) -> Option<String> {
     match &project.readme {
@@ -1699,6 +2226,41 @@ impl<P: ResolveToPath> TomlDependency<P> {
     }
 }
 
+impl<P> DetailedTomlDependency<P> {
+    /// Checks that a `workspace = true` dependency only sets the keys that
+    /// can be combined with inheritance.
+    fn check_inherited(&self, name: &str) -> CargoResult<()> {
+        let conflicting = [
+            ("version", self.version.is_some()),
+            ("registry", self.registry.is_some()),
+            ("registry-index", self.registry_index.is_some()),
+            ("path", self.path.is_some()),
+            ("git", self.git.is_some()),
+            ("branch", self.branch.is_some()),
+            ("tag", self.tag.is_some()),
+            ("rev", self.rev.is_some()),
+            ("ref", self.git_ref.is_some()),
+            ("subdir", self.subdir.is_some()),
+            ("verify-signature", self.verify_signature.is_some()),
+            (
+                "default-features",
+                self.default_features.is_some() || self.default_features2.is_some(),
+            ),
+            ("package", self.package.is_some()),
+            ("public", self.public.is_some()),
+        ];
+        if let Some((key, _)) = conflicting.iter().find(|(_, set)| *set) {
+            bail!(
+                "dependency `{}` cannot specify `{}` along with `workspace = true`, \
+                 only `features` and `optional` may be combined with it",
+                name,
+                key
+            );
+        }
+        Ok(())
+    }
+}
+
 impl<P: ResolveToPath> DetailedTomlDependency<P> {
     fn to_dependency(
         &self,
@@ -1706,6 +2268,14 @@ impl<P: ResolveToPath> DetailedTomlDependency<P> {
         cx: &mut Context<'_, '_>,
         kind: Option<DepKind>,
     ) -> CargoResult<Dependency> {
+        if self.workspace.is_some() {
+            bail!(
+                "dependency ({}) specifies `workspace`, which is only supported \
+                 in the dependency tables of a package",
+                name_in_toml
+            );
+        }
+
         if self.version.is_none() && self.path.is_none() && self.git.is_none() {
             let msg = format!(
                 "dependency ({}) specified without \
@@ -1730,13 +2300,16 @@ impl<P: ResolveToPath> DetailedTomlDependency<P> {
 
         if self.git.is_none() {
             let git_only_keys = [
-                (&self.branch, "branch"),
-                (&self.tag, "tag"),
-                (&self.rev, "rev"),
+                (self.branch.is_some(), "branch"),
+                (self.tag.is_some(), "tag"),
+                (self.rev.is_some(), "rev"),
+                (self.git_ref.is_some(), "ref"),
+                (self.subdir.is_some(), "subdir"),
+                (self.verify_signature.is_some(), "verify-signature"),
             ];
 
             for &(key, key_name) in &git_only_keys {
-                if key.is_some() {
+                if key {
                     let msg = format!(
                         "key `{}` is ignored for dependency ({}). \
                          This will be considered an error in future versions",
@@ -1803,7 +2376,7 @@ impl<P: ResolveToPath> DetailedTomlDependency<P> {
                     cx.warnings.push(msg)
                 }
 
-                let n_details = [&self.branch, &self.tag, &self.rev]
+                let n_details = [&self.branch, &self.tag, &self.rev, &self.git_ref]
                     .iter()
                     .filter(|d| d.is_some())
                     .count();
@@ -1811,17 +2384,35 @@ impl<P: ResolveToPath> DetailedTomlDependency<P> {
                 if n_details > 1 {
                     bail!(
                         "dependency ({}) specification is ambiguous. \
-                         Only one of `branch`, `tag` or `rev` is allowed.",
+                         Only one of `branch`, `tag`, `rev` or `ref` is allowed.",
                         name_in_toml
                     );
                 }
 
+                if let Some(git_ref) = &self.git_ref {
+                    if !cx.config.cli_unstable().git_option("refs") {
+                        bail!(
+                            "the `ref` key of dependency `{}` requires `-Zgit=refs`",
+                            name_in_toml
+                        );
+                    }
+                    if !git_ref.starts_with("refs/") {
+                        bail!(
+                            "the `ref` of dependency `{}` must be a fully qualified \
+                             reference starting with `refs/`, found `{}`",
+                            name_in_toml,
+                            git_ref
+                        );
+                    }
+                }
+
                 let reference = self
                     .branch
                     .clone()
                     .map(GitReference::Branch)
                     .or_else(|| self.tag.clone().map(GitReference::Tag))
                     .or_else(|| self.rev.clone().map(GitReference::Rev))
+                    .or_else(|| self.git_ref.clone().map(GitReference::Ref))
                     .unwrap_or(GitReference::DefaultBranch);
                 let loc = git.into_url()?;
 
@@ -1889,6 +2480,31 @@ impl<P: ResolveToPath> DetailedTomlDependency<P> {
             dep.set_registry_id(registry_id);
         }
 
+        if let (Some(subdir), Some(_)) = (&self.subdir, &self.git) {
+            if !cx.config.cli_unstable().git_option("sparse-checkout") {
+                bail!(
+                    "the `subdir` key of dependency `{}` requires `-Zgit=sparse-checkout`",
+                    name_in_toml
+                );
+            }
+            dep.set_git_subdir(normalize_git_subdir(subdir, name_in_toml)?);
+        }
+        if let (Some(verify), Some(_)) = (self.verify_signature, &self.git) {
+            if !cx.config.cli_unstable().git_option("verify-signatures") {
+                bail!(
+                    "the `verify-signature` key of dependency `{}` requires \
+                     `-Zgit=verify-signatures`",
+                    name_in_toml
+                );
+            }
+            dep.set_verify_git_signature(verify);
+            if verify {
+                // Verified as soon as the source is updated.
+                cx.config
+                    .signed_git_sources()
+                    .insert(dep.source_id().with_precise(None));
+            }
+        }
         if let Some(kind) = kind {
             dep.set_kind(kind);
         }
@@ -1910,6 +2526,50 @@ impl<P: ResolveToPath> DetailedTomlDependency<P> {
     }
 }
 
+/// Checks that the `subdir` of a git dependency stays within the repository,
+/// and returns it with `/` separators and without `.` components.
+fn normalize_git_subdir(subdir: &str, name_in_toml: &str) -> CargoResult<String> {
+    let parts: Vec<&str> = subdir
+        .split(|c| c == '/' || c == '\\')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect();
+    if subdir.starts_with(|c| c == '/' || c == '\\')
+        || Path::new(subdir).is_absolute()
+        || parts.contains(&"..")
+    {
+        bail!(
+            "the `subdir` of dependency `{}` must be a relative path within the \
+             repository, found `{}`",
+            name_in_toml,
+            subdir
+        );
+    }
+    Ok(parts.join("/"))
+}
+
+/// Checks that a `read` or `write` path of `[package.build-capabilities]`
+/// stays within the package, and returns it joined to `package_root`. Paths
+/// outside of the package can only be granted in cargo config.
+fn build_capability_path(package_root: &Path, key: &str, path: &str) -> CargoResult<PathBuf> {
+    let package_root = paths::normalize_path(package_root);
+    let full = paths::normalize_path(&package_root.join(path));
+    if path.starts_with(|c| c == '/' || c == '\\')
+        || Path::new(path).is_absolute()
+        || !full.starts_with(&package_root)
+    {
+        bail!(
+            "the `{}` paths of `[package.build-capabilities]` must be relative paths \
+             within the package, found `{}`\n\
+             Paths outside of the package may be granted with `build.sandbox-{}` \
+             in cargo config.",
+            key,
+            path,
+            key
+        );
+    }
+    Ok(full)
+}
+
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
 struct TomlTarget {
     name: Option<String>,
@@ -0,0 +1,469 @@
+//! Format-preserving edits of `Cargo.toml` files.
+//!
+//! The manifest types in [`crate::util::toml`] are only meant for reading: a
+//! manifest which is deserialized into them and serialized again loses all
+//! of its comments, and its tables and keys end up in a different order.
+//! Commands which modify the user's manifest, such as `cargo add` and
+//! `cargo remove`, instead edit a [`toml_edit::Document`], which keeps
+//! everything that isn't touched by the edit exactly as it was written.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use cargo_util::paths;
+use toml_edit::{Array, Document, InlineTable, Item, Table, TableLike, Value};
+
+use crate::core::dependency::DepKind;
+use crate::util::errors::CargoResult;
+
+/// Keys of a dependency which describe where it comes from.
+const SOURCE_KEYS: &[&str] = &[
+    "version",
+    "registry",
+    "registry-index",
+    "path",
+    "git",
+    "branch",
+    "tag",
+    "rev",
+];
+
+/// One of the dependency tables of a manifest, such as `[dev-dependencies]`
+/// or `[target.'cfg(unix)'.dependencies]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepTable {
+    kind: DepKind,
+    target: Option<String>,
+}
+
+impl DepTable {
+    pub fn new(kind: DepKind, target: Option<String>) -> DepTable {
+        DepTable { kind, target }
+    }
+
+    pub fn kind(&self) -> DepKind {
+        self.kind
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// Returns the name of the table, and the older name with an underscore
+    /// which Cargo still accepts.
+    fn kind_names(&self) -> (&'static str, Option<&'static str>) {
+        match self.kind {
+            DepKind::Normal => ("dependencies", None),
+            DepKind::Development => ("dev-dependencies", Some("dev_dependencies")),
+            DepKind::Build => ("build-dependencies", Some("build_dependencies")),
+        }
+    }
+}
+
+impl fmt::Display for DepTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind_names().0)?;
+        if let Some(target) = &self.target {
+            write!(f, " for target `{}`", target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a [`ManifestDependency`] comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DepSource {
+    Registry {
+        version: String,
+        /// The name of an alternative registry.
+        registry: Option<String>,
+    },
+    Path {
+        /// Path relative to the manifest's directory, using `/` as separator.
+        path: String,
+        version: Option<String>,
+    },
+    Git {
+        url: String,
+        branch: Option<String>,
+        tag: Option<String>,
+        rev: Option<String>,
+        version: Option<String>,
+    },
+}
+
+impl DepSource {
+    fn entries(&self) -> Vec<(&'static str, &str)> {
+        let mut entries = Vec::new();
+        match self {
+            DepSource::Registry { version, registry } => {
+                entries.push(("version", version.as_str()));
+                if let Some(registry) = registry {
+                    entries.push(("registry", registry.as_str()));
+                }
+            }
+            DepSource::Path { path, version } => {
+                if let Some(version) = version {
+                    entries.push(("version", version.as_str()));
+                }
+                entries.push(("path", path.as_str()));
+            }
+            DepSource::Git {
+                url,
+                branch,
+                tag,
+                rev,
+                version,
+            } => {
+                if let Some(version) = version {
+                    entries.push(("version", version.as_str()));
+                }
+                entries.push(("git", url.as_str()));
+                for (key, value) in [("branch", branch), ("tag", tag), ("rev", rev)] {
+                    if let Some(value) = value {
+                        entries.push((key, value.as_str()));
+                    }
+                }
+            }
+        }
+        entries
+    }
+}
+
+/// A dependency as it is written into a manifest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestDependency {
+    /// The key of the dependency, which is its name in the `extern prelude`
+    /// of the package.
+    pub name: String,
+    /// The name of the package, if the dependency is renamed.
+    pub package: Option<String>,
+    pub source: DepSource,
+    pub features: Vec<String>,
+    /// `None` leaves the existing setting alone.
+    pub default_features: Option<bool>,
+    /// `None` leaves the existing setting alone.
+    pub optional: Option<bool>,
+}
+
+impl ManifestDependency {
+    /// Whether this can be written as a plain version requirement string.
+    fn is_simple(&self) -> bool {
+        matches!(self.source, DepSource::Registry { registry: None, .. })
+            && self.package.is_none()
+            && self.features.is_empty()
+            && self.default_features.unwrap_or(true)
+            && !self.optional.unwrap_or(false)
+    }
+
+    fn to_item(&self) -> Item {
+        if let (true, DepSource::Registry { version, .. }) = (self.is_simple(), &self.source) {
+            return toml_edit::value(version.as_str());
+        }
+        let mut table = InlineTable::new();
+        self.update_table(&mut table);
+        table.fmt();
+        toml_edit::value(table)
+    }
+
+    /// Updates an existing entry for this dependency.
+    ///
+    /// Keys which aren't affected are kept, along with their formatting.
+    fn update_item(&self, item: &mut Item) {
+        match item {
+            Item::Value(Value::InlineTable(table)) => {
+                let last = table
+                    .iter()
+                    .last()
+                    .map(|(k, v)| (k.to_string(), v.decor().suffix().map(str::to_string)));
+                self.update_table(table);
+                // Keep the space before the closing brace after whatever
+                // entry is last now.
+                let new_last = table.iter().last().map(|(k, _)| k.to_string());
+                if let (Some((old_last, suffix)), Some(new_last)) = (last, new_last) {
+                    if old_last != new_last {
+                        if let Some(value) = table.get_mut(&old_last) {
+                            value.decor_mut().set_suffix("");
+                        }
+                        if let (Some(value), Some(suffix)) = (table.get_mut(&new_last), suffix) {
+                            value.decor_mut().set_suffix(suffix);
+                        }
+                    }
+                }
+            }
+            Item::Table(table) => self.update_table(table),
+            _ => {
+                let mut new = self.to_item();
+                if let (Some(old), Some(new)) = (item.as_value(), new.as_value_mut()) {
+                    *new.decor_mut() = old.decor().clone();
+                }
+                *item = new;
+            }
+        }
+    }
+
+    fn update_table(&self, table: &mut dyn TableLike) {
+        let entries = self.source.entries();
+        for key in SOURCE_KEYS {
+            // An explicit version next to a path or git source stays valid.
+            let keep_version = *key == "version"
+                && matches!(self.source, DepSource::Path { .. } | DepSource::Git { .. });
+            if !keep_version && !entries.iter().any(|(k, _)| k == key) {
+                table.remove(key);
+            }
+        }
+        for (key, value) in entries {
+            set_value(table, key, Value::from(value));
+        }
+        if let Some(package) = &self.package {
+            set_value(table, "package", Value::from(package.as_str()));
+        }
+        if let Some(default_features) = self.default_features {
+            table.remove("default_features");
+            if default_features {
+                table.remove("default-features");
+            } else {
+                set_value(table, "default-features", Value::from(false));
+            }
+        }
+        if !self.features.is_empty() {
+            match table.get_mut("features").and_then(|f| f.as_array_mut()) {
+                Some(features) => {
+                    for feature in &self.features {
+                        if !features.iter().any(|f| f.as_str() == Some(feature)) {
+                            features.push(feature.as_str());
+                        }
+                    }
+                }
+                None => {
+                    let features: Array = self.features.iter().map(|s| s.as_str()).collect();
+                    set_value(table, "features", Value::Array(features));
+                }
+            }
+        }
+        match self.optional {
+            Some(true) => set_value(table, "optional", Value::from(true)),
+            Some(false) => {
+                table.remove("optional");
+            }
+            None => {}
+        }
+    }
+}
+
+/// Sets `key` to `value`, keeping the formatting around an existing value.
+fn set_value(table: &mut dyn TableLike, key: &str, mut value: Value) {
+    match table.get_mut(key) {
+        Some(Item::Value(old)) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        _ => {
+            table.insert(key, Item::Value(value));
+        }
+    }
+}
+
+/// A manifest on disk, which is edited in place.
+pub struct LocalManifest {
+    path: PathBuf,
+    document: Document,
+}
+
+impl LocalManifest {
+    /// Reads the manifest at `path`.
+    pub fn try_new(path: &Path) -> CargoResult<LocalManifest> {
+        let contents = paths::read(path)?;
+        let document = contents
+            .parse::<Document>()
+            .with_context(|| format!("failed to parse manifest at `{}`", path.display()))?;
+        Ok(LocalManifest {
+            path: path.to_path_buf(),
+            document,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the manifest back to disk.
+    pub fn write(&self) -> CargoResult<()> {
+        paths::write(&self.path, self.document.to_string())
+    }
+
+    /// Returns all dependency tables which exist in the manifest.
+    pub fn dep_tables(&self) -> Vec<DepTable> {
+        let kinds = [DepKind::Normal, DepKind::Development, DepKind::Build];
+        let mut tables: Vec<DepTable> = kinds
+            .iter()
+            .map(|&kind| DepTable::new(kind, None))
+            .collect();
+        if let Some(targets) = self.document.get("target").and_then(|t| t.as_table_like()) {
+            for (target, _) in targets.iter() {
+                for &kind in kinds.iter() {
+                    tables.push(DepTable::new(kind, Some(target.to_string())));
+                }
+            }
+        }
+        tables.retain(|table| self.dep_table(table).is_some());
+        tables
+    }
+
+    fn dep_table(&self, table: &DepTable) -> Option<&dyn TableLike> {
+        let parent = match &table.target {
+            Some(target) => self
+                .document
+                .get("target")?
+                .as_table_like()?
+                .get(target)?
+                .as_table_like()?,
+            None => self.document.as_table(),
+        };
+        let (name, alt_name) = table.kind_names();
+        parent
+            .get(name)
+            .or_else(|| alt_name.and_then(|alt| parent.get(alt)))?
+            .as_table_like()
+    }
+
+    /// Returns the given dependency table, creating it if it doesn't exist.
+    fn dep_table_mut(&mut self, table: &DepTable) -> CargoResult<&mut dyn TableLike> {
+        let mut parent = self.document.as_table_mut() as &mut dyn TableLike;
+        if let Some(target) = &table.target {
+            for key in ["target", target.as_str()] {
+                parent = parent
+                    .entry(key)
+                    .or_insert_with(|| {
+                        let mut table = Table::new();
+                        table.set_implicit(true);
+                        Item::Table(table)
+                    })
+                    .as_table_like_mut()
+                    .ok_or_else(|| anyhow::format_err!("`{}` in manifest is not a table", key))?;
+            }
+        }
+        let (name, alt_name) = table.kind_names();
+        let name = match alt_name {
+            Some(alt) if !parent.contains_key(name) && parent.contains_key(alt) => alt,
+            _ => name,
+        };
+        parent
+            .entry(name)
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow::format_err!("`{}` in manifest is not a table", name))
+    }
+
+    /// Returns the entry for the dependency `name` in the given table.
+    pub fn get_dependency(&self, table: &DepTable, name: &str) -> Option<&Item> {
+        self.dep_table(table)?.get(name).filter(|i| !i.is_none())
+    }
+
+    /// Adds a dependency to the given table, or updates it if it is already
+    /// there.
+    ///
+    /// New entries are kept in alphabetical order if the table already is.
+    pub fn insert_dependency(
+        &mut self,
+        table: &DepTable,
+        dep: &ManifestDependency,
+    ) -> CargoResult<()> {
+        let deps = self.dep_table_mut(table)?;
+        match deps.get_mut(&dep.name) {
+            Some(item) if !item.is_none() => dep.update_item(item),
+            _ => {
+                let keys: Vec<&str> = deps.iter().map(|(key, _)| key).collect();
+                let sorted = keys.windows(2).all(|w| w[0] <= w[1]);
+                // Indent the new entry like the existing ones.
+                let indent = keys
+                    .first()
+                    .and_then(|key| deps.key_decor(key))
+                    .and_then(|decor| decor.prefix())
+                    .map(|prefix| prefix.rsplit('\n').next().unwrap_or_default())
+                    .filter(|indent| indent.chars().all(|c| c == ' ' || c == '\t'))
+                    .map(|indent| indent.to_string());
+                deps.insert(&dep.name, dep.to_item());
+                if let (Some(indent), Some(decor)) = (indent, deps.key_decor_mut(&dep.name)) {
+                    decor.set_prefix(indent);
+                }
+                if sorted {
+                    deps.sort_values();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the dependency `name` from the given table, returning its
+    /// entry.
+    pub fn remove_dependency(&mut self, table: &DepTable, name: &str) -> CargoResult<Item> {
+        let removed = match self.dep_table(table) {
+            Some(deps) if deps.contains_key(name) => self.dep_table_mut(table)?.remove(name),
+            _ => None,
+        };
+        removed.ok_or_else(|| {
+            anyhow::format_err!(
+                "the dependency `{}` could not be found in `{}`",
+                name,
+                table
+            )
+        })
+    }
+
+    /// Removes everything in the `[features]` table which refers to the
+    /// dependency `name`.
+    ///
+    /// A bare `name` is only treated as a reference if the dependency was
+    /// optional, since otherwise it names a feature of this package.
+    pub fn remove_feature_references(&mut self, name: &str, optional: bool) {
+        let features = match self
+            .document
+            .get_mut("features")
+            .and_then(|f| f.as_table_like_mut())
+        {
+            Some(features) => features,
+            None => return,
+        };
+        let dep_feature = format!("dep:{}", name);
+        let prefixes = [format!("{}/", name), format!("{}?/", name)];
+        let refers_to_dep = |value: &str| {
+            (optional && value == name)
+                || value == dep_feature
+                || prefixes
+                    .iter()
+                    .any(|prefix| value.starts_with(prefix.as_str()))
+        };
+        for (_, values) in features.iter_mut() {
+            let values = match values.as_array_mut() {
+                Some(values) => values,
+                None => continue,
+            };
+            let first_prefix = values
+                .get(0)
+                .and_then(|v| v.decor().prefix())
+                .map(|p| p.to_string());
+            for i in (0..values.len()).rev() {
+                if values
+                    .get(i)
+                    .and_then(|v| v.as_str())
+                    .map_or(false, refers_to_dep)
+                {
+                    values.remove(i);
+                }
+            }
+            if let (Some(first), Some(prefix)) = (values.get_mut(0), first_prefix) {
+                first.decor_mut().set_prefix(prefix);
+            }
+        }
+    }
+}
+
+/// Returns whether a dependency entry enables `optional`.
+pub fn is_optional(item: &Item) -> bool {
+    item.as_table_like()
+        .and_then(|t| t.get("optional"))
+        .and_then(|o| o.as_bool())
+        .unwrap_or(false)
+}
@@ -856,7 +856,7 @@ fn ambiguous_git_reference() {
 [ERROR] failed to parse manifest at `[..]`
 
 Caused by:
-  dependency (bar) specification is ambiguous. Only one of `branch`, `tag` or `rev` is allowed.
+  dependency (bar) specification is ambiguous. Only one of `branch`, `tag`, `rev` or `ref` is allowed.
 ",
         )
         .run();
@@ -0,0 +1,261 @@
+//! Tests for -Zbuild-sandbox and `[package.build-capabilities]`.
+
+use std::net::TcpListener;
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::{basic_manifest, paths, project};
+
+/// Whether the kernel supports everything the sandbox uses, so that it
+/// doesn't warn that it's incomplete.
+fn sandbox_is_complete() -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    let p = project()
+        .at("probe")
+        .file("src/lib.rs", "")
+        .file("build.rs", "fn main() {}")
+        .build();
+    let output = p
+        .cargo("build -Zbuild-sandbox")
+        .masquerade_as_nightly_cargo()
+        .exec_with_output()
+        .unwrap();
+    !String::from_utf8_lossy(&output.stderr).contains("sandbox is incomplete")
+}
+
+#[cargo_test]
+fn out_dir_is_writable() {
+    if !sandbox_is_complete() {
+        return;
+    }
+    paths::root().join("secret").mkdir_p();
+    std::fs::write(paths::root().join("secret/key"), "hunter2").unwrap();
+    let p = project()
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "build.rs",
+            r#"
+                use std::env;
+                use std::fs;
+                use std::path::Path;
+
+                fn main() {
+                    let out_dir = env::var("OUT_DIR").unwrap();
+                    fs::write(Path::new(&out_dir).join("out.rs"), "").unwrap();
+                    fs::write(env::temp_dir().join("tmp"), "").unwrap();
+                    fs::read_to_string("src/main.rs").unwrap();
+                    assert!(fs::write("src/main.rs", "").is_err());
+                    assert!(fs::read_to_string("../secret/key").is_err());
+                    fs::read_to_string("/proc/self/status").unwrap();
+                    assert!(fs::read_to_string("/proc/1/environ").is_err());
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-sandbox")
+        .masquerade_as_nightly_cargo()
+        .run();
+}
+
+#[cargo_test]
+fn violation_names_package() {
+    if !sandbox_is_complete() {
+        return;
+    }
+    std::fs::write(paths::root().join("secret"), "hunter2").unwrap();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .file(
+            "bar/build.rs",
+            r#"
+                fn main() {
+                    std::fs::read_to_string("../../secret").unwrap();
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Zbuild-sandbox")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] the build script of `bar v0.1.0 ([CWD]/bar)` failed in the build script \
+             sandbox",
+        )
+        .with_stderr_contains("  failed to run custom build command for `bar v0.1.0 ([..])`")
+        .with_stderr_contains("[..]Permission denied[..]")
+        .run();
+
+    // Not sandboxed without the flag.
+    p.cargo("build").run();
+}
+
+#[cargo_test]
+fn capabilities_grant_access() {
+    if !sandbox_is_complete() {
+        return;
+    }
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    paths::root().join("sdk").mkdir_p();
+    std::fs::write(paths::root().join("sdk/header.h"), "").unwrap();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["build-capabilities"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.build-capabilities]
+                network = true
+            "#,
+        )
+        .file(
+            ".cargo/config",
+            r#"
+                [build]
+                sandbox-read = ["../sdk"]
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "build.rs",
+            &format!(
+                r#"
+                    fn main() {{
+                        std::fs::read_to_string("../sdk/header.h").unwrap();
+                        std::net::TcpStream::connect("{}").unwrap();
+                    }}
+                "#,
+                addr
+            ),
+        )
+        .build();
+
+    p.cargo("build -Zbuild-sandbox")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["build-capabilities"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [package.build-capabilities]
+            read = ["src"]
+        "#,
+    );
+    p.cargo("build -Zbuild-sandbox")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] the build script of `foo v0.1.0 ([CWD])` failed in the build script sandbox",
+        )
+        .with_stderr_contains("[..]Network is unreachable[..]")
+        .run();
+}
+
+#[cargo_test]
+fn capabilities_require_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [package.build-capabilities]
+                network = true
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  feature `build-capabilities` is required")
+        .run();
+}
+
+#[cargo_test]
+fn capabilities_paths_must_be_within_package() {
+    let sdk = paths::root().join("sdk");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["build-capabilities"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [package.build-capabilities]
+                    read = ['{}']
+                "#,
+                sdk.display()
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "  the `read` paths of `[package.build-capabilities]` must be relative paths \
+             within the package, found `{}`",
+            sdk.display()
+        ))
+        .with_stderr_contains(
+            "  Paths outside of the package may be granted with `build.sandbox-read` in cargo config.",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["build-capabilities"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [package.build-capabilities]
+            write = ["target/../../sdk"]
+        "#,
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  the `write` paths of `[package.build-capabilities]` must be relative paths \
+             within the package, found `target/../../sdk`",
+        )
+        .run();
+}
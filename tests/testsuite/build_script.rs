@@ -1089,6 +1089,7 @@ fn only_rerun_build_script() {
     p.cargo("build -v")
         .with_stderr(
             "\
+[DIRTY] foo v0.5.0 ([CWD]): the precalculated components changed from [..]
 [COMPILING] foo v0.5.0 ([CWD])
 [RUNNING] `[..]/build-script-build`
 [RUNNING] `rustc --crate-name foo [..]`
@@ -1195,6 +1196,7 @@ fn testing_and_such() {
     p.cargo("test -vj1")
         .with_stderr(
             "\
+[DIRTY] foo v0.5.0 ([CWD]): the precalculated components changed from [..]
 [COMPILING] foo v0.5.0 ([CWD])
 [RUNNING] `[..]/build-script-build`
 [RUNNING] `rustc --crate-name foo [..]`
@@ -1592,6 +1594,7 @@ fn out_dir_is_preserved() {
     p.cargo("build -v")
         .with_stderr(
             "\
+[DIRTY] foo [..]: the file `build.rs` has changed
 [COMPILING] foo [..]
 [RUNNING] `rustc --crate-name build_script_build [..]
 [RUNNING] `[..]/build-script-build`
@@ -1616,6 +1619,7 @@ fn out_dir_is_preserved() {
     p.cargo("build -v")
         .with_stderr(
             "\
+[DIRTY] foo [..]: the precalculated components changed from [..]
 [COMPILING] foo [..]
 [RUNNING] `[..]build-script-build`
 [RUNNING] `rustc --crate-name foo [..]
@@ -2877,6 +2881,7 @@ fn changing_an_override_invalidates() {
     p.cargo("build -v")
         .with_stderr(
             "\
+[DIRTY] foo v0.5.0 ([..]): the precalculated components changed from [..]
 [COMPILING] foo v0.5.0 ([..]
 [RUNNING] `rustc [..] -L native=bar`
 [FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
@@ -3104,6 +3109,7 @@ fn rebuild_only_on_explicit_paths() {
     p.cargo("build -v")
         .with_stderr(
             "\
+[DIRTY] foo v0.5.0 ([..]): the file `foo` is missing
 [COMPILING] foo v0.5.0 ([..])
 [RUNNING] `[..]/build-script-build`
 [RUNNING] `rustc [..] src/lib.rs [..]`
@@ -3122,6 +3128,7 @@ fn rebuild_only_on_explicit_paths() {
     p.cargo("build -v")
         .with_stderr(
             "\
+[DIRTY] foo v0.5.0 ([..]): the file `foo` has changed
 [COMPILING] foo v0.5.0 ([..])
 [RUNNING] `[..]/build-script-build`
 [RUNNING] `rustc [..] src/lib.rs [..]`
@@ -3160,6 +3167,7 @@ fn rebuild_only_on_explicit_paths() {
     p.cargo("build -v")
         .with_stderr(
             "\
+[DIRTY] foo v0.5.0 ([..]): the file `foo` has changed
 [COMPILING] foo v0.5.0 ([..])
 [RUNNING] `[..]/build-script-build`
 [RUNNING] `rustc [..] src/lib.rs [..]`
@@ -3174,6 +3182,7 @@ fn rebuild_only_on_explicit_paths() {
     p.cargo("build -v")
         .with_stderr(
             "\
+[DIRTY] foo v0.5.0 ([..]): the file `bar` is missing
 [COMPILING] foo v0.5.0 ([..])
 [RUNNING] `[..]/build-script-build`
 [RUNNING] `rustc [..] src/lib.rs [..]`
@@ -4629,6 +4638,79 @@ fn rerun_if_directory() {
     fresh();
 }
 
+#[cargo_test]
+fn rerun_if_glob() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=proto/**/*.proto");
+                }
+            "#,
+        )
+        .build();
+
+    let dirty = || {
+        p.cargo("check -Zrerun-if-changed-glob")
+            .masquerade_as_nightly_cargo()
+            .with_stderr(
+                "[COMPILING] foo [..]\n\
+                 [FINISHED] [..]",
+            )
+            .run();
+    };
+
+    let fresh = || {
+        p.cargo("check -Zrerun-if-changed-glob")
+            .masquerade_as_nightly_cargo()
+            .with_stderr("[FINISHED] [..]")
+            .run();
+    };
+
+    // Unlike a missing file, a pattern without matches isn't always dirty.
+    dirty();
+    fresh();
+
+    // Add matching files.
+    p.change_file("proto/a.proto", "");
+    p.change_file("proto/nested/b.proto", "");
+    dirty();
+    fresh();
+
+    if is_coarse_mtime() {
+        sleep_ms(1000);
+    }
+
+    // Modify a nested file.
+    p.change_file("proto/nested/b.proto", "message B {}");
+    dirty();
+    fresh();
+
+    // Files which don't match are ignored.
+    p.change_file("proto/README.md", "");
+    fresh();
+
+    // Remove a file.
+    fs::remove_file(p.root().join("proto/a.proto")).unwrap();
+    dirty();
+    fresh();
+
+    // Without the flag, the pattern is a missing file, which always reruns.
+    for _ in 0..2 {
+        p.cargo("check")
+            .with_stderr(
+                "\
+[COMPILING] foo [..]
+[FINISHED] [..]
+",
+            )
+            .run();
+    }
+}
+
 #[cargo_test]
 fn test_with_dep_metadata() {
     let p = project()
@@ -4766,3 +4848,272 @@ fn duplicate_script_with_extra_env() {
             .run();
     }
 }
+
+#[cargo_test]
+fn namespaced_directives() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { path = 'bar' }
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                #[cfg(not(foo))]
+                compile_error!{"expected foo set"}
+            "#,
+        )
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    assert_eq!(std::env::var("DEP_BAR_KEY").unwrap(), "a=b");
+                    println!("cargo::rustc-cfg=foo");
+                    println!("cargo::warning=hello");
+                }
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                links = 'bar'
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file(
+            "bar/build.rs",
+            r#"
+                fn main() {
+                    println!("cargo::metadata=key=a=b");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Znamespaced-directives")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.1.0 ([CWD]/bar)
+[COMPILING] foo v0.1.0 ([CWD])
+warning: hello
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn namespaced_directives_are_checked() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rustc-link-serach=native=foo");
+                    println!("cargo::rustc-link-serach=native=foo");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Znamespaced-directives")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+error: invalid output in build script of `foo v0.0.1 ([CWD])`: `cargo::rustc-link-serach=native=foo`
+Unknown directive `cargo::rustc-link-serach`. Metadata for dependents is passed with `cargo::metadata=rustc-link-serach=VALUE`.
+",
+        )
+        .run();
+
+    p.change_file(
+        "build.rs",
+        r#"
+            fn main() {
+                println!("cargo::metadata=foo");
+            }
+        "#,
+    );
+    p.cargo("build -Znamespaced-directives")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("Expected a line with `cargo::metadata=KEY=VALUE`.")
+        .run();
+}
+
+#[cargo_test]
+fn namespaced_error_fails_build() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo::warning=some warning");
+                    println!("cargo::error=libfoo was not found");
+                    println!("cargo::error=install it with your package manager");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Znamespaced-directives")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+The following warnings were emitted during compilation:
+
+warning: some warning
+
+error: failed to run custom build command for `foo v0.0.1 ([CWD])`
+
+Caused by:
+  libfoo was not found
+  install it with your package manager
+",
+        )
+        .run();
+
+    // `cargo:error` is metadata.
+    p.change_file(
+        "build.rs",
+        r#"
+            fn main() {
+                println!("cargo:error=not an error");
+            }
+        "#,
+    );
+    p.cargo("build -Znamespaced-directives")
+        .masquerade_as_nightly_cargo()
+        .run();
+}
+
+#[cargo_test]
+fn namespaced_error_when_script_fails() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo::error=libfoo was not found");
+                    std::process::exit(1);
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Znamespaced-directives")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+error: failed to run custom build command for `foo v0.0.1 ([CWD])`
+
+Caused by:
+  libfoo was not found
+
+Caused by:
+  process didn't exit successfully: `[..]build-script-build` (exit [..]: 1)
+  --- stdout
+  cargo::error=libfoo was not found
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn namespaced_directives_require_rust_version() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["rust-version"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                rust-version = "1.56"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo::rustc-cfg=foo");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build -Znamespaced-directives")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+error: the build script of `foo v0.1.0 ([CWD])` uses the `cargo::` prefix, which is only \
+supported as of Rust 1.57, but the package's `rust-version` is 1.56
+Use the `cargo:` prefix instead, or raise `rust-version` to 1.57.
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            cargo-features = ["rust-version"]
+
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            rust-version = "1.57"
+        "#,
+    );
+    p.cargo("build -Znamespaced-directives")
+        .masquerade_as_nightly_cargo()
+        .run();
+}
+
+#[cargo_test]
+fn namespaced_directives_are_metadata_without_flag() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo::rustc-link-serach=native=foo");
+                    println!("cargo::error=not an error");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]
+",
+        )
+        .run();
+}
@@ -0,0 +1,356 @@
+//! Tests for the `cargo add` command.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, git, project, Project};
+
+/// Checks that the manifest at `path` contains `expected`, showing the whole
+/// manifest if it doesn't.
+fn assert_manifest_contains(p: &Project, path: &str, expected: &str) {
+    let manifest = p.read_file(path);
+    assert!(
+        manifest.contains(expected),
+        "expected to find:\n{}\nin manifest:\n{}",
+        expected,
+        manifest
+    );
+}
+
+fn publish_bar() {
+    for version in ["1.0.0", "1.1.0", "1.2.0", "2.0.0-alpha.1"] {
+        Package::new("bar", version)
+            .feature("a", &[])
+            .feature("b", &[])
+            .publish();
+    }
+}
+
+#[cargo_test]
+fn preserves_formatting() {
+    publish_bar();
+    Package::new("baz", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                # The package.
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                # Comments are kept.
+                baz   =   "1.0"  # trailing comment
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v1.2.0 to dependencies
+",
+        )
+        .run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+                # The package.
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.2.0"
+                # Comments are kept.
+                baz   =   "1.0"  # trailing comment
+            "#
+    );
+    assert!(p.read_lockfile().contains("name = \"bar\""));
+}
+
+#[cargo_test]
+fn version_requirement() {
+    publish_bar();
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar@1.1")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v1.2.0 to dependencies
+",
+        )
+        .run();
+    assert_manifest_contains(&p, "Cargo.toml", "\n[dependencies]\nbar = \"1.1\"\n");
+
+    p.cargo("add bar@2.0.0-alpha.1")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v2.0.0-alpha.1 to dependencies
+",
+        )
+        .run();
+    assert_manifest_contains(
+        &p,
+        "Cargo.toml",
+        "\n[dependencies]\nbar = \"2.0.0-alpha.1\"\n",
+    );
+
+    p.cargo("add bar@nope")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] invalid version requirement `nope` in `bar@nope` (expected something like `bar@1.2`)
+
+Caused by:
+  [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn dependency_tables() {
+    publish_bar();
+    Package::new("baz", "1.0.0").publish();
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar --dev").run();
+    p.cargo("add baz --build").run();
+    p.cargo("add bar baz --target cfg(unix)")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v1.2.0 to dependencies for target `cfg(unix)`
+[ADDING] baz v1.0.0 to dependencies for target `cfg(unix)`
+",
+        )
+        .run();
+    let manifest = p.read_file("Cargo.toml");
+    assert!(manifest.contains("\n[dev-dependencies]\nbar = \"1.2.0\"\n"));
+    assert!(manifest.contains("\n[build-dependencies]\nbaz = \"1.0.0\"\n"));
+    assert!(manifest
+        .contains("\n[target.\"cfg(unix)\".dependencies]\nbar = \"1.2.0\"\nbaz = \"1.0.0\"\n"));
+}
+
+#[cargo_test]
+fn features_optional_and_rename() {
+    publish_bar();
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar -F a,b --optional --no-default-features --rename qux")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] qux v1.2.0 to optional dependencies with features: a, b
+",
+        )
+        .run();
+    assert_manifest_contains(
+        &p,
+        "Cargo.toml",
+        "\n[dependencies]\nqux = { version = \"1.2.0\", package = \"bar\", \
+         default-features = false, features = [\"a\", \"b\"], optional = true }\n",
+    );
+
+    p.cargo("add bar --features nope")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] unrecognized feature `nope` for `bar v1.2.0`
+available features: a, b
+",
+        )
+        .run();
+
+    p.cargo("add bar --dev --optional")
+        .with_status(101)
+        .with_stderr("[ERROR] dev-dependencies cannot be optional")
+        .run();
+}
+
+#[cargo_test]
+fn updates_existing_dependency() {
+    publish_bar();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = { version = "1.0", features = ["a"] } # keep me
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar --features b --optional").run();
+    assert_manifest_contains(
+        &p,
+        "Cargo.toml",
+        "\n                bar = { version = \"1.2.0\", features = [\"a\", \"b\"], \
+         optional = true } # keep me\n",
+    );
+
+    // Only what is given on the command line is changed.
+    p.cargo("add bar@~1.1")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v1.1.0 to optional dependencies
+",
+        )
+        .run();
+    assert_manifest_contains(
+        &p,
+        "Cargo.toml",
+        "\n                bar = { version = \"~1.1\", features = [\"a\", \"b\"], \
+         optional = true } # keep me\n",
+    );
+}
+
+#[cargo_test]
+fn path_dependency() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file("crates/dep/Cargo.toml", &basic_manifest("dep", "0.5.0"))
+        .file("crates/dep/src/lib.rs", "")
+        .build();
+
+    p.cargo("add --path crates/dep")
+        .with_stderr("[ADDING] dep (local) to dependencies")
+        .run();
+    assert_manifest_contains(
+        &p,
+        "Cargo.toml",
+        "\n[dependencies]\ndep = { path = \"crates/dep\" }\n",
+    );
+
+    p.cargo("add other --path crates/dep")
+        .with_status(101)
+        .with_stderr("[ERROR] could not find `other` in [..]crates/dep")
+        .run();
+}
+
+#[cargo_test]
+fn git_dependency() {
+    let git_project = git::new("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.5.0"))
+            .file("src/lib.rs", "")
+    });
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo(&format!(
+        "add dep --git {} --branch master",
+        git_project.url()
+    ))
+    .with_stderr(
+        "\
+[UPDATING] git repository `[..]`
+[ADDING] dep (git) to dependencies
+[UPDATING] git repository `[..]`
+",
+    )
+    .run();
+    assert_manifest_contains(
+        &p,
+        "Cargo.toml",
+        &format!(
+            "\n[dependencies]\ndep = {{ git = \"{}\", branch = \"master\" }}\n",
+            git_project.url()
+        ),
+    );
+    assert!(p.read_lockfile().contains("name = \"dep\""));
+}
+
+#[cargo_test]
+fn dry_run() {
+    publish_bar();
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar --dry-run")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v1.2.0 to dependencies
+[WARNING] aborting add due to dry run
+",
+        )
+        .run();
+    assert_eq!(p.read_file("Cargo.toml"), basic_manifest("foo", "0.1.0"));
+    assert!(!p.root().join("Cargo.lock").exists());
+}
+
+#[cargo_test]
+fn missing_crate() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+    cargo_test_support::registry::init();
+
+    p.cargo("add nope")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] could not find `nope` in registry `[..]`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn virtual_workspace_needs_package() {
+    publish_bar();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] `cargo add` could not determine which package to modify, use the `--package` option to select one
+available packages: a, b
+",
+        )
+        .run();
+
+    p.cargo("add bar -p b").run();
+    assert_manifest_contains(&p, "b/Cargo.toml", "\n[dependencies]\nbar = \"1.2.0\"\n");
+}
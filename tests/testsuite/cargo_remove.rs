@@ -0,0 +1,130 @@
+//! Tests for the `cargo remove` command.
+
+use cargo_test_support::project;
+use cargo_test_support::registry::Package;
+
+#[cargo_test]
+fn preserves_formatting() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                # bar is used for things.
+                bar = "1.0"
+                baz = "1.0" # so is baz
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("remove bar")
+        .with_stderr("[REMOVING] bar from dependencies")
+        .run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                baz = "1.0" # so is baz
+            "#
+    );
+    let lock = p.read_lockfile();
+    assert!(!lock.contains("name = \"bar\""));
+    assert!(lock.contains("name = \"baz\""));
+}
+
+#[cargo_test]
+fn removes_feature_references() {
+    Package::new("bar", "1.0.0").feature("x", &[]).publish();
+    Package::new("baz", "1.0.0").feature("y", &[]).publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [features]
+                default = ["bar", "baz/y"]
+                extra = ["bar/x", "baz"]
+
+                [dependencies]
+                bar = { version = "1.0", optional = true }
+                baz = { version = "1.0", optional = true }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("remove bar").run();
+    assert!(p.read_file("Cargo.toml").contains(
+        r#"
+                [features]
+                default = ["baz/y"]
+                extra = ["baz"]
+"#
+    ));
+    p.cargo("check").run();
+}
+
+#[cargo_test]
+fn dev_and_target_tables() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dev-dependencies]
+                bar = "1.0"
+
+                [target.'cfg(unix)'.dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("remove bar")
+        .with_status(101)
+        .with_stderr(
+            "\
+[REMOVING] bar from dependencies
+[ERROR] the dependency `bar` could not be found in `dependencies`
+
+help: a dependency with the same name exists in `dev-dependencies`, \
+`dependencies for target `cfg(unix)``
+",
+        )
+        .run();
+
+    p.cargo("remove bar --dev --dry-run")
+        .with_stderr(
+            "\
+[REMOVING] bar from dev-dependencies
+[WARNING] aborting remove due to dry run
+",
+        )
+        .run();
+    assert!(p.read_file("Cargo.toml").contains("[dev-dependencies]\n"));
+
+    p.cargo("remove bar --dev").run();
+    p.cargo("remove bar --target cfg(unix)").run();
+    let manifest = p.read_file("Cargo.toml");
+    assert!(!manifest.contains("bar"));
+}
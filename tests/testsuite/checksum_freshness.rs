@@ -0,0 +1,135 @@
+//! Tests for `-Zchecksum-freshness`.
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn touched_sources_are_fresh() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "mod a; fn main() { a::a(); bar::bar(); }")
+        .file("src/a.rs", "pub fn a() {}")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.0.1"))
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .build();
+
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // Simulate a fresh checkout next to a restored target directory.
+    p.root().join("src").move_into_the_future();
+    p.root().join("bar/src").move_into_the_future();
+    p.cargo("build -Zchecksum-freshness -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FRESH] bar v0.0.1 ([CWD]/bar)
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn changed_contents_rebuild() {
+    let p = project()
+        .file("src/main.rs", "mod a; fn main() { a::a(); }")
+        .file("src/a.rs", "pub fn a() { let _x = 1; }")
+        .build();
+
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // Same size, different contents.
+    p.change_file("src/a.rs", "pub fn a() { let _x = 2; }");
+    p.root().join("target").move_into_the_past();
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.root().join("target").move_into_the_past();
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn touched_sources_rebuild_without_checksums() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build").run();
+
+    p.root().join("src").move_into_the_future();
+    p.cargo("build")
+        .with_stderr(
+            "\
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn rerun_if_changed_checksums() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=gen");
+                    println!("cargo:rerun-if-changed=*.idl");
+                }
+            "#,
+        )
+        .file("gen/nested/a.txt", "a")
+        .file("api.idl", "interface A {}")
+        .build();
+
+    p.cargo("check -Zchecksum-freshness -Zrerun-if-changed-glob")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // Same size, different contents.
+    p.change_file("gen/nested/a.txt", "b");
+    p.cargo("check -v -Zchecksum-freshness -Zrerun-if-changed-glob")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[DIRTY] foo v0.0.1 ([CWD]): the contents of `gen/nested/a.txt` changed",
+        )
+        .with_stderr_contains("[COMPILING] foo v0.0.1 ([CWD])")
+        .run();
+
+    p.cargo("check -Zchecksum-freshness -Zrerun-if-changed-glob")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    p.root().join("gen").move_into_the_future();
+    p.root().join("api.idl").move_into_the_future();
+    p.cargo("check -Zchecksum-freshness -Zrerun-if-changed-glob")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
@@ -0,0 +1,108 @@
+//! Tests for -Zcritical-path-scheduling.
+
+use cargo_test_support::{basic_manifest, project, Project};
+
+/// `foo` depends on `a`, `b` and `slow`, and `a` depends on `x`.
+fn scheduling_project() -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                a = { path = "a" }
+                b = { path = "b" }
+                slow = { path = "slow" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            "a/Cargo.toml",
+            r#"
+                [package]
+                name = "a"
+                version = "0.1.0"
+
+                [dependencies]
+                x = { path = "../x" }
+            "#,
+        )
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .file("slow/Cargo.toml", &basic_manifest("slow", "0.1.0"))
+        .file("slow/src/lib.rs", "")
+        .file("x/Cargo.toml", &basic_manifest("x", "0.1.0"))
+        .file("x/src/lib.rs", "")
+        .build()
+}
+
+/// Returns the name of the first package that was compiled.
+fn first_compiled(p: &Project, args: &str) -> String {
+    let output = p
+        .cargo(args)
+        .masquerade_as_nightly_cargo()
+        .exec_with_output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    stderr
+        .lines()
+        .find_map(|line| line.trim_start().strip_prefix("Compiling "))
+        .unwrap()
+        .split(' ')
+        .next()
+        .unwrap()
+        .to_string()
+}
+
+#[cargo_test]
+fn saves_unit_durations() {
+    let p = scheduling_project();
+
+    p.cargo("build -Zcritical-path-scheduling")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let saved: serde_json::Value =
+        serde_json::from_str(&p.read_file("target/debug/.unit-durations.json")).unwrap();
+    assert_eq!(saved["version"], 1);
+    let durations = saved["durations"].as_object().unwrap();
+    let mut keys: Vec<_> = durations.keys().map(|k| k.as_str()).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, ["a", "b", "foo", "slow", "x"]);
+    assert!(durations.values().all(|d| d.as_f64().unwrap() > 0.0));
+
+    // Durations of units that were not rebuilt are kept.
+    p.change_file("src/lib.rs", "pub fn f() {}");
+    p.cargo("check -Zcritical-path-scheduling")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let saved: serde_json::Value =
+        serde_json::from_str(&p.read_file("target/debug/.unit-durations.json")).unwrap();
+    let durations = saved["durations"].as_object().unwrap();
+    assert!(durations.contains_key("slow"));
+    assert!(durations.contains_key("slow lib (check)"));
+}
+
+#[cargo_test]
+fn longest_path_first() {
+    let p = scheduling_project();
+
+    // `x` has the most units depending on it.
+    assert_eq!(first_compiled(&p, "build -j1"), "x");
+    p.cargo("clean").run();
+
+    p.change_file(
+        "target/debug/.unit-durations.json",
+        r#"{
+            "version": 1,
+            "durations": {"a": 0.1, "b": 0.1, "foo": 0.1, "slow": 10.0, "x": 0.1}
+        }"#,
+    );
+    assert_eq!(
+        first_compiled(&p, "build -j1 -Zcritical-path-scheduling"),
+        "slow"
+    );
+}
@@ -0,0 +1,142 @@
+//! Tests for explaining why units are rebuilt.
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::{basic_manifest, project, sleep_ms, Project};
+
+#[cargo_test]
+fn source_file_changed() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("check -v")
+        .with_stderr(
+            "\
+[CHECKING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    sleep_ms(1000);
+    p.change_file("src/lib.rs", "pub fn foo() {}");
+    p.cargo("check -v")
+        .with_stderr(
+            "\
+[DIRTY] foo v0.0.1 ([CWD]): the file `src/lib.rs` has changed
+[CHECKING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("check -v")
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn rustflags_changed() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("check").run();
+    p.cargo("check -v")
+        .env("RUSTFLAGS", "--cfg foo")
+        .with_stderr(
+            "\
+[DIRTY] foo v0.0.1 ([CWD]): the rustflags changed from [] to [\"--cfg\", \"foo\"]
+[CHECKING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+fn dep_project() -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.0.1"))
+        .file("bar/src/lib.rs", "")
+        .build()
+}
+
+#[cargo_test]
+fn dependency_dirty() {
+    let p = dep_project();
+
+    p.cargo("check").run();
+    p.root().join("bar/src/lib.rs").move_into_the_future();
+    p.cargo("check -v")
+        .with_stderr(
+            "\
+[DIRTY] bar v0.0.1 ([CWD]/bar): the file `src/lib.rs` has changed
+[CHECKING] bar v0.0.1 ([CWD]/bar)
+[RUNNING] `rustc [..]`
+[DIRTY] foo v0.0.1 ([CWD]): the fingerprint of the dependency `bar` changed
+[CHECKING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn dependency_rebuilt() {
+    let p = dep_project();
+
+    p.cargo("check").run();
+    sleep_ms(1000);
+    p.change_file("bar/src/lib.rs", "pub fn bar() {}");
+    p.cargo("check -p bar").run();
+    p.cargo("check -v")
+        .with_stderr(
+            "\
+[FRESH] bar v0.0.1 ([CWD]/bar)
+[DIRTY] foo v0.0.1 ([CWD]): the dependency `bar` was rebuilt
+[CHECKING] foo v0.0.1 ([CWD])
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn json_message() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("check").run();
+    p.cargo("check --message-format=json")
+        .env("RUSTFLAGS", "--cfg foo")
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "dirty-unit",
+                    "package_id": "foo 0.0.1 [..]",
+                    "target": "{...}",
+                    "mode": "check",
+                    "dirty_reason": "rustflags-changed",
+                    "message": "the rustflags changed from [] to [\"--cfg\", \"foo\"]"
+                }
+            "#,
+        )
+        .run();
+}
@@ -147,6 +147,7 @@ fn rebuild_sub_package_then_while_package() {
     p.cargo("build -pb -v")
         .with_stderr(
             "\
+[DIRTY] b v0.0.1 ([..]): the file `src/lib.rs` has changed
 [COMPILING] b [..]
 [RUNNING] `rustc --crate-name b [..]
 [FINISHED] dev [..]
@@ -163,8 +164,10 @@ fn rebuild_sub_package_then_while_package() {
         .with_stderr(
             "\
 [FRESH] b [..]
+[DIRTY] a v0.0.1 ([..]): the dependency `b` was rebuilt
 [COMPILING] a [..]
 [RUNNING] `rustc --crate-name a [..]
+[DIRTY] foo v0.0.1 ([..]): the dependency `b` was rebuilt
 [COMPILING] foo [..]
 [RUNNING] `rustc --crate-name foo [..]
 [FINISHED] dev [..]
@@ -1873,6 +1876,7 @@ fn simulated_docker_deps_stay_cached() {
 [FRESH] regdep_env [..]
 [FRESH] regdep_old_style [..]
 [FRESH] regdep_rerun [..]
+[DIRTY] foo v0.1.0 ([..]): the precalculated components changed from [..]
 [COMPILING] foo [..]
 [RUNNING] [..]/foo-[..]/build-script-build[..]
 [RUNNING] `rustc --crate-name foo[..]
@@ -2104,6 +2108,7 @@ fn rerun_if_changes() {
         .env("FOO", "1")
         .with_stderr(
             "\
+[DIRTY] foo v0.0.1 ([..]): the environment variable `FOO` changed from unset to `1`
 [COMPILING] foo [..]
 [RUNNING] `[..]build-script-build`
 [RUNNING] `rustc [..]
@@ -2121,6 +2126,7 @@ fn rerun_if_changes() {
         .env("BAR", "1")
         .with_stderr(
             "\
+[DIRTY] foo v0.0.1 ([..]): the environment variable `BAR` changed from unset to `1`
 [COMPILING] foo [..]
 [RUNNING] `[..]build-script-build`
 [RUNNING] `rustc [..]
@@ -2138,6 +2144,7 @@ fn rerun_if_changes() {
         .env("BAR", "2")
         .with_stderr(
             "\
+[DIRTY] foo v0.0.1 ([..]): the environment variable `FOO` changed from `1` to unset
 [COMPILING] foo [..]
 [RUNNING] `[..]build-script-build`
 [RUNNING] `rustc [..]
@@ -2612,6 +2619,7 @@ fn cargo_env_changes() {
         .arg("-v")
         .with_stderr(
             "\
+[DIRTY] foo v1.0.0 ([..]): the environment variable `CARGO` changed
 [CHECKING] foo [..]
 [RUNNING] `rustc [..]
 [FINISHED] [..]
@@ -0,0 +1,127 @@
+//! Tests for selecting the git backend with `-Zgit=backend`.
+
+use std::env;
+use std::path::Path;
+
+use cargo_test_support::paths::{self, CargoPathExt};
+use cargo_test_support::{basic_manifest, git, path2url, project, Project};
+
+fn disable_git_cli() -> bool {
+    // See the same function in `git.rs`.
+    env::var("CARGO_TEST_DISABLE_GIT_CLI") == Ok("1".to_string())
+}
+
+fn dep_project(dep: &Project, config: &str) -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ git = '{}' }}
+                "#,
+                dep.url()
+            ),
+        )
+        .file("src/lib.rs", "pub fn foo() { dep::dep() }")
+        .file(".cargo/config", config)
+        .build()
+}
+
+#[cargo_test]
+fn git_cli_with_submodule() {
+    if disable_git_cli() {
+        return;
+    }
+    let dep = git::new("dep", |project| {
+        project.file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+    });
+    let sub = git::new("sub", |project| project.file("lib.rs", "pub fn dep() {}"));
+    let repo = git2::Repository::open(&dep.root()).unwrap();
+    git::add_submodule(&repo, &path2url(sub.root()).to_string(), Path::new("src"));
+    git::commit(&repo);
+    let p = dep_project(
+        &dep,
+        r#"
+            [git]
+            backend = "git-cli"
+        "#,
+    );
+
+    p.cargo("build -v -Zgit=backend")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] git repository `[..]`
+[RUNNING] `git fetch [..]`
+[UPDATING] git submodule `file://[..]/sub`
+[RUNNING] `git fetch [..]`
+[COMPILING] dep [..]
+[RUNNING] `rustc [..]`
+[COMPILING] foo [..]
+[RUNNING] `rustc [..]`
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    // Without the flag, the `git.backend` config isn't used.
+    p.root().join("target").rm_rf();
+    paths::home().join(".cargo/git").rm_rf();
+    p.cargo("build -v")
+        .with_stderr_does_not_contain("[RUNNING] `git fetch [..]`")
+        .run();
+}
+
+#[cargo_test]
+fn libgit2_overrides_fetch_with_cli() {
+    let dep = git::new("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "pub fn dep() {}")
+    });
+    let p = dep_project(
+        &dep,
+        r#"
+            [net]
+            git-fetch-with-cli = true
+
+            [git]
+            backend = "libgit2"
+        "#,
+    );
+
+    p.cargo("build -v -Zgit=backend")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[RUNNING] `git fetch [..]`")
+        .run();
+}
+
+#[cargo_test]
+fn unknown_backend() {
+    let dep = git::new("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "pub fn dep() {}")
+    });
+    let p = dep_project(
+        &dep,
+        r#"
+            [git]
+            backend = "gitoxide"
+        "#,
+    );
+
+    p.cargo("build -Zgit=backend")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[..]unknown git backend `gitoxide` in `git.backend`, \
+             expected `libgit2` or `git-cli`",
+        )
+        .run();
+}
@@ -0,0 +1,139 @@
+//! Tests for git dependencies on any reference with `-Zgit=refs`.
+
+use cargo_test_support::{basic_manifest, git, project};
+
+/// Commits the index of `repo` to `refname` only, like the head of a pull
+/// request.
+fn commit_to(repo: &git2::Repository, refname: &str) -> git2::Oid {
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let sig = repo.signature().unwrap();
+    let parent = match repo.refname_to_id(refname) {
+        Ok(id) => repo.find_commit(id).unwrap(),
+        Err(_) => repo.head().unwrap().peel_to_commit().unwrap(),
+    };
+    repo.commit(Some(refname), &sig, &sig, "pr", &tree, &[&parent])
+        .unwrap()
+}
+
+#[cargo_test]
+fn pull_request_ref() {
+    let (dep, repo) = git::new_repo("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "pub const VERSION: u32 = 1;")
+    });
+    dep.change_file("src/lib.rs", "pub const VERSION: u32 = 2;");
+    git::add(&repo);
+    let first = commit_to(&repo, "refs/pull/1/head");
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ git = '{}', ref = 'refs/pull/1/head' }}
+                "#,
+                dep.url()
+            ),
+        )
+        .file(
+            "src/main.rs",
+            "fn main() { println!(\"{}\", dep::VERSION) }",
+        )
+        .build();
+
+    p.cargo("run -Zgit=refs")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("2")
+        .run();
+    let source = format!("git+{}?ref=refs/pull/1/head#{}", dep.url(), first);
+    assert!(p.read_lockfile().contains(&source));
+
+    // The lock file is kept as it is.
+    p.cargo("build -Zgit=refs")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    // `cargo update` moves the reference forward, like a branch.
+    dep.change_file("src/lib.rs", "pub const VERSION: u32 = 3;");
+    git::add(&repo);
+    let second = commit_to(&repo, "refs/pull/1/head");
+    p.cargo("update -Zgit=refs")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(&format!(
+            "\
+[UPDATING] git repository `{url}`
+[UPDATING] dep v0.1.0 ({url}?ref=refs/pull/1/head#{first}) -> #{second}
+",
+            url = dep.url(),
+            first = &first.to_string()[..8],
+            second = &second.to_string()[..8],
+        ))
+        .run();
+    p.cargo("run -Zgit=refs")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("3")
+        .run();
+}
+
+#[cargo_test]
+fn ref_requires_flag() {
+    let dep = git::new("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "")
+    });
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ git = '{}', ref = 'refs/pull/1/head' }}
+                "#,
+                dep.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("  the `ref` key of dependency `dep` requires `-Zgit=refs`")
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                dep = {{ git = '{}', ref = 'pull/1/head' }}
+            "#,
+            dep.url()
+        ),
+    );
+    p.cargo("build -Zgit=refs")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  the `ref` of dependency `dep` must be a fully qualified reference \
+             starting with `refs/`, found `pull/1/head`",
+        )
+        .run();
+}
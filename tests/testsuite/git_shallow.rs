@@ -0,0 +1,250 @@
+//! Tests for shallow and partial fetches with `-Zgit`.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, git, paths, project, Project};
+
+fn disable_git_cli() -> bool {
+    // See the same function in `git.rs`.
+    env::var("CARGO_TEST_DISABLE_GIT_CLI") == Ok("1".to_string())
+}
+
+/// The git database of the `dep` dependency, whose name ends with `suffix`.
+fn find_db(suffix: &str) -> PathBuf {
+    let dir = paths::home().join(".cargo/git/db");
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            name.starts_with("dep-") && name.ends_with(suffix)
+        })
+        .unwrap()
+}
+
+fn dep_project(dep: &Project, dependency: &str) -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ git = '{}', {} }}
+                "#,
+                dep.url(),
+                dependency
+            ),
+        )
+        .file("src/lib.rs", "pub const VERSION: u32 = dep::VERSION;")
+        .file(
+            ".cargo/config",
+            "
+                [net]
+                git-fetch-with-cli = true
+            ",
+        )
+        .build()
+}
+
+#[cargo_test]
+fn shallow_deps_fetch_locked_rev() {
+    if disable_git_cli() {
+        return;
+    }
+    let (dep, repo) = git::new_repo("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "pub const VERSION: u32 = 1;")
+    });
+    let p = dep_project(&dep, "branch = 'master'");
+
+    p.cargo("build -Zgit=shallow-deps")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(find_db("-shallow").join("shallow").exists());
+
+    // The lock file still points to the first commit, which isn't the tip of
+    // the branch anymore.
+    dep.change_file("src/lib.rs", "pub const VERSION: u32 = 2;");
+    git::add(&repo);
+    git::commit(&repo);
+    fs::remove_dir_all(paths::home().join(".cargo/git")).unwrap();
+    p.cargo("build -Zgit=shallow-deps")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let checkouts = paths::home().join(".cargo/git/checkouts");
+    let checkout = fs::read_dir(checkouts).unwrap().next().unwrap().unwrap();
+    let checkout = fs::read_dir(checkout.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    let lib = fs::read_to_string(checkout.path().join("src/lib.rs")).unwrap();
+    assert_eq!(lib, "pub const VERSION: u32 = 1;");
+}
+
+#[cargo_test]
+fn shallow_deps_deepen_for_rev() {
+    if disable_git_cli() {
+        return;
+    }
+    let (dep, repo) = git::new_repo("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "pub const VERSION: u32 = 1;")
+    });
+    let rev = repo.revparse_single("HEAD").unwrap().id().to_string();
+    dep.change_file("src/lib.rs", "pub const VERSION: u32 = 2;");
+    git::add(&repo);
+    git::commit(&repo);
+
+    // An abbreviated commit ID can't be fetched by itself, and isn't the tip
+    // of any branch.
+    let p = dep_project(&dep, &format!("rev = '{}'", &rev[..10]));
+    p.cargo("build -Zgit=shallow-deps")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(!find_db("-shallow").join("shallow").exists());
+}
+
+#[cargo_test]
+fn partial_deps() {
+    if disable_git_cli() {
+        return;
+    }
+    let (dep, repo) = git::new_repo("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "pub const VERSION: u32 = 1;")
+    });
+    repo.config()
+        .unwrap()
+        .set_bool("uploadpack.allowFilter", true)
+        .unwrap();
+    let p = dep_project(&dep, "branch = 'master'");
+
+    p.cargo("build -Zgit=partial-deps")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let db = git2::Repository::open(find_db("-partial")).unwrap();
+    let promisor = format!("remote.{}.promisor", dep.url());
+    assert!(db.config().unwrap().get_bool(&promisor).unwrap());
+}
+
+#[cargo_test]
+fn shallow_index() {
+    if disable_git_cli() {
+        return;
+    }
+    Package::new("bar", "1.0.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            "
+                [net]
+                git-fetch-with-cli = true
+            ",
+        )
+        .build();
+
+    p.cargo("generate-lockfile -Zgit=shallow-index")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let index = fs::read_dir(paths::home().join(".cargo/registry/index"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    assert!(index.to_str().unwrap().ends_with("-shallow"));
+    assert!(index.join(".git/shallow").exists());
+
+    // Fetching into the shallow index.
+    Package::new("bar", "1.0.1").publish();
+    p.cargo("update -Zgit=shallow-index")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] bar v1.0.0 -> v1.0.1
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn requires_git_cli() {
+    let dep = git::new("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "pub const VERSION: u32 = 1;")
+    });
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ git = '{}' }}
+                "#,
+                dep.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("fetch -Zgit=shallow-deps")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] git repository `[..]`
+[WARNING] shallow and partial fetches need a git backend that can fetch history, such as `git-cli`, but `libgit2` is in use, fetching all of `[..]`
+",
+        )
+        .run();
+
+    p.cargo("fetch -Zgit=shallow")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] unknown -Zgit option `shallow`, expected one of `shallow-deps`, \
+`shallow-index`, `partial-deps`, `sparse-checkout`, `verify-signatures`, `refs` or `backend`
+",
+        )
+        .run();
+
+    p.cargo("fetch -Zgit=partial-index")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] -Zgit=partial-index is not supported, as the contents of all files of the index \
+would be fetched right after
+",
+        )
+        .run();
+}
@@ -0,0 +1,191 @@
+//! Tests for verifying the signatures of git dependencies with
+//! `-Zgit=verify-signatures`.
+
+use std::fs;
+use std::process::Command;
+
+use cargo_test_support::{basic_manifest, git, paths, project, Project};
+
+/// Creates an SSH key named `name`, or returns `None` if `ssh-keygen` can't
+/// be used for signing.
+fn ssh_key(name: &str) -> Option<String> {
+    let key = paths::root().join(name);
+    let status = Command::new("ssh-keygen")
+        .args(&["-q", "-t", "ed25519", "-N", "", "-f"])
+        .arg(&key)
+        .status()
+        .ok()?;
+    if !status.success() {
+        return None;
+    }
+    let public = fs::read_to_string(key.with_extension("pub")).unwrap();
+    let allowed_signers = paths::root().join(format!("{}_signers", name));
+    fs::write(&allowed_signers, format!("dev@example.com {}", public)).unwrap();
+    Some(name.to_string())
+}
+
+/// Commits the index of `repo`, signed with the SSH key `key` if given.
+fn commit(repo: &git2::Repository, key: Option<&str>) -> git2::Oid {
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let sig = repo.signature().unwrap();
+    let parent = repo.head().unwrap().peel_to_commit().unwrap();
+    let buf = repo
+        .commit_create_buffer(&sig, &sig, "commit", &tree, &[&parent])
+        .unwrap();
+    let buf = buf.as_str().unwrap();
+    let signature = key.map(|key| {
+        let file = paths::root().join("commit");
+        fs::write(&file, buf).unwrap();
+        let status = Command::new("ssh-keygen")
+            .args(&["-q", "-Y", "sign", "-n", "git", "-f"])
+            .arg(paths::root().join(key))
+            .arg(&file)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        fs::read_to_string(file.with_extension("sig")).unwrap()
+    });
+    let id = match signature {
+        Some(signature) => repo.commit_signed(buf, &signature, None).unwrap(),
+        None => git::commit(repo),
+    };
+    repo.reference("refs/heads/master", id, true, "commit")
+        .unwrap();
+    id
+}
+
+fn dep_repo(key: Option<&str>) -> (Project, git2::Repository) {
+    let (dep, repo) = git::new_repo("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.1.0"))
+            .file("src/lib.rs", "")
+    });
+    dep.change_file("src/lib.rs", "pub fn dep() {}");
+    git::add(&repo);
+    commit(&repo, key);
+    (dep, repo)
+}
+
+fn config(key: &str, require_signed: bool) -> String {
+    format!(
+        "
+            [git]
+            require-signed = {}
+            allowed-signers = '{}'
+        ",
+        require_signed,
+        paths::root()
+            .join(format!("{}_signers", key))
+            .display()
+            .to_string()
+            .replace('\\', "/")
+    )
+}
+
+#[cargo_test]
+fn verify_signature_key() {
+    let key = match ssh_key("key") {
+        Some(key) => key,
+        None => return,
+    };
+    let (dep, repo) = dep_repo(Some(&key));
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ git = '{}', verify-signature = true }}
+                "#,
+                dep.url()
+            ),
+        )
+        .file("src/lib.rs", "pub fn foo() { dep::dep() }")
+        .file(".cargo/config", &config(&key, false))
+        .build();
+
+    p.cargo("build -Zgit=verify-signatures")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    dep.change_file("src/lib.rs", "pub fn dep() {} pub fn unsigned() {}");
+    git::add(&repo);
+    let unsigned = commit(&repo, None);
+    p.cargo("update -Zgit=verify-signatures")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "  commit `{}` of git repository `{}` is not signed, \
+             but git dependencies must be signed",
+            unsigned,
+            dep.url()
+        ))
+        .run();
+    // The unsigned commit wasn't checked out.
+    let checkouts = fs::read_dir(paths::home().join(".cargo/git/checkouts")).unwrap();
+    for checkout in checkouts {
+        let short_id = &unsigned.to_string()[..7];
+        assert!(!checkout.unwrap().path().join(short_id).exists());
+    }
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "  the `verify-signature` key of dependency `dep` requires `-Zgit=verify-signatures`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn require_signed_untrusted_key() {
+    let key = match ssh_key("key") {
+        Some(key) => key,
+        None => return,
+    };
+    let other = ssh_key("other").unwrap();
+    let (dep, repo) = dep_repo(Some(&other));
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    dep = {{ git = '{}' }}
+                "#,
+                dep.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .file(".cargo/config", &config(&key, true))
+        .build();
+
+    let head = repo.head().unwrap().target().unwrap();
+    p.cargo("build -Zgit=verify-signatures")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(&format!(
+            "  commit `{}` of git repository `{}` is not signed by a key in `[..]key_signers`",
+            head,
+            dep.url()
+        ))
+        .run();
+
+    // The config isn't ignored without the flag.
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "  `git.require-signed` is set in cargo config, but the signatures of git \
+             dependencies are only verified with `-Zgit=verify-signatures`",
+        )
+        .run();
+}
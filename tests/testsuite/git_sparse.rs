@@ -0,0 +1,152 @@
+//! Tests for sparse checkouts of git dependencies with `-Zgit=sparse-checkout`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use cargo_test_support::{basic_lib_manifest, basic_manifest, git, paths, project, Project};
+
+/// A repository with a workspace of several packages, of which `foo` depends
+/// on `bar` by path.
+fn monorepo() -> Project {
+    git::new("monorepo", |project| {
+        project
+            .file(
+                "Cargo.toml",
+                r#"
+                    [workspace]
+                    members = ["crates/*"]
+
+                    [workspace.dependencies]
+                    bar = { path = "crates/bar" }
+                "#,
+            )
+            .file(
+                "crates/foo/Cargo.toml",
+                r#"
+                    cargo-features = ["workspace-inheritance"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    bar = { workspace = true }
+                "#,
+            )
+            .file("crates/foo/src/lib.rs", "pub fn foo() { bar::bar() }")
+            .file("crates/bar/Cargo.toml", &basic_lib_manifest("bar"))
+            .file("crates/bar/src/lib.rs", "pub fn bar() {}")
+            .file(
+                "crates/unused/Cargo.toml",
+                &basic_manifest("unused", "0.1.0"),
+            )
+            .file("crates/unused/src/lib.rs", "")
+            .file("docs/large.md", "")
+    })
+}
+
+fn checkouts() -> Vec<PathBuf> {
+    let dir = paths::home().join(".cargo/git/checkouts");
+    let repo = fs::read_dir(dir).unwrap().next().unwrap().unwrap();
+    fs::read_dir(repo.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect()
+}
+
+fn build_with(repo: &Project, dependency: &str) {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "top"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    foo = {{ git = '{}'{} }}
+                "#,
+                repo.url(),
+                dependency
+            ),
+        )
+        .file("src/lib.rs", "pub fn top() { foo::foo() }")
+        .build();
+    p.cargo("build -Zgit=sparse-checkout")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let checkouts = checkouts();
+    assert_eq!(checkouts.len(), 1);
+    let checkout = &checkouts[0];
+    assert!(checkout.join("Cargo.toml").is_file());
+    assert!(checkout.join("crates/foo/src/lib.rs").is_file());
+    assert!(checkout.join("crates/bar/src/lib.rs").is_file());
+    assert!(!checkout.join("crates/unused").exists());
+    assert!(!checkout.join("docs").exists());
+}
+
+#[cargo_test]
+fn subdir() {
+    let repo = monorepo();
+    build_with(&repo, ", subdir = 'crates/foo'");
+}
+
+#[cargo_test]
+fn located_by_name() {
+    let repo = monorepo();
+    build_with(&repo, "");
+}
+
+#[cargo_test]
+fn subdir_requires_flag() {
+    let repo = monorepo();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "top"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    foo = {{ git = '{}', subdir = 'crates/foo' }}
+                "#,
+                repo.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "  the `subdir` key of dependency `foo` requires `-Zgit=sparse-checkout`",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+                [package]
+                name = "top"
+                version = "0.1.0"
+
+                [dependencies]
+                foo = {{ git = '{}', subdir = '../foo' }}
+            "#,
+            repo.url()
+        ),
+    );
+    p.cargo("build -Zgit=sparse-checkout")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  the `subdir` of dependency `foo` must be a relative path within the \
+             repository, found `../foo`",
+        )
+        .run();
+}
@@ -0,0 +1,315 @@
+//! Tests for tracking the global package cache and `cargo gc`.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, git, paths, project, Project};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn cargo_home() -> PathBuf {
+    paths::home().join(".cargo")
+}
+
+fn db_path() -> PathBuf {
+    cargo_home().join(".global-cache")
+}
+
+/// Returns the tracked entries, relative to `CARGO_HOME`.
+fn tracked() -> Vec<String> {
+    let db: serde_json::Value = serde_json::from_slice(&fs::read(db_path()).unwrap()).unwrap();
+    db["last-use"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Pretends that the tracked entries matching `pred` were last used `days`
+/// days ago.
+fn backdate(days: u64, pred: impl Fn(&str) -> bool) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut db: serde_json::Value = serde_json::from_slice(&fs::read(db_path()).unwrap()).unwrap();
+    for (key, value) in db["last-use"].as_object_mut().unwrap() {
+        if pred(key) {
+            *value = (now - days * DAY).into();
+        }
+    }
+    fs::write(db_path(), serde_json::to_vec(&db).unwrap()).unwrap();
+}
+
+fn size_of(path: &Path) -> u64 {
+    if path.is_dir() {
+        fs::read_dir(path)
+            .unwrap()
+            .map(|e| size_of(&e.unwrap().path()))
+            .sum()
+    } else {
+        path.metadata().unwrap().len()
+    }
+}
+
+fn project_with_deps(deps: &str) -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    {}
+                "#,
+                deps
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build()
+}
+
+#[cargo_test]
+fn gc_requires_flag() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("gc")
+        .with_status(101)
+        .with_stderr("[ERROR] the `gc` command is unstable, pass `-Z gc` to enable it")
+        .run();
+    p.cargo("clean gc")
+        .with_status(101)
+        .with_stderr("[ERROR] the `gc` command is unstable, pass `-Z gc` to enable it")
+        .run();
+}
+
+#[cargo_test]
+fn no_tracking_without_flag() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project_with_deps(r#"bar = "1.0""#);
+    p.cargo("fetch").run();
+    assert!(!db_path().exists());
+}
+
+#[cargo_test]
+fn removes_old_registry_entries() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "1.0.0").publish();
+    let p = project_with_deps(r#"bar = "1.0""#);
+    p.cargo("build -Zgc").masquerade_as_nightly_cargo().run();
+
+    let mut entries = tracked();
+    entries.sort();
+    assert_eq!(entries.len(), 3);
+    assert!(entries[0].starts_with("registry/cache/"));
+    assert!(entries[0].ends_with("/bar-1.0.0.crate"));
+    assert!(entries[1].starts_with("registry/index/"));
+    assert!(entries[2].starts_with("registry/src/"));
+    assert!(entries[2].ends_with("/bar-1.0.0"));
+    let index = cargo_home().join(&entries[1]);
+    let src = cargo_home().join(&entries[2]);
+    assert!(index.exists());
+    assert!(src.exists());
+
+    // Recently used entries are kept.
+    p.cargo("gc -Zgc --max-age 30days")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[REMOVED] 0 cache entries, 0 B total")
+        .run();
+    assert!(src.exists());
+
+    backdate(60, |_| true);
+    p.cargo("gc -Zgc --max-age 30days -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[REMOVING] [..]/registry/index/[..]
+[REMOVING] [..]/registry/cache/[..]/bar-1.0.0.crate
+[REMOVING] [..]/registry/src/[..]/bar-1.0.0
+[REMOVED] 3 cache entries, [..] total
+",
+        )
+        .run();
+    assert!(!index.exists());
+    assert!(!src.exists());
+    assert!(tracked().is_empty());
+
+    // Using a package again after it was collected just re-downloads it.
+    p.cargo("clean").run();
+    p.cargo("build -Zgc")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.0 (registry `[..]`)
+[COMPILING] bar v1.0.0
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(tracked().len(), 3);
+}
+
+#[cargo_test]
+fn default_max_age() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project_with_deps(r#"bar = "1.0""#);
+    p.cargo("fetch -Zgc").masquerade_as_nightly_cargo().run();
+
+    backdate(60, |_| true);
+    p.cargo("gc -Zgc")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[REMOVED] 0 cache entries, 0 B total")
+        .run();
+
+    backdate(120, |_| true);
+    p.cargo("clean gc -Zgc")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[REMOVED] 3 cache entries, [..] total")
+        .run();
+}
+
+#[cargo_test]
+fn max_age_from_config() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project_with_deps(r#"bar = "1.0""#);
+    p.cargo("fetch -Zgc").masquerade_as_nightly_cargo().run();
+    p.change_file(
+        ".cargo/config.toml",
+        r#"
+            [gc]
+            max-age = "1 week"
+        "#,
+    );
+
+    backdate(10, |key| key.starts_with("registry/src/"));
+    p.cargo("gc -Zgc -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[REMOVING] [..]/registry/src/[..]/bar-1.0.0
+[REMOVED] 1 cache entry, [..] total
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn max_size_removes_least_recently_used() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", &"// bar\n".repeat(100))
+        .publish();
+    Package::new("baz", "1.0.0").publish();
+    let p = project_with_deps(
+        r#"
+            bar = "1.0"
+            baz = "1.0"
+        "#,
+    );
+    p.cargo("fetch -Zgc").masquerade_as_nightly_cargo().run();
+    assert_eq!(tracked().len(), 5);
+
+    backdate(2, |key| key.contains("/baz-"));
+    backdate(1, |key| key.contains("/bar-"));
+    let bar_size: u64 = tracked()
+        .iter()
+        .filter(|key| key.contains("/bar-"))
+        .map(|key| size_of(&cargo_home().join(key)))
+        .sum();
+
+    let index_size = size_of(&cargo_home().join("registry/index"));
+
+    p.cargo(&format!(
+        "gc -Zgc --max-size {}B --dry-run",
+        bar_size + index_size
+    ))
+    .masquerade_as_nightly_cargo()
+    .with_stderr("[SUMMARY] 2 cache entries, [..] total would be removed")
+    .run();
+    assert_eq!(tracked().len(), 5);
+
+    p.cargo(&format!("gc -Zgc --max-size {}B -v", bar_size + index_size))
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[REMOVING] [..]/baz-1.0.0.crate
+[REMOVING] [..]/baz-1.0.0
+[REMOVED] 2 cache entries, [..] total
+",
+        )
+        .run();
+    let entries = tracked();
+    assert_eq!(entries.len(), 3);
+    assert!(entries
+        .iter()
+        .all(|key| key.contains("/bar-1.0.0") || key.starts_with("registry/index/")));
+}
+
+#[cargo_test]
+fn untracked_entries_get_a_grace_period() {
+    Package::new("bar", "1.0.0").publish();
+    let p = project_with_deps(r#"bar = "1.0""#);
+    // Populate the cache without tracking.
+    p.cargo("fetch").run();
+    assert!(!db_path().exists());
+
+    p.cargo("gc -Zgc --max-age 1day")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[REMOVED] 0 cache entries, 0 B total")
+        .run();
+    assert_eq!(tracked().len(), 3);
+}
+
+#[cargo_test]
+fn git_checkouts() {
+    let git_project = git::new("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("dep", "0.5.0"))
+            .file("src/lib.rs", "")
+    });
+    let p = project_with_deps(&format!("dep = {{ git = '{}' }}", git_project.url()));
+    p.cargo("build -Zgc").masquerade_as_nightly_cargo().run();
+
+    let mut entries = tracked();
+    entries.sort();
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].starts_with("git/checkouts/dep-"));
+    assert!(entries[1].starts_with("git/db/dep-"));
+
+    backdate(100, |_| true);
+    p.cargo("gc -Zgc --max-age 30days")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[REMOVED] 2 cache entries, [..] total")
+        .run();
+    assert!(!cargo_home().join(&entries[0]).exists());
+    assert!(!cargo_home().join(&entries[1]).exists());
+
+    // The checkout is recreated from the lock file.
+    p.cargo("build -Zgc")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[UPDATING] git repository [..]")
+        .run();
+}
+
+#[cargo_test]
+fn invalid_limits() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("gc -Zgc --max-age soon")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] failed to parse `soon` as a duration (expected something like `30 days`)",
+        )
+        .run();
+    p.cargo("gc -Zgc --max-size lots")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] failed to parse `lots` as a size [..]")
+        .run();
+}
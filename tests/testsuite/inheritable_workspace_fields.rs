@@ -0,0 +1,344 @@
+//! Tests for inheriting package fields and dependencies from the workspace.
+
+use std::fs::File;
+
+use cargo_test_support::publish::validate_crate_contents;
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_lib_manifest, project};
+
+#[cargo_test]
+fn inherit_package_fields_and_dependencies() {
+    Package::new("dep", "0.5.2")
+        .feature("a", &[])
+        .feature("b", &[])
+        .publish();
+    Package::new("dev-dep", "1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [workspace]
+                members = ["crates/bar"]
+
+                [workspace.package]
+                edition = "2018"
+                authors = ["Rustaceans"]
+                license = "MIT OR Apache-2.0"
+                repository = "https://example.com/repo"
+                description = "a shared description"
+
+                [workspace.dependencies]
+                dep = { version = "0.5", features = ["a"] }
+                dev-dep = "1.0"
+            "#,
+        )
+        .file(
+            "crates/bar/Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                edition.workspace = true
+                authors.workspace = true
+                license.workspace = true
+                repository = { workspace = true }
+                description.workspace = true
+
+                [dependencies]
+                dep = { workspace = true, features = ["b"], optional = true }
+
+                [dev-dependencies]
+                dev-dep.workspace = true
+            "#,
+        )
+        .file("crates/bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("check -p bar --features dep")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] dep v0.5.2 ([..])
+[CHECKING] dep v0.5.2
+[CHECKING] bar v0.1.0 ([CWD]/crates/bar)
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("package --no-verify")
+        .cwd("crates/bar")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let f = File::open(&p.root().join("target/package/bar-0.1.0.crate")).unwrap();
+    let rewritten_toml = format!(
+        r#"{}
+cargo-features = ["workspace-inheritance"]
+
+[package]
+edition = "2018"
+name = "bar"
+version = "0.1.0"
+authors = ["Rustaceans"]
+description = "a shared description"
+license = "MIT OR Apache-2.0"
+repository = "https://example.com/repo"
+[dependencies.dep]
+version = "0.5"
+features = ["a", "b"]
+optional = true
+[dev-dependencies.dev-dep]
+version = "1.0"
+"#,
+        cargo::core::package::MANIFEST_PREAMBLE,
+    );
+    validate_crate_contents(
+        f,
+        "bar-0.1.0.crate",
+        &["Cargo.toml", "Cargo.toml.orig", "src/lib.rs"],
+        &[("Cargo.toml", &rewritten_toml)],
+    );
+}
+
+#[cargo_test]
+fn inherit_path_dependency_from_root_package() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors.workspace = true
+
+                [workspace]
+                members = ["crates/*"]
+
+                [workspace.package]
+                authors = ["Rustaceans"]
+
+                [workspace.dependencies]
+                baz = { path = "crates/baz" }
+
+                [dependencies]
+                bar = { path = "crates/bar" }
+                baz.workspace = true
+            "#,
+        )
+        .file("src/lib.rs", "pub fn foo() { bar::bar(); baz::baz(); }")
+        .file(
+            "crates/bar/Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                authors.workspace = true
+
+                [dependencies]
+                baz = { workspace = true }
+            "#,
+        )
+        .file("crates/bar/src/lib.rs", "pub fn bar() { baz::baz(); }")
+        .file("crates/baz/Cargo.toml", &basic_lib_manifest("baz"))
+        .file("crates/baz/src/lib.rs", "pub fn baz() {}")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[CHECKING] baz v0.5.0 ([CWD]/crates/baz)
+[CHECKING] bar v0.1.0 ([CWD]/crates/bar)
+[CHECKING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn requires_cargo_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["bar"]
+
+                [workspace.package]
+                edition = "2018"
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                edition.workspace = true
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[CWD]/Cargo.toml`
+
+Caused by:
+  feature `workspace-inheritance` is required
+
+  The package requires the Cargo feature called `workspace-inheritance`, \
+but that feature is not stabilized in this version of Cargo ([..]).
+  Consider adding `cargo-features = [\"workspace-inheritance\"]` to the top of Cargo.toml \
+(above the [package] table) to tell Cargo you are opting in to use this unstable feature.
+  See https://doc.rust-lang.org/nightly/cargo/reference/unstable.html#workspace-inheritance \
+for more information about the status of this feature.
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn missing_workspace_values() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [workspace]
+                members = ["bar"]
+
+                [workspace.package]
+                license = "MIT"
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [package]
+                name = "bar"
+                version = "0.1.0"
+                edition.workspace = true
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to load manifest for workspace member `[CWD]/bar`
+
+Caused by:
+  failed to parse manifest at `[CWD]/bar/Cargo.toml`
+
+Caused by:
+  error inheriting `edition` from workspace root manifest's `workspace.package.edition`
+
+Caused by:
+  `workspace.package.edition` was not defined
+",
+        )
+        .run();
+
+    p.change_file(
+        "bar/Cargo.toml",
+        r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "bar"
+            version = "0.1.0"
+
+            [dependencies]
+            dep = { workspace = true }
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to load manifest for workspace member `[CWD]/bar`
+
+Caused by:
+  failed to parse manifest at `[CWD]/bar/Cargo.toml`
+
+Caused by:
+  error inheriting `dep` from workspace root manifest's `workspace.dependencies.dep`
+
+Caused by:
+  dependency `dep` was not found in `workspace.dependencies`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn inherited_dependency_conflicting_keys() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [workspace]
+                members = ["bar"]
+
+                [workspace.dependencies]
+                dep = "1.0"
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [package]
+                name = "bar"
+                version = "0.1.0"
+
+                [dependencies]
+                dep = { workspace = true, version = "2.0" }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to load manifest for workspace member `[CWD]/bar`
+
+Caused by:
+  failed to parse manifest at `[CWD]/bar/Cargo.toml`
+
+Caused by:
+  dependency `dep` cannot specify `version` along with `workspace = true`, \
+only `features` and `optional` may be combined with it
+",
+        )
+        .run();
+}
@@ -514,6 +514,7 @@ fn cdylib_and_rlib() {
     p.cargo("test --release -v --manifest-path bar/Cargo.toml")
         .with_stderr_unordered(
             "\
+[DIRTY] bar v0.0.0 ([..]): the profile configuration changed
 [COMPILING] registry v0.0.1
 [COMPILING] registry-shared v0.0.1
 [RUNNING] `rustc --crate-name registry [..]-C embed-bitcode=no[..]
@@ -566,6 +567,7 @@ fn dylib() {
 [COMPILING] registry-shared v0.0.1
 [FRESH] registry v0.0.1
 [RUNNING] `rustc --crate-name registry_shared [..]-C embed-bitcode=no[..]
+[DIRTY] bar v0.0.0 ([..]): the dependency `registry_shared` changed
 [COMPILING] bar [..]
 [RUNNING] `rustc --crate-name bar [..]--crate-type dylib [..]-C embed-bitcode=no[..]
 [FINISHED] [..]
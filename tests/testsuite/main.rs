@@ -15,18 +15,22 @@ mod bad_manifest_path;
 mod bench;
 mod build;
 mod build_plan;
+mod build_sandbox;
 mod build_script;
 mod build_script_env;
 mod build_script_extra_link_arg;
 mod cache_messages;
+mod cargo_add;
 mod cargo_alias_config;
 mod cargo_command;
 mod cargo_config;
 mod cargo_env_config;
 mod cargo_features;
+mod cargo_remove;
 mod cargo_targets;
 mod cfg;
 mod check;
+mod checksum_freshness;
 mod clean;
 mod collisions;
 mod concurrent;
@@ -35,6 +39,7 @@ mod config_cli;
 mod config_include;
 mod corrupt_git;
 mod credential_process;
+mod critical_path_scheduling;
 mod cross_compile;
 mod cross_publish;
 mod custom_target;
@@ -44,6 +49,7 @@ mod directory;
 mod doc;
 mod edition;
 mod error;
+mod explain_rebuilds;
 mod features;
 mod features2;
 mod features_namespaced;
@@ -54,9 +60,16 @@ mod future_incompat_report;
 mod generate_lockfile;
 mod git;
 mod git_auth;
+mod git_backend;
 mod git_gc;
+mod git_ref;
+mod git_shallow;
+mod git_signature;
+mod git_sparse;
 mod glob_targets;
+mod global_cache_gc;
 mod help;
+mod inheritable_workspace_fields;
 mod init;
 mod install;
 mod install_upgrade;
@@ -68,6 +81,7 @@ mod lockfile_compat;
 mod login;
 mod logout;
 mod lto;
+mod max_memory;
 mod member_discovery;
 mod member_errors;
 mod message_format;
@@ -100,6 +114,7 @@ mod publish;
 mod publish_lockfile;
 mod read_manifest;
 mod registry;
+mod remote_cache;
 mod rename_deps;
 mod replace;
 mod required_features;
@@ -112,9 +127,14 @@ mod rustdoc_extern_html;
 mod rustdocflags;
 mod rustflags;
 mod search;
+mod shared_cache;
 mod shell_quoting;
+mod sparse_registry;
 mod standard_lib;
 mod test;
+mod test_jobs;
+mod test_partition;
+mod test_report;
 mod timings;
 mod tool_paths;
 mod tree;
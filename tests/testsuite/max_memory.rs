@@ -0,0 +1,160 @@
+//! Tests for `build.max-memory` and -Zmax-memory.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn requires_unstable_flag() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                max-memory = "1 GiB"
+            "#,
+        )
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[WARNING] `build.max-memory` in cargo config was ignored, the -Zmax-memory command-line flag is required
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert!(!p.root().join("target/debug/.unit-memory.json").exists());
+
+    p.change_file(
+        ".cargo/config.toml",
+        r#"
+            [build]
+            max-memory = "lots"
+        "#,
+    );
+    p.cargo("build -Zmax-memory")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] invalid `build.max-memory` config value
+
+Caused by:
+  failed to parse `lots` as a size (expected something like `2 GiB`): [..]
+",
+        )
+        .run();
+}
+
+#[cfg(unix)]
+#[cargo_test]
+fn saves_peak_memory() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                a = { path = "a" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                max-memory = "1 GiB"
+            "#,
+        )
+        .build();
+
+    p.cargo("check -Zmax-memory")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let saved: serde_json::Value =
+        serde_json::from_str(&p.read_file("target/debug/.unit-memory.json")).unwrap();
+    assert_eq!(saved["version"], 1);
+    for key in ["a lib (check)", "foo lib (check)"] {
+        assert!(
+            saved["peaks"][key].as_u64().unwrap() > 0,
+            "no peak memory for `{}` in {}",
+            key,
+            saved
+        );
+    }
+}
+
+#[cargo_test]
+fn units_wait_for_memory() {
+    // Each build script checks that no other build script is running at the
+    // same time, which they would with `-j3` if it weren't for the budget.
+    let build_rs = |name: &str, markers: &std::path::Path| {
+        format!(
+            r#"
+                use std::{{fs, thread, time::Duration}};
+
+                fn main() {{
+                    let markers = std::path::Path::new(r"{}");
+                    fs::create_dir_all(markers).unwrap();
+                    let marker = markers.join("{}");
+                    fs::write(&marker, "").unwrap();
+                    thread::sleep(Duration::from_millis(500));
+                    assert_eq!(fs::read_dir(markers).unwrap().count(), 1);
+                    fs::remove_file(&marker).unwrap();
+                }}
+            "#,
+            markers.display(),
+            name
+        )
+    };
+    let markers = cargo_test_support::paths::root().join("markers");
+    let mut p = project().file(
+        "Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            a = { path = "a" }
+            b = { path = "b" }
+            c = { path = "c" }
+        "#,
+    );
+    for name in ["a", "b", "c"] {
+        p = p
+            .file(
+                &format!("{}/Cargo.toml", name),
+                &basic_manifest(name, "0.1.0"),
+            )
+            .file(&format!("{}/src/lib.rs", name), "")
+            .file(&format!("{}/build.rs", name), &build_rs(name, &markers));
+    }
+    let p = p
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build]
+                max-memory = "1 GiB"
+            "#,
+        )
+        // Units that weren't built before are expected to need as much
+        // memory as the average unit, which is the whole budget here.
+        .file(
+            "target/debug/.unit-memory.json",
+            r#"{"version": 1, "peaks": {"other": 1073741824}}"#,
+        )
+        .build();
+
+    p.cargo("build -j3 -Zmax-memory")
+        .masquerade_as_nightly_cargo()
+        .run();
+}
@@ -0,0 +1,123 @@
+//! Tests for `build.remote-cache` and -Zremote-cache.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, paths, project, Project};
+
+/// A workspace at `path` that depends on `bar` from the registry and on a
+/// local `baz`, with `helper` as the remote cache helper.
+fn workspace(path: &str, helper: &str) -> Project {
+    project()
+        .at(path)
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+                baz = { path = "baz" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); baz::baz(); }")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.1.0"))
+        .file("baz/src/lib.rs", "pub fn baz() {}")
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [build]
+                    remote-cache = '{}'
+                "#,
+                helper
+            ),
+        )
+        .build()
+}
+
+fn publish_bar() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+}
+
+#[cargo_test]
+fn shares_through_fs_helper() {
+    publish_bar();
+    let cache = paths::root().join("remote-cache");
+    std::fs::create_dir(&cache).unwrap();
+    let helper = format!("cargo:fs {}", cache.display());
+    let one = workspace("one", &helper);
+    let two = workspace("two", &helper);
+
+    one.cargo("build -v -Zremote-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]")
+        .run();
+    assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 1);
+
+    // `bar` is fetched instead of being built, and not stored again.
+    two.cargo("build -v -Zremote-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[RUNNING] `rustc --crate-name bar [..]")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name baz [..]")
+        .with_stderr_does_not_contain("[WARNING] [..]")
+        .run();
+    two.process(&two.bin("foo")).run();
+    two.cargo("build -Zremote-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+    assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 1);
+}
+
+#[cargo_test]
+fn failing_helper_only_warns() {
+    publish_bar();
+    let p = workspace("foo", "missing-remote-cache-helper");
+
+    p.cargo("build -Zremote-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "warning: failed to fetch `bar-[..]` from the remote build cache: \
+             failed to execute remote cache helper `missing-remote-cache-helper`[..]",
+        )
+        .with_stderr_contains(
+            "warning: failed to store `bar-[..]` in the remote build cache: \
+             failed to execute remote cache helper `missing-remote-cache-helper`[..]",
+        )
+        .with_stderr_contains("[COMPILING] bar v1.0.0")
+        .run();
+    p.process(&p.bin("foo")).run();
+}
+
+#[cargo_test]
+fn fs_helper_requires_directory() {
+    publish_bar();
+    let p = workspace("foo", "cargo:fs");
+
+    p.cargo("build -Zremote-cache")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] the `cargo:fs` remote cache helper expects the directory to store \
+             artifacts in, like `cargo:fs /path/to/dir`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn requires_unstable_flag() {
+    publish_bar();
+    let p = workspace("foo", "missing-remote-cache-helper");
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] `build.remote-cache` in cargo config was ignored, \
+             the -Zremote-cache command-line flag is required",
+        )
+        .with_stderr_does_not_contain("warning: failed to [..]")
+        .run();
+}
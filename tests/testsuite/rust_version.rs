@@ -258,3 +258,144 @@ fn rust_version_older_than_edition() {
         )
         .run();
 }
+
+fn publish_bar_versions() {
+    Package::new("bar", "1.0.0")
+        .cargo_feature("rust-version")
+        .rust_version("1.50")
+        .publish();
+    Package::new("bar", "1.1.0")
+        .cargo_feature("rust-version")
+        .rust_version("1.60")
+        .publish();
+    Package::new("bar", "1.2.0")
+        .cargo_feature("rust-version")
+        .rust_version("1.65")
+        .publish();
+}
+
+fn msrv_project(bar_req: &str) -> cargo_test_support::Project {
+    project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    cargo-features = ["rust-version"]
+
+                    [package]
+                    name = "foo"
+                    version = "0.0.1"
+                    rust-version = "1.60"
+
+                    [dependencies]
+                    bar = "{}"
+                "#,
+                bar_req
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build()
+}
+
+#[cargo_test]
+fn resolve_ignores_rust_version_without_flag() {
+    publish_bar_versions();
+    let p = msrv_project("1.0");
+
+    p.cargo("generate-lockfile")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("tree --depth 1")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("foo v0.0.1 ([CWD])\n└── bar v1.2.0\n")
+        .run();
+}
+
+#[cargo_test]
+fn resolve_prefers_compatible_rust_version() {
+    publish_bar_versions();
+    let p = msrv_project("1.0");
+
+    p.cargo("generate-lockfile -Zmsrv-policy")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[UPDATING] [..]")
+        .run();
+    p.cargo("tree --depth 1")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("foo v0.0.1 ([CWD])\n└── bar v1.1.0\n")
+        .run();
+}
+
+#[cargo_test]
+fn resolve_falls_back_to_incompatible_rust_version() {
+    publish_bar_versions();
+    let p = msrv_project("1.2");
+
+    p.cargo("generate-lockfile -Zmsrv-policy")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] [..]
+[WARNING] package `bar v1.2.0` requires rustc 1.65, which is newer than the \
+workspace's `rust-version` of 1.60
+no version compatible with the `rust-version` matches the requirement
+",
+        )
+        .run();
+
+    // Already locked packages aren't warned about again.
+    p.cargo("tree -Zmsrv-policy")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[WARNING] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn resolve_denies_incompatible_rust_version() {
+    publish_bar_versions();
+    let p = msrv_project("1.2");
+    p.change_file(
+        ".cargo/config.toml",
+        r#"
+            [resolver]
+            incompatible-rust-versions = "deny"
+        "#,
+    );
+
+    p.cargo("generate-lockfile -Zmsrv-policy")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] [..]
+[ERROR] failed to select a version for the requirement `bar = \"^1.2\"`
+all candidate versions require a newer Rust than the workspace's `rust-version` of 1.60:
+  bar v1.2.0 requires rustc 1.65
+required by package `foo v0.0.1 ([CWD])`
+consider raising the `rust-version`, or setting `resolver.incompatible-rust-versions = \"fallback\"`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn resolve_allows_incompatible_rust_version() {
+    publish_bar_versions();
+    let p = msrv_project("1.0");
+    p.change_file(
+        ".cargo/config.toml",
+        r#"
+            [resolver]
+            incompatible-rust-versions = "allow"
+        "#,
+    );
+
+    p.cargo("generate-lockfile -Zmsrv-policy")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[UPDATING] [..]")
+        .run();
+    p.cargo("tree --depth 1")
+        .masquerade_as_nightly_cargo()
+        .with_stdout("foo v0.0.1 ([CWD])\n└── bar v1.2.0\n")
+        .run();
+}
@@ -407,6 +407,7 @@ fn rustc_fingerprint() {
         .with_stderr_does_not_contain("-C debug-assertions")
         .with_stderr(
             "\
+[DIRTY] foo v0.5.0 ([..]): the profile configuration changed
 [COMPILING] foo [..]
 [RUNNING] `rustc [..]
 [FINISHED] [..]
@@ -0,0 +1,106 @@
+//! Tests for `build.shared-cache` and -Zshared-cache.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, paths, project, Project};
+
+/// A workspace at `path` that depends on `bar` from the registry and on a
+/// local `baz`, with the shared cache configured.
+fn workspace(path: &str) -> Project {
+    project()
+        .at(path)
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "1.0"
+                baz = { path = "baz" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); baz::baz(); }")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.1.0"))
+        .file("baz/src/lib.rs", "pub fn baz() {}")
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [build]
+                    shared-cache = '{}'
+                "#,
+                paths::root().join("shared-cache").display()
+            ),
+        )
+        .build()
+}
+
+#[cargo_test]
+fn shares_registry_dependencies() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+    let one = workspace("one");
+    let two = workspace("two");
+
+    one.cargo("build -Zshared-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_unordered(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.0 ([..])
+[COMPILING] bar v1.0.0
+[COMPILING] baz v0.1.0 ([CWD]/baz)
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    // Only the local packages are built again.
+    two.cargo("build -Zshared-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[COMPILING] baz v0.1.0 ([CWD]/baz)
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    two.process(&two.bin("foo")).run();
+    two.cargo("build -Zshared-cache")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+
+    // Different settings make a different unit.
+    two.cargo("build -Zshared-cache --release")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] bar v1.0.0")
+        .run();
+    one.cargo("build -Zshared-cache --release")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[COMPILING] bar v1.0.0")
+        .run();
+}
+
+#[cargo_test]
+fn requires_unstable_flag() {
+    Package::new("bar", "1.0.0")
+        .file("src/lib.rs", "pub fn bar() {}")
+        .publish();
+    let p = workspace("one");
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[WARNING] `build.shared-cache` in cargo config was ignored, \
+             the -Zshared-cache command-line flag is required",
+        )
+        .run();
+    assert!(!paths::root().join("shared-cache").exists());
+}
@@ -0,0 +1,265 @@
+//! Tests for registries whose index is fetched over HTTP (`sparse+` URLs).
+
+use cargo_test_support::paths;
+use cargo_test_support::registry::{registry_path, HttpServer, Package};
+use cargo_test_support::{project, t};
+use std::fs;
+
+/// Serves the test registry over HTTP and replaces crates.io with it.
+fn setup() -> HttpServer {
+    // Make sure the registry (and its `config.json`) exists.
+    cargo_test_support::registry::init();
+    let server = HttpServer::new(registry_path());
+    let root = paths::root();
+    t!(fs::create_dir_all(root.join(".cargo")));
+    t!(fs::write(
+        root.join(".cargo/config"),
+        format!(
+            "
+                [source.crates-io]
+                replace-with = 'sparse-registry'
+
+                [source.sparse-registry]
+                registry = 'sparse+{}'
+            ",
+            server.url()
+        )
+    ));
+    server
+}
+
+fn foo_with_dep(dep: &str) -> cargo_test_support::Project {
+    project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [project]
+                    name = "foo"
+                    version = "0.0.1"
+                    authors = []
+
+                    [dependencies]
+                    {}
+                "#,
+                dep
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build()
+}
+
+#[cargo_test]
+fn requires_nightly_flag() {
+    let _server = setup();
+    Package::new("bar", "0.0.1").publish();
+    let p = foo_with_dep("bar = \"0.0.1\"");
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("[..]usage of sparse registries requires `-Z sparse-registry`")
+        .run();
+}
+
+#[cargo_test]
+fn simple() {
+    let server = setup();
+    Package::new("bar", "0.0.1").publish();
+    let p = foo_with_dep("bar = \"0.0.1\"");
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `sparse+http://[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.0.1 (registry `sparse+http://[..]`)
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]s
+",
+        )
+        .run();
+    assert!(server.requests().contains(&"200 /3/b/bar".to_string()));
+    assert!(server.requests().contains(&"200 /config.json".to_string()));
+
+    p.cargo("clean").run();
+
+    // The lock file pins everything, so no requests are needed.
+    let before = server.requests().len();
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]s
+",
+        )
+        .run();
+    assert_eq!(server.requests().len(), before);
+}
+
+#[cargo_test]
+fn transitive_deps_are_prefetched() {
+    let server = setup();
+    Package::new("baz", "0.1.0").publish();
+    Package::new("bar", "0.1.0").dep("baz", "0.1").publish();
+    Package::new("unused", "1.0.0").publish();
+    let p = foo_with_dep("bar = \"0.1\"");
+
+    p.cargo("generate-lockfile -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    let requests = server.requests();
+    assert!(requests.contains(&"200 /3/b/bar".to_string()));
+    assert!(requests.contains(&"200 /3/b/baz".to_string()));
+    assert!(!requests.iter().any(|r| r.ends_with("/unused")));
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"baz\""));
+}
+
+#[cargo_test]
+fn update_revalidates_index_files() {
+    let server = setup();
+    Package::new("bar", "0.0.1").publish();
+    let p = foo_with_dep("bar = \"0.0\"");
+
+    p.cargo("generate-lockfile -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // Nothing changed, so the server should only confirm our copy.
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[UPDATING] `sparse+http://[..]` index")
+        .run();
+    assert!(server.requests().contains(&"304 /3/b/bar".to_string()));
+
+    Package::new("bar", "0.0.2").publish();
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `sparse+http://[..]` index
+[UPDATING] bar v0.0.1 -> v0.0.2
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn index_files_are_cached() {
+    let _server = setup();
+    Package::new("bar", "0.0.1").publish();
+    let p = foo_with_dep("bar = \"0.0\"");
+
+    p.cargo("generate-lockfile -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let index = fs::read_dir(paths::home().join(".cargo/registry/index"))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let cache = index.join(".cache/3/b/bar");
+    let contents = String::from_utf8_lossy(&t!(fs::read(&cache))).into_owned();
+    assert!(contents.contains("ETag: \""));
+    assert!(contents.contains("0.0.1"));
+
+    // Revalidating the file doesn't change its version, so the cache stays.
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_eq!(String::from_utf8_lossy(&t!(fs::read(&cache))), contents);
+
+    // A new copy of the file gets a new version, and replaces the cache.
+    Package::new("bar", "0.0.2").publish();
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let updated = String::from_utf8_lossy(&t!(fs::read(&cache))).into_owned();
+    assert_ne!(updated, contents);
+    assert!(updated.contains("0.0.2"));
+}
+
+#[cargo_test]
+fn missing_crate() {
+    let _server = setup();
+    Package::new("bar", "0.0.1").publish();
+    let p = foo_with_dep("baz = \"0.0.1\"");
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no matching package named `baz` found")
+        .run();
+}
+
+#[cargo_test]
+fn offline_uses_cached_index() {
+    let server = setup();
+    Package::new("bar", "0.0.1").publish();
+    let p = foo_with_dep("bar = \"0.0.1\"");
+
+    p.cargo("fetch -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    drop(server);
+
+    p.cargo("build --offline -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] dev [unoptimized + debuginfo] target(s) in [..]s
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn alternative_registry_lockfile() {
+    let server = setup();
+    Package::new("bar", "0.0.1").publish();
+    t!(fs::write(
+        paths::root().join(".cargo/config"),
+        format!(
+            "
+                [registries.sparse]
+                index = 'sparse+{}'
+            ",
+            server.url()
+        )
+    ));
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [project]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = { version = "0.0.1", registry = "sparse" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let lock = p.read_lockfile();
+    assert!(lock.contains(&format!("source = \"sparse+{}", server.url())));
+
+    // The lock file source round-trips.
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
@@ -662,3 +662,197 @@ fn no_roots() {
         .with_stderr_contains("[FINISHED] [..]")
         .run();
 }
+
+#[cargo_test]
+fn config_features_per_target() {
+    let setup = match setup() {
+        Some(s) => s,
+        None => return,
+    };
+    let p = project()
+        .file(
+            "src/lib.rs",
+            "
+                pub fn foo() {
+                    std::conditional_function();
+                }
+            ",
+        )
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [build-std]
+                    features = ["default"]
+
+                    [build-std.target.{}]
+                    features = ["feature1"]
+                "#,
+                rustc_host()
+            ),
+        )
+        .build();
+    p.cargo("build").build_std(&setup).target_host().run();
+}
+
+#[cargo_test]
+fn config_crates() {
+    let setup = match setup() {
+        Some(s) => s,
+        None => return,
+    };
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"
+                #![no_std]
+                pub fn foo() {
+                    assert_eq!(u8::MIN, 0);
+                }
+            "#,
+        )
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build-std]
+                crates = ["core"]
+            "#,
+        )
+        .build();
+    p.cargo("build -v --lib")
+        .build_std(&setup)
+        .target_host()
+        .with_stderr_does_not_contain("[..]libstd[..]")
+        .run();
+}
+
+#[cargo_test]
+fn sysroot_is_reused() {
+    let setup = match setup() {
+        Some(s) => s,
+        None => return,
+    };
+    let p = project()
+        .file(
+            "src/main.rs",
+            "
+                fn main() {
+                    std::custom_api();
+                }
+            ",
+        )
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build-std]
+                sysroot = true
+            "#,
+        )
+        .build();
+    let sysroot = p
+        .build_dir()
+        .join("build-std")
+        .join(rustc_host())
+        .join("dev")
+        .join("sysroot");
+    let lib_dir = sysroot.join("lib/rustlib").join(rustc_host()).join("lib");
+
+    p.cargo("run -v")
+        .build_std(&setup)
+        .target_host()
+        .with_stderr_contains("[COMPILING] std [..]")
+        .with_stderr_contains(&format!(
+            "[RUNNING] `[..] --crate-name foo [..]--extern 'noprelude:std={}/libstd-[..].rlib'[..]",
+            lib_dir.display()
+        ))
+        .run();
+    assert!(lib_dir.read_dir().unwrap().any(|entry| entry
+        .unwrap()
+        .file_name()
+        .to_str()
+        .unwrap()
+        .starts_with("libstd-")));
+
+    // The sysroot is used as it is, without building the standard library
+    // into the target directory of the package.
+    p.change_file("src/main.rs", "fn main() { std::custom_api(); }");
+    p.cargo("build -v")
+        .build_std(&setup)
+        .target_host()
+        .with_stderr_does_not_contain("[COMPILING] std [..]")
+        .with_stderr_contains("[COMPILING] foo [..]")
+        .run();
+    assert!(!p
+        .build_dir()
+        .join(rustc_host())
+        .join("debug/deps")
+        .read_dir()
+        .unwrap()
+        .any(|entry| entry
+            .unwrap()
+            .file_name()
+            .to_str()
+            .unwrap()
+            .starts_with("libstd-")));
+
+    let mut build_std = p.cargo("build-std");
+    enable_build_std(&mut build_std, &setup);
+    build_std.target_host().with_stdout(sysroot.display()).run();
+}
+
+#[cargo_test]
+fn different_settings_per_target_require_sysroot() {
+    let setup = match setup() {
+        Some(s) => s,
+        None => return,
+    };
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config.toml",
+            &format!(
+                r#"
+                    [build-std.target.{}]
+                    features = ["feature1"]
+                "#,
+                rustc_host()
+            ),
+        )
+        .build();
+    p.cargo("build -Zmultitarget --target i686-unknown-linux-gnu")
+        .build_std(&setup)
+        .target_host()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] building the standard library with different settings for each target \
+requires `build-std.sysroot`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn config_panic_strategy() {
+    let setup = match setup() {
+        Some(s) => s,
+        None => return,
+    };
+    let p = project()
+        .file("src/lib.rs", "#![no_std]")
+        .file(
+            ".cargo/config.toml",
+            r#"
+                [build-std]
+                crates = ["core"]
+                panic-strategy = "abort"
+            "#,
+        )
+        .build();
+    p.cargo("build -v --lib")
+        .build_std(&setup)
+        .target_host()
+        .with_stderr_contains("[RUNNING] `[..] --crate-name core [..]-C panic=abort[..]")
+        .with_stderr_contains("[RUNNING] `[..] --crate-name foo [..]-C panic=abort[..]")
+        .run();
+}
@@ -0,0 +1,97 @@
+//! Tests for running test binaries concurrently with `--test-jobs`.
+
+use cargo_test_support::{basic_manifest, project, Project};
+
+/// A test that only finishes once the test `other` started as well.
+fn rendezvous_test(name: &str, other: &str) -> String {
+    format!(
+        r#"
+            use std::path::Path;
+            use std::time::{{Duration, Instant}};
+
+            #[test]
+            fn {name}() {{
+                let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+                std::fs::write(dir.join("{name}.started"), "").unwrap();
+                let start = Instant::now();
+                while !dir.join("{other}.started").exists() {{
+                    assert!(start.elapsed() < Duration::from_secs(60), "{other} never started");
+                    std::thread::sleep(Duration::from_millis(10));
+                }}
+            }}
+        "#,
+        name = name,
+        other = other
+    )
+}
+
+fn concurrent_project() -> Project {
+    project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", &rendezvous_test("a", "b"))
+        .file("tests/b.rs", &rendezvous_test("b", "a"))
+        .build()
+}
+
+#[cargo_test]
+fn runs_binaries_concurrently() {
+    let p = concurrent_project();
+
+    p.cargo("test -Zunstable-options --test-jobs 2 --tests")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] [..] (target/debug/deps/a-[..])")
+        .with_stderr_contains("[RUNNING] [..] (target/debug/deps/b-[..])")
+        // The output of each binary is shown as a whole.
+        .with_stdout_contains("running 1 test\ntest a ... ok\n\ntest result: ok. 1 passed[..]")
+        .with_stdout_contains("running 1 test\ntest b ... ok\n\ntest result: ok. 1 passed[..]")
+        .run();
+}
+
+#[cargo_test]
+fn failures() {
+    let p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file("src/lib.rs", "")
+        .file("tests/a.rs", "#[test] fn a() { panic!(\"nope\"); }")
+        .file("tests/b.rs", "#[test] fn b() {}")
+        .file("tests/c.rs", "#[test] fn c() { panic!(\"nope\"); }")
+        .build();
+
+    p.cargo("test -Zunstable-options --test-jobs 2 --tests --no-fail-fast")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stdout_contains("test a ... FAILED")
+        .with_stdout_contains("test b ... ok")
+        .with_stdout_contains("test c ... FAILED")
+        .with_stderr_contains("[ERROR] test failed.")
+        .run();
+
+    p.cargo("test -Zunstable-options --test-jobs 2 --test a")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] test failed, to rerun pass '--test a'")
+        .run();
+}
+
+#[cargo_test]
+fn test_jobs_errors() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test --test-jobs 2")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the `--test-jobs` flag is unstable, pass `-Z unstable-options` to enable it
+See https://github.com/rust-lang/cargo/issues/5609 for more information about the `--test-jobs` flag.
+",
+        )
+        .run();
+
+    p.cargo("test -Zunstable-options --test-jobs 0")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr("[ERROR] --test-jobs must be at least 1")
+        .run();
+}
@@ -0,0 +1,137 @@
+//! Tests for splitting test targets across runners with `--partition`.
+
+use std::collections::BTreeSet;
+
+use cargo_test_support::{basic_manifest, project, Project};
+
+fn partition_project() -> Project {
+    let mut p = project()
+        .file("Cargo.toml", &basic_manifest("foo", "0.1.0"))
+        .file(
+            "src/lib.rs",
+            "/// ```\n/// foo::f();\n/// ```\npub fn f() {}",
+        )
+        .file("src/main.rs", "fn main() {}");
+    for name in ["a", "b", "c", "d", "e"] {
+        p = p.file(
+            &format!("tests/{}.rs", name),
+            &format!("#[test] fn {}() {{}}", name),
+        );
+    }
+    p.build()
+}
+
+/// Returns the test targets that were run, as shown in the `Running` and
+/// `Doc-tests` lines.
+fn run_partition(p: &Project, partition: &str) -> Vec<String> {
+    let output = p
+        .cargo(&format!(
+            "test -Zunstable-options --partition {}",
+            partition
+        ))
+        .masquerade_as_nightly_cargo()
+        .exec_with_output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    stderr
+        .lines()
+        .map(|line| line.trim_start())
+        .filter_map(|line| {
+            line.strip_prefix("Running ")
+                .or_else(|| line.strip_prefix("Doc-tests "))
+        })
+        .map(|what| what.to_string())
+        .collect()
+}
+
+#[cargo_test]
+fn hash_partitions_cover_all_targets() {
+    let p = partition_project();
+
+    let all = run_partition(&p, "hash:1/1");
+    assert_eq!(all.len(), 8, "{:?}", all);
+
+    let mut seen = BTreeSet::new();
+    for shard in 1..=3 {
+        let selected = run_partition(&p, &format!("hash:{}/3", shard));
+        // The same partition always selects the same targets.
+        assert_eq!(run_partition(&p, &format!("hash:{}/3", shard)), selected);
+        for target in selected {
+            assert!(seen.insert(target.clone()), "{} ran twice", target);
+        }
+    }
+    assert_eq!(seen, all.into_iter().collect::<BTreeSet<_>>());
+}
+
+#[cargo_test]
+fn count_partitions_only_build_selected_targets() {
+    let p = partition_project();
+
+    p.cargo("test -Zunstable-options --partition count:1/2 --test *")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] [..] (target/debug/deps/a-[..])
+[RUNNING] [..] (target/debug/deps/c-[..])
+[RUNNING] [..] (target/debug/deps/e-[..])
+",
+        )
+        .run();
+    let built: Vec<_> = p
+        .root()
+        .join("target/debug/deps")
+        .read_dir()
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert!(!built
+        .iter()
+        .any(|f| f.starts_with("b-") || f.starts_with("d-")));
+
+    p.cargo("test -Zunstable-options --partition count:2/2 --test *")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.1.0 ([CWD])
+[FINISHED] test [unoptimized + debuginfo] target(s) in [..]
+[RUNNING] [..] (target/debug/deps/b-[..])
+[RUNNING] [..] (target/debug/deps/d-[..])
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn partition_errors() {
+    let p = partition_project();
+
+    p.cargo("test --partition hash:1/2")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the `--partition` flag is unstable, pass `-Z unstable-options` to enable it
+See https://github.com/rust-lang/cargo/issues/10293 for more information about the `--partition` flag.
+",
+        )
+        .run();
+
+    p.cargo("test -Zunstable-options --partition random:1/2")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid partition `random:1/2`, expected `hash:M/N` or `count:M/N`, \
+             for example `hash:1/3`",
+        )
+        .run();
+
+    p.cargo("test -Zunstable-options --partition count:3/2")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid partition `count:3/2`, the partition index must be between 1 and 2",
+        )
+        .run();
+}
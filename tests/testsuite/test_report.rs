@@ -0,0 +1,204 @@
+//! Tests for collecting test results with `cargo test`.
+
+use cargo_test_support::project;
+
+fn mixed_project() -> cargo_test_support::Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [[test]]
+                name = "noharness"
+                harness = false
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                /// ```
+                /// assert_eq!(foo::add(1, 2), 3);
+                /// ```
+                pub fn add(a: i32, b: i32) -> i32 { a + b }
+
+                #[cfg(test)]
+                mod tests {
+                    #[test]
+                    fn passes() {}
+
+                    #[test]
+                    fn fails() {
+                        println!("some <output> & more");
+                        panic!("oops");
+                    }
+
+                    #[test]
+                    #[ignore]
+                    fn ignored() {}
+                }
+            "#,
+        )
+        .file("tests/noharness.rs", "fn main() {}")
+        .build()
+}
+
+#[cargo_test]
+fn junit_report() {
+    let p = mixed_project();
+
+    p.cargo("test -Zunstable-options --report junit=target/report.xml --no-fail-fast")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stdout_contains("test tests::passes ... ok")
+        .with_stdout_contains("---- tests::fails stdout ----")
+        .run();
+
+    let xml = p.read_file("target/report.xml");
+    for expected in &[
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"cargo test\" \
+         tests=\"5\" failures=\"1\" skipped=\"1\" time=\"",
+        "<testsuite name=\"foo (lib)\" tests=\"3\" failures=\"1\" skipped=\"1\" time=\"",
+        "<testcase classname=\"foo (lib)\" name=\"tests::passes\"/>",
+        "<testcase classname=\"foo (lib)\" name=\"tests::ignored\">\n      \
+         <skipped/>\n    </testcase>",
+        "<testcase classname=\"foo (lib)\" name=\"tests::fails\">\n      \
+         <failure message=\"test failed\">some &lt;output&gt; &amp; more\n",
+        "<testsuite name=\"foo (test &quot;noharness&quot;)\" tests=\"1\" failures=\"0\"",
+        "<testcase classname=\"foo (test &quot;noharness&quot;)\" name=\"noharness\" time=\"",
+        "<testsuite name=\"foo (doctests)\" tests=\"1\" failures=\"0\"",
+        "<testcase classname=\"foo (doctests)\" name=\"src/lib.rs - add (line 2)\"/>",
+    ] {
+        assert!(
+            xml.contains(expected),
+            "expected to find:\n{}\nin report:\n{}",
+            expected,
+            xml
+        );
+    }
+}
+
+#[cargo_test]
+fn json_messages() {
+    let p = mixed_project();
+
+    p.cargo("test -Zunstable-options --message-format=json --lib")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "test-result",
+                    "package_id": "foo 0.1.0 [..]",
+                    "target": "{...}",
+                    "kind": "test",
+                    "name": "tests::passes",
+                    "outcome": "ok",
+                    "duration": null,
+                    "stdout": null
+                }
+
+                {
+                    "reason": "test-result",
+                    "package_id": "foo 0.1.0 [..]",
+                    "target": "{...}",
+                    "kind": "test",
+                    "name": "tests::fails",
+                    "outcome": "failed",
+                    "duration": null,
+                    "stdout": "{...}"
+                }
+
+                {
+                    "reason": "test-result",
+                    "package_id": "foo 0.1.0 [..]",
+                    "target": "{...}",
+                    "kind": "test",
+                    "name": "tests::ignored",
+                    "outcome": "ignored",
+                    "duration": null,
+                    "stdout": null
+                }
+
+                {
+                    "reason": "test-summary",
+                    "package_id": "foo 0.1.0 [..]",
+                    "target": "{...}",
+                    "kind": "test",
+                    "success": false,
+                    "passed": 1,
+                    "failed": 1,
+                    "ignored": 1,
+                    "duration": "{...}"
+                }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn colored_output() {
+    let p = mixed_project();
+
+    let output = p
+        .cargo("test -Zunstable-options --report junit=target/report.xml --lib --color=always")
+        .masquerade_as_nightly_cargo()
+        .exec_with_output()
+        .unwrap_err();
+    let output = &output
+        .downcast_ref::<cargo_util::ProcessError>()
+        .unwrap()
+        .stdout
+        .as_ref()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(output);
+    assert!(stdout.contains("\x1b["), "{}", stdout);
+
+    let xml = p.read_file("target/report.xml");
+    assert!(
+        xml.contains("<testcase classname=\"foo (lib)\" name=\"tests::passes\"/>"),
+        "{}",
+        xml
+    );
+    assert!(
+        xml.contains("<failure message=\"test failed\">some &lt;output&gt; &amp; more\n"),
+        "{}",
+        xml
+    );
+}
+
+#[cargo_test]
+fn report_errors() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("test --report junit=report.xml")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the `--report` flag is unstable, pass `-Z unstable-options` to enable it
+See https://github.com/rust-lang/cargo/issues/2832 for more information about the `--report` flag.
+",
+        )
+        .run();
+
+    p.cargo("test -Zunstable-options --report html=report.html")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] unsupported test report format `html`, \
+             the only supported format is `junit`",
+        )
+        .run();
+
+    p.cargo("test -Zunstable-options --report report.xml")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid test report `report.xml`, expected `FORMAT=PATH`, \
+             for example `junit=report.xml`",
+        )
+        .run();
+}
@@ -63,3 +63,300 @@ fn timings_works() {
 
     p.cargo("doc -Ztimings").masquerade_as_nightly_cargo().run();
 }
+
+#[cargo_test]
+fn timings_json_file_and_trace() {
+    Package::new("dep", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+
+            [dependencies]
+            dep = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build -Ztimings=json,file,trace")
+        .masquerade_as_nightly_cargo()
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "timing-info",
+                    "package_id": "dep 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                    "target": "{...}",
+                    "mode": "build",
+                    "duration": "{...}",
+                    "rmeta_time": "{...}"
+                }
+            "#,
+        )
+        .with_stderr_contains("      Timing report saved to [..]/foo/cargo-timing-[..].json")
+        .with_stderr_contains("      Timing report saved to [..]/foo/cargo-timing-[..].trace.json")
+        .run();
+
+    let report: serde_json::Value =
+        serde_json::from_str(&p.read_file("cargo-timing.json")).unwrap();
+    assert_eq!(report["version"], 1);
+    assert_eq!(report["profile"], "dev");
+    assert_eq!(report["total_dirty"], 3);
+    let units: Vec<_> = report["units"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|u| format!("{} {}{}", u["name"], u["mode"], u["target"]))
+        .collect();
+    assert_eq!(units.len(), 3, "{:?}", units);
+    for unit in [
+        r#""dep" "build""""#,
+        r#""foo" "build""""#,
+        r#""foo" "build"" bin \"foo\"""#,
+    ] {
+        assert!(units.iter().any(|u| u == unit), "{} in {:?}", unit, units);
+    }
+    assert!(!report["concurrency"].as_array().unwrap().is_empty());
+
+    let trace: serde_json::Value =
+        serde_json::from_str(&p.read_file("cargo-timing.trace.json")).unwrap();
+    let events = trace["traceEvents"].as_array().unwrap();
+    let spans: Vec<_> = events
+        .iter()
+        .filter(|e| e["ph"] == "X" && e["cat"] == "unit")
+        .map(|e| e["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(spans.len(), 3, "{:?}", spans);
+    assert!(spans.contains(&"dep v0.1.0"));
+    assert!(spans.contains(&"foo v0.1.0 bin \"foo\""));
+    assert!(events
+        .iter()
+        .any(|e| e["ph"] == "X" && e["cat"] == "codegen"));
+    assert!(events
+        .iter()
+        .any(|e| e["ph"] == "C" && e["name"] == "concurrency"));
+}
+
+#[cargo_test]
+fn timings_json_does_not_save_file() {
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("build -Ztimings=json")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]Timing report saved[..]")
+        .run();
+    assert!(!p.root().join("cargo-timing.json").exists());
+}
+
+/// A report as saved by `-Ztimings=file`, with units given as
+/// `(name, version, kind, target, start, duration, unlocked_units)`.
+fn timing_report(
+    duration: f64,
+    units: &[(&str, &str, &str, &str, f64, f64, &[usize])],
+    concurrency: &[(f64, usize)],
+) -> String {
+    let units: Vec<_> = units
+        .iter()
+        .enumerate()
+        .map(
+            |(i, (name, version, kind, target, start, duration, unlocked))| {
+                serde_json::json!({
+                    "i": i,
+                    "name": name,
+                    "version": version,
+                    "kind": kind,
+                    "mode": "build",
+                    "target": target,
+                    "start": start,
+                    "duration": duration,
+                    "rmeta_time": null,
+                    "unlocked_units": unlocked,
+                    "unlocked_rmeta_units": [],
+                })
+            },
+        )
+        .collect();
+    let concurrency: Vec<_> = concurrency
+        .iter()
+        .map(|(t, active)| {
+            serde_json::json!({
+                "t": t,
+                "active": active,
+                "waiting": 0,
+                "inactive": 0,
+                "rustc_parallelism": 0,
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "version": 1,
+        "start": "2021-01-01T00:00:00Z",
+        "duration": duration,
+        "profile": "dev",
+        "jobs": 2,
+        "ncpu": 2,
+        "rustc": "rustc 1.56.0",
+        "host": "x86_64-unknown-linux-gnu",
+        "targets": ["x86_64-unknown-linux-gnu"],
+        "root_targets": [["foo 0.1.0", ["lib"]]],
+        "total_fresh": 0,
+        "total_dirty": units.len(),
+        "error": null,
+        "units": units,
+        "concurrency": concurrency,
+        "cpu_usage": [],
+    })
+    .to_string()
+}
+
+fn compare_project() -> cargo_test_support::Project {
+    project()
+        .file("src/lib.rs", "")
+        .file(
+            "old.json",
+            &timing_report(
+                10.0,
+                &[
+                    ("syn", "1.0.80", "host", "", 0.0, 4.0, &[2]),
+                    ("log", "0.4.14", "host", "", 0.0, 1.0, &[]),
+                    ("foo", "0.1.0", "host", "", 4.0, 6.0, &[]),
+                ],
+                &[(0.0, 2), (1.0, 1), (10.0, 0)],
+            ),
+        )
+        .file(
+            "new.json",
+            &timing_report(
+                12.0,
+                &[
+                    ("syn", "1.0.81", "host", "", 0.0, 3.0, &[]),
+                    ("serde", "1.0.130", "host", "", 0.0, 5.0, &[2]),
+                    ("foo", "0.1.0", "host", "", 5.0, 7.0, &[]),
+                ],
+                &[(0.0, 2), (3.0, 1), (12.0, 0)],
+            ),
+        )
+        .build()
+}
+
+#[cargo_test]
+fn compare_timings() {
+    let p = compare_project();
+
+    p.cargo("report timings --compare old.json new.json")
+        .masquerade_as_nightly_cargo()
+        .with_stdout(
+            "\
+Total time:              10.0s -> 12.0s     +2.0s (+20%)
+Critical path:           10.0s -> 12.0s     +2.0s (+20%)
+Units built:                 3 -> 3         +0
+Avg concurrency:           1.1 -> 1.2       +0.1
+Underutilized time:       9.0s -> 9.0s      +0.0s (+0%)
+
+Critical path:
+  + serde v1.0.130
+    foo v0.1.0
+  - syn v1.0.80 -> v1.0.81
+
+Unit                          Old        New  Delta
+serde v1.0.130                  -       5.0s  +5.0s
+foo v0.1.0                   6.0s       7.0s  +1.0s (+17%)
+log v0.4.14                  1.0s          -  -1.0s
+syn v1.0.80 -> v1.0.81       4.0s       3.0s  -1.0s (-25%)
+",
+        )
+        .run();
+
+    p.cargo("report timings --compare old.json new.json --html")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("      Timing comparison saved to [..]/foo/cargo-timing-compare.html")
+        .run();
+    let html = p.read_file("cargo-timing-compare.html");
+    assert!(html.contains(
+        "<tr><td>syn v1.0.80 -&gt; v1.0.81</td><td>4.0s</td><td>3.0s</td>\
+         <td class=\"faster\">-1.0s (-25%)</td></tr>"
+    ));
+    assert!(html.contains("<li class=\"removed\">syn v1.0.80 -&gt; v1.0.81</li>"));
+}
+
+#[cargo_test]
+fn compare_timings_errors() {
+    let p = compare_project();
+    p.change_file("old.json", "<html></html>");
+
+    p.cargo("report timings --compare old.json new.json")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse timing report `[CWD]/old.json`, expected a file saved with `-Ztimings=file`
+
+Caused by:
+  [..]
+",
+        )
+        .run();
+
+    p.change_file(
+        "old.json",
+        &p.read_file("new.json")
+            .replace("\"version\":1", "\"version\":2"),
+    );
+    p.cargo("report timings --compare old.json new.json")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] timing report `[CWD]/old.json` has version 2, \
+             but this version of Cargo only understands version 1",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn compare_timings_host_and_target() {
+    let target = cargo_test_support::rustc_host();
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "old.json",
+            &timing_report(
+                4.0,
+                &[
+                    ("libc", "0.2.100", "host", "", 0.0, 1.0, &[]),
+                    ("libc", "0.2.100", target, "", 0.0, 2.0, &[2]),
+                    ("foo", "0.1.0", target, "", 2.0, 2.0, &[]),
+                ],
+                &[(0.0, 2), (1.0, 1), (4.0, 0)],
+            ),
+        )
+        .file(
+            "new.json",
+            &timing_report(
+                5.0,
+                &[
+                    ("libc", "0.2.100", target, "", 0.0, 3.0, &[2]),
+                    ("libc", "0.2.100", "host", "", 0.0, 1.0, &[]),
+                    ("foo", "0.1.0", target, "", 3.0, 2.0, &[]),
+                ],
+                &[(0.0, 2), (1.0, 1), (5.0, 0)],
+            ),
+        )
+        .build();
+
+    p.cargo("report timings --compare old.json new.json")
+        .masquerade_as_nightly_cargo()
+        .with_stdout_contains(
+            "\
+Unit                        Old        New  Delta
+libc v0.2.100              2.0s       3.0s  +1.0s (+50%)
+foo v0.1.0                 2.0s       2.0s  +0.0s (+0%)
+libc v0.2.100 (host)       1.0s       1.0s  +0.0s (+0%)
+",
+        )
+        .run();
+}